@@ -24,29 +24,143 @@
 
 use nymlib::nymsocket::SocketMode;
 use paste::paste;
-use eframe::egui::{self, CentralPanel, Context, TopBottomPanel, Ui, Visuals};
+use serde::{Deserialize, Serialize};
+use chrono::NaiveTime;
+use eframe::egui::{self, CentralPanel, Color32, Context, Key, KeyboardShortcut, Modifiers, TopBottomPanel, Ui};
+use log::warn;
 
 // Standard library
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, Instant};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
 
 // local
 use crate::theme::{Theme, Tab};
-use crate::tabs::{render_share_tab, render_download_tab, render_explore_tab};
-use crate::shareable::Shareable;
+use crate::tabs::{render_share_tab, render_download_tab, render_explore_tab, render_diagnostics_window, render_peers_window, render_audit_window, render_wipe_confirmation, render_unlock_window, render_vault_window, render_network_window, render_serving_tab, render_shortcuts_window, render_clear_confirmation, remove_share};
+use crate::shareable::{HashCache, SavedShare, Shareable, ShareableError, ShareSort, SHARES_FILE};
 use crate::define_tab_messages;
 use crate::timed_message;
 use crate::define_generic_messages;
-use crate::request::{DownLoadRequest, ExploreRequest};
+use crate::request::{ClearTarget, CollisionPolicy, DownLoadRequest, DuplicatePolicy, ExploreMode, ExploreRequest, RequestSort};
+use crate::backoff::Backoff;
+use crate::netconfig::NetworkConfig;
+use crate::filetype::FileType;
+use crate::peers::{PeerSort, PeerStats, RecentExplorer, SavedService, ADDRESS_BOOK_FILE, PEERS_FILE};
+use crate::history::{self, DownloadRecord, HISTORY_FILE};
+use crate::serving::{self, ServeEvent};
+use crate::audit::{self, AuditFilter};
+use crate::bus::MessageBus;
+use crate::collection::{self, Collection};
+use crate::storage;
+use crate::wipe::WipeOptions;
+use crate::archive::BundleMode;
+use crate::network::{push_advertise, AdvertiseRate, FileStatus, DEFAULT_IDENTITY, MAX_ADVERTISE_RESPONSES, MAX_REQUESTS_PER_MINUTE, DEFAULT_CHUNK_WINDOW, FILE_CHUNK_SIZE, MIN_FILE_CHUNK_SIZE};
+use crate::watcher;
 
 
 pub static VERSION: &str = "0.0.2";
 
+/// File in CONFIG_DIR holding the theme, tab and filter selections restored at startup.
+pub const VIEW_STATE_FILE: &str = "view.json";
 
-#[derive(Clone)]
+/// Theme, tab and filter selections persisted across launches.
+/// Fields missing from the file keep their first-run defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    pub theme: Theme,
+    pub active_tab: Tab,
+    pub hide_inactive: bool,
+    pub share_type_filter: Option<FileType>,
+    pub share_sort: ShareSort,
+    pub share_recent_only: bool,
+    pub show_all_downloads: bool,
+    pub show_today_downloads: bool,
+    pub show_runtime_downloads: bool,
+    pub hide_all_downloads: bool,
+    pub show_all_requests: bool,
+    pub show_accepted_requests: bool,
+    pub show_completed_requests: bool,
+    pub hide_all_requests: bool,
+    pub download_request_sort: RequestSort,
+    pub hide_all_explore_requests: bool,
+    pub show_all_explore_requests: bool,
+    pub show_accepted_explore_requests: bool,
+    pub hide_completed_explore_requests: bool,
+    pub explore_request_sort: RequestSort,
+    pub explore_type_filter: Option<FileType>,
+    pub explore_flat_results: bool,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        FileSharingApp::default().view_state()
+    }
+}
+
+
+/// Updates posted by background tasks and applied on the UI thread in `update`.
+#[derive(Clone, Debug)]
 pub enum AppUpdate {
-             
+    Message(String),                            // Status message for the active tab
+    TabMessage(Tab, String),                    // Status message for a specific tab
+    HashComputed(PathBuf, HashCache),           // Content hash of a shared file
+    SharedFilesChanged(Vec<(PathBuf, HashCache)>, Vec<PathBuf>), // Shared files rehashed after a change, and those deleted
+    ServingAddress(String, String),             // Serving address of an identity changed (empty while starting)
+    IdentityRemoved(String),                    // Serving identity removed from the registry
+    DownloadUpdated(DownLoadRequest),           // Download request added or changed by download_manager
+    DownloadsRemoved(Vec<String>),              // Download requests dropped by download_manager
+    DownloadRecorded(DownloadRecord),           // File saved by download_manager, for the history
+    Served(ServeEvent),                         // File sent to a peer by serving_manager
+    ExploreUpdated(ExploreRequest),             // Explore request added or changed by download_manager
+    ExploresRemoved(Vec<String>),               // Explore requests dropped by download_manager
+    LinkPreviewed(String, Result<FileStatus, String>), // Answer to a link preview, for the link it was asked for
+}
+
+/// A download link being previewed in the Download tab.
+#[derive(Clone, Debug)]
+pub struct LinkPreview {
+    pub link: String,                           // Link as entered
+    pub result: Option<Result<FileStatus, String>>, // None while waiting for the service
+}
+
+/// Bus carrying AppUpdates from the managers to the UI.
+/// Lets background tasks post status without taking the FileSharingApp lock.
+pub static APP_UPDATES: LazyLock<MessageBus<AppUpdate>> = LazyLock::new(MessageBus::new);
+
+/// Posts an update to be applied by the UI on its next frame.
+pub fn post_update(update: AppUpdate) {
+    APP_UPDATES.post(update);
+}
+
+/// Hashes a shared file in the background and posts the result as a HashComputed update.
+pub fn start_hash(path: PathBuf) {
+    tokio::task::spawn_blocking(move || match HashCache::compute(&path) {
+        Ok(cache) => post_update(AppUpdate::HashComputed(path, cache)),
+        Err(e) => post_tab_message(Tab::Share, format!("Failed to hash {:?}: {}", path, e)),
+    });
+}
+
+/// Posts a status message for the active tab.
+/// Background work that belongs to one tab should use post_tab_message instead.
+pub fn post_message(msg: impl Into<String>) {
+    post_update(AppUpdate::Message(msg.into()));
+}
+
+/// Posts a status message for a specific tab, whichever tab is active when it arrives.
+pub fn post_tab_message(tab: Tab, msg: impl Into<String>) {
+    post_update(AppUpdate::TabMessage(tab, msg.into()));
+}
+
+/// Result of re-checking a downloaded file's integrity.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerifyStatus {
+    Running,                                    // Hash is being computed
+    Passed(String),                             // Hash matches the stored expected hash
+    Failed { expected: String, actual: String }, // Hash differs from the stored expected hash
+    Computed(String),                           // No stored hash, current hash only
+    Error(String),                              // File could not be read
 }
 
 #[derive(Clone)]
@@ -54,25 +168,79 @@ pub struct FileSharingApp {
     // Core application state
     pub start_time: Option<SystemTime>,         // Tracks when the application started
     pub active_tab: Tab,                        // Currently active UI tab (Share, Download, etc.)
-    pub theme: Theme,                           // UI theme (Light or Dark)
-    pub serving_addr: String,                   // Local nym address for file sharing
+    pub theme: Theme,                           // UI theme (Light, Dark or System)
+    pub offline: bool,                          // Offline mode: no sockets, requests stay queued
+    pub serving_addr: String,                   // Local nym address for file sharing (default identity)
+    pub identity_addrs: BTreeMap<String, String>, // Addresses of the other serving identities, by name
+    pub new_identity_name: String,              // Name typed for a new serving identity
+    pub confirm_new_identity: bool,             // Show the "New Identity" confirmation
     pub download_socket_mode: SocketMode,       // Track the download socket mode
+    pub pending_socket_mode: Option<SocketMode>, // Mode switch awaiting confirmation while requests are in flight
     pub advertise_mode: bool,                   // Controls whether files are advertised
+    pub advertise_window: Option<(NaiveTime, NaiveTime)>, // Local hours files are advertised in (None = always)
+    pub advertise_window_silent: bool,          // Ignore ADVERTISE outside the window instead of answering with an empty list
+    pub link_includes_hash: bool,               // Append #sha256 to copied links
+    pub watch_shared_files: bool,               // Rehash shared files when they change on disk
+    pub readvertise_on_change: bool,            // Notify recent explorers when a shared file changes
+    pub push_saved_peers_only: bool,            // Only notify explorers in the Peers list or the address book
+    pub advertise_max_delay_ms: u64,            // Longest random delay before answering ADVERTISE (0 = answer at once)
+    pub advertise_peer_cap: u32,                // File lists sent to one peer per advertise window
+    pub advertise_rate: AdvertiseRate,          // Advertise traffic in the current window, set by serving_manager
+    pub max_concurrent_uploads: usize,          // File transfers served to peers at once
+    pub max_requests_per_minute: u32,           // Requests one peer may make per minute (0 = unlimited)
+    pub requests_dropped: u32,                  // Requests dropped by that limit in the last minute, set by serving_manager
+    pub compress_uploads: bool,                 // Compress files we serve when the requester accepts it
     pub debug_logging: bool,                    // Controls whether debug logging is enabled
+    pub network_config: NetworkConfig,          // Poll intervals of the serving and download managers
+    pub show_network_window: bool,              // Show the network settings window
+    pub show_shortcuts_window: bool,            // Show the keyboard shortcuts help (F1)
+    pub focus_search: bool,                     // Ctrl+F: focus the active tab's search box on its next draw
     pub show_settings_sidebar: bool,            // Show settings sidebar
+    pub show_diagnostics_window: bool,          // Show the diagnostics report window
+    pub diagnostics_redact_addresses: bool,     // Mask Nym addresses in diagnostics
+    pub diagnostics_redact_paths: bool,         // Mask file paths in diagnostics
+    pub wipe_options: Option<WipeOptions>,      // Emergency wipe awaiting confirmation
+    pub pending_clear: Option<ClearTarget>,     // Finished requests to clear, awaiting confirmation
+    pub wipe_confirmation: String,              // Text typed to confirm the wipe
+    pub unlock_passphrase: String,              // Passphrase typed to unlock saved state
+    pub unlock_dismissed: bool,                 // Chose to run without saved state this session
+    pub unlock_forget: bool,                    // Ticked to delete saved state for a forgotten passphrase
+    pub show_vault_window: bool,                // Show the saved state encryption window
+    pub vault_passphrase: String,               // New passphrase typed to turn encryption on
+    pub vault_passphrase_confirm: String,       // Same passphrase typed again
 
     // Share Tab state
     pub shareable_files: Vec<Shareable>,        // Files available for sharing
+    pub collections: Vec<Collection>,           // Named groups of shared files, advertised as one entry
+    pub new_collection_name: String,            // Name typed for the next collection
     pub share_message: String,                  // Message displayed in Share tab
     pub share_message_time: Option<Instant>,    // Timestamp for share message
     pub share_popup_message: String,            // Popup message for Share
     pub share_popup_message_time: Option<Instant>, // Popup timestamp
     pub hide_inactive: bool,                    // Hide inactive files in Share tab
+    pub share_type_filter: Option<FileType>,    // Only list shared files of this type
+    pub share_sort: ShareSort,                  // Order of the files in Share tab
+    pub share_recent_only: bool,                // Only list files added within RECENTLY_ADDED
     pub show_share_settings_sidebar: bool,      // Show settings sidebar in Share tab
+    pub pending_sensitive_files: Vec<PathBuf>,  // Files in sensitive locations awaiting "share anyway"
+    pub pending_folder_removal: Option<PathBuf>, // Folder whose files await removal confirmation
+    pub removed_files_undo: Vec<Shareable>,     // Files removed by the last folder removal, for undo
+    pub peer_stats: HashMap<String, PeerStats>, // Per-requester transfer summary (persisted)
+    pub recent_explorers: HashMap<String, RecentExplorer>, // Individual-mode explorers to notify of catalog changes
+    pub address_book: HashMap<String, SavedService>, // Services explored before, with the list they last sent
+    pub show_peers_window: bool,                // Show the peers window
+    pub peer_sort: PeerSort,                    // Column the peers table is sorted by
+    pub peer_sort_descending: bool,             // Sort the peers table in descending order
+    pub show_audit_window: bool,                // Show the audit log window
+    pub audit_filter: AuditFilter,              // Filters applied to the audit log view
+    pub audit_page: usize,                      // Current audit log page (0-based)
 
     // Download Tab state
     pub download_dir: PathBuf,                  // Directory for saving downloads
-    pub requested_files: Vec<DownLoadRequest>,  // Pending download requests
+    pub download_dir_empty: bool,               // Whether download_dir held nothing when last checked
+    pub requested_files: Vec<DownLoadRequest>,  // Download requests, as last reported by download_manager
+    pub download_history: Vec<DownloadRecord>,  // Files downloaded, oldest first (persisted)
+    pub history_checked: Option<Instant>,       // When the history's files were last looked for on disk
     pub download_message: String,               // Message displayed in Download tab
     pub download_message_time: Option<Instant>, // Timestamp for download message
     pub download_popup_message: String,         // Popup message for Download
@@ -82,9 +250,24 @@ pub struct FileSharingApp {
     pub show_runtime_downloads: bool,           // Show only downloads since app start
     pub hide_all_downloads: bool,               // Hide all downloads
     pub search_query: String,                   // Filter files in Download tab
+    pub selected_share: Option<PathBuf>,        // Shared file selected in the list; Delete removes it
+    pub allowlist_inputs: HashMap<PathBuf, String>, // Peer address typed into each shared file's allowlist
     pub download_url: String,                   // URL input for file downloads
+    pub link_preview: Option<LinkPreview>,      // Last link previewed, shown under the URL input
     pub show_download_settings: bool,           // Show download settings
     pub show_download_requests_sidebar: bool,   // Show download requests sidebar
+    pub max_concurrent_downloads: usize,        // Max requests in flight at once (0 = unlimited)
+    pub request_timeout_secs: u64,              // Seconds a sent request may wait for its ACK before failing (0 = never)
+    pub bundle_mode: BundleMode,                // How collections and manifests are saved
+    pub collision_policy: CollisionPolicy,      // What a finished download does to an existing file of the same name
+    pub upload_limit: Option<u64>,              // Upload cap in bytes per second (None = unlimited)
+    pub backoff: Backoff,                       // Retry policy for sockets, pings and unanswered requests
+    pub max_send_attempts: u32,                 // Failed sends in a row before a request is given up
+    pub chunk_window: u32,                      // Chunks a service may send ahead of our CHUNK_ACKs (0 = no ACKs)
+    pub chunk_size_bounds: (u32, u32),          // Smallest and largest chunk, in bytes, our adaptive transfers send
+    pub extra_surbs: u32,                       // Extra SURBs attached to anonymous download requests
+    pub prefer_compression: bool,               // Ask services to compress files they send us
+    pub verify_results: Arc<Mutex<HashMap<PathBuf, VerifyStatus>>>, // Integrity re-check results, filled off-thread
 
     // Download Requests Tab state
     pub download_requests_message: String,      // Message for DownloadRequests tab
@@ -95,19 +278,37 @@ pub struct FileSharingApp {
     pub show_accepted_requests: bool,           // Show only accepted requests
     pub show_completed_requests: bool,          // Show only completed requests
     pub hide_all_requests: bool,                // Hide all requests
+    pub download_request_sort: RequestSort,     // Row order of the download requests table
 
     // Explorer Tab state
     pub explore_address: String,                // Remote peer address to explore
-    pub explore_requests: Vec<ExploreRequest>,  // Pending explore requests
+    pub explore_requests: Vec<ExploreRequest>,  // Explore requests, as last reported by download_manager
     pub explore_message: String,                // Message displayed in Explorer tab
     pub explore_message_time: Option<Instant>,  // Timestamp for explorer message
     pub explore_popup_message: String,          // Popup message for Explorer
     pub explore_popup_message_time: Option<Instant>, // Popup timestamp
-    pub explore_search_query: String,           // Filter requests in Explorer tab
+    pub explore_search_query: String,           // Filter the advertised files of every explore request
+    pub explore_flat_results: bool,             // List matching files of all services together
     pub hide_all_explore_requests: bool,        // Hide all explore requests
     pub show_all_explore_requests: bool,        // Show all explore requests
     pub show_accepted_explore_requests: bool,   // Show only accepted explore requests
+    pub hide_completed_explore_requests: bool,  // Hide completed explore requests
+    pub explore_type_filter: Option<FileType>,  // Only list advertised files of this type
+    pub explore_request_sort: RequestSort,      // Order of the explore requests
+    pub ping_before_explore: bool,              // Ping a service before queuing an explore request
+    pub download_duplicate_policy: DuplicatePolicy, // What adding an existing download request does
+    pub explore_duplicate_policy: DuplicatePolicy,  // What adding an existing explore request does
+    pub explore_mode: ExploreMode,              // Socket mode new explore requests are sent in
     pub expanded_requests: HashSet<String>,     // IDs of explore requests with expanded file lists
+
+    // Serving Tab state
+    pub serve_events: Vec<ServeEvent>,          // Files sent to peers, oldest first
+    pub bytes_served: u64,                      // Bytes sent to peers since startup, including forgotten events
+    pub serving_today_only: bool,               // Only list files served today
+    pub serving_message: String,                // Message displayed in Serving tab
+    pub serving_message_time: Option<Instant>,  // Timestamp for serving message
+    pub serving_popup_message: String,          // Popup message for Serving
+    pub serving_popup_message_time: Option<Instant>, // Popup timestamp
 }
 
 impl Default for FileSharingApp {
@@ -117,28 +318,78 @@ impl Default for FileSharingApp {
             start_time: Some(SystemTime::now()),    // Current system time
             active_tab: Tab::Share,                 // Default to Share tab
             theme: Theme::Dark,                     // Default to Dark theme
+            offline: false,                         // Connect to the mixnet at startup
             serving_addr: String::new(),            // Empty server address
+            identity_addrs: BTreeMap::new(),        // Only the default identity at startup
+            new_identity_name: String::new(),       // Nothing typed yet
+            confirm_new_identity: false,            // No confirmation pending
             download_socket_mode: SocketMode::Anonymous, // Default to Anonymous mode
+            pending_socket_mode: None,              // No mode switch pending
             advertise_mode: false,                  // Default: advertise mode off
+            advertise_window: None,                 // Advertise at any hour
+            advertise_window_silent: false,         // Outside the window, explorers learn why the list is empty
+            link_includes_hash: false,              // Plain service::filename links
+            watch_shared_files: true,               // Keep hashes in step with the files
+            readvertise_on_change: false,           // Explorers are only notified on request
+            push_saved_peers_only: false,           // Any recent explorer may be notified
+            advertise_max_delay_ms: 0,              // Answer at once
+            advertise_peer_cap: MAX_ADVERTISE_RESPONSES, // Five lists per peer per window
+            advertise_rate: AdvertiseRate::default(), // No advertise traffic yet
+            max_concurrent_uploads: 4,              // A few transfers at once; one large file cannot starve the rest
+            max_requests_per_minute: MAX_REQUESTS_PER_MINUTE, // Plenty for retries and batches, not for a flood
+            requests_dropped: 0,                    // No requests dropped yet
+            compress_uploads: true,                 // Fewer bytes over the mixnet for text, logs and code
             debug_logging: false,                   // Default: debug logging off
+            network_config: NetworkConfig::default(), // 300ms serving, 200ms send, 100ms process
+            show_network_window: false,             // Hide network settings window
+            show_shortcuts_window: false,           // Hide keyboard shortcuts help
+            focus_search: false,                    // Nothing to focus
             show_settings_sidebar: false,           // Hide settings sidebar
+            show_diagnostics_window: false,         // Hide diagnostics window
+            diagnostics_redact_addresses: true,     // Mask addresses by default
+            diagnostics_redact_paths: true,         // Mask paths by default
+            wipe_options: None,                     // No wipe pending
+            pending_clear: None,                    // Nothing to clear
+            wipe_confirmation: String::new(),       // Nothing typed
+            unlock_passphrase: String::new(),       // Nothing typed
+            unlock_dismissed: false,                // Ask at startup
+            unlock_forget: false,                   // Keep saved state
+            show_vault_window: false,               // Hide encryption window
+            vault_passphrase: String::new(),        // Nothing typed
+            vault_passphrase_confirm: String::new(), // Nothing typed
 
             // Share Tab state
             shareable_files: Vec::new(),            // No shareable files
+            collections: Vec::new(),                // No collections until restored
+            new_collection_name: String::new(),     // Empty collection name
             share_message: String::new(),           // Empty share message
             share_message_time: None,               // No share message timestamp
             share_popup_message: String::new(),     // Empty share popup message
             share_popup_message_time: None,         // No share popup timestamp
             hide_inactive: false,                   // Show all files by default
+            share_type_filter: None,                // Every type
+            share_sort: ShareSort::Listed,          // Order the files were added in
+            share_recent_only: false,               // Files added at any time
             show_share_settings_sidebar: false,     // Hide settings sidebar in Share tab
+            pending_sensitive_files: Vec::new(),    // No files awaiting confirmation
+            pending_folder_removal: None,           // No folder removal pending
+            removed_files_undo: Vec::new(),         // Nothing to undo
+            peer_stats: HashMap::new(),             // Loaded from disk at startup
+            recent_explorers: HashMap::new(),       // Nobody has explored us yet
+            address_book: HashMap::new(),           // Loaded in restore_persisted
+            show_peers_window: false,               // Hide peers window
+            peer_sort: PeerSort::LastSeen,          // Most recently seen first
+            peer_sort_descending: true,             // Descending order
+            show_audit_window: false,               // Hide audit log window
+            audit_filter: AuditFilter::default(),   // Last day, every kind
+            audit_page: 0,                          // First page
 
             // Download Tab state
-            download_dir: {
-                let dir = PathBuf::from("downloads");
-                std::fs::create_dir_all(&dir).expect("Failed to create default download directory");
-                dir
-            },
+            download_dir: PathBuf::from("downloads"), // Created at startup by prepare_download_dir
+            download_dir_empty: true,               // Checked at startup by check_download_dir
             requested_files: Vec::new(),            // Empty download requests
+            download_history: Vec::new(),           // Loaded from disk at startup
+            history_checked: None,                  // Checked on the first frame
             download_message: String::new(),        // Empty download message
             download_message_time: None,            // No download message timestamp
             download_popup_message: String::new(),  // Empty download popup message
@@ -148,9 +399,24 @@ impl Default for FileSharingApp {
             show_runtime_downloads: false,          // Don't filter by runtime
             hide_all_downloads: false,              // Don't hide downloads
             search_query: String::new(),            // Empty search query
+            selected_share: None,                   // Nothing selected
+            allowlist_inputs: HashMap::new(),       // Nothing typed yet
             download_url: String::new(),            // Empty download URL
+            link_preview: None,                     // Nothing previewed
             show_download_settings: false,          // Hide download settings
             show_download_requests_sidebar: false,  // Hide requests sidebar
+            max_concurrent_downloads: 0,            // No concurrency cap
+            request_timeout_secs: 120,              // Two minutes without an ACK
+            bundle_mode: BundleMode::Loose,         // One file per download
+            collision_policy: CollisionPolicy::Rename, // Keep both files
+            upload_limit: None,                     // No upload cap
+            backoff: Backoff::default(),            // 5s doubling to 60s, 3 retries
+            max_send_attempts: 10,                  // About eight minutes of failed sends
+            chunk_window: DEFAULT_CHUNK_WINDOW,     // 2 MiB of 256 KiB chunks in flight
+            chunk_size_bounds: (MIN_FILE_CHUNK_SIZE, FILE_CHUNK_SIZE), // Adapt over the whole range
+            extra_surbs: 10,                        // Room for the ACK and the file reply
+            prefer_compression: true,               // The mixnet is slow; trade CPU for fewer bytes
+            verify_results: Arc::new(Mutex::new(HashMap::new())), // No verify results
 
             // Download Requests Tab state
             download_requests_message: String::new(), // Empty DownloadRequests message
@@ -161,6 +427,7 @@ impl Default for FileSharingApp {
             show_accepted_requests: false,          // Hide accepted filter
             show_completed_requests: false,         // Hide completed filter
             hide_all_requests: false,               // Don't hide requests
+            download_request_sort: RequestSort::Priority, // Grouped by priority
 
             // Explorer Tab state
             explore_address: String::new(),         // Empty peer address
@@ -170,10 +437,27 @@ impl Default for FileSharingApp {
             explore_popup_message: String::new(),   // Empty explorer popup message
             explore_popup_message_time: None,       // No explorer popup timestamp
             explore_search_query: String::new(),    // Empty explorer search query
+            explore_flat_results: false,            // Matches grouped by service
             hide_all_explore_requests: false,       // Don't hide requests
             show_all_explore_requests: true,        // Show all requests
             show_accepted_explore_requests: false,  // Hide accepted requests filter
+            hide_completed_explore_requests: false, // Show completed requests
+            explore_type_filter: None,              // Every type
+            explore_request_sort: RequestSort::Recency, // Most recent first
+            ping_before_explore: false,             // Off: pinging sends an extra message to the service
+            download_duplicate_policy: DuplicatePolicy::Reject, // Keep the existing request
+            explore_duplicate_policy: DuplicatePolicy::Reject,  // Keep the existing request
+            explore_mode: ExploreMode::Downloads,   // Follow the download socket
             expanded_requests: HashSet::new(),      // Empty set for expanded request IDs
+
+            // Serving Tab state
+            serve_events: Vec::new(),               // Nothing served yet
+            bytes_served: 0,                        // Nothing served yet
+            serving_today_only: false,              // Show every event
+            serving_message: String::new(),         // Empty serving message
+            serving_message_time: None,             // No serving message timestamp
+            serving_popup_message: String::new(),   // Empty serving popup message
+            serving_popup_message_time: None,       // No serving popup timestamp
         }
     }
 }
@@ -182,16 +466,472 @@ impl FileSharingApp {
     define_tab_messages!(share, 3.0, 5.0);
     define_tab_messages!(download, 3.0, 5.0);
     define_tab_messages!(explore, 3.0, 5.0);
+    define_tab_messages!(serving, 3.0, 5.0);
+
+    /// Copy of the state for the redraw while the app lock is busy (see AppWrapper in main.rs).
+    /// The long lists are copied only for the tab or window that shows them; the others stay empty
+    /// in the copy, which is only ever drawn read-only.
+    pub fn snapshot(&mut self) -> Self {
+        let first_run = self.is_first_run();
+        let shareable_files = std::mem::take(&mut self.shareable_files);
+        let collections = std::mem::take(&mut self.collections);
+        let removed_files_undo = std::mem::take(&mut self.removed_files_undo);
+        let recent_explorers = std::mem::take(&mut self.recent_explorers);
+        let download_history = std::mem::take(&mut self.download_history);
+        let address_book = std::mem::take(&mut self.address_book);
+        let serve_events = std::mem::take(&mut self.serve_events);
+        let peer_stats = std::mem::take(&mut self.peer_stats);
+
+        let mut copy = self.clone();
+        // The copy's lists may be empty without this being a first run
+        copy.download_dir_empty &= first_run;
+        match self.active_tab {
+            Tab::Share => {
+                copy.shareable_files = shareable_files.clone();
+                copy.collections = collections.clone();
+                copy.removed_files_undo = removed_files_undo.clone();
+                copy.recent_explorers = recent_explorers.clone();
+            }
+            Tab::Download => copy.download_history = download_history.clone(),
+            Tab::Explore => copy.address_book = address_book.clone(),
+            Tab::Serving => {
+                copy.serve_events = serve_events.clone();
+                copy.peer_stats = peer_stats.clone();
+            }
+        }
+        if self.show_peers_window {
+            copy.peer_stats = peer_stats.clone();
+        }
+
+        self.shareable_files = shareable_files;
+        self.collections = collections;
+        self.removed_files_undo = removed_files_undo;
+        self.recent_explorers = recent_explorers;
+        self.download_history = download_history;
+        self.address_book = address_book;
+        self.serve_events = serve_events;
+        self.peer_stats = peer_stats;
+        copy
+    }
+
+    /// Whether the user has nothing yet: no shares, collections, requests, peer history
+    /// or downloaded files. The tabs show first-run guidance instead of empty lists while this holds.
+    /// The download directory is not read here but in `check_download_dir`, as this runs every frame.
+    pub fn is_first_run(&self) -> bool {
+        self.shareable_files.is_empty()
+            && self.collections.is_empty()
+            && self.requested_files.is_empty()
+            && self.explore_requests.is_empty()
+            && self.peer_stats.is_empty()
+            && self.download_history.is_empty()
+            && self.download_dir_empty
+    }
+
+    /// Looks whether the download directory holds anything; called at startup and when it changes.
+    pub fn check_download_dir(&mut self) {
+        self.download_dir_empty = std::fs::read_dir(&self.download_dir).map_or(true, |mut entries| entries.next().is_none());
+    }
+
+    /// Address of the serving identity called `name`; empty while it starts or if it does not exist
+    pub fn serving_addr_of(&self, name: &str) -> &str {
+        if name == DEFAULT_IDENTITY {
+            &self.serving_addr
+        } else {
+            self.identity_addrs.get(name).map(String::as_str).unwrap_or_default()
+        }
+    }
+
+    /// Records the address of the serving identity called `name`
+    pub fn set_serving_addr(&mut self, name: &str, addr: String) {
+        if name == DEFAULT_IDENTITY {
+            self.serving_addr = addr;
+        } else {
+            self.identity_addrs.insert(name.to_string(), addr);
+        }
+    }
+
+    /// Names of the serving identities, the default first
+    pub fn identity_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_IDENTITY.to_string()).chain(self.identity_addrs.keys().cloned()).collect()
+    }
+
+    /// Loads the peer history, download history, view selections, audit log, shared files and
+    /// collections saved by a previous run. Loads nothing while the vault is locked; called again
+    /// once it is unlocked.
+    pub fn restore_persisted(&mut self) {
+        let peer_stats: HashMap<String, PeerStats> = storage::load(PEERS_FILE).unwrap_or_default();
+        for (peer, stats) in peer_stats {
+            self.peer_stats.entry(peer).or_insert(stats);
+        }
+        // Downloads finished before the history was loaded come after the saved ones
+        if let Some(saved) = storage::load::<Vec<DownloadRecord>>(HISTORY_FILE) {
+            let finished = std::mem::replace(&mut self.download_history, saved);
+            for record in finished {
+                history::record(&mut self.download_history, record);
+            }
+            history::check_all(&mut self.download_history);
+        }
+        let address_book: HashMap<String, SavedService> = storage::load(ADDRESS_BOOK_FILE).unwrap_or_default();
+        for (addr, service) in address_book {
+            self.address_book.entry(addr).or_insert(service);
+        }
+        if let Some(view) = storage::load(VIEW_STATE_FILE) {
+            self.apply_view_state(view);
+        }
+        audit::load();
+        self.load_shares();
+        collection::restore(self);
+    }
+
+    /// Creates the download directory. If that fails (e.g. the working directory is read-only),
+    /// falls back to a NymShare folder in the OS downloads directory and says so, rather than
+    /// stopping the app; downloads recreate whichever directory is used if it goes missing.
+    pub fn prepare_download_dir(&mut self) {
+        let Err(e) = std::fs::create_dir_all(&self.download_dir) else { return; };
+        warn!("Cannot create download directory {}: {}", self.download_dir.display(), e);
+        let fallback = dirs::download_dir()
+            .map(|dir| dir.join("NymShare"))
+            .filter(|dir| std::fs::create_dir_all(dir).is_ok());
+        match fallback {
+            Some(dir) => {
+                self.set_popup_message(format!(
+                    "Cannot create download directory {} ({}); saving downloads to {} instead",
+                    self.download_dir.display(), e, dir.display()
+                ));
+                self.download_dir = dir;
+            }
+            None => self.set_popup_message(format!(
+                "Cannot create download directory {} ({}); choose another one in the Download settings",
+                self.download_dir.display(), e
+            )),
+        }
+    }
+
+    /// Saves the download history.
+    pub fn save_history(&self) {
+        if let Err(e) = storage::save(HISTORY_FILE, &self.download_history) {
+            warn!("Failed to save download history: {}", e);
+        }
+    }
+
+    /// Saves the address book.
+    pub fn save_address_book(&self) {
+        if let Err(e) = storage::save(ADDRESS_BOOK_FILE, &self.address_book) {
+            warn!("Failed to save the address book: {}", e);
+        }
+    }
+
+    /// Saves the share list so it is back on the next launch.
+    pub fn save_shares(&self) {
+        let saved: Vec<SavedShare> = self.shareable_files.iter().map(SavedShare::of).collect();
+        if let Err(e) = storage::save(SHARES_FILE, &saved) {
+            warn!("Failed to save shared files: {}", e);
+        }
+    }
+
+    /// Loads the share list saved by a previous run, skipping files already listed.
+    /// Files that can no longer be shared (moved, deleted, unreadable) are dropped with a warning.
+    pub fn load_shares(&mut self) {
+        let Some(saved) = storage::load::<Vec<SavedShare>>(SHARES_FILE) else { return; };
+        for share in saved {
+            if self.shareable_files.iter().any(|f| f.path == share.path) {
+                continue;
+            }
+            let path = share.path.clone();
+            match share.restore() {
+                Ok(file) => self.shareable_files.push(file),
+                Err(e) => warn!("Dropping saved share '{}': {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Adds a file to the share list, skipping files that are already listed, and starts hashing it.
+    /// Files in sensitive locations are queued for a "share anyway" confirmation
+    /// instead of being rejected outright.
+    ///
+    /// Returns Ok(true) if the file was added.
+    pub fn add_share(&mut self, path: PathBuf) -> Result<bool, ShareableError> {
+        if self.shareable_files.iter().any(|f| f.path == path) {
+            return Ok(false);
+        }
+
+        match Shareable::new(path) {
+            Ok(s) => {
+                start_hash(s.path.clone());
+                self.shareable_files.push(s);
+                self.save_shares();
+                Ok(true)
+            }
+            Err(ShareableError::SensitiveLocation(path)) => {
+                if !self.pending_sensitive_files.contains(&path) {
+                    self.pending_sensitive_files.push(path);
+                }
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Shares every file below a folder (see Shareable::from_dir), skipping files already listed.
+    ///
+    /// Returns how many files were added.
+    pub fn add_share_dir(&mut self, dir: &Path) -> Result<usize, String> {
+        let mut added = 0;
+        for file in Shareable::from_dir(dir)? {
+            if self.shareable_files.iter().any(|f| f.path == file.path) {
+                continue;
+            }
+            start_hash(file.path.clone());
+            self.shareable_files.push(file);
+            added += 1;
+        }
+        if added > 0 {
+            self.save_shares();
+        }
+        Ok(added)
+    }
+
+    /// Returns the theme, tab and filter selections to persist.
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            theme: self.theme,
+            active_tab: self.active_tab.clone(),
+            hide_inactive: self.hide_inactive,
+            share_type_filter: self.share_type_filter,
+            share_sort: self.share_sort,
+            share_recent_only: self.share_recent_only,
+            show_all_downloads: self.show_all_downloads,
+            show_today_downloads: self.show_today_downloads,
+            show_runtime_downloads: self.show_runtime_downloads,
+            hide_all_downloads: self.hide_all_downloads,
+            show_all_requests: self.show_all_requests,
+            show_accepted_requests: self.show_accepted_requests,
+            show_completed_requests: self.show_completed_requests,
+            hide_all_requests: self.hide_all_requests,
+            download_request_sort: self.download_request_sort,
+            hide_all_explore_requests: self.hide_all_explore_requests,
+            show_all_explore_requests: self.show_all_explore_requests,
+            show_accepted_explore_requests: self.show_accepted_explore_requests,
+            hide_completed_explore_requests: self.hide_completed_explore_requests,
+            explore_request_sort: self.explore_request_sort,
+            explore_type_filter: self.explore_type_filter,
+            explore_flat_results: self.explore_flat_results,
+        }
+    }
+
+    /// Restores theme, tab and filter selections saved by a previous run.
+    pub fn apply_view_state(&mut self, state: ViewState) {
+        let ViewState {
+            theme,
+            active_tab,
+            hide_inactive,
+            share_type_filter,
+            share_sort,
+            share_recent_only,
+            show_all_downloads,
+            show_today_downloads,
+            show_runtime_downloads,
+            hide_all_downloads,
+            show_all_requests,
+            show_accepted_requests,
+            show_completed_requests,
+            hide_all_requests,
+            download_request_sort,
+            hide_all_explore_requests,
+            show_all_explore_requests,
+            show_accepted_explore_requests,
+            hide_completed_explore_requests,
+            explore_request_sort,
+            explore_type_filter,
+            explore_flat_results,
+        } = state;
+        self.theme = theme;
+        self.active_tab = active_tab;
+        self.hide_inactive = hide_inactive;
+        self.share_type_filter = share_type_filter;
+        self.share_sort = share_sort;
+        self.share_recent_only = share_recent_only;
+        self.show_all_downloads = show_all_downloads;
+        self.show_today_downloads = show_today_downloads;
+        self.show_runtime_downloads = show_runtime_downloads;
+        self.hide_all_downloads = hide_all_downloads;
+        self.show_all_requests = show_all_requests;
+        self.show_accepted_requests = show_accepted_requests;
+        self.show_completed_requests = show_completed_requests;
+        self.hide_all_requests = hide_all_requests;
+        self.download_request_sort = download_request_sort;
+        self.hide_all_explore_requests = hide_all_explore_requests;
+        self.show_all_explore_requests = show_all_explore_requests;
+        self.show_accepted_explore_requests = show_accepted_explore_requests;
+        self.hide_completed_explore_requests = hide_completed_explore_requests;
+        self.explore_request_sort = explore_request_sort;
+        self.explore_type_filter = explore_type_filter;
+        self.explore_flat_results = explore_flat_results;
+    }
+
+    /// Applies all updates posted by background tasks since the last frame.
+    pub fn apply_pending_updates(&mut self) {
+        for update in APP_UPDATES.drain() {
+            match update {
+                AppUpdate::Message(msg) => self.set_message(msg),
+                AppUpdate::TabMessage(tab, msg) => self.set_tab_message(&tab, msg),
+                AppUpdate::ServingAddress(name, addr) => self.set_serving_addr(&name, addr),
+                AppUpdate::IdentityRemoved(name) => { self.identity_addrs.remove(&name); }
+                AppUpdate::DownloadUpdated(request) => {
+                    match self.requested_files.iter_mut().find(|r| r.request_id == request.request_id) {
+                        Some(existing) => *existing = request,
+                        None => self.requested_files.push(request),
+                    }
+                }
+                AppUpdate::DownloadsRemoved(ids) => self.requested_files.retain(|r| !ids.contains(&r.request_id)),
+                AppUpdate::DownloadRecorded(record) => {
+                    self.download_dir_empty = false;
+                    history::record(&mut self.download_history, record);
+                    self.save_history();
+                }
+                AppUpdate::Served(event) => {
+                    self.bytes_served = self.bytes_served.saturating_add(event.bytes);
+                    serving::record(&mut self.serve_events, event);
+                }
+                AppUpdate::ExploreUpdated(request) => {
+                    match self.explore_requests.iter_mut().find(|r| r.request_id == request.request_id) {
+                        Some(existing) => *existing = request,
+                        None => self.explore_requests.push(request),
+                    }
+                }
+                AppUpdate::ExploresRemoved(ids) => {
+                    self.explore_requests.retain(|r| !ids.contains(&r.request_id));
+                    for id in &ids {
+                        self.expanded_requests.remove(id);
+                    }
+                }
+                AppUpdate::LinkPreviewed(link, result) => {
+                    if let Some(preview) = self.link_preview.as_mut().filter(|p| p.link == link) {
+                        preview.result = Some(result);
+                    }
+                }
+                AppUpdate::HashComputed(path, cache) => {
+                    for f in self.shareable_files.iter_mut().filter(|f| f.path == path) {
+                        f.hash_cache = Some(cache.clone());
+                        f.refresh_disk_state();
+                    }
+                }
+                AppUpdate::SharedFilesChanged(changed, removed) => self.apply_file_changes(changed, removed),
+            }
+        }
+    }
+
+    /// Takes in shared files changed on disk: stores their new hashes and stops sharing deleted ones.
+    /// Recent explorers are notified when the catalog changed and the user asked for it.
+    fn apply_file_changes(&mut self, changed: Vec<(PathBuf, HashCache)>, removed: Vec<PathBuf>) {
+        let mut catalog_changed = false;
+        for (path, cache) in changed {
+            for f in self.shareable_files.iter_mut().filter(|f| f.path == path) {
+                catalog_changed |= f.is_active() && f.hash_cache.as_ref().is_none_or(|c| c.digest != cache.digest);
+                f.hash_cache = Some(cache.clone());
+                f.refresh_disk_state();
+            }
+        }
+
+        let mut deactivated = 0;
+        for path in &removed {
+            for f in self.shareable_files.iter_mut().filter(|f| &f.path == path) {
+                f.refresh_disk_state();
+                if f.is_active() {
+                    f.deactivate();
+                    deactivated += 1;
+                }
+            }
+        }
+        if deactivated > 0 {
+            catalog_changed = true;
+            self.set_tab_message(&Tab::Share, format!("{} shared file(s) deleted from disk; stopped sharing them", deactivated));
+        }
+
+        let explorers = self.push_targets();
+        if catalog_changed && self.readvertise_on_change && self.advertise_mode && !self.offline && !explorers.is_empty() {
+            tokio::spawn(push_advertise(explorers, self.shareable_files.clone(), self.collections.clone()));
+        }
+    }
+
+    /// Recent explorers a catalog push goes to: all of them, or with push_saved_peers_only
+    /// just those in the Peers list or the address book.
+    pub fn push_targets(&self) -> HashMap<String, RecentExplorer> {
+        self.recent_explorers.iter()
+            .filter(|(addr, _)| {
+                !self.push_saved_peers_only || self.peer_stats.contains_key(*addr) || self.address_book.contains_key(*addr)
+            })
+            .map(|(addr, explorer)| (addr.clone(), explorer.clone()))
+            .collect()
+    }
 }
 
 impl eframe::App for FileSharingApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        self.apply_pending_updates();
+        self.handle_shortcuts(ctx);
+        if self.watch_shared_files {
+            watcher::sync(&self.shareable_files);
+        } else {
+            watcher::stop();
+        }
+        if self.history_checked.is_none_or(|at| at.elapsed() >= history::CHECK_INTERVAL) {
+            history::check_all(&mut self.download_history);
+            self.history_checked = Some(Instant::now());
+        }
+        self.render(ctx);
+    }
+}
+
+impl FileSharingApp {
+    /// Applies keyboard shortcuts before the panels are drawn; F1 lists them.
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        let previous_tab = self.active_tab.clone();
+        let tab_keys = [(Key::Num1, Tab::Share), (Key::Num2, Tab::Download), (Key::Num3, Tab::Explore), (Key::Num4, Tab::Serving)];
+        for (key, tab) in tab_keys {
+            if ctx.input_mut(|i| i.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, key))) {
+                self.active_tab = tab;
+            }
+        }
+        if self.active_tab != previous_tab {
+            self.close_sidebars();
+        }
+
+        // The Serving tab has no search box to focus
+        if ctx.input_mut(|i| i.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, Key::F))) {
+            self.focus_search = self.active_tab != Tab::Serving;
+        }
+
+        if ctx.input(|i| i.key_pressed(Key::F1)) {
+            self.show_shortcuts_window = !self.show_shortcuts_window;
+        }
+        if self.show_shortcuts_window && ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.show_shortcuts_window = false;
+        }
+
+        // Delete removes the selected share, unless a text field is taking the key
+        if self.active_tab == Tab::Share && !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(Key::Delete)) {
+            let index = self.selected_share.as_ref().and_then(|path| self.shareable_files.iter().position(|f| &f.path == path));
+            if let Some(index) = index {
+                let removed = remove_share(self, index);
+                self.set_message(format!("Removed {}", removed.file_name().unwrap_or_default()));
+            }
+        }
+    }
+
+    /// Closes the sidebars of every tab.
+    fn close_sidebars(&mut self) {
+        self.show_share_settings_sidebar = false;
+        self.show_download_requests_sidebar = false;
+        self.show_settings_sidebar = false;
+    }
+
+    /// Draws the whole UI from the current state.
+    /// Kept apart from `update` so a snapshot can be drawn without consuming posted updates.
+    pub fn render(&mut self, ctx: &Context) {
         let previous_tab = self.active_tab.clone();
         // Apply theme
-        ctx.set_visuals(match self.theme {
-            Theme::Light => Visuals::light(),
-            Theme::Dark => Visuals::dark(),
-        });
+        ctx.set_visuals(self.theme.visuals(ctx));
 
         // Top navigation panel
         TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -209,23 +949,72 @@ impl eframe::App for FileSharingApp {
                 if ui.selectable_label(self.active_tab == Tab::Explore, "🔎 Explore").clicked() {
                     self.active_tab = Tab::Explore;
                 }
+                if ui.selectable_label(self.active_tab == Tab::Serving, "📡 Serving").clicked() {
+                    self.active_tab = Tab::Serving;
+                }
+
+                if self.offline {
+                    ui.separator();
+                    ui.colored_label(Color32::ORANGE, "⛔ Offline")
+                        .on_hover_text("Network activity is disabled; requests stay queued until you go online");
+                }
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("🐞 Diagnostics").on_hover_text("Collect diagnostics for a bug report").clicked() {
+                        self.show_diagnostics_window = !self.show_diagnostics_window;
+                    }
+
+                    if ui.button("👥 Peers").on_hover_text("Show what each requester has downloaded").clicked() {
+                        self.show_peers_window = !self.show_peers_window;
+                    }
+
+                    if ui.button("📜 Audit Log").on_hover_text("Browse protocol activity").clicked() {
+                        self.show_audit_window = !self.show_audit_window;
+                    }
+
+                    if ui.button("📡 Network").on_hover_text("Tune how often the managers poll the mixnet").clicked() {
+                        self.show_network_window = !self.show_network_window;
+                    }
+
+                    if ui.button("🔒 Encryption").on_hover_text("Encrypt saved settings and history with a passphrase").clicked() {
+                        self.show_vault_window = !self.show_vault_window;
+                    }
+
                     if ui
-                        .button(match self.theme {
-                            Theme::Light => "🌙 Dark Mode",
-                            Theme::Dark => "☀️ Light Mode",
-                        })
+                        .button(self.theme.next().label())
+                        .on_hover_text("Switch between light, dark and the system theme")
                         .clicked()
                     {
-                        self.theme = match self.theme {
-                            Theme::Light => Theme::Dark,
-                            Theme::Dark => Theme::Light,
-                        };
-                        ctx.set_visuals(match self.theme {
-                            Theme::Light => Visuals::light(),
-                            Theme::Dark => Visuals::dark(),
-                        });
+                        self.theme = self.theme.next();
+                        ctx.set_visuals(self.theme.visuals(ctx));
+                    }
+
+                    let (label, hint) = if self.offline {
+                        ("🌐 Go Online", "Connect to the mixnet and resume queued requests")
+                    } else {
+                        ("🔌 Go Offline", "Stop sending and receiving; sockets stay open")
+                    };
+                    if ui.button(label).on_hover_text(hint).clicked() {
+                        self.offline = !self.offline;
+                    }
+
+                    if ui.button(egui::RichText::new("🚨 Panic Wipe").color(Color32::RED))
+                        .on_hover_text("Stop everything and erase requests, shares, identity, config and logs")
+                        .clicked()
+                    {
+                        self.wipe_options = Some(WipeOptions::default());
+                        self.wipe_confirmation.clear();
+                    }
+
+                    if storage::is_wiped()
+                        && ui.button("💾 Resume Saving")
+                            .on_hover_text("The config was wiped and nothing is written to disk; save settings, shares and history again")
+                            .clicked()
+                    {
+                        storage::set_wiped(false);
+                        self.save_shares();
+                        self.save_history();
+                        self.set_message("Saving to disk again".to_string());
                     }
                 });
             });
@@ -234,9 +1023,7 @@ impl eframe::App for FileSharingApp {
 
         // Close all sidebars if the tab has changed
         if self.active_tab != previous_tab {
-            self.show_share_settings_sidebar = false;
-            self.show_download_requests_sidebar = false;
-            self.show_settings_sidebar = false;
+            self.close_sidebars();
         }
 
 
@@ -247,12 +1034,24 @@ impl eframe::App for FileSharingApp {
                 Tab::Share => render_share_tab(self, ui),
                 Tab::Download => render_download_tab(self, ui),
                 Tab::Explore => render_explore_tab(self, ui), 
+                Tab::Serving => render_serving_tab(self, ui),
             }
         });
 
+        render_diagnostics_window(self, ctx);
+        render_peers_window(self, ctx);
+        render_audit_window(self, ctx);
+        render_wipe_confirmation(self, ctx);
+        render_clear_confirmation(self, ctx);
+        render_unlock_window(self, ctx);
+        render_vault_window(self, ctx);
+        render_network_window(self, ctx);
+        render_shortcuts_window(self, ctx);
+
         self.render_share_popup(ctx);
         self.render_download_popup(ctx);
         self.render_explore_popup(ctx);
+        self.render_serving_popup(ctx);
 
 
         ctx.request_repaint();
@@ -262,5 +1061,6 @@ impl eframe::App for FileSharingApp {
 define_generic_messages!(
     (Share, share),
     (Download, download),
-    (Explore, explore)
+    (Explore, explore),
+    (Serving, serving)
 );
\ No newline at end of file
@@ -0,0 +1,275 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use log::{info, warn};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// Standard library
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local
+use crate::app::post_tab_message;
+use crate::helper::{format_size, unused_path};
+use crate::request::DownLoadRequest;
+use crate::theme::Tab;
+
+
+/// Prefix of the hidden directory the members of a zip download are staged in.
+const STAGING_PREFIX: &str = ".nymshare-zip-";
+
+/// How the files of a collection or manifest are saved.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum BundleMode {
+    /// Each file on its own in the download directory.
+    #[default]
+    Loose,
+    /// One zip in the download directory, filled as each file completes.
+    Zip,
+}
+
+impl BundleMode {
+    pub const ALL: [BundleMode; 2] = [BundleMode::Loose, BundleMode::Zip];
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            BundleMode::Loose => "📄 Loose files",
+            BundleMode::Zip => "🗜 Zip archive",
+        }
+    }
+}
+
+/// Creates a fresh staging directory under `download_dir` for the members of one zip download.
+pub fn create_staging_dir(download_dir: &Path) -> io::Result<PathBuf> {
+    let dir = download_dir.join(format!("{}{}", STAGING_PREFIX, Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// File name of the archive for a bundle called `name`, safe to use as a path.
+pub fn archive_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect();
+    let stem = stem.trim_matches('.');
+    let stem = if stem.is_empty() { "download" } else { stem };
+    if stem.to_lowercase().ends_with(".zip") {
+        stem.to_string()
+    } else {
+        format!("{}.zip", stem)
+    }
+}
+
+/// A zip being written while its members download.
+pub struct ArchiveWriter {
+    /// Where the archive is written.
+    path: PathBuf,
+
+    /// Open archive; entries are appended as members complete.
+    zip: ZipWriter<File>,
+
+    /// Request IDs of the members already added or noted as failed.
+    handled: HashSet<String>,
+
+    /// Number of files stored in the archive.
+    added: usize,
+
+    /// Members left out, with the reason.
+    failed: Vec<(String, String)>,
+
+    /// Members stored that completed with a warning, with the warning.
+    warnings: Vec<(String, String)>,
+}
+
+impl ArchiveWriter {
+    /// Creates `name` in `dir`, numbering it if a file of that name already exists.
+    fn create(dir: &Path, name: &str) -> io::Result<Self> {
+        let path = unused_path(&dir.join(name));
+        let file = File::create(&path)?;
+        info!("Writing archive '{}'", path.display());
+        Ok(Self {
+            path,
+            zip: ZipWriter::new(file),
+            handled: HashSet::new(),
+            added: 0,
+            failed: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Stores the file at `source` as the entry `name`.
+    fn add(&mut self, name: &str, source: &Path) -> io::Result<()> {
+        let mut file = File::open(source)?;
+        let size = file.metadata()?.len();
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(size >= u32::MAX as u64);
+        self.zip.start_file(name, options)?;
+        io::copy(&mut file, &mut self.zip)?;
+        self.added += 1;
+        Ok(())
+    }
+
+    /// Writes the central directory, listing failed members in the archive comment.
+    fn finish(mut self) -> Result<PathBuf, String> {
+        let mut comment = format!("Downloaded with NymShare: {} file(s)", self.added);
+        if !self.failed.is_empty() {
+            comment.push_str(&format!("\nSkipped {} file(s):", self.failed.len()));
+            for (name, reason) in &self.failed {
+                comment.push_str(&format!("\n- {}: {}", name, reason));
+            }
+        }
+        if !self.warnings.is_empty() {
+            comment.push_str(&format!("\nStored {} file(s) with warnings:", self.warnings.len()));
+            for (name, warning) in &self.warnings {
+                comment.push_str(&format!("\n- {}: {}", name, warning));
+            }
+        }
+        self.zip.set_comment(comment);
+        self.zip.finish().map_err(|e| e.to_string())?;
+        Ok(self.path)
+    }
+}
+
+/// Archives being written by download_manager.
+#[derive(Default)]
+pub struct Archives {
+    /// Open archives, keyed by the staging directory of their members.
+    open: HashMap<PathBuf, ArchiveWriter>,
+
+    /// Staging directories whose archive could not be created; their members are saved loose.
+    abandoned: HashSet<PathBuf>,
+
+    /// Staging directories whose archive is finished; members still listed are left alone.
+    closed: HashSet<PathBuf>,
+}
+
+/// Moves finished members of zip downloads into their archives and closes every archive
+/// with no member left in flight. Failed members are skipped and noted in the archive comment.
+/// Blocking; download_manager calls it inside `block_in_place`.
+pub fn collect(downloads: &[DownLoadRequest], archives: &mut Archives) {
+    let mut in_flight: HashSet<&Path> = HashSet::new();
+
+    for req in downloads {
+        let (Some(name), Some(staging)) = (&req.archive, &req.save_dir) else { continue; };
+        if !req.is_done() {
+            in_flight.insert(staging.as_path());
+            continue;
+        }
+        let dir = staging.parent().unwrap_or(staging);
+        let source = staging.join(&req.filename);
+
+        if archives.closed.contains(staging) {
+            continue;
+        }
+        if !archives.abandoned.contains(staging) && !archives.open.contains_key(staging) {
+            match ArchiveWriter::create(dir, name) {
+                Ok(writer) => { archives.open.insert(staging.clone(), writer); }
+                Err(e) => {
+                    warn!("Cannot create archive '{}': {}", name, e);
+                    post_tab_message(Tab::Download, format!("Cannot create '{}': {}; saving the files loose", name, e));
+                    archives.abandoned.insert(staging.clone());
+                }
+            }
+        }
+
+        // Fall back to loose files when the archive cannot be written
+        if archives.abandoned.contains(staging) {
+            if !req.is_failed() && source.exists() {
+                // Files from a shared folder keep their subfolders
+                let target = unused_path(&dir.join(&req.filename));
+                if let Some(parent) = target.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                if let Err(e) = fs::rename(&source, target) {
+                    warn!("Cannot move '{}' out of '{}': {}", req.filename, staging.display(), e);
+                }
+            }
+            continue;
+        }
+        if archives.open.get(staging).is_some_and(|a| a.handled.contains(&req.request_id)) {
+            continue;
+        }
+        let Some(writer) = archives.open.get_mut(staging) else { continue; };
+        writer.handled.insert(req.request_id.clone());
+
+        if let Some(reason) = req.failure_reason() {
+            writer.failed.push((req.filename.clone(), reason.to_string()));
+        } else if let Err(e) = writer.add(&req.filename, &source) {
+            warn!("Cannot add '{}' to '{}': {}", req.filename, writer.path.display(), e);
+            writer.failed.push((req.filename.clone(), e.to_string()));
+        } else if let Some(warning) = req.warning() {
+            writer.warnings.push((req.filename.clone(), warning.to_string()));
+        }
+        let _ = fs::remove_file(&source);
+    }
+
+    // Close the archives whose members are all done or were removed
+    let abandoned: Vec<PathBuf> = archives
+        .abandoned
+        .iter()
+        .filter(|staging| !in_flight.contains(staging.as_path()))
+        .cloned()
+        .collect();
+    for staging in abandoned {
+        archives.abandoned.remove(&staging);
+        let _ = fs::remove_dir_all(&staging);
+        archives.closed.insert(staging);
+    }
+    let finished: Vec<PathBuf> = archives
+        .open
+        .keys()
+        .filter(|staging| !in_flight.contains(staging.as_path()))
+        .cloned()
+        .collect();
+    for staging in finished {
+        let Some(writer) = archives.open.remove(&staging) else { continue; };
+        let (added, failed) = (writer.added, writer.failed.len());
+        match writer.finish() {
+            Ok(path) => {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                info!("Archive '{}' done: {} file(s), {} skipped", path.display(), added, failed);
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let mut status = format!("Saved {} file(s) to '{}' ({})", added, name, format_size(size));
+                if failed > 0 {
+                    status.push_str(&format!("; {} skipped, see the archive comment", failed));
+                }
+                post_tab_message(Tab::Download, status);
+            }
+            Err(e) => {
+                warn!("Cannot finish archive: {}", e);
+                post_tab_message(Tab::Download, format!("Cannot finish archive: {}", e));
+            }
+        }
+        let _ = fs::remove_dir_all(&staging);
+        archives.closed.insert(staging);
+    }
+}
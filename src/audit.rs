@@ -0,0 +1,275 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+use log::warn;
+
+// Standard library
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+// Local
+use crate::storage;
+use crate::vault;
+
+
+/// File in CONFIG_DIR the audit log is appended to, one JSON event per line.
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+/// Events kept in memory (and reloaded at startup); older ones are dropped.
+const MAX_EVENTS: usize = 10_000;
+
+/// Lines the audit file may grow past MAX_EVENTS before it is rewritten with the newest events.
+const FILE_SLACK: usize = 1_000;
+
+/// Kind of protocol activity recorded in the audit log.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AuditKind {
+    RequestReceived,
+    RequestSent,
+    Ack,
+    FileSent,
+    FileReceived,
+    Advertise,
+    Nack,
+    Error,
+}
+
+impl AuditKind {
+    pub const ALL: [AuditKind; 8] = [
+        AuditKind::RequestReceived,
+        AuditKind::RequestSent,
+        AuditKind::Ack,
+        AuditKind::FileSent,
+        AuditKind::FileReceived,
+        AuditKind::Advertise,
+        AuditKind::Nack,
+        AuditKind::Error,
+    ];
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditKind::RequestReceived => "Request received",
+            AuditKind::RequestSent => "Request sent",
+            AuditKind::Ack => "ACK",
+            AuditKind::FileSent => "File sent",
+            AuditKind::FileReceived => "File received",
+            AuditKind::Advertise => "Advertise",
+            AuditKind::Nack => "NACK",
+            AuditKind::Error => "Error",
+        }
+    }
+}
+
+/// Time window the audit log view is limited to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuditRange {
+    LastHour,
+    LastDay,
+    LastWeek,
+    All,
+}
+
+impl AuditRange {
+    pub const ALL: [AuditRange; 4] = [AuditRange::LastHour, AuditRange::LastDay, AuditRange::LastWeek, AuditRange::All];
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditRange::LastHour => "Last hour",
+            AuditRange::LastDay => "Last day",
+            AuditRange::LastWeek => "Last week",
+            AuditRange::All => "All time",
+        }
+    }
+
+    /// Returns the oldest time included in the range, if bounded.
+    fn since(&self, now: SystemTime) -> Option<SystemTime> {
+        let span = match self {
+            AuditRange::LastHour => Duration::from_secs(3600),
+            AuditRange::LastDay => Duration::from_secs(86400),
+            AuditRange::LastWeek => Duration::from_secs(7 * 86400),
+            AuditRange::All => return None,
+        };
+        now.checked_sub(span)
+    }
+}
+
+/// A single protocol event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub time: SystemTime,
+    pub kind: AuditKind,
+    pub command: String,
+    pub peer: String,
+    pub detail: String,
+}
+
+/// Filters applied when viewing the audit log.
+#[derive(Clone, Debug)]
+pub struct AuditFilter {
+    pub kind: Option<AuditKind>, // None = every kind
+    pub peer: String,            // Substring of the peer address
+    pub search: String,          // Substring of the command or detail
+    pub range: AuditRange,
+}
+
+impl Default for AuditFilter {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            peer: String::new(),
+            search: String::new(),
+            range: AuditRange::LastDay,
+        }
+    }
+}
+
+/// In-memory audit log and the number of lines in the audit file.
+#[derive(Default)]
+struct AuditLog {
+    events: VecDeque<AuditEvent>,   // Oldest first, at most MAX_EVENTS
+    file_lines: usize,              // Lines in AUDIT_FILE, to know when to compact it
+}
+
+static AUDIT_LOG: LazyLock<Mutex<AuditLog>> = LazyLock::new(|| Mutex::new(AuditLog::default()));
+
+/// Loads persisted events, keeping the newest MAX_EVENTS. Events recorded since startup
+/// (while the vault was locked) are kept after them.
+pub fn load() {
+    let Ok(data) = fs::read_to_string(storage::config_path(AUDIT_FILE)) else {
+        return;
+    };
+    let mut events: VecDeque<AuditEvent> = data
+        .lines()
+        .filter_map(vault::open_line)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    let mut log = AUDIT_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    events.append(&mut log.events);
+    if events.len() > MAX_EVENTS {
+        events.drain(..events.len() - MAX_EVENTS);
+    }
+    log.events = events;
+    log.file_lines = data.lines().count();
+}
+
+/// Records a protocol event and appends it to the audit file.
+pub fn record(kind: AuditKind, command: &str, peer: impl ToString, detail: impl Into<String>) {
+    let event = AuditEvent {
+        time: SystemTime::now(),
+        kind,
+        command: command.to_string(),
+        peer: peer.to_string(),
+        detail: detail.into(),
+    };
+
+    let mut log = AUDIT_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    match append(&event) {
+        Ok(true) => log.file_lines += 1,
+        Ok(false) => {}
+        Err(e) => warn!("Failed to persist audit event: {}", e),
+    }
+
+    log.events.push_back(event);
+    if log.events.len() > MAX_EVENTS {
+        log.events.pop_front();
+    }
+
+    if log.file_lines > MAX_EVENTS + FILE_SLACK {
+        match compact(&log.events) {
+            Ok(true) => log.file_lines = log.events.len(),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to compact the audit file: {}", e),
+        }
+    }
+}
+
+/// Appends one event to the audit file. Returns false when nothing may be written
+/// (after a wipe or while the vault is locked).
+fn append(event: &AuditEvent) -> std::io::Result<bool> {
+    if storage::is_wiped() {
+        return Ok(false);
+    }
+    let line = serde_json::to_string(event).map_err(std::io::Error::other)?;
+    let Some(line) = vault::seal_line(line) else { return Ok(false); };
+    fs::create_dir_all(storage::CONFIG_DIR)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(storage::config_path(AUDIT_FILE))?;
+    writeln!(file, "{}", line)?;
+    Ok(true)
+}
+
+/// Rewrites the audit file with only the events kept in memory, so it stops growing.
+/// Returns false when nothing may be written.
+fn compact(events: &VecDeque<AuditEvent>) -> std::io::Result<bool> {
+    if storage::is_wiped() {
+        return Ok(false);
+    }
+    let mut data = String::new();
+    for event in events {
+        let line = serde_json::to_string(event).map_err(std::io::Error::other)?;
+        let Some(line) = vault::seal_line(line) else { return Ok(false); };
+        data.push_str(&line);
+        data.push('\n');
+    }
+    storage::write_atomic(&storage::config_path(AUDIT_FILE), data.as_bytes())?;
+    Ok(true)
+}
+
+/// Forgets every event in memory; the audit file is left to the caller.
+pub fn clear() {
+    let mut log = AUDIT_LOG.lock().unwrap_or_else(|e| e.into_inner());
+    log.events.clear();
+    log.file_lines = 0;
+}
+
+/// Returns the events matching the filter, newest first.
+pub fn query(filter: &AuditFilter) -> Vec<AuditEvent> {
+    let since = filter.range.since(SystemTime::now());
+    let peer = filter.peer.to_lowercase();
+    let search = filter.search.to_lowercase();
+
+    AUDIT_LOG
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .events
+        .iter()
+        .rev()
+        .filter(|e| since.is_none_or(|t| e.time >= t))
+        .filter(|e| filter.kind.is_none_or(|k| e.kind == k))
+        .filter(|e| peer.is_empty() || e.peer.to_lowercase().contains(&peer))
+        .filter(|e| {
+            search.is_empty()
+                || e.command.to_lowercase().contains(&search)
+                || e.detail.to_lowercase().contains(&search)
+        })
+        .cloned()
+        .collect()
+}
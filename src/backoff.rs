@@ -0,0 +1,236 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Standard library
+use std::time::{Duration, Instant};
+
+
+/// Retry policy shared by socket creation, pings and unanswered requests.
+/// Attempt `n` (0-based) waits `initial * multiplier^n`, capped at `max`,
+/// then spread by up to `jitter` (a fraction) either way so peers do not retry in lockstep.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Backoff {
+    /// Delay after the first attempt.
+    pub initial: Duration,
+
+    /// Upper bound for any single delay, before jitter.
+    pub max: Duration,
+
+    /// Growth factor between consecutive delays (at least 1).
+    pub multiplier: f64,
+
+    /// Random spread applied to each delay, as a fraction between 0 and 1.
+    pub jitter: f64,
+
+    /// Attempts made after the first one before giving up.
+    pub retries: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(5),
+            max: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retries: 3,
+        }
+    }
+}
+
+impl Backoff {
+    /// Policy for requests the socket failed to send: 1s doubling to 60s.
+    /// The attempt limit is a setting of its own, so `retries` is unused.
+    pub const SEND_FAILURE: Backoff = Backoff {
+        initial: Duration::from_secs(1),
+        max: Duration::from_secs(60),
+        multiplier: 2.0,
+        jitter: 0.0,
+        retries: 0,
+    };
+
+    /// Attempts in all: the first one plus `retries`.
+    pub fn attempts(&self) -> u32 {
+        self.retries.saturating_add(1)
+    }
+
+    /// Delay before retrying after attempt `attempt` (0-based), without jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(attempt.min(i32::MAX as u32) as i32);
+        let secs = self.initial.as_secs_f64() * factor;
+        Duration::from_secs_f64(secs.min(self.max.as_secs_f64()))
+    }
+
+    /// `delay(attempt)` spread randomly by up to ±jitter.
+    pub fn jittered_delay(&self, attempt: u32) -> Duration {
+        let spread = self.jitter.clamp(0.0, 1.0) * (random_unit() * 2.0 - 1.0);
+        self.delay(attempt).mul_f64(1.0 + spread)
+    }
+}
+
+/// Retry state of one request under a [`Backoff`] policy.
+/// `next` is when the request may go out: after a send, when to resend it if unanswered;
+/// after a failed send, when to try again. None sends it right away.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Retry {
+    pub attempts: u32,          // Times the request went out
+    pub send_failures: u32,     // Sends in a row the socket failed; reset once one goes out
+    pub next: Option<Instant>,  // Earliest time the request goes out (again)
+}
+
+impl Retry {
+    /// Whether the request may go out at `now`.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.next.is_none_or(|t| t <= now)
+    }
+
+    /// Whether the request went out as often as `policy` allows.
+    pub fn is_exhausted(&self, policy: &Backoff) -> bool {
+        self.attempts >= policy.attempts()
+    }
+
+    /// Records a send that went out; it is resent after `policy`'s delay if unanswered.
+    pub fn sent(&mut self, policy: &Backoff, now: Instant) {
+        self.next = Some(now + policy.jittered_delay(self.attempts));
+        self.attempts += 1;
+        self.send_failures = 0;
+    }
+
+    /// Records a send the socket failed and backs off by [`Backoff::SEND_FAILURE`].
+    /// Returns false once `max_failures` sends failed in a row and the request should be given up.
+    pub fn send_failed(&mut self, max_failures: u32, now: Instant) -> bool {
+        self.send_failures += 1;
+        if self.send_failures >= max_failures {
+            return false;
+        }
+        self.next = Some(now + Backoff::SEND_FAILURE.delay(self.send_failures - 1));
+        true
+    }
+}
+
+/// Returns a uniformly distributed number in [0, 1).
+/// Drawn from a v4 UUID: the low 64 bits are random apart from the two variant bits.
+pub fn random_unit() -> f64 {
+    let bits = (Uuid::new_v4().as_u64_pair().1 << 2) >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(delays: impl Iterator<Item = Duration>) -> Vec<f64> {
+        delays.map(|d| d.as_secs_f64()).collect()
+    }
+
+    #[test]
+    fn delays_grow_by_the_multiplier_up_to_the_cap() {
+        let policy = Backoff::default();
+        assert_eq!(secs((0..7).map(|n| policy.delay(n))), [5.0, 10.0, 20.0, 40.0, 60.0, 60.0, 60.0]);
+
+        let send = Backoff::SEND_FAILURE;
+        assert_eq!(secs((0..8).map(|n| send.delay(n))), [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 60.0, 60.0]);
+
+        let slow = Backoff { multiplier: 1.5, ..Backoff::default() };
+        assert_eq!(secs((0..3).map(|n| slow.delay(n))), [5.0, 7.5, 11.25]);
+    }
+
+    #[test]
+    fn delays_stay_at_the_cap() {
+        let policy = Backoff::default();
+        for attempt in [10, 1_000, i32::MAX as u32, u32::MAX] {
+            assert_eq!(policy.delay(attempt), policy.max);
+        }
+
+        // A first delay above the cap is capped too
+        let policy = Backoff { initial: Duration::from_secs(90), ..Backoff::default() };
+        assert_eq!(policy.delay(0), policy.max);
+    }
+
+    #[test]
+    fn delays_never_shrink() {
+        let policy = Backoff { multiplier: 0.5, ..Backoff::default() };
+        assert_eq!(secs((0..4).map(|n| policy.delay(n))), [5.0; 4]);
+    }
+
+    #[test]
+    fn jitter_stays_within_its_spread() {
+        let policy = Backoff::default();
+        for _ in 0..1_000 {
+            let delay = policy.jittered_delay(2).as_secs_f64();
+            assert!((16.0..=24.0).contains(&delay), "{}", delay);
+        }
+
+        // No jitter leaves the delay as is; too much is clamped to ±100%
+        let exact = Backoff { jitter: 0.0, ..Backoff::default() };
+        assert_eq!(exact.jittered_delay(1), exact.delay(1));
+        let wild = Backoff { jitter: 5.0, ..Backoff::default() };
+        for _ in 0..1_000 {
+            assert!(wild.jittered_delay(1) <= wild.delay(1) * 2);
+        }
+    }
+
+    #[test]
+    fn attempts_are_the_first_plus_the_retries() {
+        assert_eq!(Backoff::default().attempts(), 4);
+        assert_eq!(Backoff { retries: 0, ..Backoff::default() }.attempts(), 1);
+        assert_eq!(Backoff { retries: u32::MAX, ..Backoff::default() }.attempts(), u32::MAX);
+    }
+
+    #[test]
+    fn retries_back_off_after_failed_sends() {
+        let now = Instant::now();
+        let mut retry = Retry::default();
+        assert!(retry.is_due(now));
+        assert!(retry.send_failed(3, now));
+        assert!(!retry.is_due(now));
+        assert!(retry.is_due(now + Duration::from_secs(1)));
+        assert!(retry.send_failed(3, now));
+        assert_eq!(retry.next, Some(now + Duration::from_secs(2)));
+        assert!(!retry.send_failed(3, now));
+
+        // A send that goes out resets the failures and waits for an answer
+        let policy = Backoff { jitter: 0.0, ..Backoff::default() };
+        let mut retry = Retry { send_failures: 2, ..Retry::default() };
+        retry.sent(&policy, now);
+        assert_eq!((retry.attempts, retry.send_failures), (1, 0));
+        assert_eq!(retry.next, Some(now + policy.initial));
+        for _ in 1..policy.attempts() {
+            assert!(!retry.is_exhausted(&policy));
+            retry.sent(&policy, now);
+        }
+        assert!(retry.is_exhausted(&policy));
+    }
+
+    #[test]
+    fn random_unit_is_in_range() {
+        for _ in 0..1_000 {
+            assert!((0.0..1.0).contains(&random_unit()));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+
+// External crates
+use tokio::sync::{mpsc, Mutex};
+
+// Standard library
+use std::sync::LazyLock;
+
+// Local
+use crate::request::{DownLoadRequest, ExploreRequest, Priority};
+
+
+/// Unbounded many-to-one channel between background tasks and the UI.
+/// Any task can post; a single consumer drains (UI, once per frame) or awaits (managers).
+pub struct MessageBus<T> {
+    sender: mpsc::UnboundedSender<T>,
+    receiver: Mutex<mpsc::UnboundedReceiver<T>>,
+}
+
+impl<T> Default for MessageBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MessageBus<T> {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self { sender, receiver: Mutex::new(receiver) }
+    }
+
+    /// Queues a message; never blocks.
+    pub fn post(&self, message: T) {
+        let _ = self.sender.send(message);
+    }
+
+    /// Takes every queued message without waiting.
+    /// Returns nothing if another consumer is reading at the moment.
+    pub fn drain(&self) -> Vec<T> {
+        let Ok(mut receiver) = self.receiver.try_lock() else { return Vec::new(); };
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Whether nothing is queued. Reports a non-empty bus while another consumer is reading.
+    pub fn is_empty(&self) -> bool {
+        self.receiver.try_lock().is_ok_and(|receiver| receiver.is_empty())
+    }
+
+    /// Waits for the next message. Cancel safe, so it can be used in `tokio::select!`.
+    pub async fn recv(&self) -> Option<T> {
+        self.receiver.lock().await.recv().await
+    }
+}
+
+
+/// Changes to the request queues owned by download_manager.
+/// The UI never edits its copy of the queues directly; it posts a command and
+/// download_manager answers with the updated request (see AppUpdate).
+#[derive(Clone, Debug)]
+pub enum ManagerCommand {
+    AddDownload(DownLoadRequest),               // Queue a download (ignored if the same file is already queued)
+    ResendDownload(String),                     // Send an unanswered download request again
+    RefreshDownload(String),                    // Reset a download request to queued
+    SetPriority(String, Priority),              // Reprioritize a download request
+    RemoveDownloads(Vec<String>),               // Drop download requests
+    CancelDownload(String),                     // Abandon a download request and its partial file
+    AddExplore(ExploreRequest),                 // Queue an explore request (ignored if the service is already explored)
+    ResendExplore(String),                      // Send an unanswered explore request again
+    RefreshExplore(String),                     // Reset an explore request to queued, dropping its list
+    LoadMorePages(String),                      // Fetch the next page of an explored list (ignored while one is loading)
+    RetryPage(String),                          // Ask again for a page whose reply went to a replaced socket
+    RemoveExplores(Vec<String>),                // Drop explore requests
+    FetchCollection(String, String),            // Ask an explored service for the members of a collection
+}
+
+/// Commands for download_manager, applied as soon as it picks them up.
+pub static MANAGER_COMMANDS: LazyLock<MessageBus<ManagerCommand>> = LazyLock::new(MessageBus::new);
+
+/// Posts a command to download_manager.
+pub fn post_command(command: ManagerCommand) {
+    MANAGER_COMMANDS.post(command);
+}
@@ -0,0 +1,324 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// Standard library
+use std::path::PathBuf;
+
+// Local
+use crate::app::FileSharingApp;
+use crate::archive::BundleMode;
+use crate::manifest::{Manifest, MANIFEST_EXTENSION};
+use crate::network::MAX_UPLOADS_LIMIT;
+use crate::theme::Tab;
+
+
+/// Largest accepted --max-concurrent value.
+const MAX_CONCURRENT_LIMIT: usize = 1000;
+
+/// Largest accepted --surbs value.
+const MAX_SURBS: u32 = 100;
+
+/// Settings given on the command line.
+/// Each one overrides the corresponding FileSharingApp field when present.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// Maximum downloads in flight at once (0 = unlimited).
+    pub max_concurrent: Option<usize>,
+
+    /// Upload cap in bytes per second.
+    pub upload_limit: Option<u64>,
+
+    /// File transfers served to peers at once.
+    pub max_uploads: Option<usize>,
+
+    /// Directory downloads are saved to.
+    pub download_dir: Option<PathBuf>,
+
+    /// Extra SURBs attached to anonymous download requests.
+    pub surbs: Option<u32>,
+
+    /// Run the loopback self-test once the managers have started.
+    pub self_test: bool,
+
+    /// Start without touching the mixnet; sockets are created on going online.
+    pub offline: bool,
+
+    /// Manifests to queue downloads from, validated while parsing.
+    pub manifests: Vec<Manifest>,
+
+    /// Files and folders to add to the share list.
+    pub shares: Vec<PathBuf>,
+
+    /// Serve the share list without opening a window.
+    pub headless: bool,
+
+    /// Print usage and exit.
+    pub help: bool,
+}
+
+/// Returns the usage text printed for --help and on bad input.
+pub fn usage() -> String {
+    format!(
+        "Usage: nymshare [OPTIONS]\n\n\
+         Options:\n\
+         \x20 --max-concurrent <N>   Maximum concurrent downloads, 0 = unlimited (0-{})\n\
+         \x20 --upload-limit <RATE>  Upload cap in bytes/s; accepts K, M, G suffixes (e.g. 512K)\n\
+         \x20 --max-uploads <N>      Files served to peers at once (1-{})\n\
+         \x20 --download-dir <DIR>   Directory to save downloads to (created if missing)\n\
+         \x20 --surbs <N>            Extra SURBs for anonymous download requests (0-{})\n\
+         \x20 --self-test            Serve and download a tiny file to ourselves at startup\n\
+         \x20 --offline              Start without connecting to the mixnet\n\
+         \x20 --share <PATH>         Add a file or folder to the share list (repeatable)\n\
+         \x20 --headless             Serve the share list without a window until Ctrl-C;\n\
+         \x20                        every listed file is activated\n\
+         \x20 --import <FILE>        Queue the downloads listed in a .{} manifest\n\
+         \x20                        (a bare FILE.{} argument does the same)\n\
+         \x20 -h, --help             Print this help",
+        MAX_CONCURRENT_LIMIT, MAX_UPLOADS_LIMIT, MAX_SURBS, MANIFEST_EXTENSION, MANIFEST_EXTENSION
+    )
+}
+
+/// Parses command line arguments (without the program name).
+/// Accepts both `--flag value` and `--flag=value`. Only reads the disk to check
+/// shared paths and load manifests; the download directory is created by `apply`.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg.clone(), None),
+        };
+
+        // Switches take no value; `--offline=no` would otherwise still turn it on
+        let switch = match flag.as_str() {
+            "-h" | "--help" => Some(&mut parsed.help),
+            "--self-test" => Some(&mut parsed.self_test),
+            "--offline" => Some(&mut parsed.offline),
+            "--headless" => Some(&mut parsed.headless),
+            _ => None,
+        };
+        if let Some(switch) = switch {
+            if inline_value.is_some() {
+                return Err(format!("{} does not take a value", flag));
+            }
+            *switch = true;
+            continue;
+        }
+
+        // Opening a manifest with NymShare passes its path as the only argument
+        let path = PathBuf::from(&arg);
+        if !arg.starts_with('-') && path.extension().is_some_and(|ext| ext == MANIFEST_EXTENSION) {
+            parsed.manifests.push(Manifest::load(&path)?);
+            continue;
+        }
+
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for {}", flag))
+        };
+
+        match flag.as_str() {
+            "--max-concurrent" => {
+                let n: usize = value()?
+                    .parse()
+                    .map_err(|_| "--max-concurrent expects a whole number".to_string())?;
+                if n > MAX_CONCURRENT_LIMIT {
+                    return Err(format!("--max-concurrent must be at most {}", MAX_CONCURRENT_LIMIT));
+                }
+                parsed.max_concurrent = Some(n);
+            }
+            "--upload-limit" => {
+                let rate = parse_rate(&value()?)
+                    .ok_or_else(|| "--upload-limit expects a rate such as 65536, 512K or 2M".to_string())?;
+                if rate == 0 {
+                    return Err("--upload-limit must be greater than 0".to_string());
+                }
+                parsed.upload_limit = Some(rate);
+            }
+            "--max-uploads" => {
+                let n: usize = value()?
+                    .parse()
+                    .map_err(|_| "--max-uploads expects a whole number".to_string())?;
+                if !(1..=MAX_UPLOADS_LIMIT).contains(&n) {
+                    return Err(format!("--max-uploads must be between 1 and {}", MAX_UPLOADS_LIMIT));
+                }
+                parsed.max_uploads = Some(n);
+            }
+            "--download-dir" => {
+                let dir = value()?;
+                if dir.is_empty() {
+                    return Err("--download-dir expects a directory".to_string());
+                }
+                parsed.download_dir = Some(PathBuf::from(dir));
+            }
+            "--surbs" => {
+                let n: u32 = value()?
+                    .parse()
+                    .map_err(|_| "--surbs expects a whole number".to_string())?;
+                if n > MAX_SURBS {
+                    return Err(format!("--surbs must be at most {}", MAX_SURBS));
+                }
+                parsed.surbs = Some(n);
+            }
+            "--share" => {
+                let path = PathBuf::from(value()?);
+                if !path.exists() {
+                    return Err(format!("Cannot share {}: no such file or folder", path.display()));
+                }
+                parsed.shares.push(path);
+            }
+            "--import" => {
+                parsed.manifests.push(Manifest::load(&PathBuf::from(value()?))?);
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Parses a byte rate with an optional K/M/G (1024-based) suffix.
+fn parse_rate(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, multiplier) = match text.chars().last()?.to_ascii_uppercase() {
+        'K' => (&text[..text.len() - 1], 1024),
+        'M' => (&text[..text.len() - 1], 1024 * 1024),
+        'G' => (&text[..text.len() - 1], 1024 * 1024 * 1024),
+        _ => (text, 1),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+impl CliArgs {
+    /// Seeds the app state with the values given on the command line.
+    pub fn apply(&self, app: &mut FileSharingApp) {
+        if let Some(n) = self.max_concurrent {
+            app.max_concurrent_downloads = n;
+        }
+        if let Some(rate) = self.upload_limit {
+            app.upload_limit = Some(rate);
+        }
+        if let Some(n) = self.max_uploads {
+            app.max_concurrent_uploads = n;
+        }
+        // Created here rather than while parsing, so --help or a bad flag leave the disk alone
+        if let Some(dir) = &self.download_dir {
+            match std::fs::create_dir_all(dir) {
+                Ok(()) => app.download_dir = dir.clone(),
+                Err(e) => eprintln!("Cannot use {} as download directory: {}", dir.display(), e),
+            }
+        }
+        if let Some(n) = self.surbs {
+            app.extra_surbs = n;
+        }
+        if self.offline {
+            app.offline = true;
+        }
+        for path in &self.shares {
+            let added = if path.is_dir() {
+                app.add_share_dir(path)
+            } else {
+                app.add_share(path.clone()).map(usize::from).map_err(|e| e.to_string())
+            };
+            if let Err(e) = added {
+                eprintln!("Cannot share {}: {}", path.display(), e);
+            }
+        }
+        if !self.manifests.is_empty() {
+            let added: usize = self.manifests.iter().map(|m| m.queue_downloads(app, BundleMode::Loose, "")).sum();
+            app.active_tab = Tab::Download;
+            app.set_download_message(format!("Queued {} download(s) from manifest", added));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helper::TestDir;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn rates_take_binary_suffixes() {
+        assert_eq!(parse_rate("65536"), Some(65536));
+        assert_eq!(parse_rate("512K"), Some(512 * 1024));
+        assert_eq!(parse_rate(" 2m "), Some(2 * 1024 * 1024));
+        assert_eq!(parse_rate("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_rate("K"), None);
+        assert_eq!(parse_rate("1.5M"), None);
+        assert_eq!(parse_rate(""), None);
+        assert_eq!(parse_rate(&format!("{}G", u64::MAX)), None);
+    }
+
+    #[test]
+    fn values_come_inline_or_after_the_flag() {
+        let args = parse(&["--max-concurrent", "4", "--upload-limit=512K", "--surbs=3", "--offline"]).unwrap();
+        assert_eq!(args.max_concurrent, Some(4));
+        assert_eq!(args.upload_limit, Some(512 * 1024));
+        assert_eq!(args.surbs, Some(3));
+        assert!(args.offline);
+        assert!(!args.headless);
+    }
+
+    #[test]
+    fn bad_values_are_refused() {
+        assert!(parse(&["--max-concurrent"]).is_err());
+        assert!(parse(&["--max-concurrent", "many"]).is_err());
+        assert!(parse(&["--max-concurrent", &(MAX_CONCURRENT_LIMIT + 1).to_string()]).is_err());
+        assert!(parse(&["--max-uploads=0"]).is_err());
+        assert!(parse(&["--upload-limit=0"]).is_err());
+        assert!(parse(&["--surbs", &(MAX_SURBS + 1).to_string()]).is_err());
+        assert!(parse(&["--download-dir="]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn switches_take_no_value() {
+        assert!(parse(&["--offline=no"]).is_err());
+        assert!(parse(&["--headless=1"]).is_err());
+        assert!(parse(&["--help=me"]).is_err());
+        assert!(parse(&["-h"]).unwrap().help);
+    }
+
+    #[test]
+    fn the_download_dir_is_created_only_when_applied() {
+        let dir = TestDir::new("cli-download-dir");
+        let target = dir.join("downloads");
+        let args = parse(&["--download-dir", target.to_str().unwrap(), "--help"]).unwrap();
+        assert!(args.help);
+        assert!(!target.exists(), "parsing created the directory");
+
+        let mut app = FileSharingApp::default();
+        args.apply(&mut app);
+        assert!(target.is_dir());
+        assert_eq!(app.download_dir, target);
+    }
+}
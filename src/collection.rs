@@ -0,0 +1,150 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+// Standard library
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Local
+use crate::app::FileSharingApp;
+use crate::manifest::Manifest;
+use crate::shareable::Shareable;
+use crate::storage;
+
+
+/// File in CONFIG_DIR holding the collections.
+pub const COLLECTIONS_FILE: &str = "collections.json";
+
+/// Most files a collection may hold; keeps its manifest well under an advertise message.
+pub const MAX_COLLECTION_MEMBERS: usize = 1000;
+
+/// Longest accepted collection name, in characters.
+const MAX_COLLECTION_NAME: usize = 100;
+
+/// A named group of shared files, advertised as one entry and downloaded together.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+    /// Name the collection is advertised and requested under.
+    pub name: String,
+
+    /// Paths of the member files in the share catalog.
+    pub members: Vec<PathBuf>,
+
+    /// Whether the collection is advertised and its members served.
+    pub active: bool,
+}
+
+impl Collection {
+    /// Creates an inactive collection of `files`.
+    /// Files sharing a name with an earlier member are skipped, since members are
+    /// requested by name, as is anything past MAX_COLLECTION_MEMBERS.
+    pub fn new<'a>(name: String, files: impl IntoIterator<Item = &'a Shareable>) -> Self {
+        let mut names = HashSet::new();
+        let members = files
+            .into_iter()
+            .filter(|f| f.file_name().is_some_and(|n| names.insert(n)))
+            .map(|f| f.path.clone())
+            .take(MAX_COLLECTION_MEMBERS)
+            .collect();
+        Self { name, members, active: false }
+    }
+
+    /// Whether the file at `path` belongs to the collection.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.members.iter().any(|m| m == path)
+    }
+
+    /// Catalog entries of the members, in member order; members missing from the catalog are left out.
+    pub fn member_files<'a>(&'a self, catalog: &'a [Shareable]) -> impl Iterator<Item = &'a Shareable> + 'a {
+        self.members.iter().filter_map(|m| catalog.iter().find(|f| &f.path == m))
+    }
+
+    /// Manifest of the members served at `service`, as returned for GETCOLLECTION.
+    pub fn manifest(&self, service: &str, catalog: &[Shareable]) -> Manifest {
+        Manifest::for_files(service, self.member_files(catalog))
+    }
+}
+
+/// Checks a name for a new collection against the existing ones.
+pub fn validate_name(name: &str, collections: &[Collection]) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+    if name.chars().count() > MAX_COLLECTION_NAME {
+        return Err(format!("Collection name is longer than {} characters", MAX_COLLECTION_NAME));
+    }
+    if collections.iter().any(|c| c.name == name) {
+        return Err(format!("A collection named '{}' already exists", name));
+    }
+    Ok(())
+}
+
+/// Whether `file` may be served: it is active itself or belongs to an active collection.
+pub fn is_served(file: &Shareable, collections: &[Collection]) -> bool {
+    file.is_active() || collections.iter().any(|c| c.active && c.contains(&file.path))
+}
+
+/// Names of the active collections with at least one member in the catalog.
+pub fn advertised_names(collections: &[Collection], catalog: &[Shareable]) -> Vec<String> {
+    collections
+        .iter()
+        .filter(|c| c.active && c.member_files(catalog).next().is_some())
+        .map(|c| c.name.clone())
+        .collect()
+}
+
+/// Saves the collections, logging rather than failing if they cannot be written.
+pub fn save(collections: &[Collection]) {
+    if let Err(e) = storage::save(COLLECTIONS_FILE, &collections) {
+        warn!("Failed to save collections: {}", e);
+    }
+}
+
+/// Loads the saved collections and puts their members back in the share catalog
+/// (inactive, like newly added files). Members that can no longer be shared are
+/// dropped from their collection with a warning.
+pub fn restore(app: &mut FileSharingApp) {
+    let Some(mut collections) = storage::load::<Vec<Collection>>(COLLECTIONS_FILE) else { return; };
+    for collection in &mut collections {
+        collection.members.retain(|path| {
+            if app.shareable_files.iter().any(|f| &f.path == path) {
+                return true;
+            }
+            match Shareable::new_allow_sensitive(path.clone()) {
+                Ok(file) => {
+                    app.shareable_files.push(file);
+                    true
+                }
+                Err(e) => {
+                    warn!("Dropping '{}' from collection '{}': {}", path.display(), collection.name, e);
+                    false
+                }
+            }
+        });
+    }
+    app.collections = collections;
+}
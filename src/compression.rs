@@ -0,0 +1,112 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+// Standard library
+use std::io::{self, Read, Write};
+
+// Local
+use crate::filetype::FileType;
+
+
+/// Encoding of a file payload on the wire.
+/// Downloaders list the encodings they accept in their request; the service picks one
+/// and names it after the payload. Peers that predate negotiation only ever send raw.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Raw,
+    Deflate,
+}
+
+impl Encoding {
+    /// Every encoding this build supports.
+    pub const ALL: [Encoding; 2] = [Encoding::Raw, Encoding::Deflate];
+
+    /// Name used on the wire and in logs.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Raw => "raw",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    /// Parses a label sent by a peer; unknown labels are None.
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "raw" => Some(Encoding::Raw),
+            "deflate" => Some(Encoding::Deflate),
+            _ => None,
+        }
+    }
+
+    /// Encodings a downloader accepts, most preferred first. Raw is always accepted.
+    pub fn accepted(prefer_compression: bool) -> Vec<String> {
+        let encodings: &[Encoding] = if prefer_compression { &[Encoding::Deflate, Encoding::Raw] } else { &[Encoding::Raw] };
+        encodings.iter().map(|e| e.label().to_string()).collect()
+    }
+
+    /// Picks the encoding for a transfer: the requester's first accepted encoding we support,
+    /// or raw when it listed none we know. Files of types that are already compressed are always sent raw.
+    pub fn negotiate(accepted: &[String], file_type: FileType) -> Self {
+        let compressible = matches!(file_type, FileType::Document | FileType::Other);
+        accepted
+            .iter()
+            .filter_map(|label| Encoding::from_label(label))
+            .find(|e| compressible || *e == Encoding::Raw)
+            .unwrap_or(Encoding::Raw)
+    }
+
+    /// Encodes `data`, returning the encoding actually used.
+    /// Falls back to raw if encoding fails or does not make the payload smaller.
+    pub fn encode(self, data: Vec<u8>) -> (Encoding, Vec<u8>) {
+        match self {
+            Encoding::Raw => (Encoding::Raw, data),
+            Encoding::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                match encoder.write_all(&data).and_then(|_| encoder.finish()) {
+                    Ok(compressed) if compressed.len() < data.len() => (Encoding::Deflate, compressed),
+                    _ => (Encoding::Raw, data),
+                }
+            }
+        }
+    }
+
+    /// Decodes a payload sent with this encoding, refusing output larger than `limit` bytes.
+    pub fn decode(self, data: Vec<u8>, limit: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Raw => Ok(data),
+            Encoding::Deflate => {
+                let mut out = Vec::new();
+                DeflateDecoder::new(data.as_slice()).take(limit + 1).read_to_end(&mut out)?;
+                if out.len() as u64 > limit {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "decoded payload exceeds the size limit"));
+                }
+                Ok(out)
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use nymlib::nymsocket::SocketMode;
+
+// Standard library
+use std::fs;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+// local
+use crate::app::{FileSharingApp, VERSION};
+use crate::helper::{format_duration, LOG_FILE};
+use crate::compression::Encoding;
+use crate::network::effective_chunk_size;
+use crate::vault;
+
+
+/// Number of trailing log lines included in a report
+const LOG_TAIL_LINES: usize = 50;
+
+/// Placeholder used for masked Nym addresses
+const MASKED_ADDRESS: &str = "<nym-address>";
+
+/// Placeholder used for masked file paths
+const MASKED_PATH: &str = "<path>";
+
+
+/// Assembles a plain-text diagnostics report for bug reports.
+///
+/// Includes version, OS, socket modes, settings, request counts, recent
+/// warnings/errors and the tail of the log file. Nym addresses and file
+/// paths can be masked independently.
+pub fn build_report(app: &FileSharingApp, redact_addresses: bool, redact_paths: bool) -> String {
+    let mut report = String::new();
+
+    // Known sensitive strings, masked in every section including the log tail
+    let mut known_paths: Vec<String> = app.shareable_files
+        .iter()
+        .map(|f| f.path.display().to_string())
+        .collect();
+    known_paths.push(app.download_dir.display().to_string());
+    known_paths.sort_by_key(|p| std::cmp::Reverse(p.len()));
+
+    let redact = |text: &str| -> String {
+        let mut text = text.to_string();
+        if redact_paths {
+            for path in &known_paths {
+                if !path.is_empty() {
+                    text = text.replace(path.as_str(), MASKED_PATH);
+                }
+            }
+        }
+        if redact_addresses {
+            text = mask_addresses(&text);
+        }
+        text
+    };
+
+    let mode = |m: &SocketMode| match m {
+        SocketMode::Anonymous => "Anonymous",
+        SocketMode::Individual => "Individual",
+    };
+
+    let _ = writeln!(report, "NymShare diagnostics");
+    let _ = writeln!(report, "====================");
+    let _ = writeln!(report, "Version: {}", VERSION);
+    let _ = writeln!(report, "OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH);
+    if let Some(uptime) = app.start_time.and_then(|t| SystemTime::now().duration_since(t).ok()) {
+        let _ = writeln!(report, "Uptime: {}", format_duration(uptime));
+    }
+    let _ = writeln!(report, "Saved state: {}", match (vault::is_enabled(), vault::is_locked()) {
+        (false, _) => "plain",
+        (true, true) => "encrypted, locked",
+        (true, false) => "encrypted",
+    });
+
+    let _ = writeln!(report, "\n[Sockets]");
+    let _ = writeln!(report, "Download socket mode: {}", mode(&app.download_socket_mode));
+    let _ = writeln!(report, "Explore mode: {}", app.explore_mode.label());
+    let _ = writeln!(report, "Serving socket mode: Individual");
+    let _ = writeln!(report, "Serving address: {}", if app.serving_addr.is_empty() {
+        "(not initialized)".to_string()
+    } else {
+        redact(&app.serving_addr)
+    });
+    for (name, addr) in &app.identity_addrs {
+        let bound = app.shareable_files.iter().filter(|f| f.served_by(name)).count();
+        let _ = writeln!(report, "Identity '{}': {} ({} file(s))", name, if addr.is_empty() { "(not running)".to_string() } else { redact(addr) }, bound);
+    }
+
+    let _ = writeln!(report, "\n[Settings]");
+    let _ = writeln!(report, "Advertise mode: {}", app.advertise_mode);
+    if let Some((start, end)) = app.advertise_window {
+        let outside = if app.advertise_window_silent { "ignored" } else { "empty list" };
+        let _ = writeln!(report, "Advertise window: {}-{} (outside: {})", start.format("%H:%M"), end.format("%H:%M"), outside);
+    }
+    let _ = writeln!(report, "Debug logging: {}", app.debug_logging);
+    let _ = writeln!(report, "Theme: {:?}", app.theme);
+    let _ = writeln!(report, "Download directory: {}", redact(&app.download_dir.display().to_string()));
+    let _ = writeln!(report, "Max concurrent downloads: {}", app.max_concurrent_downloads);
+    let _ = writeln!(report, "Compress transfers: {}", app.prefer_compression);
+    let _ = writeln!(report, "Compress served files: {}", app.compress_uploads);
+    let _ = writeln!(report, "File requests per peer per minute: {}", app.max_requests_per_minute);
+    let _ = writeln!(report, "Chunks in flight: {}", app.chunk_window);
+    let _ = writeln!(report, "Chunk size: {}-{} KB (last sent: {})", app.chunk_size_bounds.0 / 1024, app.chunk_size_bounds.1 / 1024,
+        effective_chunk_size().map_or("none yet".to_string(), |size| format!("{} KB", size / 1024)));
+
+    let _ = writeln!(report, "\n[Counts]");
+    let active = app.shareable_files.iter().filter(|f| f.is_active()).count();
+    let _ = writeln!(report, "Shareable files: {} (active: {})", app.shareable_files.len(), active);
+    let _ = writeln!(
+        report,
+        "Download requests: {} (sent: {}, accepted: {}, completed: {}, with warnings: {}, failed: {})",
+        app.requested_files.len(),
+        app.requested_files.iter().filter(|r| r.sent).count(),
+        app.requested_files.iter().filter(|r| r.accepted).count(),
+        app.requested_files.iter().filter(|r| r.is_completed()).count(),
+        app.requested_files.iter().filter(|r| r.warning().is_some()).count(),
+        app.requested_files.iter().filter(|r| r.is_failed()).count(),
+    );
+    let encodings: Vec<String> = Encoding::ALL
+        .iter()
+        .map(|e| format!("{}: {}", e.label(), app.requested_files.iter().filter(|r| r.encoding == Some(*e)).count()))
+        .collect();
+    let _ = writeln!(report, "Received encodings: {}", encodings.join(", "));
+    let _ = writeln!(
+        report,
+        "Explore requests: {} (sent: {}, accepted: {}, completed: {})",
+        app.explore_requests.len(),
+        app.explore_requests.iter().filter(|r| r.sent).count(),
+        app.explore_requests.iter().filter(|r| r.accepted).count(),
+        app.explore_requests.iter().filter(|r| r.completed).count(),
+    );
+
+    // Log file sections
+    let log = fs::read_to_string(LOG_FILE).unwrap_or_default();
+    let lines: Vec<&str> = log.lines().collect();
+
+    let _ = writeln!(report, "\n[Recent warnings and errors]");
+    let problems: Vec<&&str> = lines.iter()
+        .filter(|l| l.contains("[WARN]") || l.contains("[ERROR]"))
+        .collect();
+    if problems.is_empty() {
+        let _ = writeln!(report, "(none)");
+    }
+    for line in problems.iter().skip(problems.len().saturating_sub(LOG_TAIL_LINES)) {
+        let _ = writeln!(report, "{}", redact(line));
+    }
+
+    let _ = writeln!(report, "\n[Last {} log lines]", LOG_TAIL_LINES);
+    if lines.is_empty() {
+        let _ = writeln!(report, "(log file empty or unreadable)");
+    }
+    for line in lines.iter().skip(lines.len().saturating_sub(LOG_TAIL_LINES)) {
+        let _ = writeln!(report, "{}", redact(line));
+    }
+
+    report
+}
+
+
+/// Replaces anything that looks like a Nym address (`<id>.<key>@<gateway>`) with a placeholder.
+fn mask_addresses(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let token = word.trim_matches(|c: char| c.is_whitespace() || "\"'(),:;[]{}".contains(c));
+            if token.len() >= 40 && token.contains('@') && token.contains('.') {
+                word.replace(token, MASKED_ADDRESS)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect()
+}
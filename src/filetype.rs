@@ -0,0 +1,124 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Standard library
+use std::path::Path;
+
+
+/// Coarse content type of a file, used to filter the share list and explore results.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum FileType {
+    Audio,
+    Video,
+    Image,
+    Document,
+    Archive,
+    Other,
+}
+
+/// Extensions recognised for each type, lowercase and without the dot.
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "opus", "wav", "m4a", "aac", "wma", "aiff"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "avi", "mov", "wmv", "m4v", "mpg", "mpeg", "flv"];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "svg", "tif", "tiff", "heic", "ico"];
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "txt", "md", "doc", "docx", "odt", "rtf", "xls", "xlsx", "ods", "csv", "ppt", "pptx", "odp", "epub", "mobi", "html",
+];
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "tar", "gz", "tgz", "bz2", "xz", "7z", "rar", "zst", "iso"];
+
+impl FileType {
+    /// Every type, in the order shown in the filter dropdowns.
+    pub const ALL: [FileType; 6] = [
+        FileType::Audio,
+        FileType::Video,
+        FileType::Image,
+        FileType::Document,
+        FileType::Archive,
+        FileType::Other,
+    ];
+
+    /// Human readable label used in the UI and in advertise lists.
+    pub fn label(&self) -> &'static str {
+        match self {
+            FileType::Audio => "Audio",
+            FileType::Video => "Video",
+            FileType::Image => "Image",
+            FileType::Document => "Document",
+            FileType::Archive => "Archive",
+            FileType::Other => "Other",
+        }
+    }
+
+    /// Parses a label sent by a service; unknown labels are None.
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.label() == label)
+    }
+
+    /// Guesses the type from a file name's extension; Other when it is missing or unknown.
+    pub fn from_name(name: &str) -> Self {
+        let Some((_, ext)) = name.rsplit_once('.') else {
+            return FileType::Other;
+        };
+        let ext = ext.to_ascii_lowercase();
+        let ext = ext.as_str();
+        if AUDIO_EXTENSIONS.contains(&ext) {
+            FileType::Audio
+        } else if VIDEO_EXTENSIONS.contains(&ext) {
+            FileType::Video
+        } else if IMAGE_EXTENSIONS.contains(&ext) {
+            FileType::Image
+        } else if DOCUMENT_EXTENSIONS.contains(&ext) {
+            FileType::Document
+        } else if ARCHIVE_EXTENSIONS.contains(&ext) {
+            FileType::Archive
+        } else {
+            FileType::Other
+        }
+    }
+
+    /// Detects the type of a file on disk: by extension first, then by its magic bytes.
+    pub fn detect(path: &Path) -> Self {
+        let by_name = path
+            .file_name()
+            .map(|name| Self::from_name(&name.to_string_lossy()))
+            .unwrap_or(FileType::Other);
+        if by_name != FileType::Other {
+            return by_name;
+        }
+
+        match infer::get_from_path(path) {
+            Ok(Some(kind)) => match kind.matcher_type() {
+                infer::MatcherType::Audio => FileType::Audio,
+                infer::MatcherType::Video => FileType::Video,
+                infer::MatcherType::Image => FileType::Image,
+                infer::MatcherType::Doc | infer::MatcherType::Book | infer::MatcherType::Text => FileType::Document,
+                infer::MatcherType::Archive => FileType::Archive,
+                _ => FileType::Other,
+            },
+            _ => FileType::Other,
+        }
+    }
+}
@@ -0,0 +1,326 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+/// Times in a row the unacknowledged chunks are sent again before a transfer is given up
+pub const MAX_CHUNK_RETRANSMITS: u32 = 5;
+
+/// Chunk size a flow-controlled transfer starts at before it adapts
+pub const INITIAL_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Window moves in a row after which a flow-controlled transfer doubles its chunk size
+pub const CHUNK_GROW_STREAK: u32 = 4;
+
+
+/// Sliding window over the chunks of one transfer. `acked` counts the chunks the
+/// requester has appended, in order; at most `size` chunks past it are sent.
+/// A window of 0 sends every chunk once without waiting for ACKs.
+#[derive(Debug)]
+pub struct ChunkWindow {
+    pub size: u32,          // Chunks that may be unacknowledged at once (0 = no flow control)
+    pub acked: u32,         // Chunks the requester confirmed
+    pub next: u32,          // Next chunk to send
+    pub sent: u32,          // Chunks sent at least once
+    pub last: Option<u32>,  // Index of the final chunk, once it has been sent
+    pub retransmits: u32,   // Timeouts in a row without the window moving
+}
+
+impl ChunkWindow {
+    pub fn new(size: u32) -> Self {
+        Self { size, acked: 0, next: 0, sent: 0, last: None, retransmits: 0 }
+    }
+
+    /// Whether chunk `next` may go out now.
+    pub fn is_open(&self) -> bool {
+        if self.last.is_some_and(|last| self.next > last) {
+            return false;
+        }
+        self.size == 0 || self.next < self.acked.saturating_add(self.size)
+    }
+
+    /// Records that chunk `index` went out.
+    pub fn sent(&mut self, index: u32, is_last: bool) {
+        self.next = index + 1;
+        self.sent = self.sent.max(self.next);
+        if is_last {
+            self.last = Some(index);
+        }
+    }
+
+    /// Takes in an ACK for the first `count` chunks. Returns true if it moved the window.
+    pub fn ack(&mut self, count: u32) -> bool {
+        let count = count.min(self.sent);
+        if count <= self.acked {
+            return false;
+        }
+        self.acked = count;
+        self.next = self.next.max(count);
+        self.retransmits = 0;
+        true
+    }
+
+    /// Goes back to the first unacknowledged chunk after an ACK timeout.
+    /// Returns false once the transfer has been retried MAX_CHUNK_RETRANSMITS times in a row.
+    pub fn rewind(&mut self) -> bool {
+        self.retransmits += 1;
+        self.next = self.acked;
+        self.retransmits <= MAX_CHUNK_RETRANSMITS
+    }
+
+    /// Whether every chunk has been sent and, with flow control, acknowledged.
+    pub fn is_done(&self) -> bool {
+        self.last.is_some_and(|last| if self.size == 0 { self.next > last } else { self.acked > last })
+    }
+}
+
+/// Chunk size of a flow-controlled transfer: doubles after CHUNK_GROW_STREAK ACKs in a row
+/// move the window and halves when chunks have to be sent again, within `min..=max`.
+/// Transfers without ACKs have no signal to adapt on and keep `max`.
+#[derive(Debug)]
+pub struct ChunkSizer {
+    pub size: u32,    // Size of the next new chunk
+    pub min: u32,     // Smallest size it shrinks to
+    pub max: u32,     // Largest size it grows to
+    pub streak: u32,  // ACKs in a row since the size last changed
+}
+
+impl ChunkSizer {
+    pub fn new(min: u32, max: u32) -> Self {
+        let min = min.min(max);
+        Self { size: INITIAL_CHUNK_SIZE.clamp(min, max), min, max, streak: 0 }
+    }
+
+    /// A fixed size, for transfers without ACKs.
+    pub fn fixed(size: u32) -> Self {
+        Self { size, min: size, max: size, streak: 0 }
+    }
+
+    /// An ACK moved the window.
+    pub fn on_ack(&mut self) {
+        self.streak += 1;
+        if self.streak >= CHUNK_GROW_STREAK {
+            self.size = self.size.saturating_mul(2).min(self.max);
+            self.streak = 0;
+        }
+    }
+
+    /// Chunks went unacknowledged and are sent again.
+    pub fn on_loss(&mut self) {
+        self.size = (self.size / 2).max(self.min);
+        self.streak = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chunk size bounds of the transfer path
+    const MIN_CHUNK: u32 = 16 * 1024;
+    const MAX_CHUNK: u32 = 256 * 1024;
+
+    #[test]
+    fn chunk_window_without_acks_sends_everything() {
+        let mut window = ChunkWindow::new(0);
+        for index in 0..100 {
+            assert!(window.is_open());
+            window.sent(index, index == 99);
+        }
+        assert!(!window.is_open());
+        assert!(window.is_done());
+    }
+
+    #[test]
+    fn chunk_window_stops_at_its_size() {
+        let mut window = ChunkWindow::new(4);
+        for index in 0..4 {
+            assert!(window.is_open());
+            window.sent(index, false);
+        }
+        assert!(!window.is_open());
+
+        // An ACK for more than was sent only confirms what was sent
+        assert!(window.ack(u32::MAX));
+        assert_eq!(window.acked, 4);
+        assert!(!window.ack(3));
+        assert!(window.is_open());
+        window.sent(4, true);
+        assert!(!window.is_done());
+        assert!(window.ack(5));
+        assert!(window.is_done());
+    }
+
+    #[test]
+    fn chunk_window_resends_after_a_lost_ack() {
+        let mut window = ChunkWindow::new(4);
+        for index in 0..4 {
+            window.sent(index, index == 3);
+        }
+        window.ack(2);
+        assert!(!window.is_open());
+
+        // Nothing moved the window: go back to the first unconfirmed chunk
+        assert!(window.rewind());
+        assert_eq!(window.next, 2);
+        assert!(window.is_open());
+        window.sent(2, false);
+
+        // An ACK for chunks still being resent skips them
+        assert!(window.ack(3));
+        assert_eq!(window.next, 3);
+        window.sent(3, true);
+        assert!(window.ack(4));
+        assert!(window.is_done());
+    }
+
+    #[test]
+    fn chunk_window_gives_up_after_repeated_timeouts() {
+        let mut window = ChunkWindow::new(2);
+        window.sent(0, false);
+        window.sent(1, false);
+        for _ in 0..MAX_CHUNK_RETRANSMITS {
+            assert!(window.rewind());
+        }
+        assert!(!window.rewind());
+
+        // Progress resets the count
+        let mut window = ChunkWindow::new(2);
+        window.sent(0, false);
+        window.sent(1, false);
+        for _ in 0..MAX_CHUNK_RETRANSMITS {
+            assert!(window.rewind());
+        }
+        window.ack(1);
+        assert!(window.rewind());
+    }
+
+    /// A producer that can send a chunk every tick and a consumer that appends one every
+    /// third tick: the producer waits on the window instead of running ahead, and every
+    /// chunk reaches the consumer once, in order.
+    #[test]
+    fn chunk_window_holds_a_fast_producer_to_a_slow_consumer() {
+        const TOTAL: u32 = 40;
+        const WINDOW: u32 = 6;
+        let mut window = ChunkWindow::new(WINDOW);
+        let mut in_flight = std::collections::VecDeque::new();
+        let (mut appended, mut waits, mut most_unacked) = (Vec::new(), 0, 0);
+
+        for tick in 0.. {
+            if window.is_done() {
+                break;
+            }
+            assert!(tick < 1_000, "transfer did not finish");
+            if window.is_open() {
+                let index = window.next;
+                in_flight.push_back(index);
+                window.sent(index, index + 1 == TOTAL);
+                most_unacked = most_unacked.max(window.sent - window.acked);
+            } else {
+                waits += 1;
+            }
+            if tick % 3 == 2 && let Some(index) = in_flight.pop_front() {
+                appended.push(index);
+                window.ack(index + 1);
+            }
+        }
+
+        assert_eq!(appended, (0..TOTAL).collect::<Vec<_>>());
+        assert!(most_unacked <= WINDOW, "{} chunks unacknowledged", most_unacked);
+        assert!(waits > 0, "the producer never waited");
+    }
+
+    #[test]
+    fn chunk_sizer_grows_on_acks_and_shrinks_on_loss() {
+        let mut sizer = ChunkSizer::new(MIN_CHUNK, MAX_CHUNK);
+        assert_eq!(sizer.size, INITIAL_CHUNK_SIZE);
+        for _ in 0..CHUNK_GROW_STREAK - 1 {
+            sizer.on_ack();
+        }
+        assert_eq!(sizer.size, INITIAL_CHUNK_SIZE);
+        sizer.on_ack();
+        assert_eq!(sizer.size, INITIAL_CHUNK_SIZE * 2);
+
+        // A loss halves the size and restarts the streak
+        for _ in 0..CHUNK_GROW_STREAK - 1 {
+            sizer.on_ack();
+        }
+        sizer.on_loss();
+        assert_eq!(sizer.size, INITIAL_CHUNK_SIZE);
+        sizer.on_ack();
+        assert_eq!(sizer.size, INITIAL_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn chunk_sizer_stays_within_its_bounds() {
+        let mut sizer = ChunkSizer::new(32 * 1024, 128 * 1024);
+        for _ in 0..100 {
+            sizer.on_ack();
+        }
+        assert_eq!(sizer.size, 128 * 1024);
+        for _ in 0..100 {
+            sizer.on_loss();
+        }
+        assert_eq!(sizer.size, 32 * 1024);
+
+        // Bounds the wrong way round and bounds below the start still hold
+        assert_eq!(ChunkSizer::new(128 * 1024, 32 * 1024).size, 32 * 1024);
+        assert_eq!(ChunkSizer::new(MIN_CHUNK, 32 * 1024).size, 32 * 1024);
+
+        let mut fixed = ChunkSizer::fixed(MAX_CHUNK);
+        fixed.on_loss();
+        assert_eq!(fixed.size, MAX_CHUNK);
+    }
+
+    /// Chunks over `lossy` bytes are always lost: the size keeps probing above that limit
+    /// but spends most rounds at it, and goes back up once the loss stops.
+    #[test]
+    fn chunk_size_adapts_under_simulated_loss() {
+        let lossy = 64 * 1024;
+        let mut sizer = ChunkSizer::new(MIN_CHUNK, MAX_CHUNK);
+        let mut sizes = Vec::new();
+        for _ in 0..400 {
+            if sizer.size > lossy {
+                sizer.on_loss();
+            } else {
+                sizer.on_ack();
+            }
+            sizes.push(sizer.size);
+        }
+        let settled = &sizes[100..];
+        assert!(settled.iter().all(|&size| size <= lossy * 2), "size stayed above the lossy limit");
+        let over = settled.iter().filter(|&&size| size > lossy).count();
+        assert!(over * 3 < settled.len(), "{} of {} rounds over the lossy limit", over, settled.len());
+        assert!(settled.iter().all(|&size| size >= lossy / 2), "size fell well below the lossy limit");
+
+        for _ in 0..CHUNK_GROW_STREAK * 4 {
+            sizer.on_ack();
+        }
+        assert_eq!(sizer.size, MAX_CHUNK);
+
+        // Losing everything shrinks to the smallest chunk
+        for _ in 0..10 {
+            sizer.on_loss();
+        }
+        assert_eq!(sizer.size, MIN_CHUNK);
+    }
+}
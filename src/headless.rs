@@ -0,0 +1,102 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+
+
+// External crates
+use log::info;
+use tokio::sync::Mutex;
+use tokio::time::interval;
+
+// Standard library
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Local
+use crate::app::FileSharingApp;
+use crate::tabs::share_link;
+use crate::watcher;
+
+
+/// Serves the share list without a window until Ctrl-C.
+///
+/// Every listed file is activated and each serving address is printed to stdout once
+/// known, followed by a link per file. The updates the GUI applies each frame are
+/// applied here on a timer instead.
+pub async fn run(app: Arc<Mutex<FileSharingApp>>) {
+    {
+        let mut app = app.lock().await;
+        if app.offline {
+            eprintln!("Running offline: nothing is served until restarted without --offline");
+        }
+        // Sensitive files need the GUI's "share anyway" confirmation
+        for path in std::mem::take(&mut app.pending_sensitive_files) {
+            eprintln!("Not sharing {} (sensitive location; add it from the GUI to share anyway)", path.display());
+        }
+        if app.shareable_files.is_empty() {
+            eprintln!("No files to share; add some with --share <PATH>");
+        }
+        for file in &mut app.shareable_files {
+            file.activate();
+        }
+        app.save_shares();
+        info!("[*] Headless: serving {} file(s)", app.shareable_files.len());
+    }
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut tick = interval(Duration::from_millis(200));
+    let mut printed: HashMap<String, String> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => {
+                info!("[*] Headless: interrupted; stopping");
+                break;
+            }
+            _ = tick.tick() => {
+                let mut app = app.lock().await;
+                app.apply_pending_updates();
+                if app.watch_shared_files {
+                    watcher::sync(&app.shareable_files);
+                } else {
+                    watcher::stop();
+                }
+
+                // Print each address once it is known, and again if it changes
+                for name in app.identity_names() {
+                    let addr = app.serving_addr_of(&name).to_string();
+                    if addr.is_empty() || printed.get(&name) == Some(&addr) {
+                        continue;
+                    }
+                    println!("Serving address ({}): {}", name, addr);
+                    for file in app.shareable_files.iter().filter(|f| f.is_active() && f.served_by(&name)) {
+                        println!("  {}", share_link(&addr, file, app.link_includes_hash));
+                    }
+                    printed.insert(name, addr);
+                }
+            }
+        }
+    }
+}
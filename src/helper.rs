@@ -22,11 +22,24 @@
 
 
 // External crates
+use nymlib::nymsocket::SockAddr;
 use simplelog::*;
+use sha2::{Digest, Sha256};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
 
 // Standard library
-use std::time::Instant;
-use std::fs::OpenOptions;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+// Local
+use crate::filetype::FileType;
+use crate::vault;
+
+/// Path of the application log file.
+pub const LOG_FILE: &str = "debug.log";
 
 /// Initializes logging to a file.
 pub fn init_logging(log_file_path: &str) {
@@ -44,6 +57,18 @@ pub fn init_logging(log_file_path: &str) {
 
     WriteLogger::init(LevelFilter::Debug, config, log_file)
         .expect("Failed to initialize logger");
+    sync_log_level();
+}
+
+/// Turns file logging off while saved state is encrypted, and back to Debug otherwise.
+/// The log is plain text and names files, request ids and peer addresses.
+pub fn sync_log_level() {
+    log::set_max_level(if vault::is_enabled() { LevelFilter::Off } else { LevelFilter::Debug });
+}
+
+/// Empties the log file, keeping it open for the logger.
+pub fn empty_log() -> io::Result<()> {
+    OpenOptions::new().write(true).open(LOG_FILE)?.set_len(0)
 }
 
 /// Converts elapsed time since sent_time to a human readable format.
@@ -58,4 +83,424 @@ pub fn time_ago(sent_time: Instant) -> String {
     } else {
         format!("{} days ago", elapsed.as_secs() / 86400)
     }
-}
\ No newline at end of file
+}
+
+/// Converts the time remaining until `deadline` to a human readable format.
+/// Returns None once the deadline has passed.
+pub fn time_left(deadline: SystemTime) -> Option<String> {
+    let remaining = deadline.duration_since(SystemTime::now()).ok()?;
+    Some(format_duration(remaining))
+}
+
+/// Formats a duration as a compact "1d 2h", "3h 5m", "4m 10s" string.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 86400 {
+        format!("{}d {}h", secs / 86400, (secs % 86400) / 3600)
+    } else if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Computes the SHA-256 digest of a file as a lowercase hex string.
+/// Reads the file in blocks so large files are not loaded into memory.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Computes the SHA-256 digest of an in-memory buffer as a lowercase hex string.
+pub fn sha256_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Returns true if `s` looks like a hex SHA-256 digest.
+pub fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Returns true if `input`, trimmed, parses as a service address rather than a search term.
+pub fn is_service_address(input: &str) -> bool {
+    let input = input.trim();
+    !input.is_empty() && !SockAddr::from(input).is_null()
+}
+
+/// A well-formed Nym address, for tests
+#[cfg(test)]
+pub const TEST_ADDRESS: &str = "6x5SYnLroiN7WYq8NQYU9KHcH4YjpBbwpUfVu3EB7ieH.4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi@6x5SYnLroiN7WYq8NQYU9KHcH4YjpBbwpUfVu3EB7ieH";
+
+/// A well-formed Nym address differing from TEST_ADDRESS in its encryption key, for tests
+#[cfg(test)]
+pub const OTHER_TEST_ADDRESS: &str = "6x5SYnLroiN7WYq8NQYU9KHcH4YjpBbwpUfVu3EB7ieH.8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR@6x5SYnLroiN7WYq8NQYU9KHcH4YjpBbwpUfVu3EB7ieH";
+
+/// A fresh directory under the system temp dir for one test, removed on drop so it is
+/// cleaned up when the test fails too.
+#[cfg(test)]
+pub struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("nymshare-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// `path`, or `stem (n).ext` next to it for the first n not taken.
+pub fn unused_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let (stem, ext) = name.rsplit_once('.').unwrap_or((&name, ""));
+    (1..)
+        .map(|n| match ext {
+            "" => path.with_file_name(format!("{} ({})", stem, n)),
+            ext => path.with_file_name(format!("{} ({}).{}", stem, n, ext)),
+        })
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Turns a served file name such as "photos/2024/a.jpg" into the path it is saved under,
+/// relative to the download directory. Returns None for names that are empty or would
+/// leave that directory ("..", absolute paths, backslashes).
+pub fn relative_download_path(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for part in name.split('/') {
+        // Each part must be a single plain name on this platform too
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if !part.contains('\\') => path.push(part),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Resolves `dir` to an absolute path so later joins don't depend on the working directory.
+/// Falls back to joining onto the current directory if the path cannot be canonicalized.
+pub fn absolute_dir(dir: &Path) -> PathBuf {
+    std::fs::canonicalize(dir)
+        .or_else(|_| std::path::absolute(dir))
+        .unwrap_or_else(|_| dir.to_path_buf())
+}
+
+/// Checks that files can be created in `dir` by writing and removing a probe file.
+pub fn check_writable_dir(dir: &Path) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "not a directory"));
+    }
+    let probe = dir.join(format!(".nymshare-write-test-{}", std::process::id()));
+    OpenOptions::new().write(true).create_new(true).open(&probe)?;
+    std::fs::remove_file(&probe)
+}
+
+/// Opens a file with the OS default handler. Fails if the file is gone.
+pub fn open_with_default_app(path: &Path) -> io::Result<()> {
+    if !path.is_file() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "file no longer exists"));
+    }
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(path);
+    spawn_reaped(command)
+}
+
+/// Shows a file in the OS file manager, selected where the platform supports it.
+/// Fails if the file is gone.
+pub fn reveal_in_file_manager(path: &Path) -> io::Result<()> {
+    if !path.is_file() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "file no longer exists"));
+    }
+    let command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        // No common way to select a file on Linux; open its folder
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(Path::new(".")));
+        command
+    };
+    spawn_reaped(command)
+}
+
+/// Starts a helper process without waiting for it; a thread reaps it once it exits.
+fn spawn_reaped(mut command: Command) -> io::Result<()> {
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Returns true if `dir` has room for `needed` bytes.
+/// If free space cannot be determined the check passes, so the write itself reports the error.
+pub fn has_free_space(dir: &Path, needed: u64) -> bool {
+    match fs2::available_space(dir) {
+        Ok(available) => available >= needed,
+        Err(_) => true,
+    }
+}
+
+/// Formats a byte count as a human readable size ("512 B", "1.5 MB").
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Formats the time since `time` like time_ago, for wall-clock timestamps.
+pub fn system_time_ago(time: SystemTime) -> String {
+    match SystemTime::now().duration_since(time) {
+        Ok(elapsed) => format!("{} ago", format_duration(elapsed)),
+        Err(_) => "just now".to_string(),
+    }
+}
+
+/// Decides whether a download modified at `modified` passes the Today/Runtime filters.
+///
+/// Robust to clock anomalies:
+/// - files dated in the future count as "today" and as "since start";
+/// - if the clock was set back after start, the runtime threshold follows `now`;
+/// - files dated before the Unix epoch (or out of chrono's range) match neither filter.
+pub fn download_matches_filter(
+    modified: SystemTime,
+    now: SystemTime,
+    start_time: SystemTime,
+    today: NaiveDate,
+    show_today: bool,
+    show_runtime: bool,
+) -> bool {
+    let Ok(since_epoch) = modified.duration_since(UNIX_EPOCH) else {
+        return false;
+    };
+
+    let in_future = modified > now;
+    let is_today = in_future
+        || i64::try_from(since_epoch.as_secs())
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|utc| utc.with_timezone(&Local).date_naive() == today)
+            .unwrap_or(false);
+    let since_start = in_future || modified >= start_time.min(now);
+
+    (show_today && is_today) || (show_runtime && since_start)
+}
+
+/// Whether `now` falls in the daily window from `start` to `end` (start inclusive, end exclusive).
+/// A window whose end is before its start runs past midnight; equal ends cover the whole day.
+pub fn time_in_window(now: NaiveTime, (start, end): (NaiveTime, NaiveTime)) -> bool {
+    if start <= end {
+        start == end || (start <= now && now < end)
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// Extensions of source code and config files, lowercase and without the dot.
+/// They are Other or Document to FileType but get an icon of their own.
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "c", "h", "cpp", "hpp", "cs", "go", "java", "kt", "rb", "php", "swift",
+    "sh", "ps1", "sql", "json", "toml", "yaml", "yml", "xml", "css", "ini",
+];
+
+/// Icon shown before a file name in the share and download lists.
+/// Picked from the extension alone, so it works for files that are missing from disk.
+pub fn file_type_icon(path: &Path) -> &'static str {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let is_code = name.rsplit_once('.')
+        .is_some_and(|(_, ext)| CODE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    if is_code {
+        return "🖹";
+    }
+    match FileType::from_name(&name) {
+        FileType::Audio => "🎵",
+        FileType::Video => "🎞",
+        FileType::Image => "🖼",
+        FileType::Document => "📄",
+        FileType::Archive => "📦",
+        FileType::Other => "🗋",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Runs the filter for a file modified at `modified`, with `now` and `today` taken from the clock.
+    fn matches(modified: SystemTime, start_time: SystemTime, show_today: bool, show_runtime: bool) -> bool {
+        let now = SystemTime::now();
+        download_matches_filter(modified, now, start_time, Local::now().date_naive(), show_today, show_runtime)
+    }
+
+    #[test]
+    fn filter_counts_future_files_as_today_and_since_start() {
+        let now = SystemTime::now();
+        let start = now - DAY;
+        let next_year = now + 365 * DAY;
+        assert!(matches(next_year, start, true, false));
+        assert!(matches(next_year, start, false, true));
+
+        // Even when `today` is a different day from the file's date
+        let long_ago = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        assert!(download_matches_filter(next_year, now, start, long_ago, true, false));
+    }
+
+    #[test]
+    fn filter_drops_files_dated_before_the_epoch() {
+        let start = UNIX_EPOCH - 10 * DAY;
+        for modified in [UNIX_EPOCH - Duration::from_secs(1), UNIX_EPOCH - 365 * DAY] {
+            assert!(!matches(modified, start, true, false));
+            assert!(!matches(modified, start, false, true));
+            assert!(!matches(modified, start, true, true));
+        }
+        // The epoch itself is a normal, old date
+        assert!(!matches(UNIX_EPOCH, SystemTime::now() - DAY, true, true));
+    }
+
+    #[test]
+    fn filter_follows_a_clock_set_back_after_start() {
+        // Started "an hour from now": the clock was set back an hour since
+        let now = SystemTime::now();
+        let start = now + Duration::from_secs(60 * 60);
+        assert!(download_matches_filter(now, now, start, Local::now().date_naive(), false, true));
+        assert!(!download_matches_filter(now - Duration::from_secs(60), now, start, Local::now().date_naive(), false, true));
+    }
+
+    #[test]
+    fn download_paths_stay_in_the_download_dir() {
+        for name in ["", "..", ".", "../../etc/passwd", "photos/../../x", "/etc/passwd", "//server/share",
+            "..\\..\\windows", "photos\\a.jpg", "photos//a.jpg", "photos/", "/", "./a.jpg"] {
+            assert_eq!(relative_download_path(name), None, "{:?}", name);
+        }
+        assert_eq!(relative_download_path("a.jpg"), Some(PathBuf::from("a.jpg")));
+        // '/' separates the folders of a shared folder
+        assert_eq!(relative_download_path("photos/2024/a.jpg"), Some(Path::new("photos").join("2024").join("a.jpg")));
+    }
+
+    #[test]
+    fn absolute_dir_resolves_against_the_working_dir() {
+        let cwd = std::env::current_dir().unwrap();
+        let missing = Path::new("nymshare-missing-downloads");
+        assert_eq!(absolute_dir(missing), cwd.join(missing));
+        assert!(absolute_dir(Path::new(".")).is_absolute());
+
+        let temp = std::env::temp_dir();
+        assert_eq!(absolute_dir(&temp), temp.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn windows_style_names_stay_in_the_download_dir() {
+        for name in ["..\\..\\Windows\\System32\\evil.dll", "C:\\Windows\\evil.dll", "photos\\a.jpg", "\\\\server\\share\\a.jpg"] {
+            assert_eq!(relative_download_path(name), None, "{:?}", name);
+        }
+        let dir = Path::new("downloads");
+        let target = dir.join(relative_download_path("photos/2024/a.jpg").unwrap());
+        assert_eq!(target, dir.join("photos").join("2024").join("a.jpg"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn absolute_dir_takes_windows_paths() {
+        let dir = Path::new(r"C:\nymshare-missing\downloads");
+        assert_eq!(absolute_dir(dir), dir);
+        assert_eq!(absolute_dir(Path::new(r"C:/nymshare-missing/./downloads")), dir);
+
+        // Drive prefixes are not plain relative names
+        assert_eq!(relative_download_path("C:/Windows/evil.dll"), None);
+        assert_eq!(relative_download_path("C:evil.dll"), None);
+    }
+
+    #[test]
+    fn search_text_around_45_characters_is_not_an_address() {
+        // The old check took anything longer than 45 characters for an address
+        for len in [44, 45, 46, 47, 100] {
+            let text = "a".repeat(len);
+            assert!(!is_service_address(&text), "{} characters", len);
+            let words = "holiday photos from the summer of 2024 in Lisbon, Portugal and Spain"[..len.min(68)].to_string();
+            assert!(!is_service_address(&words), "{:?}", words);
+        }
+        // Shaped like an address but not one
+        assert!(!is_service_address(&format!("{}.{}@{}", "a".repeat(15), "b".repeat(15), "c".repeat(15))));
+        assert!(!is_service_address(&TEST_ADDRESS[..45]));
+        assert!(!is_service_address(&TEST_ADDRESS[..46]));
+        assert!(!is_service_address(""));
+        assert!(!is_service_address(&" ".repeat(50)));
+    }
+
+    #[test]
+    fn addresses_are_recognized_whatever_their_length() {
+        assert!(TEST_ADDRESS.len() > 45);
+        assert!(is_service_address(TEST_ADDRESS));
+        assert!(is_service_address(&format!("  {}\n", TEST_ADDRESS)));
+        // Without its gateway it is not one
+        assert!(!is_service_address(TEST_ADDRESS.split_once('@').unwrap().0));
+    }
+
+    #[test]
+    fn filter_matches_only_what_is_shown() {
+        let now = SystemTime::now();
+        let start = now - Duration::from_secs(60);
+        assert!(matches(now, start, true, false));
+        assert!(matches(now, start, false, true));
+        assert!(!matches(now, start, false, false));
+
+        // Older than start and from another day
+        let last_week = now - 7 * DAY;
+        assert!(!matches(last_week, start, true, true));
+    }
+}
@@ -0,0 +1,147 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Standard library
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+
+/// File in CONFIG_DIR holding the download history.
+pub const HISTORY_FILE: &str = "history.json";
+
+/// Most downloads remembered; the oldest are forgotten first.
+pub const MAX_HISTORY: usize = 1000;
+
+/// How often the history is checked for files deleted or moved outside the app.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A finished download, kept after its file is moved or deleted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    /// Name the file was requested under.
+    pub filename: String,
+
+    /// Address of the service it came from.
+    pub source_addr: String,
+
+    /// Size of the file received.
+    pub bytes: u64,
+
+    /// When the file was saved.
+    pub completed_at: SystemTime,
+
+    /// Where the file was saved, after any rename on collision.
+    pub path: PathBuf,
+
+    /// Request the file answered, to find it in the download queue.
+    #[serde(default)]
+    pub request_id: String,
+
+    /// SHA-256 the file was asked for or announced with, when one was known.
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+
+    /// Whether the file was still where it was saved when last checked; not saved.
+    #[serde(skip)]
+    pub on_disk: bool,
+}
+
+impl DownloadRecord {
+    /// Checks whether the file is still where it was saved.
+    pub fn check(&mut self) {
+        self.on_disk = self.path.is_file();
+    }
+}
+
+/// Checks every file in the history; drawing the list reads `on_disk` instead of the disk.
+pub fn check_all(history: &mut [DownloadRecord]) {
+    history.iter_mut().for_each(DownloadRecord::check);
+}
+
+/// Adds a download to the history, forgetting the oldest past MAX_HISTORY.
+pub fn record(history: &mut Vec<DownloadRecord>, mut record: DownloadRecord) {
+    record.check();
+    history.push(record);
+    if history.len() > MAX_HISTORY {
+        let excess = history.len() - MAX_HISTORY;
+        history.drain(..excess);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_saved_before_the_hash_still_load() {
+        let saved = r#"[{"filename":"a.txt","source_addr":"service","bytes":3,
+            "completed_at":{"secs_since_epoch":1700000000,"nanos_since_epoch":0},"path":"downloads/a.txt"}]"#;
+        let history: Vec<DownloadRecord> = serde_json::from_str(saved).unwrap();
+        assert_eq!(history[0].expected_hash, None);
+        assert_eq!(history[0].request_id, "");
+    }
+
+    #[test]
+    fn the_expected_hash_survives_a_round_trip() {
+        let record = DownloadRecord {
+            filename: "a.txt".to_string(),
+            source_addr: "service".to_string(),
+            bytes: 3,
+            completed_at: SystemTime::UNIX_EPOCH,
+            path: PathBuf::from("downloads/a (1).txt"),
+            request_id: "request".to_string(),
+            expected_hash: Some("ab".repeat(32)),
+            on_disk: false,
+        };
+        let loaded: DownloadRecord = serde_json::from_str(&serde_json::to_string(&record).unwrap()).unwrap();
+        assert_eq!(loaded, record);
+    }
+
+    #[test]
+    fn checks_follow_the_file() {
+        let dir = crate::helper::TestDir::new("history-check");
+        let path = dir.join("a.txt");
+        std::fs::write(&path, b"abc").unwrap();
+        let mut history = Vec::new();
+        record(&mut history, DownloadRecord {
+            filename: "a.txt".to_string(),
+            source_addr: "service".to_string(),
+            bytes: 3,
+            completed_at: SystemTime::now(),
+            path: path.clone(),
+            request_id: String::new(),
+            expected_hash: None,
+            on_disk: false,
+        });
+        assert!(history[0].on_disk);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(history[0].on_disk, "only a check looks at the disk");
+        check_all(&mut history);
+        assert!(!history[0].on_disk);
+    }
+}
@@ -97,6 +97,12 @@ macro_rules! define_generic_messages {
                     }
                 }
 
+                pub fn set_tab_message(&mut self, tab: &Tab, msg: impl Into<String>) {
+                    match tab {
+                        $(Tab::$enum_variant => self.[<set_ $name _message>](msg),)+
+                    }
+                }
+
                 pub fn show_message(&self) -> bool {
                     match self.active_tab {
                         $(Tab::$enum_variant => self.[<show_ $name _message>](),)+
@@ -26,6 +26,29 @@ mod shareable;
 mod request;
 mod helper;
 mod network;
+mod flow;
+mod diagnostics;
+mod storage;
+mod peers;
+mod cli;
+mod selftest;
+mod throughput;
+mod audit;
+mod manifest;
+mod backoff;
+mod filetype;
+mod bus;
+mod collection;
+mod compression;
+mod source;
+mod wipe;
+mod archive;
+mod vault;
+mod watcher;
+mod headless;
+mod netconfig;
+mod history;
+mod serving;
 
 #[macro_use]
 mod macros;
@@ -38,6 +61,7 @@ use log::{debug, info, warn, error};
 
 // Standard library
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // local 
 use crate::app::{FileSharingApp, AppUpdate};
@@ -46,78 +70,197 @@ use crate::app::{FileSharingApp, AppUpdate};
 
 
 
+/// Longest a snapshot goes without a refresh; catches state background tasks edit directly
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(1);
+
+/// Drops this frame's key presses, typed text and clicks before a snapshot is drawn.
+/// The snapshot is drawn by the real render functions, so without this Enter in the unlock
+/// dialog or the explore box would act on the throw-away copy.
+fn mute_input(ctx: &egui::Context) {
+    ctx.input_mut(|i| {
+        i.events.clear();
+        i.keys_down.clear();
+        i.pointer = Default::default();
+    });
+}
+
+/// Drawn over a snapshot while the app lock is held elsewhere.
+/// Keeps the pointer off the widgets underneath, and shows a small hint.
+fn render_busy_overlay(ctx: &egui::Context) {
+    egui::Area::new(egui::Id::new("busy_overlay"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(egui::Pos2::ZERO)
+        .show(ctx, |ui| {
+            ui.allocate_rect(ctx.screen_rect(), egui::Sense::click_and_drag());
+        });
+    egui::Area::new(egui::Id::new("busy_hint"))
+        .order(egui::Order::Tooltip)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .show(ctx, |ui| {
+            ui.label(egui::RichText::new("⏳ updating…").weak().small());
+        });
+}
+
+
 #[tokio::main]
 async fn main() -> Result<(), eframe::Error> {
+    // Parse command line flags before anything starts
+    let cli_args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::usage());
+            std::process::exit(2);
+        }
+    };
+    if cli_args.help {
+        println!("{}", cli::usage());
+        return Ok(());
+    }
+
     // Initialize logging
-    helper::init_logging(&"debug.log");
+    helper::init_logging(helper::LOG_FILE);
 
     // Create Tokio runtime for async tasks
     let rt = tokio::runtime::Runtime::new().unwrap();
 
     // Shared application state
-    let app_shared = Arc::new(Mutex::new(FileSharingApp::default()));
+    let mut app = FileSharingApp::default();
+    app.restore_persisted();
+    // Command line flags override persisted and default settings
+    cli_args.apply(&mut app);
+    app.prepare_download_dir();
+    app.download_dir = helper::absolute_dir(&app.download_dir);
+    app.check_download_dir();
+    let offline = app.offline;
+    let app_shared = Arc::new(Mutex::new(app));
 
-    // Initialize sockets
-    network::initialize_sockets(app_shared.clone()).await;
+    // Initialize sockets; offline mode defers this until the user goes online
+    network::init_stop_signal().await;
+    if offline {
+        info!("[*] Starting offline; sockets not initialized");
+    } else {
+        network::initialize_sockets(app_shared.clone()).await;
+    }
 
     let app_clone = app_shared.clone();
 
-    // Download manager task
-    tokio::spawn({
-        let app_clone = app_clone.clone();
-        async move {
-            if let Err(e) = network::download_manager(app_clone).await {
-                eprintln!("download_manager error: {:?}", e);
-            }
-        }
-    });
+    // Download and serving manager tasks
+    network::spawn_managers(app_clone.clone());
 
-    // Serving manager task
-    tokio::spawn({
-        let app_clone = app_clone.clone();
-        async move {
-            if let Err(e) = network::serving_manager(app_clone).await {
-                eprintln!("serving_manager error: {:?}", e);
-            }
+    // Optional loopback self-test
+    if cli_args.self_test {
+        if offline {
+            warn!("[*] Skipping self-test in offline mode");
+        } else {
+            tokio::spawn(selftest::run_self_test(app_clone.clone()));
         }
-    });
+    }
+
+    // Serve without a window until Ctrl-C
+    if cli_args.headless {
+        headless::run(app_shared.clone()).await;
+        network::shutdown(network::SHUTDOWN_TIMEOUT).await;
+        app_shared.lock().await.save_shares();
+        network::stop().await;
+        return Ok(());
+    }
 
     // Window options
+    // The inner size is only the first-run default: with `persist_window` eframe restores
+    // the last size, position and maximized state, clamped to the current monitors.
+    // eframe writes that geometry itself, so it stays outside the vault.
     let options = NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
             .with_inner_size([950.0, 500.0])
             .with_drag_and_drop(true),
+        persist_window: true,
         ..Default::default()
     };
 
     // Wrapper for shared FileSharingApp
     struct AppWrapper {
         app: Arc<Mutex<FileSharingApp>>,
+        snapshot: Option<FileSharingApp>,  // Last state drawn with the lock held, redrawn while it is busy
+        snapshot_taken: Instant,  // When the snapshot was last refreshed
+        frames: u64,          // Frames rendered since the last contention report
+        waiting_frames: u64,  // Frames that could not take the app lock
     }
 
     impl eframe::App for AppWrapper {
         fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+            self.frames += 1;
             if let Ok(mut app) = self.app.try_lock() {
+                // Clicks, typing and dropped files are what change the state between frames, so the
+                // copy is only refreshed after them, or once it is SNAPSHOT_MAX_AGE old; pointer moves,
+                // hovering and held buttons leave it alone
+                let changed = ctx.input(|i| {
+                    !i.raw.dropped_files.is_empty()
+                        || i.events.iter().any(|e| matches!(e,
+                            egui::Event::Key { .. } | egui::Event::Text(_) | egui::Event::Paste(_)
+                                | egui::Event::Cut | egui::Event::Ime(_) | egui::Event::PointerButton { .. }))
+                });
                 FileSharingApp::update(&mut app, ctx, frame);
+                if changed || self.snapshot.is_none() || self.snapshot_taken.elapsed() >= SNAPSHOT_MAX_AGE {
+                    self.snapshot = Some(app.snapshot());
+                    self.snapshot_taken = Instant::now();
+                }
             } else {
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    ui.label("Waiting for app state...");
-                });
+                self.waiting_frames += 1;
+                match &mut self.snapshot {
+                    // Redraw the last state read-only; nothing reaches the copy's widgets
+                    Some(snapshot) => {
+                        mute_input(ctx);
+                        snapshot.render(ctx);
+                        render_busy_overlay(ctx);
+                    }
+                    None => {
+                        egui::CentralPanel::default().show(ctx, |ui| {
+                            ui.label("Waiting for app state...");
+                        });
+                    }
+                }
+            }
+
+            // Report lock contention periodically
+            if self.frames >= 1000 {
+                if self.waiting_frames > 0 {
+                    debug!("Waiting for app state in {} of the last {} frames", self.waiting_frames, self.frames);
+                }
+                self.frames = 0;
+                self.waiting_frames = 0;
             }
 
             
 
             ctx.request_repaint();
         }
+
+        // Let the transfers in progress finish before the window's runtime goes away
+        fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(network::shutdown(network::SHUTDOWN_TIMEOUT))
+            });
+        }
     }
 
     // Run native eframe app
     let result = eframe::run_native(
         "NymShare",
         options,
-        Box::new(|_cc| Ok(Box::new(AppWrapper { app: app_shared.clone() }) as Box<dyn App>)),
+        Box::new(|_cc| Ok(Box::new(AppWrapper { app: app_shared.clone(), snapshot: None, snapshot_taken: Instant::now(), frames: 0, waiting_frames: 0 }) as Box<dyn App>)),
     );
 
+    // Remember the theme, tab, filters and shared files (with their counts and limits) for the
+    // next launch, unless the user wiped the config
+    let app_guard = app_shared.lock().await;
+    if !storage::is_wiped() {
+        if let Err(e) = storage::save(app::VIEW_STATE_FILE, &app_guard.view_state()) {
+            warn!("Failed to save view state: {}", e);
+        }
+        app_guard.save_shares();
+    }
+    drop(app_guard);
+
     // Clean up
     network::stop().await;
 
@@ -0,0 +1,185 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use log::warn;
+use nymlib::nymsocket::SockAddr;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Standard library
+use std::fs;
+use std::path::Path;
+
+// Local
+use crate::app::FileSharingApp;
+use crate::archive::{archive_name, create_staging_dir, BundleMode};
+use crate::request::DownLoadRequest;
+use crate::shareable::Shareable;
+use crate::bus::{post_command, ManagerCommand};
+
+
+/// File extension of exported manifests.
+pub const MANIFEST_EXTENSION: &str = "nymshare";
+
+/// Manifest format version written by this build.
+const MANIFEST_VERSION: u32 = 1;
+
+/// Largest manifest accepted on import; a manifest only lists names and hashes.
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// A self-describing `.nymshare` file: where to download from and what to expect.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Format version, checked on import.
+    pub version: u32,
+
+    /// Serving address the files are downloaded from.
+    pub service: String,
+
+    /// Files offered by the service.
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A single file listed in a manifest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Name the file is shared under.
+    pub name: String,
+
+    /// Size in bytes at export time.
+    pub size: u64,
+
+    /// SHA-256 (hex), if it was computed before export.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Manifest {
+    /// Builds a manifest for the given files served at `service`.
+    pub fn for_files<'a>(service: &str, files: impl IntoIterator<Item = &'a Shareable>) -> Self {
+        let files = files
+            .into_iter()
+            .filter_map(|f| {
+                Some(ManifestEntry {
+                    name: f.file_name()?,
+                    size: f.size().ok()?,
+                    sha256: f.cached_hash().map(str::to_string),
+                })
+            })
+            .collect();
+        Self { version: MANIFEST_VERSION, service: service.to_string(), files }
+    }
+
+    /// Writes the manifest as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+
+    /// Reads and validates a manifest.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let size = fs::metadata(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?
+            .len();
+        if size > MAX_MANIFEST_SIZE {
+            return Err(format!("{} is too large to be a manifest", path.display()));
+        }
+        let data = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Self::from_json(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses and validates a manifest from JSON, such as one sent for a collection.
+    pub fn from_json(data: &[u8]) -> Result<Self, String> {
+        let manifest: Self = serde_json::from_slice(data)
+            .map_err(|e| format!("not a valid manifest: {}", e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Checks the manifest can be turned into download requests.
+    /// File names end up as paths in the download directory, so anything that
+    /// could escape it is rejected.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != MANIFEST_VERSION {
+            return Err(format!("Unsupported manifest version {}", self.version));
+        }
+        if SockAddr::from(self.service.as_str()).is_null() {
+            return Err("Manifest has an invalid service address".to_string());
+        }
+        if self.files.is_empty() {
+            return Err("Manifest lists no files".to_string());
+        }
+        for entry in &self.files {
+            let name = entry.name.as_str();
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                return Err(format!("Manifest has an invalid file name: {:?}", name));
+            }
+            if let Some(hash) = &entry.sha256
+                && (hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit())) {
+                return Err(format!("Manifest has an invalid hash for {:?}", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues a download for every listed file not already requested from the service.
+    /// With [`BundleMode::Zip`] the files are collected into `<name>.zip` in the download directory.
+    /// Returns the number of requests added.
+    pub fn queue_downloads(&self, app: &FileSharingApp, mode: BundleMode, name: &str) -> usize {
+        let sock_addr = SockAddr::from(self.service.as_str());
+        let staging = match mode {
+            BundleMode::Loose => None,
+            BundleMode::Zip => match create_staging_dir(&app.download_dir) {
+                Ok(dir) => Some(dir),
+                Err(e) => {
+                    warn!("Cannot stage a zip in '{}': {}; saving the files loose", app.download_dir.display(), e);
+                    None
+                }
+            },
+        };
+        let mut added = 0;
+        for entry in &self.files {
+            let already_requested = app.requested_files.iter().any(|r| {
+                r.filename == entry.name && r.from == sock_addr && r.range.is_none()
+            });
+            if already_requested {
+                continue;
+            }
+
+            let mut request = DownLoadRequest::new(sock_addr.clone(), entry.name.clone(), Uuid::new_v4().to_string());
+            request.expected_hash = entry.sha256.as_ref().map(|h| h.to_lowercase());
+            if let Some(dir) = &staging {
+                request.save_dir = Some(dir.clone());
+                request.archive = Some(archive_name(name));
+            }
+            post_command(ManagerCommand::AddDownload(request));
+            added += 1;
+        }
+        if added == 0
+            && let Some(dir) = &staging {
+            let _ = fs::remove_dir(dir);
+        }
+        added
+    }
+}
@@ -0,0 +1,104 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+// Standard library
+use std::sync::LazyLock;
+use std::time::Duration;
+
+
+/// Shortest poll interval accepted for any of the manager loops
+pub const MIN_POLL_MS: u64 = 10;
+
+/// Longest poll interval accepted for any of the manager loops
+pub const MAX_POLL_MS: u64 = 10_000;
+
+/// How often the managers poll their sockets and queues.
+/// Slow mixnet routes want longer intervals; a LAN can take shorter ones.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Interval at which serving_manager reads incoming requests.
+    pub serve_poll_ms: u64,
+
+    /// Interval at which download_manager sends queued download and explore requests.
+    pub send_poll_ms: u64,
+
+    /// Interval at which download_manager reads replies from services.
+    pub process_poll_ms: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            serve_poll_ms: 300,
+            send_poll_ms: 200,
+            process_poll_ms: 100,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Serving poll interval, clamped to MIN_POLL_MS..=MAX_POLL_MS.
+    pub fn serve_poll(&self) -> Duration {
+        poll_duration(self.serve_poll_ms)
+    }
+
+    /// Send poll interval, clamped to MIN_POLL_MS..=MAX_POLL_MS.
+    pub fn send_poll(&self) -> Duration {
+        poll_duration(self.send_poll_ms)
+    }
+
+    /// Process poll interval, clamped to MIN_POLL_MS..=MAX_POLL_MS.
+    pub fn process_poll(&self) -> Duration {
+        poll_duration(self.process_poll_ms)
+    }
+}
+
+fn poll_duration(ms: u64) -> Duration {
+    Duration::from_millis(ms.clamp(MIN_POLL_MS, MAX_POLL_MS))
+}
+
+
+/// Config the running managers poll at. Each subscribes once and rebuilds its
+/// intervals when a new config is published, so changes apply without a restart.
+static NETWORK_CONFIG: LazyLock<watch::Sender<NetworkConfig>> =
+    LazyLock::new(|| watch::Sender::new(NetworkConfig::default()));
+
+/// Hands `config` to the managers; does nothing if it is already in use.
+pub fn publish(config: &NetworkConfig) {
+    NETWORK_CONFIG.send_if_modified(|current| {
+        if current == config {
+            return false;
+        }
+        *current = config.clone();
+        true
+    });
+}
+
+/// Receiver for the config in use, marked as seen so only later changes wake it.
+pub fn subscribe() -> watch::Receiver<NetworkConfig> {
+    NETWORK_CONFIG.subscribe()
+}
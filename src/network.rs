@@ -25,21 +25,48 @@
 use nymlib::nymsocket::{Socket, SockAddr, SocketMode};
 use nymlib::serialize::{DataStream, Serialize};
 use tokio::{
-    sync::{broadcast, mpsc, Mutex},
+    sync::{broadcast, mpsc, oneshot, watch, Mutex, MutexGuard, Semaphore},
+    task::JoinHandle,
+    io::{AsyncReadExt, AsyncWriteExt},
     time::{Duration, interval},
 };
 use log::{debug, info, warn, error};
+use sha2::{Digest, Sha256};
+use chrono::Local;
 
 
 // Standard library
 use std::sync::LazyLock;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::io::Write;
 use std::time::Instant;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 // Local 
-use crate::app::FileSharingApp;
-use crate::shareable::Shareable;
+use crate::app::{AppUpdate, FileSharingApp, post_tab_message, post_update};
+use crate::theme::Tab;
+use crate::shareable::{HashCache, Shareable, MAX_SHARE_SIZE};
+use crate::request::{CollisionPolicy, Completion, DownLoadRequest, ExploreRequest, SavedDownload, DOWNLOADS_FILE};
+use crate::bus::{ManagerCommand, MANAGER_COMMANDS, post_command};
+use crate::helper::{has_free_space, is_sha256_hex, relative_download_path, sha256_bytes, time_in_window, unused_path};
+use crate::peers::{record_download, remember_explorer, PeerStats, RecentExplorer, PEERS_FILE};
+use crate::storage;
+use crate::vault;
+use crate::throughput;
+use crate::audit::{self, AuditKind};
+use crate::backoff::{random_unit, Backoff, Retry};
+use crate::flow::{ChunkSizer, ChunkWindow};
+use crate::collection::{advertised_names, is_served, Collection};
+use crate::manifest::Manifest;
+use crate::compression::Encoding;
+use crate::source::{read_source, ShareSource};
+use crate::archive::{self, Archives};
+use crate::history::DownloadRecord;
+use crate::serving::ServeEvent;
+use crate::netconfig;
 
 
 
@@ -48,10 +75,67 @@ use crate::shareable::Shareable;
 pub static DOWNLOAD_SOCKET: LazyLock<Mutex<Option<Arc<Mutex<Socket>>>>> = 
     LazyLock::new(|| Mutex::new(None));
 
-/// Global reference to the serving socket
+/// Name of the serving identity that shares without an explicit identity are served from
+pub const DEFAULT_IDENTITY: &str = "default";
+
+/// A named serving identity: its own data directory, and so its own Nym address
 /// Used to serve local files to peers in Individual mode
-pub static SERVING_SOCKET: LazyLock<Mutex<Option<Arc<Mutex<Socket>>>>> = 
-    LazyLock::new(|| Mutex::new(None));
+pub struct ServingIdentity {
+    pub name: String,
+    pub datadir: String,                                    // Data directory holding the Nym identity
+    pub socket: Mutex<Option<Arc<Mutex<Socket>>>>,          // Serving socket, once created
+    pub listener: Mutex<Option<JoinHandle<()>>>,            // Listener task of the socket
+}
+
+impl ServingIdentity {
+    fn new(name: &str, datadir: String) -> Arc<Self> {
+        Arc::new(Self {
+            name: name.to_string(),
+            datadir,
+            socket: Mutex::new(None),
+            listener: Mutex::new(None),
+        })
+    }
+}
+
+/// Registry of serving identities by name
+/// Always holds DEFAULT_IDENTITY, whose data lives in SERVING_DATADIR
+static SERVING_IDENTITIES: LazyLock<std::sync::Mutex<HashMap<String, Arc<ServingIdentity>>>> =
+    LazyLock::new(|| {
+        let default = ServingIdentity::new(DEFAULT_IDENTITY, SERVING_DATADIR.to_string());
+        std::sync::Mutex::new(HashMap::from([(DEFAULT_IDENTITY.to_string(), default)]))
+    });
+
+/// Returns the serving identity called `name`, if registered
+pub fn serving_identity(name: &str) -> Option<Arc<ServingIdentity>> {
+    SERVING_IDENTITIES.lock().unwrap_or_else(|e| e.into_inner()).get(name).cloned()
+}
+
+/// Returns the default serving identity
+pub fn default_identity() -> Arc<ServingIdentity> {
+    serving_identity(DEFAULT_IDENTITY).expect("default serving identity is always registered")
+}
+
+/// Returns every registered serving identity, the default first, then by name
+pub fn serving_identities() -> Vec<Arc<ServingIdentity>> {
+    let mut identities: Vec<_> = SERVING_IDENTITIES.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect();
+    identities.sort_by(|a, b| (a.name != DEFAULT_IDENTITY, &a.name).cmp(&(b.name != DEFAULT_IDENTITY, &b.name)));
+    identities
+}
+
+/// Checks a name for a new serving identity; it becomes part of a directory name
+pub fn validate_identity_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Identity name is empty".to_string());
+    }
+    if name.len() > 32 || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("Use up to 32 letters, digits, '-' or '_'".to_string());
+    }
+    if serving_identity(name).is_some() {
+        return Err(format!("Identity '{}' already exists", name));
+    }
+    Ok(())
+}
 
 /// Broadcast channel for signaling stop events to background tasks
 /// Shared between serving_manager and download_manager
@@ -59,19 +143,280 @@ pub static STOP_SIGNAL: LazyLock<Arc<Mutex<Option<broadcast::Sender<bool>>>>> =
     LazyLock::new(|| Arc::new(Mutex::new(None))); 
 
 
+/// Sends a message and records its size in the throughput counters
+async fn send_counted(socket: &mut Socket, data: Vec<u8>, to: SockAddr) -> bool {
+    let len = data.len() as u64;
+    let sent = socket.send(data, to).await;
+    if sent {
+        throughput::record_sent(len);
+    }
+    sent
+}
+
+
+/// Listener task of the download socket
+/// Kept so a listener that exits or panics can be detected and restarted
+pub static DOWNLOAD_LISTENER: LazyLock<Mutex<Option<JoinHandle<()>>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// Second download socket, in the mode opposite the download socket's.
+/// Created on demand for explore requests that ask for the other mode; replies come
+/// back on the socket a request went out on, so download_manager drains both.
+pub static ALT_DOWNLOAD_SOCKET: LazyLock<Mutex<Option<Arc<Mutex<Socket>>>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// Listener task of the alternate download socket
+pub static ALT_DOWNLOAD_LISTENER: LazyLock<Mutex<Option<JoinHandle<()>>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// Bookkeeping for the alternate download socket; the socket does not expose its mode
+#[derive(Default)]
+struct AltSocketState {
+    mode: Option<SocketMode>,   // Mode of the socket in ALT_DOWNLOAD_SOCKET
+    creating: bool,             // A socket is being created in the background
+    failures: u32,              // Failed creations in a row
+    retry_at: Option<Instant>,  // No new attempt before this after a failure
+}
+
+static ALT_SOCKET_STATE: LazyLock<Mutex<AltSocketState>> =
+    LazyLock::new(|| Mutex::new(AltSocketState::default()));
+
+/// Data directory holding the default serving identity; other identities use `serving_datadir-<name>`
+pub const SERVING_DATADIR: &str = "serving_datadir";
+
+/// How often the managers check that their socket's listener is still running
+const LISTENER_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+
+/// A socket locked for sending together with the slot it was found in, so it cannot be
+/// replaced between the check in lock_current_socket and the send.
+struct CurrentSocket<'a> {
+    socket: MutexGuard<'a, Socket>,
+    _slot: MutexGuard<'a, Option<Arc<Mutex<Socket>>>>,
+}
+
+impl std::ops::Deref for CurrentSocket<'_> {
+    type Target = Socket;
+
+    fn deref(&self) -> &Socket {
+        &self.socket
+    }
+}
+
+impl std::ops::DerefMut for CurrentSocket<'_> {
+    fn deref_mut(&mut self) -> &mut Socket {
+        &mut self.socket
+    }
+}
+
+/// Locks `socket` for sending if it is still the socket stored in `slot`.
+/// None means it was cleared or replaced (mode switch, new identity, stop) since it
+/// was fetched, and anything sent on it would go out on a dead handle.
+/// Locks the slot before the socket, like the code that replaces sockets, so it must be
+/// called holding neither; drop the result before locking the slot again.
+async fn lock_current_socket<'a>(
+    slot: &'a Mutex<Option<Arc<Mutex<Socket>>>>,
+    socket: &'a Arc<Mutex<Socket>>,
+) -> Option<CurrentSocket<'a>> {
+    let slot_guard = slot.lock().await;
+    if !slot_guard.as_ref().is_some_and(|current| Arc::ptr_eq(current, socket)) {
+        return None;
+    }
+    Some(CurrentSocket { socket: socket.lock().await, _slot: slot_guard })
+}
+
+
+/// Spawns a background listener on a clone of the socket
+fn spawn_listener(socket: &Socket) -> JoinHandle<()> {
+    let mut listen_socket = socket.clone();
+    tokio::spawn(async move {
+        listen_socket.listen().await;
+    })
+}
+
+/// Restarts the listener if it is missing or has finished
+/// Returns true if a new listener was spawned
+async fn restart_dead_listener(
+    socket: &Mutex<Option<Arc<Mutex<Socket>>>>,
+    listener: &Mutex<Option<JoinHandle<()>>>,
+) -> bool {
+    let mut listener_guard = listener.lock().await;
+    if listener_guard.as_ref().is_some_and(|h| !h.is_finished()) {
+        return false;
+    }
+
+    let Some(p_socket) = socket.lock().await.clone() else { return false; };
+    let handle = spawn_listener(&*p_socket.lock().await);
+    *listener_guard = Some(handle);
+    true
+}
+
+
+/// Runs `create` until it yields a socket, waiting between attempts as the backoff policy says.
+/// Returns None once the policy's retries are used up.
+async fn create_with_retries<F, Fut>(policy: &Backoff, what: &str, mut create: F) -> Option<Socket>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<Socket>>,
+{
+    for attempt in 0..policy.attempts() {
+        if let Some(socket) = create().await {
+            return Some(socket);
+        }
+        if attempt + 1 < policy.attempts() {
+            let delay = policy.jittered_delay(attempt);
+            warn!("Failed to create {} (attempt {}); retrying in {:.1}s", what, attempt + 1, delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+        }
+    }
+    None
+}
+
+
+/// Whether two socket modes are the same
+fn same_mode(a: &SocketMode, b: &SocketMode) -> bool {
+    matches!((a, b), (SocketMode::Anonymous, SocketMode::Anonymous) | (SocketMode::Individual, SocketMode::Individual))
+}
+
+
+/// Returns the alternate download socket if it is in `mode`.
+/// Otherwise starts creating one in the background, replacing one in the other mode,
+/// and returns None so the caller tries again on a later tick.
+async fn alt_download_socket(mode: &SocketMode, backoff: &Backoff) -> Option<Arc<Mutex<Socket>>> {
+    let mut state = ALT_SOCKET_STATE.lock().await;
+    if state.mode.as_ref().is_some_and(|m| same_mode(m, mode))
+        && let Some(socket) = ALT_DOWNLOAD_SOCKET.lock().await.clone() {
+        return Some(socket);
+    }
+    if state.creating || state.retry_at.is_some_and(|t| Instant::now() < t) {
+        return None;
+    }
+    state.creating = true;
+    tokio::spawn(create_alt_download_socket(mode.clone(), backoff.clone()));
+    None
+}
+
+
+/// Creates the alternate download socket in `mode` and starts its listener.
+/// A failure is retried on demand once the backoff delay has passed.
+async fn create_alt_download_socket(mode: SocketMode, backoff: Backoff) {
+    info!("[*] Creating alternate download socket");
+    let socket = Socket::new_ephemeral(mode.clone()).await;
+
+    let mut state = ALT_SOCKET_STATE.lock().await;
+    state.creating = false;
+    let Some(socket) = socket else {
+        let delay = backoff.jittered_delay(state.failures);
+        state.failures = state.failures.saturating_add(1);
+        state.retry_at = Some(Instant::now() + delay);
+        warn!("Failed to create alternate download socket; retrying in {:.1}s", delay.as_secs_f64());
+        post_tab_message(Tab::Explore, "Failed to create a socket in the requested mode; retrying");
+        return;
+    };
+    state.failures = 0;
+    state.retry_at = None;
+
+    let mut listener_guard = ALT_DOWNLOAD_LISTENER.lock().await;
+    let mut socket_slot = ALT_DOWNLOAD_SOCKET.lock().await;
+    if let Some(old) = socket_slot.take() {
+        old.lock().await.disconnect().await;
+    }
+    if let Some(handle) = listener_guard.take() {
+        handle.abort();
+    }
+    *listener_guard = Some(spawn_listener(&socket));
+    *socket_slot = Some(Arc::new(Mutex::new(socket)));
+    state.mode = Some(mode);
+}
+
+
+/// Socket an explore request goes out on, with the slot it lives in: the download
+/// socket, or the alternate one when the request asks for the other mode.
+/// None while the alternate socket is being created.
+async fn explore_socket(
+    request: &ExploreRequest,
+    download_socket: &Arc<Mutex<Socket>>,
+    download_mode: &SocketMode,
+    backoff: &Backoff,
+) -> Option<(&'static Mutex<Option<Arc<Mutex<Socket>>>>, Arc<Mutex<Socket>>)> {
+    let mode = request.mode.socket_mode(download_mode);
+    if same_mode(&mode, download_mode) {
+        return Some((&DOWNLOAD_SOCKET, download_socket.clone()));
+    }
+    let socket = alt_download_socket(&mode, backoff).await?;
+    Some((&ALT_DOWNLOAD_SOCKET, socket))
+}
+
+
+/// Sets up the stop signal the managers subscribe to.
+/// Kept apart from socket setup so the managers can run while offline.
+pub async fn init_stop_signal() {
+    let (tx, _rx) = broadcast::channel(1);
+    *STOP_SIGNAL.lock().await = Some(tx);
+}
+
+
+/// Bumped to start the managers again after STOP_SIGNAL, e.g. after an emergency wipe.
+/// Without a bump a stopped manager stays stopped.
+static MANAGER_GENERATION: LazyLock<watch::Sender<u64>> = LazyLock::new(|| watch::Sender::new(0));
+
+/// Managers currently running; each one counts itself out once it has stopped.
+static MANAGERS_RUNNING: LazyLock<watch::Sender<usize>> = LazyLock::new(|| watch::Sender::new(0));
+
+/// Longest a graceful shutdown waits for the managers before giving up on them
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Spawns download_manager and serving_manager.
+/// Each runs until STOP_SIGNAL and is started again by restart_managers.
+pub fn spawn_managers(app: Arc<Mutex<FileSharingApp>>) {
+    tokio::spawn(supervise("download_manager", app.clone(), download_manager));
+    tokio::spawn(supervise("serving_manager", app, serving_manager));
+}
+
+/// Starts the managers stopped by STOP_SIGNAL again.
+/// init_stop_signal must be called first so they subscribe to a fresh signal.
+pub fn restart_managers() {
+    MANAGER_GENERATION.send_modify(|generation| *generation += 1);
+}
+
+async fn supervise<F, Fut>(name: &str, app: Arc<Mutex<FileSharingApp>>, run: F)
+where
+    F: Fn(Arc<Mutex<FileSharingApp>>) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let mut generation = MANAGER_GENERATION.subscribe();
+    loop {
+        generation.mark_unchanged();
+        MANAGERS_RUNNING.send_modify(|running| *running += 1);
+        if let Err(e) = run(app.clone()).await {
+            error!("{} error: {}", name, e);
+        }
+        MANAGERS_RUNNING.send_modify(|running| *running = running.saturating_sub(1));
+        if generation.changed().await.is_err() {
+            break;
+        }
+        info!("[*] Restarting {}", name);
+    }
+}
+
+
+/// Set while download_manager brings the sockets up after going online, so a second
+/// switch to online before they are up does not start them twice.
+static SOCKETS_STARTING: AtomicBool = AtomicBool::new(false);
+
 /// Initializes both serving and download sockets
-/// Spawns background listeners, sets up stop signal, and updates app state
+/// Spawns background listeners and updates app state
 pub async fn initialize_sockets(app: Arc<Mutex<FileSharingApp>>) {
     info!("[*] Started initialize_sockets");
 
-    // Get the socket mode from app state
-    let socket_mode = {
+    // Get the socket mode and retry policy from app state
+    let (socket_mode, backoff) = {
         let app_guard = app.lock().await;
-        app_guard.download_socket_mode.clone()
+        (app_guard.download_socket_mode.clone(), app_guard.backoff.clone())
     };
 
     // Initialize download socket with the selected mode: Default to Anonymous
-    let download_socket = match Socket::new_ephemeral(socket_mode).await {
+    let download_socket = match create_with_retries(&backoff, "download socket", || Socket::new_ephemeral(socket_mode.clone())).await {
         Some(s) => s,
         None => {
             error!("Failed to create download socket; aborting");
@@ -80,49 +425,110 @@ pub async fn initialize_sockets(app: Arc<Mutex<FileSharingApp>>) {
     };
 
     // spawn background listener for download socket
-    let mut download_listen_socket = download_socket.clone();
-    tokio::spawn(async move {
-        download_listen_socket.listen().await;
-    });
+    *DOWNLOAD_LISTENER.lock().await = Some(spawn_listener(&download_socket));
 
     let p_socket = Arc::new(Mutex::new(download_socket));
     *DOWNLOAD_SOCKET.lock().await = Some(p_socket.clone());
 
-    // initialize serving socket (individual mode)
-    let serving_socket = match Socket::new_standard("serving_datadir", SocketMode::Individual).await {
-        Some(s) => s,
-        None => {
-            error!("Failed to create serving socket; aborting");
+    // initialize a serving socket (individual mode) for every identity
+    for identity in serving_identities() {
+        let Some(addr) = start_identity(&identity, &backoff).await else {
+            error!("Failed to create serving socket for identity '{}'; aborting", identity.name);
             return;
+        };
+
+        // update app with serving socket address
+        app.lock().await.set_serving_addr(&identity.name, addr);
+    }
+    post_tab_message(Tab::Share, "Socket initialized successfully");
+}
+
+
+/// Creates the identity's serving socket and spawns its listener
+/// Returns the socket's address, or None if it could not be created
+async fn start_identity(identity: &ServingIdentity, backoff: &Backoff) -> Option<String> {
+    let what = format!("serving socket '{}'", identity.name);
+    let socket = create_with_retries(backoff, &what, || Socket::new_standard(&identity.datadir, SocketMode::Individual)).await?;
+    let addr = socket.getaddr().await?.to_string();
+
+    // spawn background listener for serving socket
+    *identity.listener.lock().await = Some(spawn_listener(&socket));
+    *identity.socket.lock().await = Some(Arc::new(Mutex::new(socket)));
+    Some(addr)
+}
+
+
+/// Registers a new serving identity and starts its socket.
+/// Its data directory is `serving_datadir-<name>`, so re-adding a removed name
+/// brings back the same address.
+pub async fn add_serving_identity(name: String, backoff: Backoff) {
+    if let Err(e) = validate_identity_name(&name) {
+        post_tab_message(Tab::Share, e);
+        return;
+    }
+
+    let identity = ServingIdentity::new(&name, format!("{}-{}", SERVING_DATADIR, name));
+    SERVING_IDENTITIES.lock().unwrap_or_else(|e| e.into_inner()).insert(name.clone(), identity.clone());
+    post_update(AppUpdate::ServingAddress(name.clone(), String::new()));
+
+    match start_identity(&identity, &backoff).await {
+        Some(addr) => {
+            info!("[*] Serving identity '{}' started: {}", name, addr);
+            post_update(AppUpdate::ServingAddress(name.clone(), addr));
+            post_tab_message(Tab::Share, format!("Identity '{}' is ready", name));
+        }
+        None => {
+            SERVING_IDENTITIES.lock().unwrap_or_else(|e| e.into_inner()).remove(&name);
+            post_update(AppUpdate::IdentityRemoved(name.clone()));
+            post_tab_message(Tab::Share, format!("Failed to start identity '{}'", name));
         }
+    }
+}
+
+
+/// Stops a serving identity and forgets it; its data directory is kept.
+/// Files bound to it are not served from any other identity until rebound.
+pub async fn remove_serving_identity(name: String) {
+    if name == DEFAULT_IDENTITY {
+        return;
+    }
+    let Some(identity) = SERVING_IDENTITIES.lock().unwrap_or_else(|e| e.into_inner()).remove(&name) else {
+        return;
     };
 
-    let serving_socket_addr = serving_socket.getaddr().await;
+    // Take the listener first, like rotate_serving_identity, so the watchdog cannot restart it
+    let mut listener_guard = identity.listener.lock().await;
+    if let Some(p_socket) = identity.socket.lock().await.take() {
+        p_socket.lock().await.disconnect().await;
+    }
+    if let Some(handle) = listener_guard.take() {
+        handle.abort();
+    }
+    drop(listener_guard);
 
-    // spawn background listener for serving socket
-    let mut serving_listen_socket = serving_socket.clone();
-    tokio::spawn(async move {
-        serving_listen_socket.listen().await;
-    });
+    info!("[*] Serving identity '{}' removed", name);
+    post_update(AppUpdate::IdentityRemoved(name.clone()));
+    post_tab_message(Tab::Share, format!("Identity '{}' removed", name));
+}
 
-    let p_socket = Arc::new(Mutex::new(serving_socket));
-    *SERVING_SOCKET.lock().await = Some(p_socket.clone());
 
-    // setup stop signal
-    let (tx, _rx) = broadcast::channel(1);
-    {
-        let mut stop_signal = STOP_SIGNAL.lock().await;
-        *stop_signal = Some(tx);
+/// Fires STOP_SIGNAL and waits up to `timeout` for both managers to stop, which
+/// serving_manager does only once the transfers it started have been sent.
+/// Returns false if a manager was still running when the timeout ran out.
+pub async fn shutdown(timeout: Duration) -> bool {
+    info!("[*] Shutting down; waiting for transfers in progress");
+    let mut running = MANAGERS_RUNNING.subscribe();
+    if let Some(signal) = STOP_SIGNAL.lock().await.as_ref() {
+        let _ = signal.send(true);
     }
 
-    // update app with serving socket address
-    {
-        let mut app_opt = app.lock().await;
-        app_opt.serving_addr = serving_socket_addr
-            .expect("Failed to get addr")
-            .to_string();
-        app_opt.set_message("Socket initialized successfully");
+    let stopped = matches!(tokio::time::timeout(timeout, running.wait_for(|running| *running == 0)).await, Ok(Ok(_)));
+    if stopped {
+        info!("[*] Managers stopped");
+    } else {
+        warn!("[*] Managers still running after {:?}; shutting down anyway", timeout);
     }
+    stopped
 }
 
 
@@ -136,9 +542,11 @@ pub async fn stop() {
     }
 
 
-    // Disconnect the SERVING_SOCKET socket 
-    if let Some(socket) = SERVING_SOCKET.lock().await.as_ref().cloned() {
-        socket.lock().await.disconnect().await;
+    // Disconnect the serving sockets of every identity
+    for identity in serving_identities() {
+        if let Some(socket) = identity.socket.lock().await.as_ref().cloned() {
+            socket.lock().await.disconnect().await;
+        }
     }
 
     // Disconnect the DOWNLOAD_SOCKET socket 
@@ -146,242 +554,1764 @@ pub async fn stop() {
         socket.lock().await.disconnect().await;
     }
 
+    // Disconnect the ALT_DOWNLOAD_SOCKET socket, if one was created
+    if let Some(socket) = ALT_DOWNLOAD_SOCKET.lock().await.as_ref().cloned() {
+        socket.lock().await.disconnect().await;
+    }
+
     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
     // clear socket references
-    *SERVING_SOCKET.lock().await = None;
+    for identity in serving_identities() {
+        *identity.socket.lock().await = None;
+    }
     *DOWNLOAD_SOCKET.lock().await = None;
+    *ALT_DOWNLOAD_SOCKET.lock().await = None;
+    ALT_SOCKET_STATE.lock().await.mode = None;
 
     info!("[*] Tasks stopped");
 }
 
 
+/// Replaces the default serving identity with a fresh one (new Nym address).
+///
+/// Holds the listener and socket locks for the whole rotation, so serving_manager
+/// finishes any transfer in progress first and the watchdog cannot restart the
+/// old listener. The old data directory is kept as `serving_datadir.old-<secs>`;
+/// if the new socket cannot be created it is moved back and the old identity restored.
+pub async fn rotate_serving_identity() {
+    info!("[*] Rotating serving identity");
+
+    let identity = default_identity();
+    let mut listener_guard = identity.listener.lock().await;
+    let mut socket_slot = identity.socket.lock().await;
+
+    // Stop the old listener and socket; waits for any send in progress
+    if let Some(p_socket) = socket_slot.take() {
+        p_socket.lock().await.disconnect().await;
+    }
+    if let Some(handle) = listener_guard.take() {
+        handle.abort();
+    }
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let backup = PathBuf::from(format!("{}.old-{}", SERVING_DATADIR, stamp));
+    let rotated = Path::new(SERVING_DATADIR).exists() && std::fs::rename(SERVING_DATADIR, &backup).is_ok();
+
+    let socket = match Socket::new_standard(SERVING_DATADIR, SocketMode::Individual).await {
+        Some(s) => s,
+        None => {
+            error!("Failed to create new serving socket; restoring previous identity");
+            if rotated {
+                let _ = std::fs::remove_dir_all(SERVING_DATADIR);
+                let _ = std::fs::rename(&backup, SERVING_DATADIR);
+            }
+            match Socket::new_standard(SERVING_DATADIR, SocketMode::Individual).await {
+                Some(s) => {
+                    *listener_guard = Some(spawn_listener(&s));
+                    *socket_slot = Some(Arc::new(Mutex::new(s)));
+                }
+                None => error!("Failed to restore serving socket"),
+            }
+            post_tab_message(Tab::Share, "Failed to create a new identity; kept the previous one");
+            return;
+        }
+    };
+
+    let addr = socket.getaddr().await.map(|a| a.to_string()).unwrap_or_default();
+    *listener_guard = Some(spawn_listener(&socket));
+    *socket_slot = Some(Arc::new(Mutex::new(socket)));
+    drop(socket_slot);
+    drop(listener_guard);
+
+    info!("[*] New serving identity: {}", addr);
+    post_update(AppUpdate::ServingAddress(DEFAULT_IDENTITY.to_string(), addr));
+    post_tab_message(Tab::Share, "New identity created; previous links no longer work");
+}
+
+
+/// Identities whose serving socket is being restarted
+static SERVING_RESTARTS: LazyLock<std::sync::Mutex<HashSet<String>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashSet::new()));
+
+/// Recreates an identity's serving socket from its data directory, so its address stays the same.
+/// Used when the socket failed to start (leaving the address empty) or stopped answering.
+///
+/// Takes the listener lock first, like remove_serving_identity, so the watchdog cannot
+/// restart the old listener while the old socket is disconnected; the old listener is
+/// aborted before the new socket and its listener are started.
+pub async fn reinitialize_serving_socket(name: String, backoff: Backoff) {
+    let Some(identity) = serving_identity(&name) else {
+        return;
+    };
+    if !SERVING_RESTARTS.lock().unwrap_or_else(|e| e.into_inner()).insert(name.clone()) {
+        post_tab_message(Tab::Share, format!("Identity '{}' is already restarting", name));
+        return;
+    }
+    info!("[*] Reinitializing serving socket '{}'", name);
+
+    // Stop the old listener and socket; waits for any send in progress
+    let mut listener_guard = identity.listener.lock().await;
+    if let Some(p_socket) = identity.socket.lock().await.take() {
+        p_socket.lock().await.disconnect().await;
+    }
+    if let Some(handle) = listener_guard.take() {
+        handle.abort();
+    }
+    drop(listener_guard);
+    post_update(AppUpdate::ServingAddress(name.clone(), String::new()));
+
+    match start_identity(&identity, &backoff).await {
+        Some(addr) => {
+            info!("[*] Serving socket '{}' restarted: {}", name, addr);
+            post_update(AppUpdate::ServingAddress(name.clone(), addr));
+            post_tab_message(Tab::Share, format!("Serving restarted for '{}'", name));
+        }
+        None => {
+            error!("Failed to recreate serving socket '{}'", name);
+            post_tab_message(Tab::Share, format!("Failed to restart serving for '{}'", name));
+        }
+    }
+    SERVING_RESTARTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&name);
+}
+
+
 /// Reinitializes the download socket with the specified mode
 pub async fn reinitialize_download_socket(app: Arc<Mutex<FileSharingApp>>) {
     info!("[*] Reinitializing download socket");
 
-    // Get the socket mode from app state
-    let socket_mode = {
+    // Get the socket mode and retry policy from app state
+    let (socket_mode, backoff) = {
         let app_guard = app.lock().await;
-        app_guard.download_socket_mode.clone()
+        (app_guard.download_socket_mode.clone(), app_guard.backoff.clone())
     };
 
     // Create new download socket with the selected mode
-    let download_socket = match Socket::new_ephemeral(socket_mode).await {
+    let download_socket = match create_with_retries(&backoff, "download socket", || Socket::new_ephemeral(socket_mode.clone())).await {
         Some(s) => s,
         None => {
             error!("Failed to create download socket; aborting");
-            let mut app_guard = app.lock().await;
-            app_guard.set_message("Failed to reinitialize download socket");
+            post_tab_message(Tab::Download, "Failed to reinitialize download socket");
             return;
         }
     };
 
-    // spawn background listener for download socket
-    let mut download_listen_socket = download_socket.clone();
-    tokio::spawn(async move {
-        download_listen_socket.listen().await;
-    });
-
-    // Update global DOWNLOAD_SOCKET
-    let p_socket = Arc::new(Mutex::new(download_socket));
-    *DOWNLOAD_SOCKET.lock().await = Some(p_socket.clone());
-
+    // Stop the old listener and socket under the listener lock, so the watchdog cannot
+    // restart the old listener, then start the new ones
+    let mut listener_guard = DOWNLOAD_LISTENER.lock().await;
+    let mut socket_slot = DOWNLOAD_SOCKET.lock().await;
+    if let Some(old) = socket_slot.take() {
+        old.lock().await.disconnect().await;
+    }
+    if let Some(handle) = listener_guard.take() {
+        handle.abort();
+    }
+    *listener_guard = Some(spawn_listener(&download_socket));
+    *socket_slot = Some(Arc::new(Mutex::new(download_socket)));
 }
 
 
 
 
 
+/// Message commands. Each message is its command followed by the command's fields.
+///
+/// Fields added after a command first shipped are trailing and optional: senders append
+/// them after the older fields, and readers take each one with a default when it is
+/// missing. Older peers stop reading before fields they do not know, and newer peers
+/// reading a message from an older one get the defaults, which keep the old behaviour.
 pub mod COMMANDS {
     pub const FILE_REQUEST: &str = "FILE_REQUEST";   
     pub const GETFILE: &str = "GETFILE";
+    pub const GETFILE_BY_HASH: &str = "GETFILE_BY_HASH";
+    pub const FILE_RANGE_REQUEST: &str = "FILE_RANGE_REQUEST";
+    pub const GETFILE_RANGE: &str = "GETFILE_RANGE";
+    pub const GETFILE_CHUNK: &str = "GETFILE_CHUNK";
     pub const ACK_FILE_REQUEST: &str = "ACK_FILE_REQUEST";   
+    pub const ACK_FILE_REQUEST_FAIL: &str = "ACK_FILE_REQUEST_FAIL";
     pub const ADVERTISE: &str = "ADVERTISE";         
     pub const GETADVERTISE: &str = "GETADVERTISE"; 
     pub const ACK_ADVERTISE_REQUEST: &str = "ACK_ADVERTISE_REQUEST";   
+    pub const PUSH_ADVERTISE: &str = "PUSH_ADVERTISE";
+    pub const PING: &str = "PING";
+    pub const PONG: &str = "PONG";
+    pub const COLLECTION_REQUEST: &str = "COLLECTION_REQUEST";
+    pub const GETCOLLECTION: &str = "GETCOLLECTION";
+    pub const FILE_STATUS: &str = "FILE_STATUS";
+    pub const FILE_STATUS_REPLY: &str = "FILE_STATUS_REPLY";
+    pub const CHUNK_ACK: &str = "CHUNK_ACK";
         
 }
 
 
-/// Background task that manages serving local files to peers.
-///
-/// Responsibilities:
-/// 1. Listens for incoming file requests from remote peers.
-/// 2. Sends an acknowledgment (ACK) for each valid request.
-/// 3. Reads the requested file from disk and sends it to the requester.
-/// 4. Updates the app state (download counts, logging).
-pub async fn serving_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), String> {
-    info!("[*] Started serving_manager");
+/// Window in which a peer's ADVERTISE requests count once toward advertise stats
+const ADVERTISE_WINDOW: Duration = Duration::from_secs(600);
 
-    // Initialize stop signal
-    let mut stop_signal_rx = {
-        let guard = STOP_SIGNAL.lock().await;
-        guard
-            .as_ref()
-            .ok_or_else(|| "Stop signal not initialized".to_string())?
-            .subscribe()
-    };
+/// Default for the first-page ADVERTISE responses sent to a single peer per window
+pub const MAX_ADVERTISE_RESPONSES: u32 = 5;
 
-    // Setup periodic interval
-    let mut interval = interval(Duration::from_millis(300));
+/// Maximum responses to follow-up page requests sent to a single peer per window
+const MAX_ADVERTISE_PAGE_RESPONSES: u32 = 200;
 
-    loop {
-        tokio::select! {
-            // Handle stop signal
-            result = stop_signal_rx.recv() => {
-                match result {
-                    Ok(true) => {
-                        info!("[*] Stopping serving_manager task");
-                        break Ok(());
-                    }
-                    Ok(false) => continue,
-                    Err(e) => {
-                        info!("[*] Stop signal error: {}", e);
-                        break Ok(());
-                    }
-                }
-            }
+/// File names per GETADVERTISE page; keeps each page well under MAX_ADVERTISE_MESSAGE
+pub const ADVERTISE_PAGE_SIZE: usize = 200;
 
-            // Process incoming messages
-            _ = interval.tick() => {
-                // Lock socket and drain messages
-                let socket_opt = SERVING_SOCKET.lock().await;
-                let Some(p_socket) = &*socket_opt else { continue; };
+/// Per-peer ADVERTISE bookkeeping kept by serving_manager
+#[derive(Debug)]
+struct AdvertisePeerState {
+    window_start: Instant,  // Start of the current window
+    responses: u32,         // First-page responses sent in the current window
+    page_responses: u32,    // Follow-up page responses sent in the current window
+    counted_pages: HashSet<u32>, // Pages whose files already bumped the advertise counters for this peer
+    refused: u32,           // Requests refused by the caps in the current window
+}
 
-                // Drain messages while holding the lock briefly
-                let messages: Vec<_> = {
-                    let mut socket_guard = p_socket.lock().await;
-                    let mut recv_guard = socket_guard.recv.lock().await;
-                    recv_guard.drain(..).collect()
-                };
+impl AdvertisePeerState {
+    fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            responses: 0,
+            page_responses: 0,
+            counted_pages: HashSet::new(),
+            refused: 0,
+        }
+    }
+}
 
-                // Process each message without holding the socket lock
-                for message in messages {
-                    let mut stream = DataStream::default();
-                    stream.write(&message.data);
+/// Advertise traffic in the current window, summed over peers, for display in the Share tab
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AdvertiseRate {
+    pub peers: usize,     // Peers that asked for our list
+    pub responses: u32,   // Lists and pages sent
+    pub refused: u32,     // Requests refused by the per-peer caps
+}
 
-                    let command = match stream.stream_out::<String>() {
-                        Ok(cmd) => cmd,
-                        Err(_) => {
-                            warn!("Invalid message format: missing command");
-                            continue;
-                        }
-                    };
+impl AdvertiseRate {
+    fn of(peers: &HashMap<String, AdvertisePeerState>) -> Self {
+        Self {
+            peers: peers.len(),
+            responses: peers.values().map(|p| p.responses + p.page_responses).sum(),
+            refused: peers.values().map(|p| p.refused).sum(),
+        }
+    }
+}
 
-                    match command.as_str() {
-                        COMMANDS::FILE_REQUEST => {
-                            info!("[*] Received FILE_REQUEST");
+/// Window for the per-peer file request limit
+const REQUEST_RATE_WINDOW: Duration = Duration::from_secs(60);
 
-                            let (request_id, requested_file_name) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
-                                (Ok(id), Ok(name)) => (id, name),
-                                (Err(_), _) => { info!("Missing request_id"); continue; },
-                                (_, Err(_)) => { info!("Missing filename"); continue; },
-                            };
+/// Default for the file requests a single peer may make per window
+pub const MAX_REQUESTS_PER_MINUTE: u32 = 30;
 
-                            let mut app_guard = app.lock().await;
-                            let file_opt = app_guard.shareable_files.iter_mut()
-                                .find(|f| f.file_name().map(|n| n == requested_file_name).unwrap_or(false) && f.is_active());
+/// Per-peer file request bookkeeping kept by serving_manager
+#[derive(Debug)]
+struct RequestRateState {
+    window_start: Instant,  // Start of the current window
+    requests: u32,          // Requests let through in the current window
+    dropped: u32,           // Requests dropped over the limit in the current window
+}
 
-                            let Some(file) = file_opt else {
-                                info!("File {} not found or inactive", requested_file_name);
-                                continue;
-                            };
+/// Counts a request from `from` against its per-peer limit (0 = unlimited) and returns
+/// false when it is over. Only the first drop in a window is logged at warn level and
+/// audited, so a flood does not flood the log as well.
+fn admit_request(peers: &mut HashMap<String, RequestRateState>, identity: &str, from: &SockAddr, command: &str, limit: u32) -> bool {
+    peers.retain(|_, p| p.window_start.elapsed() < REQUEST_RATE_WINDOW);
+    let peer = peers.entry(format!("{}/{}", identity, from)).or_insert_with(|| RequestRateState {
+        window_start: Instant::now(),
+        requests: 0,
+        dropped: 0,
+    });
+    if limit == 0 || peer.requests < limit {
+        peer.requests += 1;
+        return true;
+    }
+    peer.dropped += 1;
+    if peer.dropped == 1 {
+        warn!("Dropping {} from {}: over the limit of {} requests per minute", command, from, limit);
+        audit::record(AuditKind::Error, command, from, format!("over the limit of {} requests per minute; dropping until the window ends", limit));
+    } else {
+        debug!("Dropping {} from {} ({} dropped this window)", command, from, peer.dropped);
+    }
+    false
+}
 
-                            let mut socket_guard = p_socket.lock().await;
+/// Requests dropped by the per-peer limit in the current window, summed over peers
+fn dropped_requests(peers: &HashMap<String, RequestRateState>) -> u32 {
+    peers.values().map(|p| p.dropped).sum()
+}
 
-                            // Send ACK
-                            let mut ack_stream = DataStream::default();
-                            ack_stream.stream_in(&COMMANDS::ACK_FILE_REQUEST);
-                            ack_stream.stream_in(&request_id);
-                            if socket_guard.send(ack_stream.data.clone(), message.from.clone()).await {
-                                info!("Sent ACK for '{}' (id={})", requested_file_name, request_id);
-                            } else {
-                                warn!("Failed to send ACK for '{}'", requested_file_name);
-                                continue;
-                            }
 
-                            // Send file
-                            let file_bytes = match file.read_bytes() {
-                                Ok(b) => b,
-                                Err(e) => { warn!("Failed to read '{}': {:?}", requested_file_name, e); continue; },
-                            };
+/// Size of a control message: command, request id, a file name or hash and a few numbers
+const MAX_CONTROL_MESSAGE: usize = 4 * 1024;
 
-                            let mut out_stream = DataStream::default();
-                            out_stream.stream_in(&COMMANDS::GETFILE);
-                            out_stream.stream_in(&request_id);
-                            out_stream.stream_in(&file_bytes);
+/// Size of an advertise list (names, hashes and a note)
+const MAX_ADVERTISE_MESSAGE: usize = 1024 * 1024;
 
-                            if socket_guard.send(out_stream.data.clone(), message.from.clone()).await {
-                                file.downloads = file.downloads.saturating_add(1);
-                                info!("Sent file {} to {:?}", requested_file_name, message.from.to_string());
-                            } else {
-                                warn!("Failed to send file {}", requested_file_name);
+/// Size of a file message: the largest shareable file plus framing
+const MAX_FILE_MESSAGE: usize = MAX_SHARE_SIZE as usize + MAX_CONTROL_MESSAGE;
+
+/// Largest GETFILE_CHUNK payload; requesters announce it and services send chunks of at most this size
+pub const FILE_CHUNK_SIZE: u32 = 256 * 1024;
+
+/// Smallest chunk a service sends, however small a chunk the requester asked for
+pub const MIN_FILE_CHUNK_SIZE: u32 = 16 * 1024;
+
+/// Default for the chunks a requester lets a service send ahead of its CHUNK_ACKs
+pub const DEFAULT_CHUNK_WINDOW: u32 = 8;
+
+/// Largest window a service honours, whatever the requester announced
+pub const MAX_CHUNK_WINDOW: u32 = 256;
+
+/// How long a service waits for a CHUNK_ACK that moves the window before it sends
+/// the unacknowledged chunks again
+const CHUNK_ACK_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// How often a requester repeats its last CHUNK_ACK while no chunk arrives, in case it was lost
+const CHUNK_REACK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Size of a chunk message: one chunk plus framing
+const MAX_CHUNK_MESSAGE: usize = FILE_CHUNK_SIZE as usize + MAX_CONTROL_MESSAGE;
+
+/// Returns the largest message accepted for a command.
+/// Checked right after the command is read, before any payload is deserialized,
+/// so a control message claiming a huge payload is dropped immediately.
+fn max_message_size(command: &str) -> usize {
+    match command {
+        COMMANDS::GETFILE | COMMANDS::GETFILE_RANGE => MAX_FILE_MESSAGE,
+        COMMANDS::GETFILE_CHUNK => MAX_CHUNK_MESSAGE,
+        COMMANDS::GETADVERTISE | COMMANDS::PUSH_ADVERTISE | COMMANDS::GETCOLLECTION => MAX_ADVERTISE_MESSAGE,
+        _ => MAX_CONTROL_MESSAGE,
+    }
+}
+
+/// Returns false (and logs) if a message is larger than its command allows.
+fn within_size_limit(command: &str, message_len: usize, from: &SockAddr) -> bool {
+    let limit = max_message_size(command);
+    if message_len <= limit {
+        return true;
+    }
+    warn!("Dropping {} from {}: {} bytes exceeds the {} byte limit", command, from, message_len, limit);
+    audit::record(AuditKind::Error, command, from, format!("oversized message ({} bytes, limit {})", message_len, limit));
+    false
+}
+
+
+/// One page of the active files, as carried by an advertise list.
+struct AdvertisePage {
+    page: u32,
+    total_pages: u32,     // Always at least one
+    names: Vec<String>,
+    hashes: Vec<String>,  // Parallel to the names, "" when not yet computed
+    types: Vec<String>,   // Parallel to the names, FileType labels
+    collections: Vec<String>, // Active collection names, first page only
+    note: &'static str,   // Why the list is empty, overriding the default note ("" for none)
+}
+
+/// Returns one page of the active files; the first page also lists the active collections.
+fn advertise_page(shareable_files: &[Shareable], collections: &[Collection], page: u32) -> AdvertisePage {
+    let active_files: Vec<(String, &Shareable)> = shareable_files
+        .iter()
+        .filter(|f| f.is_active())
+        .filter_map(|f| f.file_name().map(|n| (n, f)))
+        .collect();
+
+    let total_pages = active_files.len().div_ceil(ADVERTISE_PAGE_SIZE).max(1) as u32;
+    let collections = if page == 0 { advertised_names(collections, shareable_files) } else { Vec::new() };
+    let mut listed = AdvertisePage { page, total_pages, names: Vec::new(), hashes: Vec::new(), types: Vec::new(), collections, note: "" };
+    for (name, file) in active_files.into_iter().skip(page as usize * ADVERTISE_PAGE_SIZE).take(ADVERTISE_PAGE_SIZE) {
+        listed.names.push(name);
+        listed.hashes.push(file.cached_hash().unwrap_or_default().to_string());
+        listed.types.push(file.file_type.label().to_string());
+    }
+    listed
+}
+
+/// Encodes an advertise list page as GETADVERTISE or PUSH_ADVERTISE.
+fn encode_advertise(command: &str, request_id: &str, listed: &AdvertisePage) -> Vec<u8> {
+    let mut out_stream = DataStream::default();
+    out_stream.stream_in(&command.to_string());
+    out_stream.stream_in(&request_id.to_string());
+    out_stream.stream_in(&listed.names);
+    // Cached hashes, parallel to the names ("" when not yet hashed)
+    out_stream.stream_in(&listed.hashes);
+    // Note explaining an empty list ("" when there is nothing to say)
+    let note = if !listed.note.is_empty() {
+        listed.note
+    } else if listed.page == 0 && listed.names.is_empty() && listed.collections.is_empty() {
+        "no files are currently active"
+    } else {
+        ""
+    };
+    out_stream.stream_in(&note.to_string());
+    // Page of the list carried and how many pages there are
+    out_stream.stream_in(&listed.page);
+    out_stream.stream_in(&listed.total_pages);
+    // Content types, parallel to the names
+    out_stream.stream_in(&listed.types);
+    // Collections, fetched member by member with COLLECTION_REQUEST
+    out_stream.stream_in(&listed.collections);
+    out_stream.data
+}
+
+/// Pushes the first page of our current catalog to explorers that asked for it before,
+/// echoing each one's explore request id so their existing request is refreshed.
+/// The caller checks advertise mode; explorers fetch further pages themselves.
+/// Explorers are only remembered by the default identity, so only its files are pushed.
+pub async fn push_advertise(explorers: HashMap<String, RecentExplorer>, mut shareable_files: Vec<Shareable>, collections: Vec<Collection>) {
+    shareable_files.retain(|f| f.served_by(DEFAULT_IDENTITY));
+    let listed = advertise_page(&shareable_files, &collections, 0);
+
+    let identity = default_identity();
+    let Some(p_socket) = identity.socket.lock().await.clone() else {
+        post_tab_message(Tab::Share, "Serving socket is not running; nobody was notified");
+        return;
+    };
+
+    let mut notified = 0;
+    for (addr, explorer) in &explorers {
+        let to = SockAddr::from(addr.as_str());
+        let data = encode_advertise(COMMANDS::PUSH_ADVERTISE, &explorer.request_id, &listed);
+        let Some(mut socket_guard) = lock_current_socket(&identity.socket, &p_socket).await else {
+            warn!("Serving socket changed while notifying explorers; stopping");
+            break;
+        };
+        if send_counted(&mut socket_guard, data, to.clone()).await {
+            notified += 1;
+            audit::record(AuditKind::Advertise, COMMANDS::PUSH_ADVERTISE, &to, format!("{} file(s) pushed", listed.names.len()));
+        } else {
+            warn!("Failed to send PUSH_ADVERTISE to {}", addr);
+            audit::record(AuditKind::Error, COMMANDS::PUSH_ADVERTISE, &to, "send failed");
+        }
+    }
+    post_tab_message(Tab::Share, format!("Notified {} of {} explorer(s) of catalog changes", notified, explorers.len()));
+}
+
+
+/// Answers an ADVERTISE that passed the per-peer caps: the ACK (first page only), then the page.
+/// The page is taken before the socket is locked so the app is never locked while holding it.
+/// Lists only the files bound to the identity the request reached.
+async fn answer_advertise(ctx: ServeContext, page: u32, count_advertise: bool, outside_window: bool) {
+    let ServeContext { app, identity, p_socket, from, request_id } = ctx;
+    let listed = if outside_window {
+        AdvertisePage {
+            page, total_pages: 1, names: Vec::new(), hashes: Vec::new(), types: Vec::new(), collections: Vec::new(),
+            note: "files are only advertised at certain hours",
+        }
+    } else {
+        let app_guard = app.lock().await;
+        let served: Vec<Shareable> = app_guard.shareable_files.iter().filter(|f| f.served_by(&identity.name)).cloned().collect();
+        advertise_page(&served, &app_guard.collections, page)
+    };
+    let data = encode_advertise(COMMANDS::GETADVERTISE, &request_id, &listed);
+    let (shareable_files, total_pages) = (listed.names, listed.total_pages);
+
+    {
+        let Some(mut socket_guard) = lock_current_socket(&identity.socket, &p_socket).await else {
+            warn!("Serving socket changed before answering ADVERTISE (id={}); dropping request", request_id);
+            return;
+        };
+
+        // Send ACK; follow-up pages are answered with the page alone
+        if page == 0 {
+            let mut ack_stream = DataStream::default();
+            ack_stream.stream_in(&COMMANDS::ACK_ADVERTISE_REQUEST);
+            ack_stream.stream_in(&request_id);
+            if send_counted(&mut socket_guard, ack_stream.data.clone(), from.clone()).await {
+                info!("Sent ACK_ADVERTISE_REQUEST for (id={})", request_id);
+            } else {
+                warn!("Failed to send ACK_ADVERTISE_REQUEST for '{}'", request_id);
+                return;
+            }
+        }
+
+        if send_counted(&mut socket_guard, data, from.clone()).await {
+            info!("[*] Sent GETADVERTISE page {}/{} {:?} to {}", page + 1, total_pages, shareable_files, from);
+            audit::record(AuditKind::Advertise, COMMANDS::GETADVERTISE, &from, format!("{} file(s) listed (page {}/{})", shareable_files.len(), page + 1, total_pages));
+        } else {
+            info!("[*] Failed to send GETADVERTISE to {:?}", from);
+            audit::record(AuditKind::Error, COMMANDS::GETADVERTISE, &from, "send failed");
+            return;
+        }
+    }
+
+    let mut app_guard = app.lock().await;
+
+    // Individual-mode explorers can be told about later catalog changes (pushed from the default identity)
+    if page == 0 && !from.is_null() && identity.name == DEFAULT_IDENTITY {
+        remember_explorer(&mut app_guard.recent_explorers, from.to_string(), request_id.clone());
+    }
+
+    // Increment advertise counts once per distinct peer and page per window
+    if !count_advertise {
+        return;
+    }
+    for filename in &shareable_files {
+        for f in app_guard.shareable_files.iter_mut().filter(|f| f.served_by(&identity.name)) {
+            if let Some(name) = &f.file_name()
+                && name == filename {
+                f.advertise = f.advertise.saturating_add(1);
+            }
+        }
+    }
+}
+
+
+/// Sends an ACK_FILE_REQUEST_FAIL telling the requester why its request was refused.
+async fn send_file_request_fail(socket: &mut Socket, to: SockAddr, request_id: &str, reason: &str) {
+    let mut stream = DataStream::default();
+    stream.stream_in(&COMMANDS::ACK_FILE_REQUEST_FAIL);
+    stream.stream_in(&request_id.to_string());
+    stream.stream_in(&reason.to_string());
+    audit::record(AuditKind::Nack, COMMANDS::ACK_FILE_REQUEST_FAIL, &to, format!("{} ({})", request_id, reason));
+    if !send_counted(socket, stream.data.clone(), to).await {
+        warn!("Failed to send ACK_FILE_REQUEST_FAIL for request '{}'", request_id);
+    }
+}
+
+
+/// Sends ACK_FILE_REQUEST naming the file being served. Returns false if the send failed.
+async fn send_file_ack(socket: &mut Socket, to: SockAddr, request_id: &str, file_name: &str) -> bool {
+    let mut stream = DataStream::default();
+    stream.stream_in(&COMMANDS::ACK_FILE_REQUEST);
+    stream.stream_in(&request_id.to_string());
+    stream.stream_in(&file_name.to_string());
+    if send_counted(socket, stream.data.clone(), to.clone()).await {
+        info!("Sent ACK for '{}' (id={})", file_name, request_id);
+        audit::record(AuditKind::Ack, COMMANDS::ACK_FILE_REQUEST, &to, format!("{} ({})", file_name, request_id));
+        true
+    } else {
+        warn!("Failed to send ACK for '{}'", file_name);
+        audit::record(AuditKind::Error, COMMANDS::ACK_FILE_REQUEST, &to, format!("send failed for '{}'", file_name));
+        false
+    }
+}
+
+
+/// Whole-file transfers in progress per shared path. Counted against the download
+/// limit so concurrent requests for the same file cannot overshoot it.
+static RESERVED_DOWNLOADS: LazyLock<std::sync::Mutex<HashMap<PathBuf, u32>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// A download counted in RESERVED_DOWNLOADS until it is dropped
+struct DownloadReservation(PathBuf);
+
+impl DownloadReservation {
+    fn take(path: &Path) -> Self {
+        *RESERVED_DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner()).entry(path.to_path_buf()).or_default() += 1;
+        Self(path.to_path_buf())
+    }
+
+    /// Transfers of `path` reserved so far
+    fn count(path: &Path) -> u32 {
+        RESERVED_DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner()).get(path).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for DownloadReservation {
+    fn drop(&mut self) {
+        let mut reserved = RESERVED_DOWNLOADS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = reserved.get_mut(&self.0) {
+            *count -= 1;
+            if *count == 0 {
+                reserved.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// How long a served request_id is remembered; a resend within it only gets the ACK again.
+const SERVED_REQUEST_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Progress of a file request being or recently served, keyed by (requester, request_id).
+struct ServedRequest {
+    file_name: Option<String>, // Name sent in the ACK, once it is out
+    sent_at: Option<Instant>,  // When the file went out; None while still serving
+}
+
+static SERVED_REQUESTS: LazyLock<std::sync::Mutex<HashMap<(String, String), ServedRequest>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// A request_id being served. Dropped without [`ServedGuard::sent`] (refused or failed),
+/// it is forgotten so a resend is served in full.
+struct ServedGuard {
+    key: (String, String),
+    sent: bool,
+}
+
+/// What to do with an incoming file request.
+enum ServeStart {
+    /// First time in the window; serve it.
+    New(ServedGuard),
+    /// Already served or being served; the ACK name to send again, if the ACK went out.
+    Duplicate(Option<String>),
+}
+
+impl ServedGuard {
+    fn start(from: &SockAddr, request_id: &str) -> ServeStart {
+        let key = (from.to_string(), request_id.to_string());
+        let mut served = SERVED_REQUESTS.lock().unwrap_or_else(|e| e.into_inner());
+        served.retain(|_, r| r.sent_at.is_none_or(|t| t.elapsed() < SERVED_REQUEST_WINDOW));
+        if let Some(existing) = served.get(&key) {
+            return ServeStart::Duplicate(existing.file_name.clone());
+        }
+        served.insert(key.clone(), ServedRequest { file_name: None, sent_at: None });
+        ServeStart::New(Self { key, sent: false })
+    }
+
+    /// Records the name the ACK went out with, for resending it.
+    fn acked(&self, file_name: &str) {
+        if let Some(r) = SERVED_REQUESTS.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&self.key) {
+            r.file_name = Some(file_name.to_string());
+        }
+    }
+
+    /// Marks the file sent; resends within the window are answered with the ACK only.
+    fn sent(mut self) {
+        if let Some(r) = SERVED_REQUESTS.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&self.key) {
+            r.sent_at = Some(Instant::now());
+        }
+        self.sent = true;
+    }
+}
+
+impl Drop for ServedGuard {
+    fn drop(&mut self) {
+        if !self.sent {
+            SERVED_REQUESTS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+        }
+    }
+}
+
+/// Largest accepted max_concurrent_uploads
+pub const MAX_UPLOADS_LIMIT: usize = 64;
+
+/// A request being answered by serving_manager: the app, the identity and socket it
+/// reached, and who to reply to under which request id.
+struct ServeContext {
+    app: Arc<Mutex<FileSharingApp>>,
+    identity: Arc<ServingIdentity>,
+    p_socket: Arc<Mutex<Socket>>,
+    from: SockAddr,
+    request_id: String,
+}
+
+impl ServeContext {
+    fn new(
+        app: &Arc<Mutex<FileSharingApp>>,
+        identity: &Arc<ServingIdentity>,
+        p_socket: &Arc<Mutex<Socket>>,
+        from: &SockAddr,
+        request_id: String,
+    ) -> Self {
+        Self { app: app.clone(), identity: identity.clone(), p_socket: p_socket.clone(), from: from.clone(), request_id }
+    }
+
+    /// Locks the socket the request arrived on for a send; None, logged, once it was replaced.
+    async fn socket(&self) -> Option<CurrentSocket<'_>> {
+        let socket = lock_current_socket(&self.identity.socket, &self.p_socket).await;
+        if socket.is_none() {
+            warn!("Serving socket changed while answering request '{}'; dropping the reply", self.request_id);
+        }
+        socket
+    }
+
+    /// Refuses the request with `reason`, unless the socket it arrived on was replaced.
+    async fn refuse(&self, reason: &str) {
+        if let Some(mut socket) = self.socket().await {
+            send_file_request_fail(&mut socket, self.from.clone(), &self.request_id, reason).await;
+        }
+    }
+}
+
+/// Earliest time the next upload may start under the upload cap, shared by all transfers
+static UPLOAD_PACE: LazyLock<Mutex<Option<Instant>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Waits for this payload's turn under the upload cap.
+/// Each transfer books the time its payload takes at `limit` bytes/s after the
+/// previous booking, so concurrent transfers together stay under the cap.
+async fn pace_upload(len: usize, limit: u64) {
+    let start = {
+        let mut next = UPLOAD_PACE.lock().await;
+        let now = Instant::now();
+        let start = next.filter(|t| *t > now).unwrap_or(now);
+        *next = Some(start + Duration::from_secs_f64(len as f64 / limit as f64));
+        start
+    };
+    if start > Instant::now() {
+        debug!("Upload limit {} B/s; waiting {:?} before sending", limit, start - Instant::now());
+        tokio::time::sleep_until(start.into()).await;
+    }
+}
+
+
+/// How a requester takes a whole file, from the trailing fields of its request.
+#[derive(Clone, Copy, Debug, Default)]
+struct ChunkOptions {
+    size: u32,    // Largest chunk it takes (0 = one GETFILE; it predates chunking)
+    offset: u64,  // Bytes it already has
+    window: u32,  // Chunks it takes ahead of its CHUNK_ACKs (0 = it sends none)
+}
+
+/// Why send_file_chunks stopped before the end of the file
+#[derive(Debug, PartialEq)]
+enum ChunkStop {
+    Unreadable,  // The file could not be read; the requester is refused
+    Abandoned,   // A send failed or the requester stopped acknowledging
+}
+
+impl ChunkOptions {
+    /// Reads the chunk size, offset and window trailing a file request.
+    fn read(stream: &mut DataStream) -> Self {
+        let size = stream.stream_out::<u32>().unwrap_or(0);
+        let offset = stream.stream_out::<u64>().unwrap_or(0);
+        let window = stream.stream_out::<u32>().unwrap_or(0);
+        Self { size, offset, window }
+    }
+}
+
+/// Reads the encodings a requester accepts, trailing its request (none = raw only).
+fn read_accepted_encodings(stream: &mut DataStream) -> Vec<String> {
+    stream.stream_out::<Vec<String>>().unwrap_or_default()
+}
+
+/// Size of the last new chunk sent, for the diagnostics report (None = no chunked transfer yet)
+static EFFECTIVE_CHUNK_SIZE: std::sync::Mutex<Option<u32>> = std::sync::Mutex::new(None);
+
+/// Size of the last new chunk any chunked transfer sent, or None if none has run yet.
+pub fn effective_chunk_size() -> Option<u32> {
+    *EFFECTIVE_CHUNK_SIZE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Latest CHUNK_ACK count of each transfer, keyed by (requester, request_id)
+type ChunkAckCounts = HashMap<(String, String), watch::Sender<u32>>;
+
+/// CHUNK_ACK counts of the chunked transfers being served
+static CHUNK_ACKS: LazyLock<std::sync::Mutex<ChunkAckCounts>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// CHUNK_ACKs of one flow-controlled transfer, for as long as it is being sent.
+struct ChunkAcks {
+    key: (String, String),
+    receiver: watch::Receiver<u32>,
+}
+
+impl ChunkAcks {
+    fn register(from: &SockAddr, request_id: &str) -> Self {
+        let key = (from.to_string(), request_id.to_string());
+        let (sender, receiver) = watch::channel(0);
+        CHUNK_ACKS.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone(), sender);
+        Self { key, receiver }
+    }
+
+    /// Waits for an ACK above `acked` and returns its count.
+    async fn past(&mut self, acked: u32) -> u32 {
+        self.receiver.wait_for(|count| *count > acked).await.map_or(acked, |count| *count)
+    }
+}
+
+impl Drop for ChunkAcks {
+    fn drop(&mut self) {
+        CHUNK_ACKS.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+    }
+}
+
+/// Hands a CHUNK_ACK to the transfer it belongs to. Returns false if no such transfer is being sent
+/// to that requester, so an ACK cannot move another requester's window.
+fn record_chunk_ack(from: &SockAddr, request_id: &str, count: u32) -> bool {
+    let acks = CHUNK_ACKS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(sender) = acks.get(&(from.to_string(), request_id.to_string())) else { return false; };
+    sender.send_modify(|acked| *acked = (*acked).max(count));
+    true
+}
+
+/// Serves a single file request: checks limits, sends the ACK and then the file.
+///
+/// The ACK carries the served file name as a trailing field so requesters that
+/// asked by content hash learn what to call the file.
+/// With a range (start, end exclusive; u64::MAX = end of file) only that slice is
+/// sent as GETFILE_RANGE; range requests do not count as downloads.
+/// Whole files go out as GETFILE_CHUNK messages when the requester announced a
+/// chunk size, and as a single GETFILE to requesters that predate chunking.
+/// A chunked transfer starts at the announced offset when a requester resumes a partial download.
+/// The payload is encoded with the first of the requester's `accepted` encodings
+/// we support and the encoding is named after it.
+///
+/// Runs alongside other transfers: the app is locked only to pick the file and to
+/// count the download, and the socket only for each send.
+///
+/// Idempotent per request_id within SERVED_REQUEST_WINDOW: a resend (its ACK was lost)
+/// gets the ACK again but neither the file nor another download count, unless the
+/// earlier attempt failed.
+async fn serve_file_request(
+    ctx: &ServeContext,
+    requested: &str,
+    range: Option<(u64, u64)>,
+    accepted: &[String],
+    chunks: &ChunkOptions,
+    matches: impl Fn(&Shareable) -> bool,
+) {
+    let ServeContext { app, identity, from, request_id, .. } = ctx;
+    // Only files bound to the identity the request reached
+    let matches = |f: &Shareable| f.served_by(&identity.name) && matches(f);
+
+    let served = match ServedGuard::start(from, request_id) {
+        ServeStart::New(guard) => guard,
+        ServeStart::Duplicate(file_name) => {
+            info!("Repeated request '{}' for '{}'; not sending the file again", request_id, requested);
+            if let Some(file_name) = file_name
+                && let Some(mut socket) = ctx.socket().await {
+                send_file_ack(&mut socket, from.clone(), request_id, &file_name).await;
+            }
+            return;
+        }
+    };
+
+    // Pick the file and reserve a download against its limit
+    let (file, listed, refusal, reservation, upload_limit, compress, chunk_bounds) = {
+        let app_guard = app.lock().await;
+        // Prefer a file that is being served over an inactive one with the same name
+        let index = app_guard.shareable_files.iter().position(|f| matches(f) && is_served(f, &app_guard.collections))
+            .or_else(|| app_guard.shareable_files.iter().position(&matches));
+        let served = index.is_some_and(|i| is_served(&app_guard.shareable_files[i], &app_guard.collections));
+        let file = index.map(|i| app_guard.shareable_files[i].clone());
+
+        // Refuse peers missing from the file's allowlist, expired files and files whose download limit has been reached,
+        // counting whole-file transfers still in progress
+        let refusal = match file.as_ref() {
+            Some(f) if !f.allows(from) => Some("unauthorized"),
+            Some(f) if f.is_expired() => Some("share expired"),
+            Some(f) if f.limit_reached() => Some("download limit reached"),
+            Some(f) if range.is_none() && f.remaining_downloads().is_some_and(|left| left <= DownloadReservation::count(&f.path)) =>
+                Some("download limit reached"),
+            _ => None,
+        };
+        let listed = file.is_some();
+        let file = file.filter(|_| served);
+        let reservation = match (&file, refusal, range) {
+            (Some(f), None, None) => Some(DownloadReservation::take(&f.path)),
+            _ => None,
+        };
+        (file, listed, refusal, reservation, app_guard.upload_limit, app_guard.compress_uploads, app_guard.chunk_size_bounds)
+    };
+    // With compression off, answer as if the requester accepted no encoding but raw
+    let accepted = if compress { accepted } else { &[] };
+
+    if let Some(reason) = refusal {
+        info!("Refusing '{}': {}", requested, reason);
+        ctx.refuse(reason).await;
+        return;
+    }
+
+    // Say why at once rather than leave the requester waiting for its timeout
+    let Some(file) = file else {
+        let reason = if listed { "inactive" } else { "not found" };
+        info!("Refusing '{}': {}", requested, reason);
+        ctx.refuse(reason).await;
+        return;
+    };
+    let file_name = file.share_name().unwrap_or_default();
+    debug!("Serving {:?} to {}", file, from);
+
+    // Resolve and validate the range against the current length, when the source knows it
+    let range = match range {
+        None => None,
+        Some((start, end)) => {
+            let size = match file.content_len().await {
+                Ok(size) => size,
+                Err(e) => {
+                    warn!("Failed to stat '{}': {:?}", requested, e);
+                    audit::record(AuditKind::Error, COMMANDS::GETFILE_RANGE, from, format!("cannot read '{}': {}", file_name, e));
+                    ctx.refuse("read error").await;
+                    return;
+                }
+            };
+            let end = size.map_or(end, |size| end.min(size));
+            if start >= end {
+                info!("Invalid range {}-{} for '{}' ({:?} bytes)", start, end, requested, size);
+                ctx.refuse("invalid range").await;
+                return;
+            }
+            Some((start, end))
+        }
+    };
+
+    // Send ACK
+    let Some(mut socket) = ctx.socket().await else { return; };
+    if !send_file_ack(&mut socket, from.clone(), request_id, &file_name).await {
+        return;
+    }
+    drop(socket);
+    served.acked(&file_name);
+
+    if range.is_none() && chunks.size > 0 {
+        let send = async |data: Vec<u8>, index: u32, total: u32| {
+            // Pace uploads: wait for our turn under the cap shared with the other transfers
+            if let Some(limit) = upload_limit.filter(|l| *l > 0) {
+                pace_upload(data.len(), limit).await;
+            }
+            let Some(mut socket) = lock_current_socket(&ctx.identity.socket, &ctx.p_socket).await else {
+                warn!("Serving socket changed while sending '{}'; stopping at chunk {}/{}", file_name, index + 1, total);
+                return false;
+            };
+            if !send_counted(&mut socket, data, from.clone()).await {
+                warn!("Failed to send chunk {}/{} of {}", index + 1, total, file_name);
+                audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("send failed for '{}' at chunk {}/{}", file_name, index + 1, total));
+                return false;
+            }
+            true
+        };
+        let file_len = match send_file_chunks(&file, chunks, chunk_bounds, accepted, from, request_id, send).await {
+            Ok(file_len) => file_len,
+            Err(ChunkStop::Unreadable) => {
+                ctx.refuse("read error").await;
+                return;
+            }
+            Err(ChunkStop::Abandoned) => return,
+        };
+        served.sent();
+        post_update(AppUpdate::Served(ServeEvent::new(file_name.clone(), from, file_len)));
+        info!("Sent file {} to {} in chunks", file_name, from);
+        count_download(app, &file, from, file_len, reservation).await;
+        return;
+    }
+
+    // Read the file (or the requested slice)
+    let (start, end) = range.map_or((0, None), |(start, end)| (start, Some(end)));
+    let file_bytes = match read_source(&file, start, end, MAX_SHARE_SIZE).await {
+        Ok(b) => b,
+        Err(e) => {
+            warn!("Failed to read '{}': {:?}", requested, e);
+            audit::record(AuditKind::Error, COMMANDS::GETFILE, from, format!("cannot read '{}': {}", file_name, e));
+            ctx.refuse("read error").await;
+            return;
+        },
+    };
+
+    let file_len = file_bytes.len() as u64;
+    // Digest of exactly the bytes sent, so the requester can check what it wrote
+    let hash = range.is_none().then(|| tokio::task::block_in_place(|| sha256_bytes(&file_bytes)));
+    let encoding = Encoding::negotiate(accepted, file.file_type);
+    let (encoding, payload) = tokio::task::block_in_place(|| encoding.encode(file_bytes));
+
+    let mut out_stream = DataStream::default();
+    match range {
+        Some((start, _)) => {
+            out_stream.stream_in(&COMMANDS::GETFILE_RANGE);
+            out_stream.stream_in(&request_id.to_string());
+            out_stream.stream_in(&start);
+        }
+        None => {
+            out_stream.stream_in(&COMMANDS::GETFILE);
+            out_stream.stream_in(&request_id.to_string());
+        }
+    }
+    out_stream.stream_in(&payload);
+    out_stream.stream_in(&encoding.label().to_string());
+    if let Some(hash) = &hash {
+        out_stream.stream_in(hash);
+    }
+
+    // Pace uploads: wait for our turn under the cap shared with the other transfers
+    if let Some(limit) = upload_limit.filter(|l| *l > 0) {
+        pace_upload(payload.len(), limit).await;
+    }
+
+    let Some(mut socket) = ctx.socket().await else { return; };
+    let sent = send_counted(&mut socket, out_stream.data.clone(), from.clone()).await;
+    drop(socket);
+    if !sent {
+        warn!("Failed to send file {}", file_name);
+        audit::record(AuditKind::Error, COMMANDS::GETFILE, from, format!("send failed for '{}'", file_name));
+        return;
+    }
+    served.sent();
+    post_update(AppUpdate::Served(ServeEvent::new(file_name.clone(), from, file_len)));
+    let sent_command = if range.is_some() { COMMANDS::GETFILE_RANGE } else { COMMANDS::GETFILE };
+    audit::record(AuditKind::FileSent, sent_command, from, format!("{} ({} bytes, {} as {} bytes)", file_name, file_len, encoding.label(), payload.len()));
+
+    if let Some((start, end)) = range {
+        info!("Sent bytes {}-{} of {} to {}", start, end, file_name, from);
+        return;
+    }
+    info!("Sent file {} to {}", file_name, from);
+    count_download(app, &file, from, file_len, reservation).await;
+}
+
+
+/// Counts a whole-file download on the shared entry (it may have been removed meanwhile)
+/// and in the requester's summary. The reservation is released under the same lock.
+async fn count_download(
+    app: &Arc<Mutex<FileSharingApp>>,
+    file: &Shareable,
+    from: &SockAddr,
+    file_len: u64,
+    reservation: Option<DownloadReservation>,
+) {
+    let mut app_guard = app.lock().await;
+    if let Some(shared) = app_guard.shareable_files.iter_mut().find(|f| f.path == file.path) {
+        shared.downloads = shared.downloads.saturating_add(1);
+
+        // Stop sharing once the download limit is reached
+        if shared.limit_reached() {
+            shared.deactivate();
+            info!("Download limit reached for '{}'; deactivated", file.share_name().unwrap_or_default());
+        }
+    }
+    drop(reservation);
+
+    // Per-peer summary; Anonymous requesters have no address to record
+    if !from.is_null() {
+        record_download(&mut app_guard.peer_stats, from.to_string(), file_len);
+        save_peer_stats(app_guard.peer_stats.clone());
+    }
+}
+
+
+/// Number of the latest peer stats snapshot taken, and of the latest one written
+static PEER_STATS_TAKEN: AtomicU64 = AtomicU64::new(0);
+static PEER_STATS_SAVED: LazyLock<std::sync::Mutex<u64>> = LazyLock::new(|| std::sync::Mutex::new(0));
+
+/// Writes a snapshot of the peer stats on a blocking thread, so the app lock is not held
+/// for the file write. Take the snapshot under the app lock: snapshots are numbered in
+/// that order, and one older than the last written is dropped.
+fn save_peer_stats(stats: HashMap<String, PeerStats>) {
+    let snapshot = PEER_STATS_TAKEN.fetch_add(1, Ordering::SeqCst) + 1;
+    tokio::task::spawn_blocking(move || {
+        let mut saved = PEER_STATS_SAVED.lock().unwrap_or_else(|e| e.into_inner());
+        if *saved > snapshot {
+            return;
+        }
+        *saved = snapshot;
+        if let Err(e) = storage::save(PEERS_FILE, &stats) {
+            warn!("Failed to save peer stats: {}", e);
+        }
+    });
+}
+
+
+/// Streams a whole file as GETFILE_CHUNK messages of at most the requested chunk size, each
+/// tagged with the request id, its index and the chunk count and encoded on its own.
+/// Every chunk also carries the file's SHA-256, so it reaches the requester whichever chunk arrives.
+/// Chunks cover the file from the requested offset on, and carry that offset so the requester
+/// knows where they start; an offset past the end sends the whole file.
+///
+/// A requester that announced a window is sent at most that many chunks past its last
+/// CHUNK_ACK, and each chunk names the window so it knows to acknowledge. When no ACK moves
+/// the window for CHUNK_ACK_TIMEOUT, the unacknowledged chunks are sent again; the transfer
+/// is given up after MAX_CHUNK_RETRANSMITS timeouts in a row. A slow requester therefore
+/// holds the transfer back instead of being sent chunks it has no room for.
+///
+/// Such transfers also adapt their chunk size within `bounds` (see [`ChunkSizer`]).
+/// Each chunk then names the chunk count as it stands when the chunk is first sent; only the
+/// last chunk's count is final. Resent chunks keep the bounds and count they were first sent with.
+/// Only one chunk is held in memory at a time.
+///
+/// Each message goes out through `send` with the chunk's index and count; it returns false
+/// when the message could not be sent. Returns the file length.
+async fn send_file_chunks(
+    file: &Shareable,
+    chunks: &ChunkOptions,
+    bounds: (u32, u32),
+    accepted: &[String],
+    from: &SockAddr,
+    request_id: &str,
+    mut send: impl AsyncFnMut(Vec<u8>, u32, u32) -> bool,
+) -> Result<u64, ChunkStop> {
+    let request_id = request_id.to_string();
+    let file_name = file.share_name().unwrap_or_default();
+    let max_size = chunks.size.clamp(MIN_FILE_CHUNK_SIZE, FILE_CHUNK_SIZE);
+
+    let opened = async {
+        let len = file.content_len().await?
+            .ok_or_else(|| std::io::Error::other("length is not known up front"))?;
+        let offset = if chunks.offset > len { 0 } else { chunks.offset };
+        Ok::<_, std::io::Error>((len, offset, file.chunk_reader(offset, len - offset).await?))
+    }.await;
+    let (file_len, offset, mut reader) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            warn!("Failed to open '{}': {:?}", file_name, e);
+            audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("cannot read '{}': {}", file_name, e));
+            return Err(ChunkStop::Unreadable);
+        }
+    };
+    // Where the reader is in the file; a rewind reopens it
+    let mut reader_pos = offset;
+
+    let hash = {
+        let file = file.clone();
+        match tokio::task::spawn_blocking(move || file.sha256()).await {
+            Ok(Ok(hash)) => hash,
+            Ok(Err(e)) => {
+                warn!("Failed to hash '{}': {:?}", file_name, e);
+                audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("cannot read '{}': {}", file_name, e));
+                return Err(ChunkStop::Unreadable);
+            }
+            Err(_) => return Err(ChunkStop::Abandoned),
+        }
+    };
+
+    let encoding = Encoding::negotiate(accepted, file.file_type);
+    let mut payload_len = 0u64;
+
+    // ACKs are only waited for when the requester said it sends them
+    let mut window = ChunkWindow::new(chunks.window.min(MAX_CHUNK_WINDOW));
+    let mut acks = (window.size > 0).then(|| ChunkAcks::register(from, &request_id));
+    let mut sizer = if window.size > 0 {
+        ChunkSizer::new(bounds.0.max(MIN_FILE_CHUNK_SIZE), bounds.1.min(max_size))
+    } else {
+        ChunkSizer::fixed(max_size)
+    };
+    // Start, length and chunk count of the chunks sent but not yet acknowledged
+    let mut unacked: BTreeMap<u32, (u64, u64, u32)> = BTreeMap::new();
+    // Where the next new chunk starts
+    let mut next_start = offset;
+    let mut total = 1;
+
+    while !window.is_done() {
+        if !window.is_open() {
+            let Some(acks) = acks.as_mut() else { break; };
+            match tokio::time::timeout(CHUNK_ACK_TIMEOUT, acks.past(window.acked)).await {
+                Ok(count) => {
+                    if window.ack(count) {
+                        sizer.on_ack();
+                        unacked = unacked.split_off(&window.acked);
+                    }
+                }
+                Err(_) if window.rewind() => {
+                    sizer.on_loss();
+                    info!("No CHUNK_ACK for '{}' past chunk {}/{}; sending from there again", file_name, window.acked, total);
+                }
+                Err(_) => {
+                    warn!("'{}' stalled at chunk {}/{}; giving up", file_name, window.acked, total);
+                    audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("no CHUNK_ACK for '{}' past chunk {}/{}", file_name, window.acked, total));
+                    return Err(ChunkStop::Abandoned);
+                }
+            }
+            continue;
+        }
+
+        let index = window.next;
+        let (start, len, chunk_total) = match unacked.get(&index) {
+            Some(sent) => *sent,
+            None => {
+                // An empty file is still one (empty) chunk, so the requester learns it is complete
+                let size = u64::from(sizer.size);
+                let len = size.min(file_len - next_start);
+                let left = (file_len - next_start - len).div_ceil(size);
+                total = u32::try_from(u64::from(index) + 1 + left).map_err(|_| ChunkStop::Abandoned)?;
+                next_start += len;
+                *EFFECTIVE_CHUNK_SIZE.lock().unwrap_or_else(|e| e.into_inner()) = Some(sizer.size);
+                if window.size > 0 {
+                    unacked.insert(index, (next_start - len, len, total));
+                }
+                (next_start - len, len, total)
+            }
+        };
+        if start != reader_pos {
+            reader = match file.chunk_reader(start, file_len - start).await {
+                Ok(reader) => reader,
+                Err(e) => {
+                    warn!("Failed to reopen '{}' at chunk {}: {:?}", file_name, index, e);
+                    audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("cannot read '{}': {}", file_name, e));
+                    return Err(ChunkStop::Unreadable);
+                }
+            };
+            reader_pos = start;
+        }
+
+        let mut chunk = Vec::with_capacity(len as usize);
+        if let Err(e) = (&mut reader).take(len).read_to_end(&mut chunk).await {
+            warn!("Failed to read chunk {} of '{}': {:?}", index, file_name, e);
+            audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, from, format!("cannot read '{}': {}", file_name, e));
+            return Err(ChunkStop::Unreadable);
+        }
+        reader_pos += chunk.len() as u64;
+        let (used, payload) = tokio::task::block_in_place(|| encoding.encode(chunk));
+        payload_len += payload.len() as u64;
+
+        let mut out_stream = DataStream::default();
+        out_stream.stream_in(&COMMANDS::GETFILE_CHUNK);
+        out_stream.stream_in(&request_id);
+        out_stream.stream_in(&index);
+        out_stream.stream_in(&chunk_total);
+        out_stream.stream_in(&payload);
+        out_stream.stream_in(&used.label().to_string());
+        out_stream.stream_in(&hash);
+        out_stream.stream_in(&file_len);
+        out_stream.stream_in(&offset);
+        out_stream.stream_in(&window.size);
+
+        if !send(out_stream.data, index, chunk_total).await {
+            return Err(ChunkStop::Abandoned);
+        }
+        window.sent(index, index + 1 == chunk_total);
+    }
+
+    audit::record(AuditKind::FileSent, COMMANDS::GETFILE_CHUNK, from, format!("{} ({} bytes from {} in {} chunk(s), {} as {} bytes)", file_name, file_len, offset, total, encoding.label(), payload_len));
+    Ok(file_len)
+}
+
+
+/// Answers a FILE_STATUS: whether `name` (or `#<sha256>`) is served, its size and hash.
+/// The hash is computed off the async runtime when not cached, and cached for later requests.
+async fn answer_file_status(
+    app: Arc<Mutex<FileSharingApp>>,
+    identity: Arc<ServingIdentity>,
+    p_socket: Arc<Mutex<Socket>>,
+    from: SockAddr,
+    request_id: String,
+    name: String,
+) {
+    let file = {
+        let app_guard = app.lock().await;
+        let hash = name.strip_prefix('#');
+        app_guard.shareable_files.iter()
+            .filter(|f| f.served_by(&identity.name) && is_served(f, &app_guard.collections))
+            .find(|f| match hash {
+                Some(hash) => f.cached_hash().is_some_and(|h| h.eq_ignore_ascii_case(hash)),
+                None => f.file_name().is_some_and(|n| n == name),
+            })
+            .cloned()
+    };
+
+    let mut status = String::new();
+    let (mut served_name, mut size, mut hash) = (String::new(), 0u64, String::new());
+    match &file {
+        None => status = "not found".to_string(),
+        Some(f) if !f.allows(&from) => status = "unauthorized".to_string(),
+        Some(f) if f.is_expired() => status = "share expired".to_string(),
+        Some(f) if f.limit_reached() => status = "download limit reached".to_string(),
+        Some(f) => {
+            served_name = f.share_name().unwrap_or_default();
+            match f.content_len().await {
+                Ok(len) => size = len.unwrap_or(u64::MAX),
+                Err(e) => status = format!("cannot read file: {}", e),
+            }
+            hash = match f.cached_hash() {
+                Some(h) => h.to_string(),
+                None => {
+                    let path = f.path.clone();
+                    match tokio::task::spawn_blocking(move || HashCache::compute(&path)).await {
+                        Ok(Ok(cache)) => {
+                            let digest = cache.digest.clone();
+                            post_update(AppUpdate::HashComputed(f.path.clone(), cache));
+                            digest
+                        }
+                        _ => String::new(),
+                    }
+                }
+            };
+        }
+    }
+
+    let mut reply = DataStream::default();
+    reply.stream_in(&COMMANDS::FILE_STATUS_REPLY);
+    reply.stream_in(&request_id);
+    reply.stream_in(&status);
+    reply.stream_in(&served_name);
+    reply.stream_in(&size);
+    reply.stream_in(&hash);
+
+    let Some(mut socket) = lock_current_socket(&identity.socket, &p_socket).await else {
+        warn!("Serving socket changed before answering FILE_STATUS (id={}); dropping it", request_id);
+        return;
+    };
+    if !send_counted(&mut socket, reply.data.clone(), from).await {
+        warn!("Failed to send FILE_STATUS_REPLY for (id={})", request_id);
+    }
+}
+
+
+/// Transfers holding a permit, i.e. being sent right now; serving_manager waits for
+/// them to finish before it stops.
+static ACTIVE_TRANSFERS: LazyLock<watch::Sender<usize>> = LazyLock::new(|| watch::Sender::new(0));
+
+/// Runs a file transfer on its own task once a permit is free,
+/// so one slow or large transfer does not hold up other requesters.
+fn spawn_transfer(permits: &Arc<Semaphore>, transfer: impl Future<Output = ()> + Send + 'static) {
+    let permits = permits.clone();
+    tokio::spawn(async move {
+        let Ok(_permit) = permits.acquire_owned().await else {
+            debug!("Upload slots closed; dropping a queued transfer");
+            return;
+        };
+        ACTIVE_TRANSFERS.send_modify(|active| *active += 1);
+        transfer.await;
+        ACTIVE_TRANSFERS.send_modify(|active| *active = active.saturating_sub(1));
+    });
+}
+
+/// Resizes `permits` from `from` to `to` permits in all. Permits transfers still hold are
+/// added to `owed` and forgotten by later calls, once those transfers give them back.
+fn resize_permits(permits: &Semaphore, from: usize, to: usize, owed: &mut usize) {
+    if to > from {
+        let repaid = (to - from).min(*owed);
+        *owed -= repaid;
+        permits.add_permits(to - from - repaid);
+    } else {
+        *owed += from - to;
+    }
+    *owed -= permits.forget_permits(*owed);
+}
+
+
+/// Serves a GETFILE_BY_HASH: finds the served file with that SHA-256 and sends it,
+/// or tells the requester none matches.
+async fn serve_hash_request(ctx: &ServeContext, hash: &str, accepted: &[String], chunks: &ChunkOptions) {
+    let ServeContext { app, identity, .. } = ctx;
+
+    // Use cached hashes first; hash the rest off the async runtime
+    let app_guard = app.lock().await;
+    let (cached, uncached): (Vec<_>, Vec<_>) = app_guard.shareable_files
+        .iter()
+        .filter(|f| f.served_by(&identity.name) && is_served(f, &app_guard.collections))
+        .map(|f| (f.path.clone(), f.cached_hash().map(str::to_string)))
+        .partition(|(_, h)| h.is_some());
+    drop(app_guard);
+
+    let mut matched = cached.into_iter()
+        .find(|(_, h)| h.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(hash)))
+        .map(|(path, _)| path);
+
+    if matched.is_none() && !uncached.is_empty() {
+        let computed: Vec<(PathBuf, HashCache)> = tokio::task::spawn_blocking(move || {
+            uncached.into_iter()
+                .filter_map(|(path, _)| HashCache::compute(&path).ok().map(|c| (path, c)))
+                .collect()
+        }).await.unwrap_or_default();
+
+        matched = computed.iter()
+            .find(|(_, c)| c.digest.eq_ignore_ascii_case(hash))
+            .map(|(path, _)| path.clone());
+
+        for (path, cache) in computed {
+            post_update(AppUpdate::HashComputed(path, cache));
+        }
+    }
+
+    let Some(path) = matched else {
+        info!("No active file matches hash {}", hash);
+        ctx.refuse("not found").await;
+        return;
+    };
+
+    serve_file_request(
+        ctx,
+        hash,
+        None,
+        accepted,
+        chunks,
+        |f| f.path == path,
+    ).await;
+}
+
+
+/// Background task that manages serving local files to peers.
+///
+/// Responsibilities:
+/// 1. Listens for incoming file requests from remote peers.
+/// 2. Sends an acknowledgment (ACK) for each valid request.
+/// 3. Reads the requested file from disk and sends it to the requester, running up to
+///    `max_concurrent_uploads` transfers at once.
+/// 4. Updates the app state (download counts, logging).
+pub async fn serving_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), String> {
+    info!("[*] Started serving_manager");
+
+    // Initialize stop signal
+    let mut stop_signal_rx = {
+        let guard = STOP_SIGNAL.lock().await;
+        guard
+            .as_ref()
+            .ok_or_else(|| "Stop signal not initialized".to_string())?
+            .subscribe()
+    };
+
+    // Setup periodic intervals; the poll interval follows the network config
+    let mut expiry_interval = interval(Duration::from_secs(5));
+    let mut listener_interval = interval(LISTENER_CHECK_INTERVAL);
+    let mut network_config = netconfig::subscribe();
+    let mut interval = interval(network_config.borrow().serve_poll());
+
+    // Distinct advertisers per window, keyed by identity and sender address
+    let mut advertise_peers: HashMap<String, AdvertisePeerState> = HashMap::new();
+
+    // File requests per window, keyed by identity and sender address
+    let mut request_peers: HashMap<String, RequestRateState> = HashMap::new();
+
+    // Permits for file transfers running at once; resized in place when the setting changes.
+    // Permits held by running transfers when it shrinks are forgotten as they come back
+    let mut upload_slots = app.lock().await.max_concurrent_uploads.max(1);
+    let transfer_permits = Arc::new(Semaphore::new(upload_slots));
+    let mut permits_to_forget = 0;
+
+    loop {
+        tokio::select! {
+            // Handle stop signal
+            result = stop_signal_rx.recv() => {
+                match result {
+                    Ok(true) => {
+                        // Transfers not started yet are dropped; those being sent are let finish
+                        transfer_permits.close();
+                        let active = *ACTIVE_TRANSFERS.borrow();
+                        if active > 0 {
+                            info!("[*] Waiting for {} transfer(s) before stopping serving_manager", active);
+                            let _ = ACTIVE_TRANSFERS.subscribe().wait_for(|active| *active == 0).await;
+                        }
+                        info!("[*] Stopping serving_manager task");
+                        break Ok(());
+                    }
+                    Ok(false) => continue,
+                    Err(e) => {
+                        info!("[*] Stop signal error: {}", e);
+                        break Ok(());
+                    }
+                }
+            }
+
+            // Poll at the new interval once the network config changes
+            Ok(()) = network_config.changed() => {
+                let poll = network_config.borrow_and_update().serve_poll();
+                info!("[*] serving_manager now polls every {:?}", poll);
+                interval = tokio::time::interval(poll);
+            }
+
+            // Restart serving listeners that died
+            _ = listener_interval.tick() => {
+                for identity in serving_identities() {
+                    if restart_dead_listener(&identity.socket, &identity.listener).await {
+                        warn!("[*] Serving listener of '{}' was not running; restarted", identity.name);
+                        post_tab_message(Tab::Share, "Serving listener stopped; restarted");
+                    }
+                }
+            }
+
+            // Deactivate expired shares
+            _ = expiry_interval.tick() => {
+                let mut app_guard = app.lock().await;
+                for file in app_guard.shareable_files.iter_mut().filter(|f| f.is_active() && f.is_expired()) {
+                    file.deactivate();
+                    info!("Share '{:?}' expired; deactivated", file.file_name());
+                }
+
+                // Let the advertise rate shown in the Share tab age out with its window
+                advertise_peers.retain(|_, p| p.window_start.elapsed() < ADVERTISE_WINDOW);
+                app_guard.advertise_rate = AdvertiseRate::of(&advertise_peers);
+
+                // Same for the dropped file requests
+                request_peers.retain(|_, p| p.window_start.elapsed() < REQUEST_RATE_WINDOW);
+                app_guard.requests_dropped = dropped_requests(&request_peers);
+            }
+
+            // Process incoming messages
+            _ = interval.tick() => {
+                let (offline, max_uploads, request_limit) = {
+                    let app_guard = app.lock().await;
+                    (app_guard.offline, app_guard.max_concurrent_uploads.max(1), app_guard.max_requests_per_minute)
+                };
+                if offline {
+                    continue;
+                }
+
+                // Queued transfers keep their place; running ones keep their permits
+                resize_permits(&transfer_permits, upload_slots, max_uploads, &mut permits_to_forget);
+                upload_slots = max_uploads;
+
+                // Each identity has its own socket; requests are answered from the one they reached
+                for identity in serving_identities() {
+                    // Fetch the current socket; the slot lock is released so it can be swapped mid-tick
+                    let Some(p_socket) = identity.socket.lock().await.clone() else { continue; };
+                    let p_socket = &p_socket;
+
+                    // Drain messages while holding the lock briefly
+                    let messages: Vec<_> = {
+                        let mut socket_guard = p_socket.lock().await;
+                        let mut recv_guard = socket_guard.recv.lock().await;
+                        recv_guard.drain(..).collect()
+                    };
+
+                    // Process each message without holding the socket lock
+                    for message in messages {
+                        throughput::record_received(message.data.len() as u64);
+                        let mut stream = DataStream::default();
+                        stream.write(&message.data);
+
+                        let command = match stream.stream_out::<String>() {
+                            Ok(cmd) => cmd,
+                            Err(_) => {
+                                warn!("Invalid message format: missing command");
+                                continue;
                             }
+                        };
+
+                        if !within_size_limit(&command, message.data.len(), &message.from) {
+                            continue;
                         }
 
-                        COMMANDS::ADVERTISE => {
-                            info!("[*] Received ADVERTISE");
+                        match command.as_str() {
+                            COMMANDS::FILE_REQUEST => {
+                                info!("[*] Received FILE_REQUEST");
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::FILE_REQUEST, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
+                                    continue;
+                                }
 
-                            {
-                                let mut app_guard = app.lock().await;
-                                if !app_guard.advertise_mode {
-                                    info!("Skip ADVERTISE, not in advertise mode");
+                                let (request_id, requested_file_name) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                    (Ok(id), Ok(name)) => (id, name),
+                                    (Err(_), _) => { info!("Missing request_id"); continue; },
+                                    (_, Err(_)) => { info!("Missing filename"); continue; },
+                                };
+                                let accepted = read_accepted_encodings(&mut stream);
+                                let chunks = ChunkOptions::read(&mut stream);
+                                audit::record(AuditKind::RequestReceived, COMMANDS::FILE_REQUEST, &message.from, &requested_file_name);
+
+                                let ctx = ServeContext::new(&app, &identity, p_socket, &message.from, request_id);
+                                spawn_transfer(&transfer_permits, async move {
+                                    serve_file_request(
+                                        &ctx,
+                                        &requested_file_name,
+                                        None,
+                                        &accepted,
+                                        &chunks,
+                                        |f| f.file_name().map(|n| n == requested_file_name).unwrap_or(false),
+                                    ).await;
+                                });
+                            }
+
+                            COMMANDS::FILE_RANGE_REQUEST => {
+                                info!("[*] Received FILE_RANGE_REQUEST");
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::FILE_RANGE_REQUEST, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
                                     continue;
                                 }
+
+                                let (request_id, requested_file_name) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                    (Ok(id), Ok(name)) => (id, name),
+                                    (Err(_), _) => { info!("Missing request_id"); continue; },
+                                    (_, Err(_)) => { info!("Missing filename"); continue; },
+                                };
+                                let (start, end) = match (stream.stream_out::<u64>(), stream.stream_out::<u64>()) {
+                                    (Ok(start), Ok(end)) => (start, end),
+                                    _ => { info!("Missing range for '{}'", requested_file_name); continue; },
+                                };
+                                let accepted = read_accepted_encodings(&mut stream);
+                                audit::record(
+                                    AuditKind::RequestReceived, COMMANDS::FILE_RANGE_REQUEST, &message.from,
+                                    format!("{} [{}-{})", requested_file_name, start, end),
+                                );
+
+                                let ctx = ServeContext::new(&app, &identity, p_socket, &message.from, request_id);
+                                spawn_transfer(&transfer_permits, async move {
+                                    serve_file_request(
+                                        &ctx,
+                                        &requested_file_name,
+                                        Some((start, end)),
+                                        &accepted,
+                                        &ChunkOptions::default(),
+                                        |f| f.file_name().map(|n| n == requested_file_name).unwrap_or(false),
+                                    ).await;
+                                });
                             }
 
-                            let request_id = match stream.stream_out::<String>() {
-                                Ok(id) => id,
-                                Err(_) => { info!("Missing request_id for ADVERTISE"); continue; },
-                            };
+                            COMMANDS::GETFILE_BY_HASH => {
+                                info!("[*] Received GETFILE_BY_HASH");
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::GETFILE_BY_HASH, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
+                                    continue;
+                                }
 
-                            let mut socket_guard = p_socket.lock().await;
+                                let (request_id, hash) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                    (Ok(id), Ok(hash)) => (id, hash),
+                                    (Err(_), _) => { info!("Missing request_id"); continue; },
+                                    (_, Err(_)) => { info!("Missing content hash"); continue; },
+                                };
+                                let accepted = read_accepted_encodings(&mut stream);
+                                let chunks = ChunkOptions::read(&mut stream);
+                                audit::record(AuditKind::RequestReceived, COMMANDS::GETFILE_BY_HASH, &message.from, &hash);
 
-                            // Send ACK
-                            let mut ack_stream = DataStream::default();
-                            ack_stream.stream_in(&COMMANDS::ACK_ADVERTISE_REQUEST);
-                            ack_stream.stream_in(&request_id);
-                            if socket_guard.send(ack_stream.data.clone(), message.from.clone()).await {
-                                info!("Sent ACK_ADVERTISE_REQUEST for (id={})", request_id);
-                            } else {
-                                warn!("Failed to send ACK_ADVERTISE_REQUEST for '{}'", request_id);
-                                continue;
+                                let ctx = ServeContext::new(&app, &identity, p_socket, &message.from, request_id);
+                                spawn_transfer(&transfer_permits, async move {
+                                    serve_hash_request(&ctx, &hash, &accepted, &chunks).await;
+                                });
                             }
 
-                            let mut app_guard = app.lock().await;
-                            let shareable_files: Vec<String> = app_guard.shareable_files
-                                .iter()
-                                .filter(|f| f.is_active())
-                                .filter_map(|f| f.file_name().clone())
-                                .collect();
+                            COMMANDS::CHUNK_ACK => {
+                                // Not under the request limit: a fast transfer sends many, and each is a map lookup
+                                let (request_id, count) = match (stream.stream_out::<String>(), stream.stream_out::<u32>()) {
+                                    (Ok(id), Ok(count)) => (id, count),
+                                    _ => { info!("Malformed CHUNK_ACK"); continue; }
+                                };
+                                if !record_chunk_ack(&message.from, &request_id, count) {
+                                    debug!("CHUNK_ACK for a transfer not being sent to {} (id={})", message.from, request_id);
+                                }
+                            }
 
-                            let mut out_stream = DataStream::default();
-                            out_stream.stream_in(&COMMANDS::GETADVERTISE);
-                            out_stream.stream_in(&request_id);
-                            out_stream.stream_in(&shareable_files);
+                            COMMANDS::PING => {
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::PING, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
+                                    continue;
+                                }
+                                let request_id = match stream.stream_out::<String>() {
+                                    Ok(id) => id,
+                                    Err(_) => { info!("Missing request_id for PING"); continue; }
+                                };
+                                debug!("Received PING (id={})", request_id);
 
-                            if socket_guard.send(out_stream.data.clone(), message.from.clone()).await {
-                                info!("[*] Sent GETADVERTISE {:?} to {:?}", shareable_files, message.from.to_string());
-                            } else {
-                                info!("[*] Failed to send GETADVERTISE to {:?}", message.from);
-                                continue;
+                                let mut pong_stream = DataStream::default();
+                                pong_stream.stream_in(&COMMANDS::PONG);
+                                pong_stream.stream_in(&request_id);
+                                let Some(mut socket_guard) = lock_current_socket(&identity.socket, p_socket).await else {
+                                    warn!("Serving socket changed before answering PING (id={}); dropping it", request_id);
+                                    continue;
+                                };
+                                if !send_counted(&mut socket_guard, pong_stream.data.clone(), message.from.clone()).await {
+                                    warn!("Failed to send PONG for (id={})", request_id);
+                                }
                             }
 
-                            // Increment advertise counts
-                            for filename in &shareable_files {
-                                for f in app_guard.shareable_files.iter_mut() {
-                                    if let Some(name) = &f.file_name() {
-                                        if name == filename {
-                                            f.advertise = f.advertise.saturating_add(1);
-                                        }
+                            COMMANDS::FILE_STATUS => {
+                                // Status checks may hash the file, so they count toward the same limit
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::FILE_STATUS, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
+                                    continue;
+                                }
+                                let (request_id, name) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                    (Ok(id), Ok(name)) => (id, name),
+                                    (Err(_), _) => { info!("Missing request_id for FILE_STATUS"); continue; },
+                                    (_, Err(_)) => { info!("Missing filename for FILE_STATUS"); continue; },
+                                };
+                                debug!("Received FILE_STATUS for '{}' (id={})", name, request_id);
+                                audit::record(AuditKind::RequestReceived, COMMANDS::FILE_STATUS, &message.from, &name);
+
+                                // Stat and maybe hash the file on its own task
+                                tokio::spawn(answer_file_status(app.clone(), identity.clone(), p_socket.clone(), message.from.clone(), request_id, name));
+                            }
+
+                            COMMANDS::ADVERTISE => {
+                                info!("[*] Received ADVERTISE");
+
+                                let (peer_cap, max_delay, outside_window) = {
+                                    let app_guard = app.lock().await;
+                                    if !app_guard.advertise_mode {
+                                        info!("Skip ADVERTISE, not in advertise mode");
+                                        continue;
+                                    }
+                                    let outside_window = app_guard.advertise_window
+                                        .is_some_and(|window| !time_in_window(Local::now().time(), window));
+                                    if outside_window && app_guard.advertise_window_silent {
+                                        info!("Skip ADVERTISE, outside the advertise window");
+                                        continue;
                                     }
+                                    (app_guard.advertise_peer_cap, Duration::from_millis(app_guard.advertise_max_delay_ms), outside_window)
+                                };
+
+                                let request_id = match stream.stream_out::<String>() {
+                                    Ok(id) => id,
+                                    Err(_) => { info!("Missing request_id for ADVERTISE"); continue; },
+                                };
+                                // Requested page
+                                let page = stream.stream_out::<u32>().unwrap_or(0);
+                                audit::record(AuditKind::RequestReceived, COMMANDS::ADVERTISE, &message.from, format!("{} (page {})", request_id, page));
+
+                                // Dedupe advertisers and cap responses per peer within the window
+                                advertise_peers.retain(|_, p| p.window_start.elapsed() < ADVERTISE_WINDOW);
+                                let peer = advertise_peers.entry(format!("{}/{}", identity.name, message.from)).or_insert_with(AdvertisePeerState::new);
+                                let (sent, cap) = if page == 0 {
+                                    (&mut peer.responses, peer_cap)
+                                } else {
+                                    (&mut peer.page_responses, MAX_ADVERTISE_PAGE_RESPONSES)
+                                };
+                                let capped = *sent >= cap;
+                                if capped {
+                                    peer.refused += 1;
+                                } else {
+                                    *sent += 1;
+                                }
+                                let count_advertise = !capped && peer.counted_pages.insert(page);
+                                app.lock().await.advertise_rate = AdvertiseRate::of(&advertise_peers);
+                                if capped {
+                                    info!("Skip ADVERTISE (id={}, page {}), response cap reached for peer", request_id, page);
+                                    continue;
+                                }
+
+                                // Answer after a random delay so response timing says less about us;
+                                // delayed answers run on their own task so other requests are not held up
+                                let ctx = ServeContext::new(&app, &identity, p_socket, &message.from, request_id);
+                                let reply = answer_advertise(ctx, page, count_advertise && !outside_window, outside_window);
+                                if max_delay.is_zero() {
+                                    reply.await;
+                                } else {
+                                    let delay = max_delay.mul_f64(random_unit());
+                                    tokio::spawn(async move {
+                                        tokio::time::sleep(delay).await;
+                                        reply.await;
+                                    });
                                 }
                             }
-                        }
 
-                        _ => {
-                            info!("Unknown command received: {}", command);
+                            COMMANDS::COLLECTION_REQUEST => {
+                                if !admit_request(&mut request_peers, &identity.name, &message.from, COMMANDS::COLLECTION_REQUEST, request_limit) {
+                                    app.lock().await.requests_dropped = dropped_requests(&request_peers);
+                                    continue;
+                                }
+                                let (request_id, name) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                    (Ok(id), Ok(name)) => (id, name),
+                                    (Err(_), _) => { info!("Missing request_id for COLLECTION_REQUEST"); continue; },
+                                    (_, Err(_)) => { info!("Missing collection name"); continue; },
+                                };
+                                audit::record(AuditKind::RequestReceived, COMMANDS::COLLECTION_REQUEST, &message.from, &name);
+
+                                // Collections are only listed to explorers, so only answer in advertise mode
+                                let manifest = {
+                                    let app_guard = app.lock().await;
+                                    if !app_guard.advertise_mode {
+                                        info!("Skip COLLECTION_REQUEST, not in advertise mode");
+                                        continue;
+                                    }
+                                    let served: Vec<Shareable> = app_guard.shareable_files.iter().filter(|f| f.served_by(&identity.name)).cloned().collect();
+                                    app_guard.collections.iter()
+                                        .find(|c| c.active && c.name == name)
+                                        .map(|c| c.manifest(app_guard.serving_addr_of(&identity.name), &served))
+                                };
+
+                                // Counted with follow-up pages against the per-peer cap
+                                advertise_peers.retain(|_, p| p.window_start.elapsed() < ADVERTISE_WINDOW);
+                                let peer = advertise_peers.entry(format!("{}/{}", identity.name, message.from)).or_insert_with(AdvertisePeerState::new);
+                                if peer.page_responses >= MAX_ADVERTISE_PAGE_RESPONSES {
+                                    peer.refused += 1;
+                                    app.lock().await.advertise_rate = AdvertiseRate::of(&advertise_peers);
+                                    info!("Skip COLLECTION_REQUEST (id={}), response cap reached for peer", request_id);
+                                    continue;
+                                }
+                                peer.page_responses += 1;
+                                app.lock().await.advertise_rate = AdvertiseRate::of(&advertise_peers);
+
+                                // An empty manifest tells the explorer the collection is no longer shared
+                                let manifest_json = manifest
+                                    .filter(|m| !m.files.is_empty())
+                                    .and_then(|m| serde_json::to_string(&m).ok())
+                                    .unwrap_or_default();
+
+                                let mut out_stream = DataStream::default();
+                                out_stream.stream_in(&COMMANDS::GETCOLLECTION);
+                                out_stream.stream_in(&request_id);
+                                out_stream.stream_in(&name);
+                                out_stream.stream_in(&manifest_json);
+                                let Some(mut socket_guard) = lock_current_socket(&identity.socket, p_socket).await else {
+                                    warn!("Serving socket changed before answering COLLECTION_REQUEST (id={}); dropping request", request_id);
+                                    continue;
+                                };
+                                if send_counted(&mut socket_guard, out_stream.data.clone(), message.from.clone()).await {
+                                    info!("[*] Sent GETCOLLECTION '{}' to {}", name, message.from);
+                                    audit::record(AuditKind::Advertise, COMMANDS::GETCOLLECTION, &message.from, format!("collection '{}'", name));
+                                } else {
+                                    warn!("Failed to send GETCOLLECTION '{}'", name);
+                                    audit::record(AuditKind::Error, COMMANDS::GETCOLLECTION, &message.from, "send failed");
+                                }
+                            }
+
+                            _ => {
+                                info!("Unknown command received: {}", command);
+                            }
                         }
                     }
                 }
@@ -393,13 +2323,616 @@ pub async fn serving_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Stri
 
 
 
+/// Outcome of a single download: the saved path, or why it failed
+pub type DownloadResult = Result<PathBuf, String>;
+
+/// Completion senders for awaited downloads, keyed by request id
+static DOWNLOAD_WAITERS: LazyLock<std::sync::Mutex<HashMap<String, oneshot::Sender<DownloadResult>>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Queues a download like the GUI does and waits until download_manager has written the
+/// file or the request failed. The request also shows up in the requests list.
+/// Used by the --self-test loopback.
+pub async fn download(service: &str, filename: &str) -> DownloadResult {
+    let sock_addr = SockAddr::from(service);
+    if sock_addr.is_null() {
+        return Err("invalid service address".to_string());
+    }
+    if relative_download_path(filename).is_none() {
+        return Err("invalid file name".to_string());
+    }
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = oneshot::channel();
+    DOWNLOAD_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).insert(request_id.clone(), sender);
+
+    post_command(ManagerCommand::AddDownload(DownLoadRequest::new(sock_addr, filename.to_string(), request_id.clone())));
+    receiver.await.unwrap_or_else(|_| Err(format!("download {} was dropped", request_id)))
+}
+
+/// Reads the encoding named after a file payload and decodes the payload.
+/// Services that predate negotiation name no encoding and always send raw.
+fn decode_file_payload(stream: &mut DataStream, payload: Vec<u8>, limit: u64) -> Result<(Encoding, Vec<u8>), String> {
+    let encoding = match stream.stream_out::<String>() {
+        Ok(label) => Encoding::from_label(&label).ok_or_else(|| format!("unknown encoding '{}'", label))?,
+        Err(_) => Encoding::Raw,
+    };
+    let data = tokio::task::block_in_place(|| encoding.decode(payload, limit))
+        .map_err(|e| format!("cannot decode {} payload: {}", encoding.label(), e))?;
+    Ok((encoding, data))
+}
+
+/// Chunks a download may run ahead of the next one to append before it is given up
+const MAX_EARLY_CHUNKS: usize = 64;
+
+/// A download arriving as GETFILE_CHUNK messages, keyed by request id in download_manager.
+/// Chunks are appended to `<name>.partial` in order; chunks the mixnet delivers early
+/// are held until the ones before them arrive. Renamed to the final name after the last chunk.
+struct ChunkedDownload {
+    partial: PathBuf,                   // File the chunks are appended to
+    next: u32,                          // Index of the next chunk to append
+    total: u32,                         // Chunks in the whole file, as the latest chunk counted them
+    last_total: Option<u32>,            // Final chunk count, once the last chunk arrived
+    early: BTreeMap<u32, Vec<u8>>,      // Chunks that arrived ahead of `next`
+    hasher: Sha256,                     // Digest of the bytes appended so far
+    service_hash: Option<String>,       // SHA-256 the service sent with the chunks
+    window: u32,                        // Chunks the service sends ahead of our CHUNK_ACKs (0 = it waits for none)
+    acked: u32,                         // Chunks our last CHUNK_ACK confirmed
+    acked_at: Instant,                  // When that CHUNK_ACK went out
+}
+
+impl ChunkedDownload {
+    /// Checks chunk `index` of `total` against the chunks before it. Services that adapt
+    /// their chunk size count the chunks anew with each one, so only the count sent with
+    /// the last chunk (`index + 1 == total`) is final.
+    fn fit(&mut self, index: u32, total: u32) -> Result<(), String> {
+        if index >= total {
+            return Err(format!("chunk {} of {} does not exist", index, total));
+        }
+        let is_last = index + 1 == total;
+        if self.last_total.is_some_and(|last| index >= last || (is_last && total != last)) {
+            return Err(format!("chunk {} of {} does not fit a file of {} chunk(s)", index, total, self.last_total.unwrap_or(self.total)));
+        }
+        if is_last {
+            self.last_total = Some(total);
+        }
+        self.total = total;
+        Ok(())
+    }
+
+    /// Whether chunk `index` may be held until the chunks before it arrive: no service
+    /// sends further ahead than the largest window, so such chunks are not buffered.
+    fn within_reach(&self, index: u32) -> bool {
+        index.saturating_sub(self.next) < self.window.max(MAX_CHUNK_WINDOW)
+    }
+
+    /// Count to confirm in a CHUNK_ACK now, if any: once half a window has been appended
+    /// since the last ACK, or always with `force`. Services that wait for no ACKs get none.
+    fn due_ack(&mut self, force: bool) -> Option<u32> {
+        if self.window == 0 || (!force && self.next < self.acked.saturating_add((self.window / 2).max(1))) {
+            return None;
+        }
+        self.acked = self.next;
+        self.acked_at = Instant::now();
+        Some(self.next)
+    }
+}
+
+/// Confirms the first `count` chunks of a chunked download to the service sending it.
+/// Acks go out on the download socket the request was sent on, so the service sees the same sender.
+async fn send_chunk_ack(to: &SockAddr, request_id: &str, count: u32) {
+    let Some(p_socket) = DOWNLOAD_SOCKET.lock().await.clone() else { return; };
+    let mut stream = DataStream::default();
+    stream.stream_in(&COMMANDS::CHUNK_ACK);
+    stream.stream_in(&request_id.to_string());
+    stream.stream_in(&count);
+    let Some(mut socket) = lock_current_socket(&DOWNLOAD_SOCKET, &p_socket).await else { return; };
+    if !send_counted(&mut socket, stream.data, to.clone()).await {
+        debug!("Failed to send CHUNK_ACK {} for {}", count, request_id);
+    }
+}
+
+/// Path a chunked download is written to until its last chunk arrives
+fn partial_path(download_path: &Path) -> PathBuf {
+    let mut name = download_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    download_path.with_file_name(name)
+}
+
+/// Path a byte range `start..end` of `download_path` is saved to ("a.bin.range-0-1024"),
+/// so a slice never takes the place of the whole file
+fn range_path(download_path: &Path, start: u64, end: u64) -> PathBuf {
+    let mut name = download_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".range-{}-{}", start, end));
+    download_path.with_file_name(name)
+}
+
+/// Where a download named `filename` is saved under `download_dir`, creating the folders
+/// a name from a shared folder ("photos/2024/a.jpg") needs. Names that would leave the
+/// directory are refused.
+async fn download_target(download_dir: &Path, filename: &str) -> Result<PathBuf, String> {
+    let relative = relative_download_path(filename).ok_or_else(|| format!("unsafe file name '{}'", filename))?;
+    let download_path = download_dir.join(relative);
+    if let Some(parent) = download_path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| format!("cannot save file: {}", e))?;
+    }
+    Ok(download_path)
+}
+
+/// Rebuilds the download requests left unfinished by the previous run. Whole-file
+/// requests whose `.partial` file is still there resume from its length.
+fn restore_downloads(default_dir: &Path) -> Vec<DownLoadRequest> {
+    let saved = storage::load::<Vec<SavedDownload>>(DOWNLOADS_FILE).unwrap_or_default();
+    saved.into_iter()
+        .filter_map(SavedDownload::restore)
+        .map(|mut req| {
+            if req.range.is_none() {
+                let partial = relative_download_path(&req.filename)
+                    .map(|relative| partial_path(&req.target_dir(default_dir).join(relative)));
+                req.offset = partial.and_then(|p| std::fs::metadata(p).ok()).map_or(0, |m| m.len());
+            }
+            req
+        })
+        .collect()
+}
+
+/// Set while an emergency wipe forgets the requests, so download_manager does not save
+/// its queue on STOP and restore it when it is started again.
+static DISCARDING_DOWNLOADS: AtomicBool = AtomicBool::new(false);
+
+/// Stops or resumes saving the download queue when download_manager stops.
+pub fn set_discarding_downloads(discarding: bool) {
+    DISCARDING_DOWNLOADS.store(discarding, Ordering::SeqCst);
+}
+
+/// Saves the unfinished, uncancelled requests to DOWNLOADS_FILE when they differ from
+/// `saved`, the list written last.
+fn save_downloads(downloads: &[DownLoadRequest], saved: &mut Vec<SavedDownload>) {
+    let unfinished: Vec<SavedDownload> = downloads.iter()
+        .filter(|r| !r.cancelled && !r.is_done())
+        .map(SavedDownload::of)
+        .collect();
+    if unfinished != *saved {
+        if let Err(e) = storage::save(DOWNLOADS_FILE, &unfinished) {
+            warn!("Failed to save {}: {}", DOWNLOADS_FILE, e);
+        }
+        *saved = unfinished;
+    }
+}
+
+/// Appends chunk `index` of `total` to the request's partial file, along with any early
+/// chunks it unblocks. Returns true once every chunk up to the last has been written.
+/// A request resuming at `offset` keeps the partial file's first `offset` bytes and
+/// counts them towards the hash; the chunks then cover only the rest of the file.
+async fn receive_chunk(
+    chunked: &mut HashMap<String, ChunkedDownload>,
+    req: &mut DownLoadRequest,
+    download_path: &Path,
+    index: u32,
+    total: u32,
+    bytes: Vec<u8>,
+    service_hash: Option<String>,
+) -> Result<bool, String> {
+    if !chunked.contains_key(&req.request_id) {
+        // First chunk to arrive: pick up the partial file from the offset, or start it over
+        let partial = partial_path(download_path);
+        let mut hasher = Sha256::new();
+        if req.offset > 0 {
+            let offset = req.offset;
+            let file = std::fs::File::open(&partial).map_err(|e| format!("cannot resume: {}", e))?;
+            if file.metadata().map_err(|e| format!("cannot resume: {}", e))?.len() != offset {
+                return Err("cannot resume: partial file changed".to_string());
+            }
+            tokio::task::block_in_place(|| std::io::copy(&mut std::io::Read::take(file, offset), &mut hasher))
+                .map_err(|e| format!("cannot resume: {}", e))?;
+        } else {
+            tokio::fs::File::create(&partial).await.map_err(|e| format!("cannot save file: {}", e))?;
+        }
+        chunked.insert(req.request_id.clone(), ChunkedDownload {
+            partial,
+            next: 0,
+            total,
+            last_total: None,
+            early: BTreeMap::new(),
+            hasher,
+            service_hash: None,
+            window: 0,
+            acked: 0,
+            acked_at: Instant::now(),
+        });
+        req.bytes_received = req.offset;
+        req.progress = 0.0;
+    }
+    let Some(state) = chunked.get_mut(&req.request_id) else { return Ok(false); };
+
+    state.fit(index, total)?;
+    if service_hash.is_some() {
+        state.service_hash = service_hash;
+    }
+    if index < state.next {
+        return Ok(false); // Repeated chunk, already written
+    }
+    if !state.within_reach(index) {
+        return Err(format!("chunk {} is too far ahead of chunk {}", index, state.next));
+    }
+    if !has_free_space(download_path.parent().unwrap_or(download_path), bytes.len() as u64) {
+        return Err("insufficient disk space".to_string());
+    }
+    state.early.insert(index, bytes);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(&state.partial)
+        .await
+        .map_err(|e| format!("cannot save file: {}", e))?;
+    while let Some(bytes) = state.early.remove(&state.next) {
+        file.write_all(&bytes).await.map_err(|e| format!("cannot save file: {}", e))?;
+        state.hasher.update(&bytes);
+        req.bytes_received = req.bytes_received.saturating_add(bytes.len() as u64);
+        state.next += 1;
+    }
+    file.flush().await.map_err(|e| format!("cannot save file: {}", e))?;
+
+    // Older services do not send the file size; count chunks instead
+    if req.total_bytes.is_some() {
+        req.update_progress();
+    } else {
+        req.progress = state.next as f32 / state.total as f32;
+    }
+
+    if state.early.len() > MAX_EARLY_CHUNKS {
+        return Err(format!("chunk {} missing; too many chunks arrived after it", state.next));
+    }
+    Ok(state.last_total == Some(state.next))
+}
+
+/// Fails a download whose bytes do not match the SHA-256 the service sent with them.
+/// The bytes stay in `<name>.partial`; the request is not completed.
+fn fail_integrity(req: &mut DownLoadRequest, from: &SockAddr, command: &str, actual: &str) {
+    warn!("Integrity check failed for '{}' (got {})", req.filename, actual);
+    audit::record(AuditKind::Error, command, from, format!("integrity check failed for '{}'", req.filename));
+    req.completion = Some(Completion::Failed("integrity check failed".to_string()));
+    post_update(AppUpdate::DownloadUpdated(req.clone()));
+    resolve_download(&req.request_id, Err("integrity check failed".to_string()));
+    post_tab_message(Tab::Download, format!("Integrity check failed for '{}'", req.filename));
+}
+
+/// Where a finished download is written when `download_path` may already exist:
+/// the path itself, the first free "name (n).ext" next to it, or None to leave the
+/// existing file alone. Members of zip downloads are staged in a folder of their own
+/// and always keep their name.
+fn collision_target(req: &DownLoadRequest, download_path: &Path, policy: CollisionPolicy) -> Option<PathBuf> {
+    if req.archive.is_some() || !download_path.exists() {
+        return Some(download_path.to_path_buf());
+    }
+    match policy {
+        CollisionPolicy::Overwrite => Some(download_path.to_path_buf()),
+        CollisionPolicy::Rename => Some(unused_path(download_path)),
+        CollisionPolicy::Skip => None,
+    }
+}
+
+/// Fails a download whose file already exists when the collision policy is Skip.
+fn skip_existing(req: &mut DownLoadRequest) {
+    info!("'{}' already exists; not saving it", req.filename);
+    req.completion = Some(Completion::Failed("file already exists".to_string()));
+    post_update(AppUpdate::DownloadUpdated(req.clone()));
+    resolve_download(&req.request_id, Err("file already exists".to_string()));
+    post_tab_message(Tab::Download, format!("Skipped '{}': a file with that name already exists", req.filename));
+}
+
+/// Adds a file saved for `req` at `path` to the download history. Members of zip
+/// downloads are left out; they end up in their archive, not at `path`.
+fn record_history(req: &DownLoadRequest, path: &Path, expected_hash: Option<String>) {
+    if req.archive.is_some() {
+        return;
+    }
+    post_update(AppUpdate::DownloadRecorded(DownloadRecord {
+        filename: req.filename.clone(),
+        source_addr: req.from.to_string(),
+        bytes: req.bytes_received,
+        completed_at: SystemTime::now(),
+        path: path.to_path_buf(),
+        request_id: req.request_id.clone(),
+        expected_hash,
+        on_disk: true,
+    }));
+}
+
+/// Hash a whole file download should have: the one from its link, the one it was
+/// requested by, or else the one the service sent with it.
+fn expected_file_hash(req: &DownLoadRequest, service_hash: Option<&str>) -> Option<String> {
+    req.expected_hash.clone()
+        .or_else(|| req.content_hash.clone())
+        .or_else(|| service_hash.map(str::to_string))
+        .map(|h| h.to_lowercase())
+}
+
+/// Status message for a finished download, naming the file it was saved as when it was renamed.
+fn downloaded_status(filename: &str, requested: &Path, saved: &Path) -> String {
+    if requested == saved {
+        format!("Downloaded file '{}'", filename)
+    } else {
+        format!("Downloaded file '{}' as '{}'", filename, saved.file_name().unwrap_or_default().to_string_lossy())
+    }
+}
+
+/// Fails sent requests that have waited longer than `timeout` for their ACK.
+fn expire_unanswered(downloads: &mut [DownLoadRequest], timeout: Duration) {
+    for req in downloads.iter_mut()
+        .filter(|r| r.sent && !r.accepted && !r.is_done() && r.sent_time.is_some_and(|t| t.elapsed() > timeout)) {
+        warn!("Request for '{}' timed out after {:?}", req.filename, timeout);
+        audit::record(AuditKind::Error, COMMANDS::FILE_REQUEST, &req.from, format!("request for '{}' timed out", req.filename));
+        let message = format!("Request for '{}' timed out", req.filename);
+        give_up_download(req, "timed out", message);
+    }
+}
+
+/// Stops retrying a download: it fails with `reason`, its waiter is told and the
+/// Download tab shows `message`.
+fn give_up_download(req: &mut DownLoadRequest, reason: &str, message: String) {
+    req.completion = Some(Completion::Failed(reason.to_string()));
+    post_update(AppUpdate::DownloadUpdated(req.clone()));
+    resolve_download(&req.request_id, Err(reason.to_string()));
+    post_tab_message(Tab::Download, message);
+}
+
+/// Stops retrying an explore request: it is marked failed and the Explore tab shows `message`.
+fn give_up_explore(req: &mut ExploreRequest, message: String) {
+    req.failed = true;
+    post_update(AppUpdate::ExploreUpdated(req.clone()));
+    post_tab_message(Tab::Explore, message);
+}
+
+/// Fails the download a file payload was meant for when the payload cannot be decoded.
+fn reject_file_payload(downloads: &mut [DownLoadRequest], command: &str, from: &SockAddr, request_id: &str, reason: String) {
+    warn!("Dropping {} for '{}': {}", command, request_id, reason);
+    audit::record(AuditKind::Error, command, from, format!("{}: {}", request_id, reason));
+    update_download(downloads, request_id, |r| r.completion = Some(Completion::Failed(reason.clone())));
+    resolve_download(request_id, Err(reason));
+}
+
+/// Fulfills the waiter for a request, if anyone is awaiting it
+fn resolve_download(request_id: &str, result: DownloadResult) {
+    if let Some(sender) = DOWNLOAD_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).remove(request_id) {
+        let _ = sender.send(result);
+    }
+}
+
+
+/// Completion senders for outstanding pings, keyed by request id
+static PING_WAITERS: LazyLock<std::sync::Mutex<HashMap<String, oneshot::Sender<()>>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Sends a PING to a service over the download socket and waits for its PONG.
+/// Unanswered pings are resent under the same id, waiting longer each time as the
+/// backoff policy says. Returns false if there is no download socket or no PONG
+/// arrives before the retries run out.
+pub async fn ping(service: SockAddr, policy: &Backoff) -> bool {
+    let Some(p_socket) = DOWNLOAD_SOCKET.lock().await.clone() else {
+        warn!("Cannot ping {}: download socket is not initialized", service);
+        return false;
+    };
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, mut receiver) = oneshot::channel();
+    PING_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).insert(request_id.clone(), sender);
+
+    let mut stream = DataStream::default();
+    stream.stream_in(&COMMANDS::PING);
+    stream.stream_in(&request_id);
+
+    let mut reachable = false;
+    for attempt in 0..policy.attempts() {
+        let sent = {
+            let Some(mut socket_guard) = lock_current_socket(&DOWNLOAD_SOCKET, &p_socket).await else {
+                warn!("Download socket changed while pinging {}; giving up", service);
+                break;
+            };
+            // Only used in anonymous mode; one reply is all a ping needs
+            socket_guard.extra_surbs = Some(1);
+            send_counted(&mut socket_guard, stream.data.clone(), service.clone()).await
+        };
+        if !sent {
+            warn!("Failed to send PING to {} (attempt {})", service, attempt + 1);
+        }
+
+        // A PONG to any earlier attempt still counts
+        match tokio::time::timeout(policy.jittered_delay(attempt), &mut receiver).await {
+            Ok(Ok(())) => {
+                reachable = true;
+                break;
+            }
+            Ok(Err(_)) => break,
+            Err(_) => debug!("No PONG from {} after attempt {}", service, attempt + 1),
+        }
+    }
+    PING_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id);
+    info!("Ping {} (id={}): {}", service, request_id, if reachable { "reachable" } else { "no reply" });
+    reachable
+}
+
+
+/// What a service reported about one of its files in reply to FILE_STATUS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileStatus {
+    pub name: String,          // Name the service would save the file under
+    pub size: Option<u64>,     // Length in bytes, when the service knows it
+    pub hash: Option<String>,  // SHA-256, when the service could compute it
+}
+
+/// Outcome of a FILE_STATUS query
+type StatusResult = Result<FileStatus, String>;
+
+/// Completion senders for outstanding FILE_STATUS queries, keyed by request id
+static STATUS_WAITERS: LazyLock<std::sync::Mutex<HashMap<String, oneshot::Sender<StatusResult>>>> =
+    LazyLock::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Asks a service over the download socket whether it serves `name` (or `#<sha256>`),
+/// retrying as the backoff policy says.
+/// Returns the file's details, or why it cannot be downloaded. Services from before
+/// FILE_STATUS ignore the query, which looks the same as an unreachable service.
+pub async fn file_status(service: SockAddr, name: String, policy: &Backoff) -> Result<FileStatus, String> {
+    let Some(p_socket) = DOWNLOAD_SOCKET.lock().await.clone() else {
+        return Err("download socket is not initialized".to_string());
+    };
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, mut receiver) = oneshot::channel();
+    STATUS_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).insert(request_id.clone(), sender);
+
+    let mut stream = DataStream::default();
+    stream.stream_in(&COMMANDS::FILE_STATUS);
+    stream.stream_in(&request_id);
+    stream.stream_in(&name);
+
+    let mut result = Err("no reply; the service may be offline or too old to answer previews".to_string());
+    for attempt in 0..policy.attempts() {
+        let sent = {
+            let Some(mut socket_guard) = lock_current_socket(&DOWNLOAD_SOCKET, &p_socket).await else {
+                result = Err("the download socket changed; try again".to_string());
+                break;
+            };
+            // Only used in anonymous mode; one reply is all a status query needs
+            socket_guard.extra_surbs = Some(1);
+            send_counted(&mut socket_guard, stream.data.clone(), service.clone()).await
+        };
+        if !sent {
+            warn!("Failed to send FILE_STATUS to {} (attempt {})", service, attempt + 1);
+        } else {
+            audit::record(AuditKind::RequestSent, COMMANDS::FILE_STATUS, &service, &name);
+        }
+
+        // A reply to any earlier attempt still counts
+        match tokio::time::timeout(policy.jittered_delay(attempt), &mut receiver).await {
+            Ok(Ok(status)) => {
+                result = status;
+                break;
+            }
+            Ok(Err(_)) => break,
+            Err(_) => debug!("No FILE_STATUS_REPLY from {} after attempt {}", service, attempt + 1),
+        }
+    }
+    STATUS_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id);
+    result
+}
+
+
+/// Applies a change to a download request and tells the UI.
+fn update_download(downloads: &mut [DownLoadRequest], request_id: &str, change: impl FnOnce(&mut DownLoadRequest)) {
+    if let Some(request) = downloads.iter_mut().find(|r| r.request_id == request_id && !r.cancelled) {
+        change(request);
+        post_update(AppUpdate::DownloadUpdated(request.clone()));
+    }
+}
+
+/// Applies a change to an explore request and tells the UI.
+fn update_explore(explores: &mut [ExploreRequest], request_id: &str, change: impl FnOnce(&mut ExploreRequest)) {
+    if let Some(request) = explores.iter_mut().find(|r| r.request_id == request_id) {
+        change(request);
+        post_update(AppUpdate::ExploreUpdated(request.clone()));
+    }
+}
+
+/// Applies a queue change posted by the UI or the library API to the requests
+/// owned by download_manager, echoing the result to the UI.
+fn apply_command(
+    command: ManagerCommand,
+    downloads: &mut Vec<DownLoadRequest>,
+    explores: &mut Vec<ExploreRequest>,
+    chunked: &mut HashMap<String, ChunkedDownload>,
+) {
+    match command {
+        ManagerCommand::AddDownload(request) => {
+            // Two adds can race past the UI's duplicate check before either is echoed
+            let duplicate = downloads.iter().any(|r| {
+                r.request_id == request.request_id
+                    || (!r.cancelled && r.filename == request.filename && r.from == request.from && r.range == request.range)
+            });
+            if duplicate {
+                info!("Ignoring duplicate download request for '{}'", request.filename);
+                resolve_download(&request.request_id, Err("already requested".to_string()));
+                return;
+            }
+            post_update(AppUpdate::DownloadUpdated(request.clone()));
+            downloads.push(request);
+        }
+        ManagerCommand::ResendDownload(id) => update_download(downloads, &id, |r| {
+            r.sent = false;
+            r.sent_time = None;
+            r.retry = Retry::default();
+        }),
+        ManagerCommand::RefreshDownload(id) => update_download(downloads, &id, |r| r.refresh()),
+        ManagerCommand::SetPriority(id, priority) => update_download(downloads, &id, |r| r.priority = priority),
+        ManagerCommand::RemoveDownloads(ids) => {
+            // Like a cancel: release waiters and drop any half-written chunked file
+            for id in &ids {
+                resolve_download(id, Err("removed".to_string()));
+                if let Some(state) = chunked.remove(id) {
+                    let _ = std::fs::remove_file(&state.partial);
+                }
+            }
+            downloads.retain(|r| !ids.contains(&r.request_id));
+            post_update(AppUpdate::DownloadsRemoved(ids));
+        }
+        ManagerCommand::CancelDownload(id) => {
+            // Kept out of the UI but not the queue, so late ACKs and chunks find it cancelled
+            let Some(request) = downloads.iter_mut().find(|r| r.request_id == id && !r.cancelled) else { return; };
+            request.cancelled = true;
+            if !request.is_done() {
+                request.completion = Some(Completion::Failed("cancelled".to_string()));
+                resolve_download(&id, Err("cancelled".to_string()));
+            }
+            if let Some(state) = chunked.remove(&id) {
+                let _ = std::fs::remove_file(&state.partial);
+            }
+            info!("Cancelled download request for '{}'", request.filename);
+            post_update(AppUpdate::DownloadsRemoved(vec![id]));
+        }
+        ManagerCommand::AddExplore(request) => {
+            if explores.iter().any(|r| r.request_id == request.request_id || r.from == request.from) {
+                info!("Ignoring duplicate explore request for {}", request.from);
+                return;
+            }
+            post_update(AppUpdate::ExploreUpdated(request.clone()));
+            explores.push(request);
+        }
+        ManagerCommand::ResendExplore(id) => update_explore(explores, &id, |r| {
+            r.sent = false;
+            r.sent_time = None;
+            r.retry = Retry::default();
+            r.failed = false;
+        }),
+        ManagerCommand::RefreshExplore(id) => update_explore(explores, &id, |r| r.refresh()),
+        ManagerCommand::FetchCollection(id, name) => update_explore(explores, &id, |r| {
+            r.collection_manifests.remove(&name);
+            r.collections_wanted.insert(name);
+        }),
+        ManagerCommand::LoadMorePages(id) => update_explore(explores, &id, |r| {
+            r.page_wanted |= !r.page_pending;
+        }),
+        ManagerCommand::RetryPage(id) => update_explore(explores, &id, |r| {
+            r.page_pending = false;
+            r.page_wanted = true;
+        }),
+        ManagerCommand::RemoveExplores(ids) => {
+            explores.retain(|r| !ids.contains(&r.request_id));
+            post_update(AppUpdate::ExploresRemoved(ids));
+        }
+    }
+}
+
+
 /// Background task that manages downloads.
 ///
 /// Responsibilities:
-/// 1. Periodically sends download requests to peers for files listed in the app state.
-/// 2. Receives replies from peers, marking requests as accepted or completed.
-/// 3. Writes downloaded file data to the local filesystem.
-/// 4. Updates the app state/UI with download progress and completion messages.
+/// 1. Owns the download and explore request queues, changed through ManagerCommands.
+/// 2. Periodically sends download requests to peers for the queued files.
+/// 3. Receives replies from peers, marking requests as accepted or completed.
+/// 4. Writes downloaded file data to the local filesystem.
+/// 5. Collects the files of zip downloads into their archives.
+/// 6. Posts every request change to the UI as an AppUpdate.
+/// 7. Saves unfinished requests to DOWNLOADS_FILE and resumes them on the next start.
 pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), String> {
     info!("[*] Started download_manager");
 
@@ -412,16 +2945,51 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
             .subscribe()
     };
 
-    // Setup intervals
-    let mut send_interval = interval(Duration::from_millis(200));
-    let mut process_interval = interval(Duration::from_millis(100));
+    // Setup intervals; the send and process intervals follow the network config
+    let mut network_config = netconfig::subscribe();
+    let (mut send_interval, mut process_interval) = {
+        let config = network_config.borrow();
+        (interval(config.send_poll()), interval(config.process_poll()))
+    };
+    let mut listener_interval = interval(LISTENER_CHECK_INTERVAL);
+    let mut archive_interval = interval(Duration::from_secs(1));
+
+    // Sockets are created lazily the first time an offline start goes online
+    let mut was_offline = app.lock().await.offline;
+
+    // Request queues; the UI keeps a copy updated from AppUpdates
+    let mut downloads: Vec<DownLoadRequest> = Vec::new();
+    let mut explores: Vec<ExploreRequest> = Vec::new();
+    let mut archives = Archives::default();
+    let mut chunked: HashMap<String, ChunkedDownload> = HashMap::new();
+
+    // Unfinished requests are restored once the saved state can be read
+    let mut restored = false;
+    let mut saved_downloads: Vec<SavedDownload> = Vec::new();
+
+    loop {
+        tokio::select! {
+            // Queue changes from the UI and the library API
+            Some(command) = MANAGER_COMMANDS.recv() => {
+                apply_command(command, &mut downloads, &mut explores, &mut chunked);
+            }
+
+            // Send and process at the new intervals once the network config changes
+            Ok(()) = network_config.changed() => {
+                let config = network_config.borrow_and_update().clone();
+                info!("[*] download_manager now sends every {:?} and processes every {:?}", config.send_poll(), config.process_poll());
+                send_interval = interval(config.send_poll());
+                process_interval = interval(config.process_poll());
+            }
 
-    loop {
-        tokio::select! {
             // Stop signal handling
             result = stop_signal_rx.recv() => {
                 match result {
                     Ok(true) => {
+                        // Keep the unfinished requests for the next start, unless a wipe is forgetting them
+                        if restored && !storage::is_wiped() && !DISCARDING_DOWNLOADS.load(Ordering::SeqCst) {
+                            save_downloads(&downloads, &mut saved_downloads);
+                        }
                         info!("[*] Stopping download_manager task");
                         break Ok(());
                     }
@@ -433,54 +3001,256 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
                 }
             }
 
+            // Restart the download listener if it died
+            _ = listener_interval.tick() => {
+                let offline = app.lock().await.offline;
+                // Spawned: creating the sockets takes a while and the loop keeps serving meanwhile
+                if was_offline && !offline && DOWNLOAD_SOCKET.lock().await.is_none()
+                    && !SOCKETS_STARTING.swap(true, Ordering::SeqCst) {
+                    info!("[*] Going online; initializing sockets");
+                    let app = app.clone();
+                    tokio::spawn(async move {
+                        initialize_sockets(app).await;
+                        SOCKETS_STARTING.store(false, Ordering::SeqCst);
+                    });
+                }
+                was_offline = offline;
+
+                if restart_dead_listener(&DOWNLOAD_SOCKET, &DOWNLOAD_LISTENER).await {
+                    warn!("[*] Download listener was not running; restarted");
+                    post_tab_message(Tab::Download, "Download listener stopped; restarted");
+                }
+                if restart_dead_listener(&ALT_DOWNLOAD_SOCKET, &ALT_DOWNLOAD_LISTENER).await {
+                    warn!("[*] Alternate download listener was not running; restarted");
+                }
+            }
+
+            // Move finished members of zip downloads into their archives
+            _ = archive_interval.tick() => {
+                if downloads.iter().any(|r| r.archive.is_some()) {
+                    tokio::task::block_in_place(|| archive::collect(&downloads, &mut archives));
+                }
+
+                // Drop the partial files of chunked downloads that were removed or reset
+                chunked.retain(|id, state| {
+                    let live = downloads.iter().any(|r| &r.request_id == id && r.sent && !r.is_done());
+                    if !live {
+                        let _ = std::fs::remove_file(&state.partial);
+                    }
+                    live
+                });
+
+                // Resume the previous run's requests, then keep the saved list current
+                if !restored && !vault::is_locked() {
+                    let default_dir = app.lock().await.download_dir.clone();
+                    for req in restore_downloads(&default_dir) {
+                        if downloads.iter().any(|r| r.request_id == req.request_id) {
+                            continue;
+                        }
+                        info!("Resuming download of '{}' from {} bytes", req.filename, req.offset);
+                        post_update(AppUpdate::DownloadUpdated(req.clone()));
+                        downloads.push(req);
+                    }
+                    restored = true;
+                }
+                if restored && !storage::is_wiped() {
+                    save_downloads(&downloads, &mut saved_downloads);
+                }
+            }
+
             // Send pending download and explore requests
             _ = send_interval.tick() => {
-                let socket_opt = DOWNLOAD_SOCKET.lock().await;
-                let Some(p_socket) = &*socket_opt else { continue; };
+                if app.lock().await.offline {
+                    continue;
+                }
+                let Some(p_socket) = DOWNLOAD_SOCKET.lock().await.clone() else { continue; };
 
-                // Lock socket once for sending all requests
-                let mut socket_guard = p_socket.lock().await;
+                // Settings are read once per tick; the queues are our own
+                let (max_concurrent, extra_surbs, backoff, accepted, download_mode, max_send_attempts, chunk_window) = {
+                    let app_guard = app.lock().await;
+                    (app_guard.max_concurrent_downloads, app_guard.extra_surbs, app_guard.backoff.clone(),
+                        Encoding::accepted(app_guard.prefer_compression), app_guard.download_socket_mode.clone(),
+                        app_guard.max_send_attempts, app_guard.chunk_window)
+                };
 
                 // Handle download requests
                 {
-                    let mut app_guard = app.lock().await;
-                    for request in app_guard.requested_files.iter_mut().filter(|r| !r.sent) {
+                    // Free slots under the concurrency cap (0 = unlimited)
+                    let in_flight = downloads.iter().filter(|r| r.sent && !r.is_done()).count();
+                    let mut free_slots = match max_concurrent {
+                        0 => usize::MAX,
+                        max => max.saturating_sub(in_flight),
+                    };
+                    let now = Instant::now();
+
+                    // Give up on requests that went unanswered through every retry
+                    for request in downloads.iter_mut()
+                        .filter(|r| r.retry_due(now) && r.retry.is_exhausted(&backoff)) {
+                        audit::record(AuditKind::Error, COMMANDS::FILE_REQUEST, &request.from, format!("no response for '{}' after {} attempt(s)", request.filename, request.retry.attempts));
+                        let message = format!("No response for '{}'; giving up", request.filename);
+                        give_up_download(request, "no response", message);
+                    }
+
+                    // Unsent requests and due retries, ordered by priority, then insertion order (stable sort);
+                    // requests whose last send failed wait out their backoff
+                    let mut pending: Vec<usize> = downloads.iter()
+                        .enumerate()
+                        .filter(|(_, r)| !r.cancelled && !r.is_done() && ((!r.sent && r.retry.is_due(now)) || r.retry_due(now)))
+                        .map(|(i, _)| i)
+                        .collect();
+                    pending.sort_by_key(|&i| std::cmp::Reverse(downloads[i].priority));
+
+                    for i in pending {
+                        let request = &mut downloads[i];
+
+                        // Retries already hold a slot
+                        let retry = request.sent;
+                        if free_slots == 0 && !retry {
+                            continue;
+                        }
                         let mut stream = DataStream::default();
-                        stream.stream_in(&COMMANDS::FILE_REQUEST);
-                        stream.stream_in(request);
+                        match (&request.content_hash, request.range) {
+                            (Some(hash), _) => {
+                                stream.stream_in(&COMMANDS::GETFILE_BY_HASH);
+                                stream.stream_in(&request.request_id);
+                                stream.stream_in(hash);
+                            }
+                            (None, Some((start, end))) => {
+                                stream.stream_in(&COMMANDS::FILE_RANGE_REQUEST);
+                                stream.stream_in(&*request);
+                                stream.stream_in(&start);
+                                stream.stream_in(&end);
+                            }
+                            (None, None) => {
+                                stream.stream_in(&COMMANDS::FILE_REQUEST);
+                                stream.stream_in(&*request);
+                            }
+                        }
+                        // Encodings we accept for the file, most preferred first
+                        stream.stream_in(&accepted);
+                        // Largest file chunk we take; whole files then arrive as GETFILE_CHUNK
+                        stream.stream_in(&FILE_CHUNK_SIZE);
+                        // Bytes already in the partial file, for the service to skip
+                        stream.stream_in(&request.offset);
+                        // Chunks the service may send ahead of our CHUNK_ACKs
+                        stream.stream_in(&chunk_window);
                         let serialized = stream.data.clone();
 
+                        // Re-check the socket before each send; a mode switch replaces it
+                        let Some(mut socket_guard) = lock_current_socket(&DOWNLOAD_SOCKET, &p_socket).await else {
+                            warn!("Download socket changed while sending requests; retrying next tick");
+                            break;
+                        };
+
                         // Only used in anonymous mode; has no effect in individual mode 
-                        socket_guard.extra_surbs = Some(10);
+                        socket_guard.extra_surbs = Some(extra_surbs);
 
-                        if socket_guard.send(serialized, request.from.clone()).await {
+                        if send_counted(&mut socket_guard, serialized, request.from.clone()).await {
                             request.sent = true;
                             request.sent_time = Some(Instant::now());
-                            info!("[*] Sent download request for {:?} to {:?}",
-                                request.filename, request.from.to_string());
+                            request.retry.sent(&backoff, now);
+                            if !retry {
+                                free_slots -= 1;
+                            }
+                            info!("[*] Sent download request for {:?} to {} (attempt {})",
+                                request.filename, request.from, request.retry.attempts);
+                            audit::record(AuditKind::RequestSent, COMMANDS::FILE_REQUEST, &request.from, &request.filename);
+                            post_update(AppUpdate::DownloadUpdated(request.clone()));
                         } else {
-                            info!("[*] Failed to send download request for {:?} to {:?}",
-                                request.filename, request.from.to_string());
+                            info!("[*] Failed to send download request for {:?} to {}",
+                                request.filename, request.from);
+                            audit::record(AuditKind::Error, COMMANDS::FILE_REQUEST, &request.from, format!("send failed for '{}'", request.filename));
+                            if request.retry.send_failed(max_send_attempts, now) {
+                                post_update(AppUpdate::DownloadUpdated(request.clone()));
+                            } else {
+                                let message = format!("Could not send the request for '{}'; giving up", request.filename);
+                                give_up_download(request, "could not be sent", message);
+                            }
                         }
                     }
                 }
 
                 // Handle explore requests
                 {
-                    let mut app_guard = app.lock().await;
-                    for request in app_guard.explore_requests.iter_mut().filter(|r| !r.sent) {
+                    let now = Instant::now();
+
+                    // Give up on explore requests that went unanswered through every retry
+                    for request in explores.iter_mut()
+                        .filter(|r| r.retry_due(now) && r.retry.is_exhausted(&backoff)) {
+                        info!("[*] No response to explore request for {}; giving up", request.from);
+                        audit::record(AuditKind::Error, COMMANDS::ADVERTISE, &request.from, format!("no response after {} attempt(s)", request.retry.attempts));
+                        let message = format!("No response from {}; giving up", request.from);
+                        give_up_explore(request, message);
+                    }
+
+                    // Member lists of collections the UI asked for
+                    for request in explores.iter_mut().filter(|r| !r.collections_wanted.is_empty()) {
+                        let Some((slot, socket)) = explore_socket(request, &p_socket, &download_mode, &backoff).await else { continue; };
+                        let Some(mut socket_guard) = lock_current_socket(slot, &socket).await else {
+                            break;
+                        };
+                        socket_guard.extra_surbs = Some(5);
+                        for name in std::mem::take(&mut request.collections_wanted) {
+                            let mut stream = DataStream::default();
+                            stream.stream_in(&COMMANDS::COLLECTION_REQUEST);
+                            stream.stream_in(&request.request_id);
+                            stream.stream_in(&name);
+                            if send_counted(&mut socket_guard, stream.data.clone(), request.from.clone()).await {
+                                info!("[*] Requested collection '{}' from {}", name, request.from);
+                                audit::record(AuditKind::RequestSent, COMMANDS::COLLECTION_REQUEST, &request.from, &name);
+                            } else {
+                                info!("[*] Failed to request collection '{}' from {}", name, request.from);
+                                audit::record(AuditKind::Error, COMMANDS::COLLECTION_REQUEST, &request.from, format!("send failed for '{}'", name));
+                            }
+                        }
+                        post_update(AppUpdate::ExploreUpdated(request.clone()));
+                    }
+
+                    for request in explores.iter_mut()
+                        .filter(|r| !r.failed && ((!r.sent && r.retry.is_due(now)) || r.retry_due(now) || r.page_wanted)) {
+                        // Follow-up requests ask for the next page of an already received list
+                        let page = if request.page_wanted { request.pages_loaded } else { 0 };
                         let mut stream = DataStream::default();
                         stream.stream_in(&COMMANDS::ADVERTISE);
                         stream.stream_in(request);
+                        stream.stream_in(&page);
                         let serialized = stream.data.clone();
 
+                        // Requests in the other mode wait while its socket is created
+                        let Some((slot, socket)) = explore_socket(request, &p_socket, &download_mode, &backoff).await else { continue; };
+                        let Some(mut socket_guard) = lock_current_socket(slot, &socket).await else {
+                            warn!("Download socket changed while sending explore requests; retrying next tick");
+                            break;
+                        };
+
                         socket_guard.extra_surbs = Some(5);
-                        if socket_guard.send(serialized, request.from.clone()).await {
+                        if request.page_wanted {
+                            request.page_wanted = false;
+                            if send_counted(&mut socket_guard, serialized, request.from.clone()).await {
+                                request.page_pending = true;
+                                info!("[*] Requested page {} from {}", page + 1, request.from);
+                            } else {
+                                info!("[*] Failed to request page {} from {}", page + 1, request.from);
+                            }
+                            post_update(AppUpdate::ExploreUpdated(request.clone()));
+                            continue;
+                        }
+                        if send_counted(&mut socket_guard, serialized, request.from.clone()).await {
                             request.sent = true;
                             request.sent_time = Some(Instant::now());
-                            info!("[*] Sent explore request to {:?}", request.from.to_string());
+                            request.retry.sent(&backoff, now);
+                            info!("[*] Sent explore request to {} (attempt {})", request.from, request.retry.attempts);
+                            audit::record(AuditKind::RequestSent, COMMANDS::ADVERTISE, &request.from, &request.request_id);
+                            post_update(AppUpdate::ExploreUpdated(request.clone()));
                         } else {
-                            info!("[*] Failed to send explore request to {:?}", request.from.to_string());
+                            info!("[*] Failed to send explore request to {}", request.from);
+                            audit::record(AuditKind::Error, COMMANDS::ADVERTISE, &request.from, "send failed");
+                            if request.retry.send_failed(max_send_attempts, now) {
+                                post_update(AppUpdate::ExploreUpdated(request.clone()));
+                            } else {
+                                let message = format!("Could not send the explore request to {}; giving up", request.from);
+                                give_up_explore(request, message);
+                            }
                         }
                     }
                 }
@@ -488,17 +3258,45 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
 
             // Process incoming messages
             _ = process_interval.tick() => {
-                let socket_opt = DOWNLOAD_SOCKET.lock().await;
-                let Some(p_socket) = &*socket_opt else { continue; };
+                let (offline, request_timeout) = {
+                    let app_guard = app.lock().await;
+                    (app_guard.offline, app_guard.request_timeout_secs)
+                };
+                if offline {
+                    continue;
+                }
+                if request_timeout > 0 {
+                    expire_unanswered(&mut downloads, Duration::from_secs(request_timeout));
+                }
+                let Some(p_socket) = DOWNLOAD_SOCKET.lock().await.clone() else { continue; };
+
+                // Replies to explore requests sent in the other mode arrive on the alternate socket
+                let mut sockets = vec![p_socket];
+                if let Some(alt_socket) = ALT_DOWNLOAD_SOCKET.lock().await.clone() {
+                    sockets.push(alt_socket);
+                }
+
+                // Repeat the CHUNK_ACK of downloads that went quiet, in case it was lost
+                for (request_id, state) in chunked.iter_mut() {
+                    if state.acked_at.elapsed() < CHUNK_REACK_INTERVAL {
+                        continue;
+                    }
+                    let Some(req) = downloads.iter().find(|r| &r.request_id == request_id) else { continue; };
+                    if let Some(count) = state.due_ack(true) {
+                        send_chunk_ack(&req.from, request_id, count).await;
+                    }
+                }
 
-                // Lock socket only while draining messages
-                let messages: Vec<_> = {
-                    let mut socket_guard = p_socket.lock().await;
+                // Lock each socket only while draining messages
+                let mut messages = Vec::new();
+                for socket in &sockets {
+                    let mut socket_guard = socket.lock().await;
                     let mut recv_guard = socket_guard.recv.lock().await;
-                    recv_guard.drain(..).collect()
-                };
+                    messages.extend(recv_guard.drain(..));
+                }
 
                 for message in messages {
+                    throughput::record_received(message.data.len() as u64);
                     let mut stream = DataStream::default();
                     stream.write(&message.data);
 
@@ -511,6 +3309,10 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
                         }
                     };
 
+                    if !within_size_limit(&command, message.data.len(), &message.from) {
+                        continue;
+                    }
+
                     match command.as_str() {
                         COMMANDS::ACK_FILE_REQUEST => {
                             let request_id = match stream.stream_out::<String>() {
@@ -518,18 +3320,97 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
                                 Err(_) => { info!("Missing request_id for ACK"); continue; }
                             };
                             info!("Received ACK for request '{}'", request_id);
+                            audit::record(AuditKind::Ack, COMMANDS::ACK_FILE_REQUEST, &message.from, &request_id);
+
+                            // Served file name
+                            let served_name = stream.stream_out::<String>().ok();
+
+                            if let Some(req) = downloads.iter_mut()
+                                .find(|r| r.request_id == request_id && !r.cancelled) {
+                                // Only the service the request went to may accept it
+                                if req.from != message.from {
+                                    warn!("Ignoring ACK_FILE_REQUEST for '{}' from a different address", request_id);
+                                    continue;
+                                }
+                                // The file may have overtaken its ACK and already accepted the request
+                                if !req.accept() {
+                                    info!("ACK for '{}' arrived late (already accepted earlier)", request_id);
+                                    continue;
+                                }
+
+                                // Requests by content hash take the name the service serves the file under
+                                if req.content_hash.is_some() {
+                                    let served_name = served_name.as_deref()
+                                        .and_then(|n| std::path::Path::new(n).file_name())
+                                        .map(|n| n.to_string_lossy().to_string());
+                                    if let Some(name) = served_name {
+                                        req.filename = name;
+                                    }
+                                }
+                                post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                post_tab_message(Tab::Download, format!("Request for '{}' accepted", req.filename));
+                            }
+                        }
+
+                        COMMANDS::ACK_FILE_REQUEST_FAIL => {
+                            let (request_id, reason) = match (stream.stream_out::<String>(), stream.stream_out::<String>()) {
+                                (Ok(id), Ok(reason)) => (id, reason),
+                                (Err(_), _) => { info!("Missing request_id for ACK_FILE_REQUEST_FAIL"); continue; }
+                                (_, Err(_)) => { info!("Missing reason for ACK_FILE_REQUEST_FAIL"); continue; }
+                            };
+                            info!("Received ACK_FILE_REQUEST_FAIL for request '{}': {}", request_id, reason);
+                            audit::record(AuditKind::Nack, COMMANDS::ACK_FILE_REQUEST_FAIL, &message.from, format!("{} ({})", request_id, reason));
+
+                            let Some(req) = downloads.iter_mut()
+                                .find(|r| r.request_id == request_id && !r.cancelled) else { continue; };
+                            // Only the service the request went to may refuse it
+                            if req.from != message.from {
+                                warn!("Ignoring ACK_FILE_REQUEST_FAIL for '{}' from a different address", request_id);
+                                continue;
+                            }
+                            req.completion = Some(Completion::Failed(reason.clone()));
+                            post_update(AppUpdate::DownloadUpdated(req.clone()));
+                            resolve_download(&request_id, Err(reason.clone()));
+                            post_tab_message(Tab::Download, format!("Request for '{}' refused: {}", req.filename, reason));
+                        }
 
-                            let mut app_guard = app.lock().await;
-                            if let Some(req) = app_guard.requested_files.iter_mut()
-                                .find(|r| r.request_id == request_id) {
-                                req.accepted = true;
-                                req.ack_time = Some(Instant::now());
-                                let filename = req.filename.clone();
-                                drop(req);
-                                app_guard.set_message(format!("Request for '{}' accepted", filename));
+                        COMMANDS::PONG => {
+                            let request_id = match stream.stream_out::<String>() {
+                                Ok(id) => id,
+                                Err(_) => { info!("Missing request_id for PONG"); continue; }
+                            };
+                            debug!("Received PONG (id={})", request_id);
+                            if let Some(sender) = PING_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id) {
+                                let _ = sender.send(());
                             }
                         }
 
+                        COMMANDS::FILE_STATUS_REPLY => {
+                            let request_id = match stream.stream_out::<String>() {
+                                Ok(id) => id,
+                                Err(_) => { info!("Missing request_id for FILE_STATUS_REPLY"); continue; }
+                            };
+                            let Some(sender) = STATUS_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).remove(&request_id) else {
+                                debug!("Unexpected FILE_STATUS_REPLY (id={})", request_id);
+                                continue;
+                            };
+                            let status = match (
+                                stream.stream_out::<String>(),
+                                stream.stream_out::<String>(),
+                                stream.stream_out::<u64>(),
+                                stream.stream_out::<String>(),
+                            ) {
+                                (Ok(reason), _, _, _) if !reason.is_empty() => Err(reason),
+                                (Ok(_), Ok(name), Ok(size), Ok(hash)) => Ok(FileStatus {
+                                    name,
+                                    size: (size != u64::MAX).then_some(size),
+                                    hash: is_sha256_hex(&hash).then_some(hash),
+                                }),
+                                _ => Err("malformed reply".to_string()),
+                            };
+                            let _ = sender.send(status);
+                        }
+
                         COMMANDS::ACK_ADVERTISE_REQUEST => {
                             let request_id = match stream.stream_out::<String>() {
                                 Ok(id) => id,
@@ -539,15 +3420,16 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
                                 }
                             };
                             info!("Received ACK_ADVERTISE_REQUEST for request '{}'", request_id);
+                            audit::record(AuditKind::Ack, COMMANDS::ACK_ADVERTISE_REQUEST, &message.from, &request_id);
 
-                            let mut app_guard = app.lock().await;
-                            if let Some(req) = app_guard.explore_requests.iter_mut()
+                            if let Some(req) = explores.iter_mut()
                                 .find(|r| r.request_id == request_id) 
                             {
                                 if !req.accepted {
                                     req.accepted = true;
                                     req.ack_time = Some(Instant::now());
-                                    app_guard.set_message(format!(
+                                    post_update(AppUpdate::ExploreUpdated(req.clone()));
+                                    post_tab_message(Tab::Explore, format!(
                                         "ACK_ADVERTISE_REQUEST for '{}' accepted", request_id
                                     ));
                                 } else {
@@ -564,57 +3446,514 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
                                 Ok(id) => id,
                                 Err(_) => { info!("Missing request_id for GETFILE"); continue; }
                             };
-                            let file_bytes = match stream.stream_out::<Vec<u8>>() {
+                            // Only the service the request went to may send its file
+                            if downloads.iter().any(|r| r.request_id == request_id && r.from != message.from) {
+                                warn!("Ignoring GETFILE for '{}' from a different address", request_id);
+                                continue;
+                            }
+                            let payload = match stream.stream_out::<Vec<u8>>() {
                                 Ok(b) => b,
                                 Err(_) => { info!("Missing file bytes"); continue; }
                             };
+                            let payload_len = payload.len();
+                            let (encoding, file_bytes) = match decode_file_payload(&mut stream, payload, MAX_SHARE_SIZE) {
+                                Ok(decoded) => decoded,
+                                Err(e) => {
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE, &message.from, &request_id, e);
+                                    continue;
+                                }
+                            };
+                            // SHA-256 of the file
+                            let service_hash = stream.stream_out::<String>().ok().filter(|h| is_sha256_hex(h));
+                            audit::record(AuditKind::FileReceived, COMMANDS::GETFILE, &message.from, format!("{} ({} bytes, {} as {} bytes)", request_id, file_bytes.len(), encoding.label(), payload_len));
 
-                            let download_dir = app.lock().await.download_dir.clone();
+                            let (default_dir, collision_policy) = {
+                                let app_guard = app.lock().await;
+                                (app_guard.download_dir.clone(), app_guard.collision_policy)
+                            };
 
-                            let mut app_guard = app.lock().await; 
-                            if let Some(req) = app_guard.requested_files.iter_mut()
-                                .find(|r| r.request_id == request_id) {
+                            if let Some(req) = downloads.iter_mut()
+                                .find(|r| r.request_id == request_id && !r.cancelled) {
                                 
+                                if req.receive_file(file_bytes.len() as u64, encoding) {
+                                    info!("No ACK received before GETFILE; auto-marking ACK at {:?}", req.ack_time);
+                                }
+
                                 let filename = req.filename.clone(); 
-                                let download_path = format!("{}/{}", download_dir.display(), filename);
+                                let download_dir = req.target_dir(&default_dir);
+                                let download_path = match download_target(&download_dir, &filename).await {
+                                    Ok(path) => path,
+                                    Err(reason) => {
+                                        reject_file_payload(&mut downloads, COMMANDS::GETFILE, &message.from, &request_id, reason);
+                                        continue;
+                                    }
+                                };
+
+                                // Refuse the transfer rather than leave a partial file on a full disk
+                                if !has_free_space(&download_dir, file_bytes.len() as u64) {
+                                    warn!("Insufficient disk space for '{}' ({} bytes)", filename, file_bytes.len());
+                                    audit::record(AuditKind::Error, COMMANDS::GETFILE, &message.from, format!("insufficient disk space for '{}'", filename));
+                                    req.completion = Some(Completion::Failed("insufficient disk space".to_string()));
+                                    post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                    resolve_download(&request_id, Err("insufficient disk space".to_string()));
+                                    post_tab_message(Tab::Download, format!("Insufficient disk space to save '{}'", filename));
+                                    continue;
+                                }
+
+                                let actual = (req.expected_hash.is_some() || service_hash.is_some())
+                                    .then(|| tokio::task::block_in_place(|| sha256_bytes(&file_bytes)));
+
+                                // Keep what arrived as .partial rather than overwrite with a damaged file
+                                if let (Some(expected), Some(actual)) = (&service_hash, &actual)
+                                    && !expected.eq_ignore_ascii_case(actual) {
+                                    if let Err(e) = tokio::fs::write(partial_path(&download_path), &file_bytes).await {
+                                        warn!("Failed to keep partial '{}': {:?}", filename, e);
+                                    }
+                                    fail_integrity(req, &message.from, COMMANDS::GETFILE, actual);
+                                    continue;
+                                }
 
+                                // An existing file is replaced, kept beside the new one or left alone
+                                let Some(saved_path) = collision_target(req, &download_path, collision_policy) else {
+                                    skip_existing(req);
+                                    continue;
+                                };
+                                let mut status = downloaded_status(&filename, &download_path, &saved_path);
+                                let download_path = saved_path;
                                 match tokio::fs::write(&download_path, &file_bytes).await {
-                                    Ok(_) => info!("Saved '{}' to '{}'", filename, download_path),
-                                    Err(e) => debug!("Failed to save '{}': {:?}", filename, e),
+                                    Ok(_) => {
+                                        info!("Saved '{}' to '{}'", filename, download_path.display());
+                                        record_history(req, &download_path, expected_file_hash(req, service_hash.as_deref()));
+
+                                        // Check the file against the hash published with its link
+                                        match (&req.expected_hash, actual) {
+                                            (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(&actual) => {
+                                                warn!("'{}' does not match its expected hash (expected {}, got {})", filename, expected, actual);
+                                                audit::record(AuditKind::Error, COMMANDS::GETFILE, &message.from, format!("hash mismatch for '{}'", filename));
+                                                // The file is kept; the request shows it needs a look
+                                                req.completion = Some(Completion::CompletedWithWarnings("does not match its expected hash".to_string()));
+                                                resolve_download(&request_id, Err("hash mismatch".to_string()));
+                                                status = format!("Downloaded file '{}' does not match its expected hash", filename);
+                                            }
+                                            _ => {
+                                                req.completion = Some(Completion::Completed);
+                                                resolve_download(&request_id, Ok(download_path.clone()));
+                                            }
+                                        }
+                                    }
+                                    Err(e) => {
+                                        debug!("Failed to save '{}': {:?}", filename, e);
+                                        audit::record(AuditKind::Error, COMMANDS::GETFILE, &message.from, format!("cannot save '{}': {}", filename, e));
+                                        req.completion = Some(Completion::Failed(format!("cannot save file: {}", e)));
+                                        resolve_download(&request_id, Err(format!("cannot save file: {}", e)));
+                                        status = format!("Cannot save '{}': {}", filename, e);
+                                    }
+                                }
+
+                                req.update_progress();
+                                post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                post_tab_message(Tab::Download, status);
+                            }
+                        }
+
+                        COMMANDS::GETFILE_CHUNK => {
+                            let (request_id, index, total) = match (stream.stream_out::<String>(), stream.stream_out::<u32>(), stream.stream_out::<u32>()) {
+                                (Ok(id), Ok(index), Ok(total)) => (id, index, total),
+                                (Err(_), _, _) => { info!("Missing request_id for GETFILE_CHUNK"); continue; }
+                                _ => { info!("Missing chunk index for GETFILE_CHUNK"); continue; }
+                            };
+                            // Only the service the request went to may send its chunks
+                            if downloads.iter().any(|r| r.request_id == request_id && r.from != message.from) {
+                                warn!("Ignoring GETFILE_CHUNK for '{}' from a different address", request_id);
+                                continue;
+                            }
+                            let payload = match stream.stream_out::<Vec<u8>>() {
+                                Ok(b) => b,
+                                Err(_) => { info!("Missing chunk bytes"); continue; }
+                            };
+                            let (_, bytes) = match decode_file_payload(&mut stream, payload, u64::from(FILE_CHUNK_SIZE)) {
+                                Ok(decoded) => decoded,
+                                Err(e) => {
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE_CHUNK, &message.from, &request_id, e);
+                                    if let Some(state) = chunked.remove(&request_id) {
+                                        let _ = tokio::fs::remove_file(&state.partial).await;
+                                    }
+                                    continue;
+                                }
+                            };
+                            // SHA-256 and size of the whole file
+                            let service_hash = stream.stream_out::<String>().ok().filter(|h| is_sha256_hex(h));
+                            let file_len = stream.stream_out::<u64>().ok();
+                            // Where the chunks start in the file
+                            let chunk_offset = stream.stream_out::<u64>().unwrap_or(0);
+                            // Chunks the service sends ahead of our CHUNK_ACKs
+                            let window = stream.stream_out::<u32>().unwrap_or(0);
+                            debug!("Received chunk {}/{} for '{}' ({} bytes)", index + 1, total, request_id, bytes.len());
+
+                            // A chunk resent after our last ACK was lost; confirm the finished download again
+                            if window > 0 && downloads.iter().any(|r| r.request_id == request_id && r.from == message.from
+                                && matches!(r.completion, Some(Completion::Completed | Completion::CompletedWithWarnings(_)))) {
+                                send_chunk_ack(&message.from, &request_id, u32::MAX).await;
+                                continue;
+                            }
+
+                            let (default_dir, collision_policy) = {
+                                let app_guard = app.lock().await;
+                                (app_guard.download_dir.clone(), app_guard.collision_policy)
+                            };
+                            // Chunks still in flight when the download failed or was finished are dropped
+                            let Some(req) = downloads.iter_mut()
+                                .find(|r| r.request_id == request_id && !r.cancelled && !r.is_done()) else { continue; };
+
+                            if req.accept() {
+                                info!("No ACK received before GETFILE_CHUNK; auto-marking ACK at {:?}", req.ack_time);
+                            }
+                            if file_len.is_some() {
+                                req.total_bytes = file_len;
+                            }
+                            // A service that ignored the resume sends the whole file; start it over
+                            if !chunked.contains_key(&request_id) && chunk_offset != req.offset {
+                                if chunk_offset != 0 {
+                                    let reason = format!("chunks start at {} instead of {}", chunk_offset, req.offset);
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE_CHUNK, &message.from, &request_id, reason);
+                                    continue;
+                                }
+                                info!("'{}' is being sent from the start; not resuming", req.filename);
+                                req.offset = 0;
+                            }
+
+                            let filename = req.filename.clone();
+                            let download_dir = req.target_dir(&default_dir);
+                            let download_path = match download_target(&download_dir, &filename).await {
+                                Ok(path) => path,
+                                Err(reason) => {
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE_CHUNK, &message.from, &request_id, reason);
+                                    continue;
+                                }
+                            };
+                            // Skip before writing anything when the file is to be left alone
+                            if !chunked.contains_key(&request_id) && collision_target(req, &download_path, collision_policy).is_none() {
+                                skip_existing(req);
+                                continue;
+                            }
+                            let received = receive_chunk(&mut chunked, req, &download_path, index, total, bytes, service_hash).await;
+                            // Confirm what was appended so the service can send on; the last chunk always
+                            if let Some(state) = chunked.get_mut(&request_id) {
+                                state.window = window;
+                                if let Some(count) = state.due_ack(matches!(received, Ok(true))) {
+                                    send_chunk_ack(&req.from, &request_id, count).await;
+                                }
+                            }
+                            match received {
+                                Ok(false) => post_update(AppUpdate::DownloadUpdated(req.clone())),
+                                Ok(true) => {
+                                    let Some(state) = chunked.remove(&request_id) else { continue; };
+                                    let actual = format!("{:x}", state.hasher.finalize());
+
+                                    // Keep what arrived as .partial rather than put a damaged file in place
+                                    if state.service_hash.as_ref().is_some_and(|h| !h.eq_ignore_ascii_case(&actual)) {
+                                        fail_integrity(req, &message.from, COMMANDS::GETFILE_CHUNK, &actual);
+                                        continue;
+                                    }
+
+                                    // An existing file is replaced, kept beside the new one or left alone
+                                    let Some(saved_path) = collision_target(req, &download_path, collision_policy) else {
+                                        let _ = tokio::fs::remove_file(&state.partial).await;
+                                        skip_existing(req);
+                                        continue;
+                                    };
+                                    let mut status = downloaded_status(&filename, &download_path, &saved_path);
+                                    let download_path = saved_path;
+                                    if let Err(e) = tokio::fs::rename(&state.partial, &download_path).await {
+                                        warn!("Failed to move '{}' into place: {:?}", filename, e);
+                                        audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, &message.from, format!("cannot save '{}': {}", filename, e));
+                                        req.completion = Some(Completion::Failed(format!("cannot save file: {}", e)));
+                                        post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                        resolve_download(&request_id, Err(format!("cannot save file: {}", e)));
+                                        continue;
+                                    }
+                                    info!("Saved '{}' to '{}' from {} chunk(s)", filename, download_path.display(), total);
+                                    record_history(req, &download_path, expected_file_hash(req, state.service_hash.as_deref()));
+                                    audit::record(AuditKind::FileReceived, COMMANDS::GETFILE_CHUNK, &message.from, format!("{} ({} bytes in {} chunk(s))", request_id, req.bytes_received, total));
+
+                                    // Check the file against the hash published with its link
+                                    match &req.expected_hash {
+                                        Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+                                            warn!("'{}' does not match its expected hash (expected {}, got {})", filename, expected, actual);
+                                            audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, &message.from, format!("hash mismatch for '{}'", filename));
+                                            req.completion = Some(Completion::CompletedWithWarnings("does not match its expected hash".to_string()));
+                                            resolve_download(&request_id, Err("hash mismatch".to_string()));
+                                            status = format!("Downloaded file '{}' does not match its expected hash", filename);
+                                        }
+                                        _ => {
+                                            req.completion = Some(Completion::Completed);
+                                            resolve_download(&request_id, Ok(download_path));
+                                        }
+                                    }
+                                    req.update_progress();
+                                    post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                    post_tab_message(Tab::Download, status);
+                                }
+                                Err(reason) => {
+                                    warn!("Dropping chunked download of '{}': {}", filename, reason);
+                                    audit::record(AuditKind::Error, COMMANDS::GETFILE_CHUNK, &message.from, format!("{}: {}", filename, reason));
+                                    if let Some(state) = chunked.remove(&request_id) {
+                                        let _ = tokio::fs::remove_file(&state.partial).await;
+                                    }
+                                    req.completion = Some(Completion::Failed(reason.clone()));
+                                    post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                    resolve_download(&request_id, Err(reason.clone()));
+                                    post_tab_message(Tab::Download, format!("Download of '{}' failed: {}", filename, reason));
+                                }
+                            }
+                        }
+
+                        COMMANDS::GETFILE_RANGE => {
+                            let (request_id, start) = match (stream.stream_out::<String>(), stream.stream_out::<u64>()) {
+                                (Ok(id), Ok(start)) => (id, start),
+                                (Err(_), _) => { info!("Missing request_id for GETFILE_RANGE"); continue; }
+                                (_, Err(_)) => { info!("Missing offset for GETFILE_RANGE"); continue; }
+                            };
+                            // Only the service the request went to may send its bytes
+                            if downloads.iter().any(|r| r.request_id == request_id && r.from != message.from) {
+                                warn!("Ignoring GETFILE_RANGE for '{}' from a different address", request_id);
+                                continue;
+                            }
+                            let payload = match stream.stream_out::<Vec<u8>>() {
+                                Ok(b) => b,
+                                Err(_) => { info!("Missing range bytes"); continue; }
+                            };
+                            let payload_len = payload.len();
+                            let (encoding, bytes) = match decode_file_payload(&mut stream, payload, MAX_SHARE_SIZE) {
+                                Ok(decoded) => decoded,
+                                Err(e) => {
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE_RANGE, &message.from, &request_id, e);
+                                    continue;
+                                }
+                            };
+                            audit::record(AuditKind::FileReceived, COMMANDS::GETFILE_RANGE, &message.from, format!("{} ({} bytes at {}, {} as {} bytes)", request_id, bytes.len(), start, encoding.label(), payload_len));
+
+                            let (default_dir, collision_policy) = {
+                                let app_guard = app.lock().await;
+                                (app_guard.download_dir.clone(), app_guard.collision_policy)
+                            };
+                            let Some(req) = downloads.iter_mut()
+                                .find(|r| r.request_id == request_id && !r.cancelled) else { continue; };
+
+                            if req.accept() {
+                                info!("No ACK received before GETFILE_RANGE; auto-marking ACK at {:?}", req.ack_time);
+                            }
+
+                            let filename = req.filename.clone();
+                            let download_dir = req.target_dir(&default_dir);
+                            if !has_free_space(&download_dir, bytes.len() as u64) {
+                                warn!("Insufficient disk space for range of '{}' ({} bytes)", filename, bytes.len());
+                                req.completion = Some(Completion::Failed("insufficient disk space".to_string()));
+                                post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                resolve_download(&request_id, Err("insufficient disk space".to_string()));
+                                post_tab_message(Tab::Download, format!("Insufficient disk space to save '{}'", filename));
+                                continue;
+                            }
+
+                            let download_path = match download_target(&download_dir, &filename).await {
+                                Ok(path) => path,
+                                Err(reason) => {
+                                    reject_file_payload(&mut downloads, COMMANDS::GETFILE_RANGE, &message.from, &request_id, reason);
+                                    continue;
                                 }
+                            };
+                            // The slice gets a file of its own and never takes the name of the whole file
+                            let range_path = range_path(&download_path, start, start.saturating_add(bytes.len() as u64));
+                            let partial = partial_path(&range_path);
+                            let written = async {
+                                let mut file = tokio::fs::File::create(&partial).await?;
+                                file.write_all(&bytes).await?;
+                                file.flush().await
+                            }.await;
+                            if let Err(e) = written {
+                                warn!("Failed to save range of '{}': {:?}", filename, e);
+                                audit::record(AuditKind::Error, COMMANDS::GETFILE_RANGE, &message.from, format!("cannot save '{}': {}", filename, e));
+                                let _ = tokio::fs::remove_file(&partial).await;
+                                req.completion = Some(Completion::Failed(format!("cannot save file: {}", e)));
+                                post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                resolve_download(&request_id, Err(format!("cannot save file: {}", e)));
+                                continue;
+                            }
+                            req.bytes_received = req.bytes_received.saturating_add(bytes.len() as u64);
+                            req.encoding = Some(encoding);
+
+                            // A published hash covers the whole file, so only a slice from 0 to the end can be checked
+                            let whole_file = start == 0 && req.range.is_some_and(|(_, end)| end == u64::MAX);
+                            let actual = (whole_file && req.expected_hash.is_some())
+                                .then(|| tokio::task::block_in_place(|| sha256_bytes(&bytes)));
+
+                            // An earlier copy of the same slice is replaced, kept beside it or left alone
+                            let Some(saved_path) = collision_target(req, &range_path, collision_policy) else {
+                                let _ = tokio::fs::remove_file(&partial).await;
+                                skip_existing(req);
+                                continue;
+                            };
+                            let download_path = saved_path;
+                            if let Err(e) = tokio::fs::rename(&partial, &download_path).await {
+                                warn!("Failed to move range of '{}' into place: {:?}", filename, e);
+                                audit::record(AuditKind::Error, COMMANDS::GETFILE_RANGE, &message.from, format!("cannot save '{}': {}", filename, e));
+                                req.completion = Some(Completion::Failed(format!("cannot save file: {}", e)));
+                                post_update(AppUpdate::DownloadUpdated(req.clone()));
+                                resolve_download(&request_id, Err(format!("cannot save file: {}", e)));
+                                continue;
+                            }
+                            info!("Saved {} bytes of '{}' at offset {} to '{}'", bytes.len(), filename, start, download_path.display());
+                            // A slice of the file cannot be checked against the whole file's hash
+                            record_history(req, &download_path, whole_file.then(|| expected_file_hash(req, None)).flatten());
 
-                                req.completed = true;
-                                app_guard.set_message(format!("Downloaded file '{}'", filename));
+                            // A slice shorter than asked for means the file ended early or was cut off
+                            let asked = req.range.and_then(|(start, end)| (end != u64::MAX).then(|| end.saturating_sub(start)));
+                            let mut status = format!("Downloaded {} bytes of '{}' as '{}'", bytes.len(), filename,
+                                download_path.file_name().unwrap_or_default().to_string_lossy());
+                            match (&req.expected_hash, actual) {
+                                (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(&actual) => {
+                                    warn!("'{}' does not match its expected hash (expected {}, got {})", filename, expected, actual);
+                                    audit::record(AuditKind::Error, COMMANDS::GETFILE_RANGE, &message.from, format!("hash mismatch for '{}'", filename));
+                                    req.completion = Some(Completion::CompletedWithWarnings("does not match its expected hash".to_string()));
+                                    resolve_download(&request_id, Err("hash mismatch".to_string()));
+                                    status = format!("Downloaded file '{}' does not match its expected hash", filename);
+                                }
+                                _ => {
+                                    req.completion = Some(match asked {
+                                        Some(asked) if (bytes.len() as u64) < asked => Completion::CompletedWithWarnings(
+                                            format!("truncated: {} of {} bytes", bytes.len(), asked)),
+                                        _ => Completion::Completed,
+                                    });
+                                    resolve_download(&request_id, Ok(download_path));
+                                }
                             }
+                            req.total_bytes = Some(asked.unwrap_or(bytes.len() as u64));
+                            req.update_progress();
+                            post_update(AppUpdate::DownloadUpdated(req.clone()));
+                            post_tab_message(Tab::Download, status);
                         }
 
-                        COMMANDS::GETADVERTISE => {
+                        // A pushed list is handled like a reply to the request id it echoes
+                        COMMANDS::GETADVERTISE | COMMANDS::PUSH_ADVERTISE => {
                             let request_id = match stream.stream_out::<String>() {
                                 Ok(id) => id,
-                                Err(_) => { info!("Missing request_id for GETADVERTISE"); continue; }
+                                Err(_) => { info!("Missing request_id for {}", command); continue; }
                             };
                             let file_names = match stream.stream_out::<Vec<String>>() {
                                 Ok(names) => names,
-                                Err(_) => { info!("Missing file names for GETADVERTISE"); continue; }
+                                Err(_) => { info!("Missing file names for {}", command); continue; }
+                            };
+                            // Hashes parallel to the names
+                            let file_hashes = stream.stream_out::<Vec<String>>().unwrap_or_default();
+                            // Service note
+                            let note = stream.stream_out::<String>().ok().filter(|n| !n.is_empty());
+                            // Page carried and total pages
+                            let (page, total_pages) = match (stream.stream_out::<u32>(), stream.stream_out::<u32>()) {
+                                (Ok(page), Ok(total)) => (page, total.max(1)),
+                                _ => (0, 1),
                             };
-                            info!("[*] Received GETADVERTISE for request '{}': {:?}", request_id, file_names);
+                            // Content types parallel to the names
+                            let file_types = stream.stream_out::<Vec<String>>().unwrap_or_default();
+                            // Collection names, first page only
+                            let collections = stream.stream_out::<Vec<String>>().unwrap_or_default();
+                            info!("[*] Received {} page {}/{} for request '{}': {:?}", command, page + 1, total_pages, request_id, file_names);
+                            audit::record(AuditKind::Advertise, &command, &message.from, format!("{} file(s) listed (page {}/{})", file_names.len(), page + 1, total_pages));
 
+                            let pushed = command == COMMANDS::PUSH_ADVERTISE;
 
-                            let mut app_guard = app.lock().await;
-                            if let Some(req) = app_guard.explore_requests.iter_mut()
+                            if let Some(req) = explores.iter_mut()
                                     .find(|r| r.request_id == request_id) 
                                 {
+                                    // Only the explored service may refresh its own list
+                                    if pushed && req.from != message.from {
+                                        warn!("Ignoring PUSH_ADVERTISE for '{}' from a different address", request_id);
+                                        continue;
+                                    }
+
                                     if !req.accepted {
                                         req.accepted = true;
                                         req.ack_time = Some(Instant::now());
                                         info!("No ACK received before GETADVERTISE; auto-marking ACK at {:?}", req.ack_time);
                                     }
 
-                                    req.advertise_files = file_names.clone();
+                                    let hashes = file_names.iter()
+                                        .zip(file_hashes)
+                                        .filter(|(_, hash)| !hash.is_empty())
+                                        .map(|(name, hash)| (name.clone(), hash));
+
+                                    // The first page starts a fresh list; later pages are appended in order
+                                    if page == 0 {
+                                        req.advertise_hashes = hashes.collect();
+                                        req.advertise_files.clear();
+                                        req.advertise_search.clear();
+                                        req.advertise_types.clear();
+                                        req.append_advertised(&file_names, &file_types);
+                                        req.advertise_note = note;
+                                        req.collection_manifests.retain(|name, _| collections.contains(name));
+                                        req.advertise_collections = collections;
+                                    } else if page == req.pages_loaded {
+                                        req.advertise_hashes.extend(hashes);
+                                        req.append_advertised(&file_names, &file_types);
+                                    } else {
+                                        info!("Ignoring out-of-order page {} for '{}' ({} loaded)", page, request_id, req.pages_loaded);
+                                        continue;
+                                    }
+                                    req.pages_loaded = page + 1;
+                                    req.total_pages = total_pages;
+                                    req.page_pending = false;
                                     req.completed = true;
-                                    app_guard.set_message(format!("Discovered files for '{}'", request_id));
+                                    post_update(AppUpdate::ExploreUpdated(req.clone()));
+                                    if pushed {
+                                        post_tab_message(Tab::Explore, format!("Service {} updated its file list", message.from));
+                                    } else if page == 0 {
+                                        post_tab_message(Tab::Explore, format!("Discovered files for '{}'", request_id));
+                                    }
+                                }
+                            }
+                        COMMANDS::GETCOLLECTION => {
+                            let (request_id, name, manifest_json) = match (
+                                stream.stream_out::<String>(),
+                                stream.stream_out::<String>(),
+                                stream.stream_out::<String>(),
+                            ) {
+                                (Ok(id), Ok(name), Ok(json)) => (id, name, json),
+                                _ => { info!("Malformed GETCOLLECTION"); continue; }
+                            };
+                            audit::record(AuditKind::Advertise, COMMANDS::GETCOLLECTION, &message.from, format!("collection '{}'", name));
+
+                            let Some(req) = explores.iter_mut()
+                                .find(|r| r.request_id == request_id) else { continue; };
+                            if req.from != message.from {
+                                warn!("Ignoring GETCOLLECTION for '{}' from a different address", request_id);
+                                continue;
+                            }
+
+                            if manifest_json.is_empty() {
+                                req.advertise_collections.retain(|n| n != &name);
+                                req.collection_manifests.remove(&name);
+                                post_update(AppUpdate::ExploreUpdated(req.clone()));
+                                post_tab_message(Tab::Explore, format!("Collection '{}' is no longer shared", name));
+                                continue;
+                            }
+
+                            // Members are downloaded from the service we asked, whatever the manifest says
+                            match Manifest::from_json(manifest_json.as_bytes()).and_then(|mut manifest| {
+                                manifest.service = req.from.to_string();
+                                manifest.validate().map(|_| manifest)
+                            }) {
+                                Ok(manifest) => {
+                                    info!("Received {} member(s) of collection '{}'", manifest.files.len(), name);
+                                    req.collection_manifests.insert(name, manifest);
+                                    post_update(AppUpdate::ExploreUpdated(req.clone()));
+                                }
+                                Err(e) => {
+                                    warn!("Invalid manifest for collection '{}': {}", name, e);
+                                    audit::record(AuditKind::Error, COMMANDS::GETCOLLECTION, &message.from, format!("invalid manifest for '{}'", name));
+                                    post_tab_message(Tab::Explore, format!("Collection '{}' could not be read", name));
                                 }
                             }
+                        }
+
                         _ => {
                             warn!("[*] Unknown command received: '{}'", command);
                         }
@@ -623,4 +3962,215 @@ pub async fn download_manager(app: Arc<Mutex<FileSharingApp>>) -> Result<(), Str
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helper::{TestDir, OTHER_TEST_ADDRESS, TEST_ADDRESS};
+
+    fn chunked_download(window: u32) -> ChunkedDownload {
+        ChunkedDownload {
+            partial: PathBuf::new(),
+            next: 0,
+            total: 0,
+            last_total: None,
+            early: BTreeMap::new(),
+            hasher: Sha256::new(),
+            service_hash: None,
+            window,
+            acked: 0,
+            acked_at: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn chunk_acks_only_reach_their_transfer() {
+        let requester = SockAddr::from(TEST_ADDRESS);
+        let acks = ChunkAcks::register(&requester, "acks-only-theirs");
+        assert!(!record_chunk_ack(&SockAddr::from(OTHER_TEST_ADDRESS), "acks-only-theirs", 5));
+        assert!(!record_chunk_ack(&requester, "another-request", 5));
+        assert!(record_chunk_ack(&requester, "acks-only-theirs", 5));
+
+        // A late, lower ACK does not move the window back
+        assert!(record_chunk_ack(&requester, "acks-only-theirs", 2));
+        assert_eq!(*acks.receiver.borrow(), 5);
+
+        drop(acks);
+        assert!(!record_chunk_ack(&requester, "acks-only-theirs", 6));
+    }
+
+    #[test]
+    fn download_acks_every_half_window() {
+        let mut state = chunked_download(8);
+        state.next = 3;
+        assert_eq!(state.due_ack(false), None);
+        state.next = 4;
+        assert_eq!(state.due_ack(false), Some(4));
+        state.next = 5;
+        assert_eq!(state.due_ack(false), None);
+        assert_eq!(state.due_ack(true), Some(5));
+
+        // Services that wait for no ACKs get none
+        let mut state = chunked_download(0);
+        state.next = 10;
+        assert_eq!(state.due_ack(true), None);
+    }
+
+    #[test]
+    fn download_takes_changing_chunk_counts_until_the_last_chunk() {
+        let mut state = chunked_download(8);
+        assert!(state.fit(0, 10).is_ok());
+        assert!(state.fit(1, 6).is_ok());
+        assert!(state.fit(5, 6).is_ok());
+        assert_eq!(state.last_total, Some(6));
+
+        // Once the last chunk is known, nothing past it fits and the count cannot change
+        assert!(state.fit(3, 10).is_ok());
+        assert!(state.fit(6, 10).is_err());
+        assert!(state.fit(6, 7).is_err());
+        assert!(state.fit(4, 5).is_err());
+        assert!(state.fit(7, 7).is_err());
+        assert_eq!(state.last_total, Some(6));
+
+        // A chunk past any count is refused, even the last possible one
+        assert!(chunked_download(8).fit(u32::MAX, u32::MAX).is_err());
+        assert!(chunked_download(8).fit(u32::MAX - 1, u32::MAX).is_ok());
+    }
+
+    #[test]
+    fn download_buffers_no_further_than_the_largest_window() {
+        let mut state = chunked_download(8);
+        state.next = 10;
+        assert!(state.within_reach(10 + MAX_CHUNK_WINDOW - 1));
+        assert!(!state.within_reach(10 + MAX_CHUNK_WINDOW));
+        assert!(!state.within_reach(u32::MAX));
+    }
+
+    #[tokio::test]
+    async fn download_target_joins_names_onto_the_download_dir() {
+        let dir = TestDir::new("target");
+        let target = download_target(&dir, "photos/2024/a.jpg").await.unwrap();
+        assert_eq!(target, dir.join("photos").join("2024").join("a.jpg"));
+        assert!(dir.join("photos").join("2024").is_dir());
+
+        for name in ["..\\..\\evil.exe", "C:\\Windows\\evil.dll", "photos\\a.jpg", "../evil"] {
+            assert!(download_target(&dir, name).await.is_err(), "{:?}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn download_resolves_when_the_manager_finishes_it() {
+        assert_eq!(download("service", "a.txt").await, Err("invalid service address".to_string()));
+        assert_eq!(download(TEST_ADDRESS, "../a.txt").await, Err("invalid file name".to_string()));
+
+        let waiting = tokio::spawn(download(TEST_ADDRESS, "awaited.txt"));
+        let request_id = loop {
+            if let Some(id) = DOWNLOAD_WAITERS.lock().unwrap().keys().next().cloned() {
+                break id;
+            }
+            tokio::task::yield_now().await;
+        };
+        resolve_download(&request_id, Ok(PathBuf::from("awaited.txt")));
+        assert_eq!(waiting.await.unwrap(), Ok(PathBuf::from("awaited.txt")));
+    }
+
+    #[test]
+    fn upload_slots_shrink_as_transfers_finish() {
+        let permits = Arc::new(Semaphore::new(3));
+        let running: Vec<_> = (0..3).map(|_| permits.clone().try_acquire_owned().unwrap()).collect();
+        let mut owed = 0;
+        resize_permits(&permits, 3, 1, &mut owed);
+        assert_eq!(owed, 2);
+
+        // Finished transfers pay off what is owed before a new one may start
+        drop(running);
+        resize_permits(&permits, 1, 1, &mut owed);
+        assert_eq!((owed, permits.available_permits()), (0, 1));
+
+        // Growing again first cancels what is still owed
+        let running = permits.clone().try_acquire_owned().unwrap();
+        resize_permits(&permits, 1, 0, &mut owed);
+        resize_permits(&permits, 0, 2, &mut owed);
+        assert_eq!((owed, permits.available_permits()), (0, 1));
+        drop(running);
+        assert_eq!(permits.available_permits(), 2);
+        assert!(!permits.is_closed());
+    }
+
+    #[test]
+    fn byte_ranges_are_saved_beside_the_whole_file() {
+        let target = Path::new("downloads").join("photos").join("a.jpg");
+        let range = range_path(&target, 1024, 4096);
+        assert_eq!(range, Path::new("downloads").join("photos").join("a.jpg.range-1024-4096"));
+        assert_ne!(partial_path(&range), partial_path(&target));
+    }
+
+    /// send_file_chunks against a requester that appends one chunk at a time, slower than
+    /// the file can be sent: the sender stops at the window until it is acknowledged.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn slow_consumer_holds_the_sender_back() {
+        const TOTAL: u32 = 40;
+        const WINDOW: u32 = 6;
+        let dir = TestDir::new("slow-consumer");
+        let contents: Vec<u8> = (0..TOTAL * MIN_FILE_CHUNK_SIZE).map(|i| (i % 251) as u8).collect();
+        std::fs::write(dir.join("slow.bin"), &contents).unwrap();
+        let file = Shareable::new(dir.join("slow.bin")).unwrap();
+
+        let requester = SockAddr::from(TEST_ADDRESS);
+        let request_id = "slow-consumer";
+        let (chunks_tx, mut chunks_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
+        let sender = {
+            let requester = requester.clone();
+            tokio::spawn(async move {
+                let chunks = ChunkOptions { size: MIN_FILE_CHUNK_SIZE, offset: 0, window: WINDOW };
+                let bounds = (MIN_FILE_CHUNK_SIZE, MIN_FILE_CHUNK_SIZE);
+                send_file_chunks(&file, &chunks, bounds, &[], &requester, request_id, async |data, _, _| {
+                    chunks_tx.send(data).is_ok()
+                }).await
+            })
+        };
+
+        // Nothing is acknowledged yet: the sender fills the window and waits
+        let mut queued = Vec::new();
+        for _ in 0..WINDOW {
+            let data = tokio::time::timeout(Duration::from_secs(5), chunks_rx.recv()).await
+                .expect("the sender did not fill the window")
+                .expect("sender stopped early");
+            queued.push(data);
+        }
+        assert!(tokio::time::timeout(Duration::from_millis(50), chunks_rx.recv()).await.is_err(),
+            "the sender ran past the window");
+
+        let mut state = chunked_download(WINDOW);
+        let mut received = Vec::new();
+        let mut queued = queued.into_iter();
+        while state.next < TOTAL {
+            let data = match queued.next() {
+                Some(data) => data,
+                None => chunks_rx.recv().await.expect("sender stopped early"),
+            };
+            let mut stream = DataStream::default();
+            stream.write(&data);
+            assert_eq!(stream.stream_out::<String>().unwrap(), COMMANDS::GETFILE_CHUNK);
+            assert_eq!(stream.stream_out::<String>().unwrap(), request_id);
+            let index = stream.stream_out::<u32>().unwrap();
+            assert_eq!(index, state.next, "chunks arrive in order");
+            assert!(index < state.acked + WINDOW, "chunk {} sent past the window", index);
+            assert_eq!(stream.stream_out::<u32>().unwrap(), TOTAL);
+            let payload = stream.stream_out::<Vec<u8>>().unwrap();
+            received.extend(decode_file_payload(&mut stream, payload, u64::from(FILE_CHUNK_SIZE)).unwrap().1);
+
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            state.next += 1;
+            if let Some(count) = state.due_ack(state.next == TOTAL) {
+                record_chunk_ack(&requester, request_id, count);
+            }
+        }
+
+        assert_eq!(sender.await.unwrap(), Ok(u64::from(TOTAL * MIN_FILE_CHUNK_SIZE)));
+        assert!(received == contents, "the file arrived changed");
+        assert!(chunks_rx.try_recv().is_err(), "no chunk was sent twice");
+    }
+}
@@ -0,0 +1,174 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// External crates
+use serde::{Deserialize, Serialize};
+
+// Standard library
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+// Local
+use crate::request::ExploreRequest;
+
+
+/// File in CONFIG_DIR holding the per-peer transfer summary.
+pub const PEERS_FILE: &str = "peers.json";
+
+/// File in CONFIG_DIR holding the address book: services explored before and what they listed.
+pub const ADDRESS_BOOK_FILE: &str = "address_book.json";
+
+/// Transfer summary for a single requester address.
+/// Only populated for Individual requesters; Anonymous requests carry no address.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeerStats {
+    /// Number of files served to this peer.
+    pub downloads: u32,
+
+    /// Total bytes served to this peer.
+    pub bytes: u64,
+
+    /// Last time this peer sent us a request.
+    pub last_seen: SystemTime,
+}
+
+impl Default for PeerStats {
+    fn default() -> Self {
+        Self {
+            downloads: 0,
+            bytes: 0,
+            last_seen: SystemTime::now(),
+        }
+    }
+}
+
+/// Column the peers table is sorted by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PeerSort {
+    Address,
+    Downloads,
+    Bytes,
+    LastSeen,
+}
+
+/// How long an explorer is remembered for catalog change notifications.
+const EXPLORER_MEMORY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most explorers remembered at once; the least recently seen are dropped first.
+const MAX_RECENT_EXPLORERS: usize = 100;
+
+/// An Individual-mode peer that recently explored our catalog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecentExplorer {
+    /// Id of the peer's explore request, echoed back when pushing an update.
+    pub request_id: String,
+
+    /// Last time the peer explored us.
+    pub last_seen: SystemTime,
+}
+
+/// Remembers that `addr` explored us under `request_id`, forgetting stale entries.
+pub fn remember_explorer(explorers: &mut HashMap<String, RecentExplorer>, addr: String, request_id: String) {
+    let now = SystemTime::now();
+    explorers.retain(|_, e| now.duration_since(e.last_seen).map(|age| age < EXPLORER_MEMORY).unwrap_or(true));
+    explorers.insert(addr, RecentExplorer { request_id, last_seen: now });
+
+    while explorers.len() > MAX_RECENT_EXPLORERS {
+        let Some(oldest) = explorers.iter().min_by_key(|(_, e)| e.last_seen).map(|(a, _)| a.clone()) else { break };
+        explorers.remove(&oldest);
+    }
+}
+
+/// Most services kept in the address book; the least recently explored are dropped first.
+const MAX_SAVED_SERVICES: usize = 500;
+
+/// A service in the address book, with the list it last advertised to us.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedService {
+    /// Files the service advertised.
+    pub files: Vec<String>,
+
+    /// SHA-256 of advertised files, keyed by file name, when the service sent one.
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+
+    /// Names of the collections the service advertised.
+    #[serde(default)]
+    pub collections: Vec<String>,
+
+    /// Note the service sent alongside its list.
+    #[serde(default)]
+    pub note: Option<String>,
+
+    /// When the list was received.
+    pub explored_at: SystemTime,
+}
+
+impl SavedService {
+    /// Takes the list a completed explore request received.
+    pub fn of(request: &ExploreRequest) -> Self {
+        Self {
+            files: request.advertise_files.clone(),
+            hashes: request.advertise_hashes.clone(),
+            collections: request.advertise_collections.clone(),
+            note: request.advertise_note.clone(),
+            explored_at: SystemTime::now(),
+        }
+    }
+}
+
+/// Saves the list `addr` advertised in the address book, replacing the one kept before.
+pub fn remember_service(book: &mut HashMap<String, SavedService>, addr: String, service: SavedService) {
+    book.insert(addr, service);
+
+    while book.len() > MAX_SAVED_SERVICES {
+        let Some(oldest) = book.iter().min_by_key(|(_, s)| s.explored_at).map(|(a, _)| a.clone()) else { break };
+        book.remove(&oldest);
+    }
+}
+
+/// Records a file of `bytes` served to `addr`.
+pub fn record_download(peers: &mut HashMap<String, PeerStats>, addr: String, bytes: u64) {
+    let stats = peers.entry(addr).or_default();
+    stats.downloads = stats.downloads.saturating_add(1);
+    stats.bytes = stats.bytes.saturating_add(bytes);
+    stats.last_seen = SystemTime::now();
+}
+
+/// Returns the peers sorted by the given column.
+pub fn sorted_peers(
+    peers: &HashMap<String, PeerStats>,
+    sort: PeerSort,
+    descending: bool,
+) -> Vec<(&String, &PeerStats)> {
+    let mut rows: Vec<_> = peers.iter().collect();
+    rows.sort_by(|(a_addr, a), (b_addr, b)| match sort {
+        PeerSort::Address => a_addr.cmp(b_addr),
+        PeerSort::Downloads => a.downloads.cmp(&b.downloads),
+        PeerSort::Bytes => a.bytes.cmp(&b.bytes),
+        PeerSort::LastSeen => a.last_seen.cmp(&b.last_seen),
+    });
+    if descending {
+        rows.reverse();
+    }
+    rows
+}
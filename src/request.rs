@@ -21,13 +21,213 @@
 
 // External crates
 use nymlib::{
-    nymsocket::SockAddr,
+    nymsocket::{SockAddr, SocketMode},
     serialize::Serialize,
     serialize_derive::impl_serialize_for_struct,
 };
 
 // Standard library
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+// Local
+use crate::backoff::Retry;
+use crate::compression::Encoding;
+use crate::filetype::FileType;
+use crate::manifest::Manifest;
+
+/// Scheduling priority of a download request.
+/// Higher-priority requests are sent first when the concurrency cap limits how many go out.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// Returns the next higher priority, saturating at High.
+    pub fn raise(self) -> Self {
+        match self {
+            Priority::Low => Priority::Normal,
+            _ => Priority::High,
+        }
+    }
+
+    /// Returns the next lower priority, saturating at Low.
+    pub fn lower(self) -> Self {
+        match self {
+            Priority::High => Priority::Normal,
+            _ => Priority::Low,
+        }
+    }
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// Lifecycle stage of a request, shown as a colored chip in the requests views.
+/// Declared in the order a request moves through, which is also the status sort order.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum RequestStatus {
+    Queued,
+    Sent,
+    Accepted,
+    Downloading,
+    Completed,
+    CompletedWithWarnings,
+    Failed,
+}
+
+impl RequestStatus {
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestStatus::Queued => "Queued",
+            RequestStatus::Sent => "Sent",
+            RequestStatus::Accepted => "Accepted",
+            RequestStatus::Downloading => "Downloading",
+            RequestStatus::Completed => "✔ Completed",
+            RequestStatus::CompletedWithWarnings => "⚠ Completed",
+            RequestStatus::Failed => "✖ Failed",
+        }
+    }
+}
+
+/// How a download ended.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Completion {
+    /// Saved and, when a hash was published, verified.
+    Completed,
+    /// Saved, but the file needs a look (hash mismatch, fewer bytes than asked for).
+    CompletedWithWarnings(String),
+    /// Not saved.
+    Failed(String),
+}
+
+/// Order of the rows in the requests views.
+#[derive(PartialEq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum RequestSort {
+    Priority,
+    Status,
+    Recency,
+}
+
+impl RequestSort {
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestSort::Priority => "Priority",
+            RequestSort::Status => "Status",
+            RequestSort::Recency => "Most recent",
+        }
+    }
+}
+
+/// Finished requests a Clear button removes, held while the user confirms.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ClearTarget {
+    CompletedDownloads,
+    FailedDownloads,
+    CompletedExplores,
+    FailedExplores,
+}
+
+impl ClearTarget {
+    /// Human readable name of the requests cleared, used in the confirmation.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClearTarget::CompletedDownloads => "completed download requests",
+            ClearTarget::FailedDownloads => "failed download requests",
+            ClearTarget::CompletedExplores => "completed explore requests",
+            ClearTarget::FailedExplores => "failed explore requests",
+        }
+    }
+}
+
+/// What to do when a request is added that matches an existing one.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DuplicatePolicy {
+    /// Keep the existing request and tell the user.
+    Reject,
+    /// Reset the existing request so it is sent again.
+    Refresh,
+}
+
+impl DuplicatePolicy {
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Reject => "Reject",
+            DuplicatePolicy::Refresh => "Refresh existing",
+        }
+    }
+}
+
+/// What to do when a finished download would replace a file that already exists.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum CollisionPolicy {
+    /// Replace the existing file.
+    Overwrite,
+    /// Save as "name (1).ext", "name (2).ext", ... whichever is free first.
+    Rename,
+    /// Keep the existing file and fail the download.
+    Skip,
+}
+
+impl CollisionPolicy {
+    pub const ALL: [CollisionPolicy; 3] = [CollisionPolicy::Overwrite, CollisionPolicy::Rename, CollisionPolicy::Skip];
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CollisionPolicy::Overwrite => "Overwrite",
+            CollisionPolicy::Rename => "Rename",
+            CollisionPolicy::Skip => "Skip",
+        }
+    }
+}
+
+/// Socket mode an explore request is sent in.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum ExploreMode {
+    /// Whatever mode the download socket is in.
+    #[default]
+    Downloads,
+    /// Through the mixnet using SURBs; the service never learns our address.
+    Anonymous,
+    /// From a Nym address the service can reply to directly.
+    Individual,
+}
+
+impl ExploreMode {
+    pub const ALL: [ExploreMode; 3] = [ExploreMode::Downloads, ExploreMode::Anonymous, ExploreMode::Individual];
+
+    /// Human readable label used in the UI.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExploreMode::Downloads => "Same as downloads",
+            ExploreMode::Anonymous => "🕶 Anonymous",
+            ExploreMode::Individual => "👥 Individual",
+        }
+    }
+
+    /// Socket mode this resolves to when the download socket is in `download_mode`.
+    pub fn socket_mode(&self, download_mode: &SocketMode) -> SocketMode {
+        match self {
+            ExploreMode::Downloads => download_mode.clone(),
+            ExploreMode::Anonymous => SocketMode::Anonymous,
+            ExploreMode::Individual => SocketMode::Individual,
+        }
+    }
+}
 
 /// Represents a client request to download a file from a remote service.
 /// Contains metadata for initiating and tracking a file download.
@@ -54,14 +254,65 @@ pub struct DownLoadRequest {
     /// Indicates if the request was accepted.
     pub accepted: bool,
 
-    /// Indicates if the download is completed.
-    pub completed: bool,
+    /// How the download ended; None while it is queued or in flight.
+    pub completion: Option<Completion>,
+
+    /// Scheduling priority of the request.
+    pub priority: Priority,
+
+    /// Expected SHA-256 of the file (hex), if known.
+    pub expected_hash: Option<String>,
+
+    /// Content hash to request the file by, instead of its filename.
+    pub content_hash: Option<String>,
+
+    /// Bytes received for this request.
+    pub bytes_received: u64,
+
+    /// Size of the file being received, once the service has said.
+    pub total_bytes: Option<u64>,
+
+    /// Fraction of the file received so far, 0.0 to 1.0.
+    pub progress: f32,
+
+    /// Byte range to fetch as (start, end), end exclusive; u64::MAX means end of file.
+    /// None fetches the whole file.
+    pub range: Option<(u64, u64)>,
+
+    /// Sends so far and when the request goes out next.
+    pub retry: Retry,
+
+    /// Share expiry published in the link; advisory only.
+    pub advertised_expiry: Option<SystemTime>,
+
+    /// Downloads left when the link was copied; advisory only.
+    pub advertised_downloads_left: Option<u32>,
+
+    /// Directory chosen for this download; None saves to the global download_dir.
+    pub save_dir: Option<PathBuf>,
+
+    /// Encoding the file arrived with, once received.
+    pub encoding: Option<Encoding>,
+
+    /// Zip the file is collected into once received; save_dir is then the staging directory
+    /// shared by every file of that archive.
+    pub archive: Option<String>,
+
+    /// Shared by the requests of one multi-file link (service::file1,file2,...).
+    pub batch_id: Option<String>,
+
+    /// Set once the user cancels; replies still in flight are ignored.
+    pub cancelled: bool,
+
+    /// Bytes of the file already in its `.partial` file from an earlier run; the service
+    /// is asked to send only the rest. 0 fetches the whole file.
+    pub offset: u64,
 }
 
 impl DownLoadRequest {
     /// Creates a new [`DownLoadRequest`] instance.
     ///
-    /// The sent field is set to false by default.
+    /// The sent field is set to false and the priority to Normal by default.
     ///
     /// # Arguments
     /// * from - The Nym service address wrapped in SockAddr.
@@ -79,11 +330,199 @@ impl DownLoadRequest {
             sent_time: None,
             ack_time: None,
             accepted: false,
-            completed: false,
+            completion: None,
+            priority: Priority::Normal,
+            expected_hash: None,
+            content_hash: None,
+            bytes_received: 0,
+            total_bytes: None,
+            progress: 0.0,
+            range: None,
+            retry: Retry::default(),
+            advertised_expiry: None,
+            advertised_downloads_left: None,
+            save_dir: None,
+            encoding: None,
+            archive: None,
+            batch_id: None,
+            cancelled: false,
+            offset: 0,
+        }
+    }
+
+    /// Directory the file is written to: the one chosen for this request, else `default_dir`.
+    pub fn target_dir(&self, default_dir: &Path) -> PathBuf {
+        self.save_dir.clone().unwrap_or_else(|| default_dir.to_path_buf())
+    }
+
+    /// Whether the request went out, was never answered and its next retry is due.
+    pub fn retry_due(&self, now: Instant) -> bool {
+        self.sent && !self.accepted && self.completion.is_none()
+            && self.retry.is_due(now)
+    }
+
+    /// Marks the request accepted, by its ACK or by the file when it overtakes the ACK.
+    /// Returns false if it already was, leaving the first acceptance time in place.
+    pub fn accept(&mut self) -> bool {
+        if self.accepted {
+            return false;
+        }
+        self.accepted = true;
+        self.ack_time = Some(Instant::now());
+        true
+    }
+
+    /// Takes in a file that arrived whole (GETFILE) as `bytes` bytes sent with `encoding`.
+    /// Accepts the request if the file overtook its ACK, returning whether it did.
+    pub fn receive_file(&mut self, bytes: u64, encoding: Encoding) -> bool {
+        let accepted = self.accept();
+        self.bytes_received = bytes;
+        self.total_bytes = Some(bytes);
+        self.encoding = Some(encoding);
+        accepted
+    }
+
+    /// Resets the request to its queued state so download_manager sends it again.
+    /// Keeps the request id, priority and what is being asked for.
+    pub fn refresh(&mut self) {
+        self.sent = false;
+        self.sent_time = None;
+        self.ack_time = None;
+        self.accepted = false;
+        self.completion = None;
+        self.bytes_received = 0;
+        self.total_bytes = None;
+        self.progress = 0.0;
+        self.offset = 0;
+        self.encoding = None;
+        self.retry = Retry::default();
+    }
+
+    /// Recomputes progress from the bytes received; a saved file counts as fully received.
+    pub fn update_progress(&mut self) {
+        self.progress = if self.is_completed() {
+            1.0
+        } else {
+            match self.total_bytes {
+                Some(total) if total > 0 => (self.bytes_received as f64 / total as f64).min(1.0) as f32,
+                _ => self.progress,
+            }
+        };
+    }
+
+    /// Whether the request has ended, saved or not.
+    pub fn is_done(&self) -> bool {
+        self.completion.is_some()
+    }
+
+    /// Whether the file was saved, with or without warnings.
+    pub fn is_completed(&self) -> bool {
+        matches!(self.completion, Some(Completion::Completed | Completion::CompletedWithWarnings(_)))
+    }
+
+    /// Whether the request ended without saving the file.
+    pub fn is_failed(&self) -> bool {
+        matches!(self.completion, Some(Completion::Failed(_)))
+    }
+
+    /// Why the request failed, if it did.
+    pub fn failure_reason(&self) -> Option<&str> {
+        match &self.completion {
+            Some(Completion::Failed(reason)) => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// What needs a look about a saved file, if anything.
+    pub fn warning(&self) -> Option<&str> {
+        match &self.completion {
+            Some(Completion::CompletedWithWarnings(warning)) => Some(warning),
+            _ => None,
+        }
+    }
+
+    /// Current lifecycle stage of the request.
+    pub fn status(&self) -> RequestStatus {
+        if let Some(completion) = &self.completion {
+            match completion {
+                Completion::Completed => RequestStatus::Completed,
+                Completion::CompletedWithWarnings(_) => RequestStatus::CompletedWithWarnings,
+                Completion::Failed(_) => RequestStatus::Failed,
+            }
+        } else if self.accepted && self.bytes_received > 0 {
+            RequestStatus::Downloading
+        } else if self.accepted {
+            RequestStatus::Accepted
+        } else if self.sent {
+            RequestStatus::Sent
+        } else {
+            RequestStatus::Queued
+        }
+    }
+}
+
+/// File in CONFIG_DIR holding the download requests still in progress, resumed at startup.
+pub const DOWNLOADS_FILE: &str = "downloads.json";
+
+/// What is saved of an unfinished download request between runs.
+/// Progress is not saved; it is read back from the request's `.partial` file.
+#[derive(PartialEq, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SavedDownload {
+    pub service: String,
+    pub filename: String,
+    pub request_id: String,
+    pub priority: Priority,
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    #[serde(default)]
+    pub range: Option<(u64, u64)>,
+    #[serde(default)]
+    pub save_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub archive: Option<String>,
+    #[serde(default)]
+    pub batch_id: Option<String>,
+}
+
+impl SavedDownload {
+    /// Takes what is kept of a download request.
+    pub fn of(request: &DownLoadRequest) -> Self {
+        Self {
+            service: request.from.to_string(),
+            filename: request.filename.clone(),
+            request_id: request.request_id.clone(),
+            priority: request.priority,
+            expected_hash: request.expected_hash.clone(),
+            content_hash: request.content_hash.clone(),
+            range: request.range,
+            save_dir: request.save_dir.clone(),
+            archive: request.archive.clone(),
+            batch_id: request.batch_id.clone(),
+        }
+    }
+
+    /// Rebuilds the request, queued to be sent again. None if the service address no longer parses.
+    pub fn restore(self) -> Option<DownLoadRequest> {
+        let from = SockAddr::from(self.service.as_str());
+        if from.is_null() {
+            return None;
         }
+        let mut request = DownLoadRequest::new(from, self.filename, self.request_id);
+        request.priority = self.priority;
+        request.expected_hash = self.expected_hash;
+        request.content_hash = self.content_hash;
+        request.range = self.range;
+        request.save_dir = self.save_dir;
+        request.archive = self.archive;
+        request.batch_id = self.batch_id;
+        Some(request)
     }
 }
 
+// Only the fields every service reads. Newer ones, such as the resume `offset`, follow the
+// request as trailing fields so services that predate them still parse what comes after.
 impl_serialize_for_struct! {
     target DownLoadRequest {
         readwrite(self.request_id);
@@ -95,7 +534,7 @@ impl_serialize_for_struct! {
 
 /// Represents a client request to explore a remote service for its advertised files.
 /// Stores metadata for initiating, sending, and tracking the exploration process.
-#[derive(Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct ExploreRequest {
     /// Address of the service being queried.
     pub from: SockAddr,
@@ -103,6 +542,30 @@ pub struct ExploreRequest {
     /// List of files advertised by the remote service.
     pub advertise_files: Vec<String>,
 
+    /// Lowercased advertise_files, index for index, so searching does not re-lowercase every frame.
+    pub advertise_search: Vec<String>,
+
+    /// Content type of each advertised file, index for index with advertise_files.
+    pub advertise_types: Vec<FileType>,
+
+    /// SHA-256 of advertised files, keyed by file name, when the service sent one.
+    pub advertise_hashes: HashMap<String, String>,
+
+    /// Note sent by the service alongside its list (e.g. why it is empty).
+    pub advertise_note: Option<String>,
+
+    /// Names of the collections advertised by the service.
+    pub advertise_collections: Vec<String>,
+
+    /// Member manifests of the collections fetched so far, keyed by collection name.
+    pub collection_manifests: HashMap<String, Manifest>,
+
+    /// Collections whose members were asked for and not yet requested from the service.
+    pub collections_wanted: HashSet<String>,
+
+    /// Socket mode the request goes out in; kept across refreshes.
+    pub mode: ExploreMode,
+
     /// Unique identifier for this exploration request.
     pub request_id: String,
 
@@ -120,21 +583,137 @@ pub struct ExploreRequest {
 
     /// Whether the exploration session has completed.
     pub completed: bool,
+
+    /// Pages of the advertised list received so far.
+    pub pages_loaded: u32,
+
+    /// Pages the service reported for its list; 1 for services that do not paginate.
+    pub total_pages: u32,
+
+    /// Whether the UI asked for the next page and it has not been sent yet.
+    pub page_wanted: bool,
+
+    /// Whether the next page was requested and has not arrived yet.
+    pub page_pending: bool,
+
+    /// Sends so far and when the request goes out next.
+    pub retry: Retry,
+
+    /// Set once the request was given up: it could not be sent, or went unanswered
+    /// through every retry.
+    pub failed: bool,
 }
 
 impl ExploreRequest {
-    pub fn new(from: SockAddr, request_id: String) -> Self {
+    pub fn new(from: SockAddr, request_id: String, mode: ExploreMode) -> Self {
         Self {
             from,
             advertise_files: Vec::new(),
+            advertise_search: Vec::new(),
+            advertise_types: Vec::new(),
+            advertise_hashes: HashMap::new(),
+            advertise_note: None,
+            advertise_collections: Vec::new(),
+            collection_manifests: HashMap::new(),
+            collections_wanted: HashSet::new(),
+            mode,
             request_id,
             sent: false,
             sent_time: None,
             ack_time: None,
             accepted: false,
             completed: false,
+            pages_loaded: 0,
+            total_pages: 0,
+            page_wanted: false,
+            page_pending: false,
+            retry: Retry::default(),
+            failed: false,
         }
     }
+
+    /// Whether the request went out, was never answered and its next retry is due.
+    pub fn retry_due(&self, now: Instant) -> bool {
+        self.sent && !self.accepted && !self.completed && !self.failed && self.retry.is_due(now)
+    }
+
+    /// Resets the request to its queued state, dropping the list received so far,
+    /// so download_manager asks the service again.
+    pub fn refresh(&mut self) {
+        self.advertise_files.clear();
+        self.advertise_search.clear();
+        self.advertise_types.clear();
+        self.advertise_hashes.clear();
+        self.advertise_note = None;
+        self.advertise_collections.clear();
+        self.collection_manifests.clear();
+        self.collections_wanted.clear();
+        self.sent = false;
+        self.sent_time = None;
+        self.ack_time = None;
+        self.accepted = false;
+        self.completed = false;
+        self.pages_loaded = 0;
+        self.total_pages = 0;
+        self.page_wanted = false;
+        self.page_pending = false;
+        self.retry = Retry::default();
+        self.failed = false;
+    }
+
+    /// Current lifecycle stage of the request; Downloading while further pages are loading.
+    pub fn status(&self) -> RequestStatus {
+        if self.page_pending {
+            RequestStatus::Downloading
+        } else if self.failed {
+            RequestStatus::Failed
+        } else if self.completed {
+            RequestStatus::Completed
+        } else if self.accepted {
+            RequestStatus::Accepted
+        } else if self.sent {
+            RequestStatus::Sent
+        } else {
+            RequestStatus::Queued
+        }
+    }
+
+    /// Appends a page of advertised names, keeping the search index and types in step.
+    /// Types the service did not send (or we do not know) are guessed from the extension.
+    pub fn append_advertised(&mut self, names: &[String], types: &[String]) {
+        self.advertise_files.extend_from_slice(names);
+        self.advertise_search.extend(names.iter().map(|name| name.to_lowercase()));
+        self.advertise_types.extend(names.iter().enumerate().map(|(i, name)| {
+            types.get(i)
+                .and_then(|label| FileType::from_label(label))
+                .unwrap_or_else(|| FileType::from_name(name))
+        }));
+    }
+
+    /// Whether the advertised file at `index` contains `query` (already lowercased)
+    /// and has type `file_type`, when one is given.
+    fn file_matches(&self, index: usize, query: &str, file_type: Option<FileType>) -> bool {
+        self.advertise_search[index].contains(query)
+            && file_type.is_none_or(|t| self.advertise_types[index] == t)
+    }
+
+    /// Indices into advertise_files of the files matching the search and type filter.
+    /// An empty query and no type match every file.
+    pub fn matching_files(&self, query: &str, file_type: Option<FileType>) -> Vec<usize> {
+        (0..self.advertise_files.len())
+            .filter(|&i| self.file_matches(i, query, file_type))
+            .collect()
+    }
+
+    /// Whether any advertised file matches the search and type filter.
+    pub fn has_matching_file(&self, query: &str, file_type: Option<FileType>) -> bool {
+        (0..self.advertise_files.len()).any(|i| self.file_matches(i, query, file_type))
+    }
+
+    /// Whether the service has pages of its list we have not fetched.
+    pub fn has_more_pages(&self) -> bool {
+        self.completed && self.pages_loaded < self.total_pages
+    }
 }
 
 
@@ -142,4 +721,79 @@ impl_serialize_for_struct! {
     target ExploreRequest {
         readwrite(self.request_id);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helper::TEST_ADDRESS;
+
+    fn sent_request() -> DownLoadRequest {
+        let mut req = DownLoadRequest::new(SockAddr::from(TEST_ADDRESS), "a.txt".to_string(), "request".to_string());
+        req.sent = true;
+        req.sent_time = Some(Instant::now());
+        req
+    }
+
+    /// What download_manager does with a GETFILE it saves.
+    fn save_file(req: &mut DownLoadRequest) {
+        req.receive_file(10, Encoding::Raw);
+        req.completion = Some(Completion::Completed);
+    }
+
+    #[test]
+    fn file_before_its_ack_still_passes_through_accepted() {
+        let mut req = sent_request();
+        assert_eq!(req.status(), RequestStatus::Sent);
+
+        // The file overtakes the ACK: it accepts the request on its way to completed
+        assert!(req.accept());
+        assert_eq!(req.status(), RequestStatus::Accepted);
+        let accepted_at = req.ack_time;
+        assert!(accepted_at.is_some());
+        save_file(&mut req);
+        assert_eq!(req.status(), RequestStatus::Completed);
+
+        // The late ACK changes nothing
+        assert!(!req.accept());
+        assert_eq!(req.ack_time, accepted_at);
+        assert_eq!(req.status(), RequestStatus::Completed);
+    }
+
+    #[test]
+    fn ack_before_its_file_keeps_the_ack_time() {
+        let mut req = sent_request();
+        assert!(req.accept());
+        let acked_at = req.ack_time;
+        assert_eq!(req.status(), RequestStatus::Accepted);
+
+        save_file(&mut req);
+        assert_eq!(req.ack_time, acked_at);
+        assert_eq!(req.status(), RequestStatus::Completed);
+    }
+
+    #[test]
+    fn received_file_accepts_and_sizes_the_request() {
+        let mut req = sent_request();
+        assert!(req.receive_file(10, Encoding::Deflate));
+        assert!(req.ack_time.is_some());
+        assert_eq!(req.status(), RequestStatus::Downloading);
+        assert_eq!((req.bytes_received, req.total_bytes, req.encoding), (10, Some(10), Some(Encoding::Deflate)));
+
+        // A second copy of the file keeps the first acceptance
+        let accepted_at = req.ack_time;
+        assert!(!req.receive_file(12, Encoding::Raw));
+        assert_eq!(req.ack_time, accepted_at);
+        assert_eq!(req.total_bytes, Some(12));
+    }
+
+    #[test]
+    fn refreshed_request_waits_for_a_new_ack() {
+        let mut req = sent_request();
+        save_file(&mut req);
+        req.refresh();
+        assert_eq!(req.ack_time, None);
+        assert!(req.accept());
+    }
+}
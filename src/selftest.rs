@@ -0,0 +1,121 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// External crates
+use tokio::sync::Mutex;
+use tokio::time::{timeout, Duration, Instant};
+use uuid::Uuid;
+use log::{info, error};
+
+// Standard library
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Local
+use crate::app::{FileSharingApp, post_message};
+use crate::network::download;
+use crate::shareable::Shareable;
+use crate::bus::{post_command, ManagerCommand};
+
+
+/// How long the loopback transfer may take before the self-test fails.
+const SELF_TEST_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Serves a tiny temporary file and downloads it from our own serving address.
+///
+/// Exercises the whole path (serving socket, download socket, managers, download
+/// directory) and reports the outcome in the UI and the log. The temporary file,
+/// its catalog entry and the downloaded copy are removed afterwards.
+pub async fn run_self_test(app: Arc<Mutex<FileSharingApp>>) {
+    info!("[*] Starting loopback self-test");
+    post_message("Self-test: starting loopback transfer");
+
+    let result = loopback_transfer(&app).await;
+    match &result {
+        Ok(elapsed) => {
+            info!("[*] Self-test passed in {:?}", elapsed);
+            post_message(format!("Self-test passed: loopback transfer took {}s", elapsed.as_secs()));
+        }
+        Err(e) => {
+            error!("[*] Self-test failed: {}", e);
+            post_message(format!("Self-test failed: {}", e));
+        }
+    }
+}
+
+async fn loopback_transfer(app: &Arc<Mutex<FileSharingApp>>) -> Result<Duration, String> {
+    let token = Uuid::new_v4().to_string();
+    let file_name = format!("nymshare-selftest-{}.txt", token);
+    let source = std::env::temp_dir().join(&file_name);
+    let payload = format!("nymshare self-test {}\n", token).into_bytes();
+    fs::write(&source, &payload).map_err(|e| format!("cannot write temp file: {}", e))?;
+
+    let result = transfer(app, &source, &file_name, &payload).await;
+
+    // Clean up the catalog entry, the request and both files
+    let download_path = {
+        let mut app_guard = app.lock().await;
+        app_guard.shareable_files.retain(|f| f.path != source);
+        let request_ids = app_guard.requested_files.iter()
+            .filter(|r| r.filename == file_name)
+            .map(|r| r.request_id.clone())
+            .collect();
+        post_command(ManagerCommand::RemoveDownloads(request_ids));
+        app_guard.download_dir.join(&file_name)
+    };
+    let _ = fs::remove_file(&source);
+    let _ = fs::remove_file(download_path);
+
+    result
+}
+
+async fn transfer(
+    app: &Arc<Mutex<FileSharingApp>>,
+    source: &Path,
+    file_name: &str,
+    payload: &[u8],
+) -> Result<Duration, String> {
+    let mut shareable = Shareable::new(source.to_path_buf()).map_err(|e| e.to_string())?;
+    shareable.activate();
+
+    let serving_addr = {
+        let mut app_guard = app.lock().await;
+        app_guard.shareable_files.push(shareable);
+        app_guard.serving_addr.clone()
+    };
+    if serving_addr.is_empty() {
+        return Err("serving address is not available".to_string());
+    }
+
+    let started = Instant::now();
+    let saved = match timeout(SELF_TEST_TIMEOUT, download(&serving_addr, file_name)).await {
+        Ok(result) => result.map_err(|e| format!("download failed ({})", e))?,
+        Err(_) => return Err(format!("no file received within {}s", SELF_TEST_TIMEOUT.as_secs())),
+    };
+
+    let received = fs::read(&saved).map_err(|e| format!("downloaded file cannot be read: {}", e))?;
+    if received != payload {
+        return Err("downloaded bytes differ from the served file".to_string());
+    }
+    Ok(started.elapsed())
+}
@@ -0,0 +1,65 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use nymlib::nymsocket::SockAddr;
+
+// Standard library
+use std::time::SystemTime;
+
+
+/// Most serve events kept; the oldest are forgotten first.
+pub const MAX_SERVE_EVENTS: usize = 500;
+
+/// A file (or part of one) sent to a peer by serving_manager.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServeEvent {
+    /// Name the file is shared under.
+    pub filename: String,
+
+    /// Address of the requester; "anonymous" when it asked in Anonymous mode.
+    pub peer: String,
+
+    /// Bytes of the file sent, before encoding.
+    pub bytes: u64,
+
+    /// When the last of it was sent.
+    pub served_at: SystemTime,
+}
+
+impl ServeEvent {
+    /// Records a transfer to `peer` that has just finished.
+    pub fn new(filename: String, peer: &SockAddr, bytes: u64) -> Self {
+        let peer = if peer.is_null() { "anonymous".to_string() } else { peer.to_string() };
+        Self { filename, peer, bytes, served_at: SystemTime::now() }
+    }
+}
+
+/// Adds a serve event to the log, forgetting the oldest past MAX_SERVE_EVENTS.
+pub fn record(events: &mut Vec<ServeEvent>, event: ServeEvent) {
+    events.push(event);
+    if events.len() > MAX_SERVE_EVENTS {
+        let excess = events.len() - MAX_SERVE_EVENTS;
+        events.drain(..excess);
+    }
+}
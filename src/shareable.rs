@@ -19,15 +19,221 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+// External crates
+use nymlib::nymsocket::SockAddr;
 
 // Standard library
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// Local
+use crate::filetype::FileType;
+use crate::helper::sha256_file;
+use crate::network::DEFAULT_IDENTITY;
+
+// Largest file accepted for sharing (requesters that predate chunking take a file in a single message)
+pub const MAX_SHARE_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+// Most files a shared folder may expand into
+pub const MAX_DIR_FILES: usize = 10_000;
+
+// Directory names that usually hold secrets (keys, credentials, Nym identity)
+const SENSITIVE_DIRS: &[&str] = &[".ssh", ".gnupg", ".aws", ".kube", ".password-store", "serving_datadir"];
+
+// System locations that should never be shared
+const SENSITIVE_ROOTS: &[&str] = &["/etc", "/proc", "/sys", "/dev"];
+
+// Reasons a path cannot be turned into a Shareable
+#[derive(Debug)]
+pub enum ShareableError {
+    NoFileName,                                  // Path has no file name component
+    NotFound(PathBuf),                           // Path does not exist
+    NotAFile(PathBuf),                           // Path exists but is not a regular file
+    TooLarge { path: PathBuf, size: u64 },       // File exceeds MAX_SHARE_SIZE
+    Unreadable(PathBuf, io::Error),              // File cannot be opened for reading
+    SensitiveLocation(PathBuf),                  // File lives in a location that usually holds secrets
+}
+
+impl fmt::Display for ShareableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareableError::NoFileName => write!(f, "Path must contain a valid file name"),
+            ShareableError::NotFound(path) => write!(f, "File does not exist: {:?}", path),
+            ShareableError::NotAFile(path) => write!(f, "Path is not a file: {:?}", path),
+            ShareableError::TooLarge { path, size } => write!(
+                f, "File is too large to share ({} bytes, max {} bytes): {:?}", size, MAX_SHARE_SIZE, path
+            ),
+            ShareableError::Unreadable(path, e) => write!(f, "File cannot be read: {:?} ({})", path, e),
+            ShareableError::SensitiveLocation(path) => write!(f, "File is in a sensitive location: {:?}", path),
+        }
+    }
+}
+
+impl std::error::Error for ShareableError {}
+
+// Returns true if the path is inside a location that usually holds secrets
+fn is_sensitive_location(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    SENSITIVE_ROOTS.iter().any(|root| path.starts_with(root))
+        || path.components().any(|c| match c {
+            Component::Normal(name) => SENSITIVE_DIRS.iter().any(|d| name == *d),
+            _ => false,
+        })
+}
+
+// SHA-256 of a file together with the size and mtime it was computed at
+// The digest is only trusted while both still match the file on disk
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HashCache {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub digest: String,
+}
+
+impl HashCache {
+    // Hashes the file, recording its size and mtime before reading it
+    pub fn compute(path: &Path) -> io::Result<Self> {
+        let meta = fs::metadata(path)?;
+        let modified = meta.modified()?;
+        let digest = sha256_file(path)?;
+        Ok(Self { size: meta.len(), modified, digest })
+    }
+
+    // Returns true if the file still has the recorded size and mtime
+    fn is_fresh(&self, path: &Path) -> bool {
+        fs::metadata(path).is_ok_and(|m| self.matches(&m))
+    }
+
+    // Returns true if `meta` has the recorded size and mtime
+    fn matches(&self, meta: &fs::Metadata) -> bool {
+        meta.len() == self.size && meta.modified().is_ok_and(|t| t == self.modified)
+    }
+}
+
+// How long a file counts as recently added in the Share tab
+pub const RECENTLY_ADDED: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Order of the files in the Share tab
+#[derive(PartialEq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum ShareSort {
+    Listed,     // The order files were added in, kept by undo
+    DateAdded,  // Newest first
+    Name,       // Alphabetical by shared name
+    Size,       // Largest first; missing files last
+    Downloads,  // Most downloaded first
+    Advertise,  // Most advertised first
+}
+
+impl ShareSort {
+    // Human readable label used in the UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShareSort::Listed => "List order",
+            ShareSort::DateAdded => "Date added",
+            ShareSort::Name => "Name",
+            ShareSort::Size => "Size",
+            ShareSort::Downloads => "Downloads",
+            ShareSort::Advertise => "Advertise count",
+        }
+    }
+
+    // Every order, as offered in the sort dropdown
+    pub const ALL: [ShareSort; 6] = [
+        ShareSort::Listed,
+        ShareSort::DateAdded,
+        ShareSort::Name,
+        ShareSort::Size,
+        ShareSort::Downloads,
+        ShareSort::Advertise,
+    ];
+
+    // Reorders `indices` into `files`; sorts are stable so ties keep list order
+    pub fn sort(&self, files: &[Shareable], indices: &mut [usize]) {
+        match self {
+            ShareSort::Listed => {}
+            ShareSort::DateAdded => indices.sort_by_key(|&i| std::cmp::Reverse(files[i].added_at)),
+            ShareSort::Name => indices.sort_by_cached_key(|&i| files[i].file_name().unwrap_or_default().to_lowercase()),
+            // None (missing on disk) sorts below every size
+            ShareSort::Size => indices.sort_by_key(|&i| std::cmp::Reverse(files[i].disk_size)),
+            ShareSort::Downloads => indices.sort_by_key(|&i| std::cmp::Reverse(files[i].downloads)),
+            ShareSort::Advertise => indices.sort_by_key(|&i| std::cmp::Reverse(files[i].advertise)),
+        }
+    }
+}
+
+// Name of the file the share list is saved to, in the config directory
+pub const SHARES_FILE: &str = "shares.json";
+
+// What is saved of a shared file between runs
+// The file is validated again when loaded and its type detected again
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SavedShare {
+    pub path: PathBuf,
+    pub active: bool,
+    pub advertise: u32,
+    pub downloads: u32,
+    #[serde(default)]
+    pub max_downloads: Option<u32>,
+    #[serde(default)]
+    pub expires_at: Option<SystemTime>,
+    #[serde(default)]
+    pub hash_cache: Option<HashCache>,
+    pub added_at: SystemTime,
+    #[serde(default)]
+    pub identity: Option<String>,
+    #[serde(default)]
+    pub relative_path: Option<PathBuf>,
+    #[serde(default)]
+    pub allowed_peers: Option<Vec<String>>,
+}
+
+impl SavedShare {
+    // Takes what is kept of a shared file
+    pub fn of(file: &Shareable) -> Self {
+        Self {
+            path: file.path.clone(),
+            active: file.active,
+            advertise: file.advertise,
+            downloads: file.downloads,
+            max_downloads: file.max_downloads,
+            expires_at: file.expires_at,
+            hash_cache: file.hash_cache.clone(),
+            added_at: file.added_at,
+            identity: file.identity.clone(),
+            relative_path: file.relative_path.clone(),
+            allowed_peers: file.allowed_peers.as_ref().map(|peers| peers.iter().map(|p| p.to_string()).collect()),
+        }
+    }
+
+    // Rebuilds the shared file, failing like Shareable::new if it can no longer be shared
+    // Sensitive locations are accepted: the user chose to share the file when it was added
+    pub fn restore(self) -> Result<Shareable, ShareableError> {
+        let mut file = Shareable::new_allow_sensitive(self.path)?;
+        file.active = self.active;
+        file.advertise = self.advertise;
+        file.downloads = self.downloads;
+        file.max_downloads = self.max_downloads;
+        file.expires_at = self.expires_at;
+        file.hash_cache = self.hash_cache;
+        file.refresh_disk_state();
+        file.added_at = self.added_at;
+        file.identity = self.identity;
+        file.relative_path = self.relative_path;
+        // Addresses that no longer parse are dropped; the list stays restrictive even if emptied
+        file.allowed_peers = self.allowed_peers.map(|peers| {
+            peers.iter().map(|p| SockAddr::from(p.as_str())).filter(|p| !p.is_null()).collect()
+        });
+        Ok(file)
+    }
+}
 
 // Represents a file that can be shared
 // Holds the file's path, sharing status, and download count
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Shareable {
     // The filesystem path to the file
     pub path: PathBuf,
@@ -40,32 +246,146 @@ pub struct Shareable {
 
     // Number of times this file has been downloaded
     pub downloads: u32,
+
+    // Optional cap on downloads; the file is deactivated once reached
+    pub max_downloads: Option<u32>,
+
+    // Optional expiry time; the file is refused and deactivated after it
+    pub expires_at: Option<SystemTime>,
+
+    // Cached content hash; recomputed only when size or mtime change
+    pub hash_cache: Option<HashCache>,
+
+    // True while hash_cache matched the file when last checked; refreshed by the file watcher
+    // and after hashing so the UI never stats the file to show the hash
+    pub hash_fresh: bool,
+
+    // Size on disk when last checked, None if the file was missing; refreshed like hash_fresh
+    pub disk_size: Option<u64>,
+
+    // Content type, detected from the extension or magic bytes when added
+    pub file_type: FileType,
+
+    // When the file was added to the share list
+    pub added_at: SystemTime,
+
+    // Serving identity the file is offered from; None means the default identity
+    pub identity: Option<String>,
+
+    // Path within a shared folder, starting with the folder's name ("photos/2024/a.jpg");
+    // None for files shared on their own
+    pub relative_path: Option<PathBuf>,
+
+    // Peers allowed to download the file; None lets anyone who knows the name fetch it
+    // Requesters are only identified in Individual mode, so anonymous requests never match
+    pub allowed_peers: Option<Vec<SockAddr>>,
 }
 
 impl Shareable {
     // Creates a new Shareable instance from a file path
-    // Returns an error if the path is invalid, does not exist, or is not a file
-    pub fn new(path: PathBuf) -> Result<Self, String> {
+    // Returns an error if the path is invalid, missing, not a file, too large,
+    // unreadable, or in a sensitive location
+    pub fn new(path: PathBuf) -> Result<Self, ShareableError> {
+        Self::build(path, false)
+    }
+
+    // Same as new, but accepts files in sensitive locations ("share anyway")
+    pub fn new_allow_sensitive(path: PathBuf) -> Result<Self, ShareableError> {
+        Self::build(path, true)
+    }
+
+    fn build(path: PathBuf, allow_sensitive: bool) -> Result<Self, ShareableError> {
         if path.file_name().is_none() {
-            return Err("Path must contain a valid file name".to_string());
+            return Err(ShareableError::NoFileName);
         }
 
         if !path.exists() {
-            return Err(format!("File does not exist: {:?}", path));
+            return Err(ShareableError::NotFound(path));
         }
 
         if !path.is_file() {
-            return Err(format!("Path is not a file: {:?}", path));
+            return Err(ShareableError::NotAFile(path));
+        }
+
+        let size = fs::metadata(&path)
+            .map_err(|e| ShareableError::Unreadable(path.clone(), e))?
+            .len();
+        if size > MAX_SHARE_SIZE {
+            return Err(ShareableError::TooLarge { path, size });
+        }
+
+        if let Err(e) = fs::File::open(&path) {
+            return Err(ShareableError::Unreadable(path, e));
+        }
+
+        if !allow_sensitive && is_sensitive_location(&path) {
+            return Err(ShareableError::SensitiveLocation(path));
         }
 
+        let file_type = FileType::detect(&path);
+
         Ok(Self {
             path,
             active: false,  // Files start as inactive
             advertise: 0,   // Advertise count starts at 0 
             downloads: 0,   // Download count starts at 0
+            max_downloads: None, // No download limit
+            expires_at: None,    // Never expires
+            hash_cache: None,    // Hashed in the background after adding
+            hash_fresh: false,   // Nothing hashed yet
+            disk_size: Some(size), // Checked above
+            file_type,           // Detected above
+            added_at: SystemTime::now(),
+            identity: None,      // Served by the default identity
+            relative_path: None, // Shared on its own
+            allowed_peers: None, // Anyone may download
         })
     }
 
+    // Expands a folder into one Shareable per file below it, each keeping its path
+    // relative to the folder's parent so the recipient can rebuild the tree
+    // Symlinks are skipped, as are files that cannot be shared (too large, unreadable,
+    // in a sensitive location); fails if the folder itself cannot be shared or read
+    pub fn from_dir(path: &Path) -> Result<Vec<Shareable>, String> {
+        let Some(root_name) = path.file_name() else {
+            return Err(format!("Folder must have a name: {:?}", path));
+        };
+        if !path.is_dir() {
+            return Err(format!("Path is not a folder: {:?}", path));
+        }
+        if is_sensitive_location(path) {
+            return Err(format!("Folder is in a sensitive location: {:?}", path));
+        }
+
+        let mut files = Vec::new();
+        let mut pending = vec![(path.to_path_buf(), PathBuf::from(root_name))];
+        while let Some((dir, relative)) = pending.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if dir == path => return Err(format!("Folder cannot be read: {:?} ({})", path, e)),
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                // file_type does not follow symlinks
+                let Ok(file_type) = entry.file_type() else { continue; };
+                let entry_relative = relative.join(entry.file_name());
+                if file_type.is_dir() {
+                    pending.push((entry.path(), entry_relative));
+                } else if file_type.is_file() {
+                    if let Ok(mut file) = Shareable::new(entry.path()) {
+                        file.relative_path = Some(entry_relative);
+                        files.push(file);
+                    }
+                    if files.len() > MAX_DIR_FILES {
+                        return Err(format!("Folder holds more than {} files: {:?}", MAX_DIR_FILES, path));
+                    }
+                }
+            }
+        }
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        Ok(files)
+    }
+
     // Marks the file as active
     pub fn activate(&mut self) {
         self.active = true;
@@ -81,16 +401,176 @@ impl Shareable {
         self.active
     }
 
-    // Reads the file contents into a byte vector
-    pub fn read_bytes(&self) -> io::Result<Vec<u8>> {
-        fs::read(&self.path)
+    // Returns the name of the serving identity the file is offered from
+    pub fn identity_name(&self) -> &str {
+        self.identity.as_deref().unwrap_or(DEFAULT_IDENTITY)
     }
 
-    // Returns the file name as a string if possible
+    // Returns true if requests reaching the identity called `name` may be answered with this file
+    pub fn served_by(&self, name: &str) -> bool {
+        self.identity_name() == name
+    }
+
+    // Returns the number of downloads left before the limit, if one is set
+    pub fn remaining_downloads(&self) -> Option<u32> {
+        self.max_downloads.map(|max| max.saturating_sub(self.downloads))
+    }
+
+    // Returns true if the download limit has been reached
+    pub fn limit_reached(&self) -> bool {
+        self.remaining_downloads() == Some(0)
+    }
+
+    // Returns true if the file was added within RECENTLY_ADDED
+    pub fn recently_added(&self) -> bool {
+        SystemTime::now().duration_since(self.added_at).map_or(true, |age| age < RECENTLY_ADDED)
+    }
+
+    // Returns true if `peer` may download the file
+    pub fn allows(&self, peer: &SockAddr) -> bool {
+        self.allowed_peers.as_ref().is_none_or(|peers| peers.contains(peer))
+    }
+
+    // Returns true if the share has an expiry time in the past
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map(|t| SystemTime::now() >= t).unwrap_or(false)
+    }
+
+    // Checks the file on disk once and records its size and whether the cached hash still matches it
+    pub fn refresh_disk_state(&mut self) {
+        let meta = fs::metadata(&self.path).ok();
+        self.disk_size = meta.as_ref().map(|m| m.len());
+        self.hash_fresh = self.hash_cache.as_ref().zip(meta.as_ref()).is_some_and(|(c, m)| c.matches(m));
+    }
+
+    // Returns the cached SHA-256 as of the last refresh_disk_state, without touching the disk
+    // For the UI; code about to serve the file uses cached_hash
+    pub fn known_hash(&self) -> Option<&str> {
+        self.hash_cache
+            .as_ref()
+            .filter(|_| self.hash_fresh)
+            .map(|c| c.digest.as_str())
+    }
+
+    // Returns the cached SHA-256 if the file has not changed since it was hashed
+    pub fn cached_hash(&self) -> Option<&str> {
+        self.hash_cache
+            .as_ref()
+            .filter(|c| c.is_fresh(&self.path))
+            .map(|c| c.digest.as_str())
+    }
+
+    // Returns the SHA-256 of the file as lowercase hex, from the cache while it is fresh
+    // Reads the whole file otherwise; call off the UI thread and async runtime
+    pub fn sha256(&self) -> io::Result<String> {
+        match self.cached_hash() {
+            Some(hash) => Ok(hash.to_string()),
+            None => sha256_file(&self.path),
+        }
+    }
+
+    // Returns the current size of the file in bytes
+    pub fn size(&self) -> io::Result<u64> {
+        Ok(fs::metadata(&self.path)?.len())
+    }
+
+    // Returns the name the file is shared under; None only if the path has no file name.
+    // Names that are not valid UTF-8 get their invalid bytes escaped (see wire_name),
+    // and requests are matched against this name, so the real path is never sent
+    // Files from a shared folder are named by their relative path, joined with '/'
     pub fn file_name(&self) -> Option<String> {
-        self.path
-            .file_name()
-            .and_then(|name| name.to_str())
-            .map(|s| s.to_string())
+        match &self.relative_path {
+            Some(relative) => Some(relative.iter().map(wire_name).collect::<Vec<_>>().join("/")),
+            None => self.path.file_name().map(wire_name),
+        }
+    }
+}
+
+// Encodes a file name for the wire: valid UTF-8 is kept as is, except '%' which becomes %25,
+// and each byte of an invalid sequence becomes %XX. Unlike to_string_lossy this is stable and
+// keeps distinct names distinct, so a file can still be requested by the name it is listed under
+fn wire_name(name: &OsStr) -> String {
+    let mut out = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        out.push_str(&chunk.valid().replace('%', "%25"));
+        for byte in chunk.invalid() {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::TestDir;
+    use crate::source::ShareSource;
+
+    // A file whose name is not valid UTF-8 is listed and requested under its escaped
+    // wire name, and that name finds the real path again
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names_map_back_to_the_real_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = TestDir::new("non-utf8");
+        let name = OsStr::from_bytes(b"caf\xe9 \xff\xfe.txt");
+        let path = dir.join(name);
+        fs::write(&path, b"contents").unwrap();
+
+        let file = Shareable::new(path.clone()).unwrap();
+        assert_eq!(file.file_name().as_deref(), Some("caf%E9 %FF%FE.txt"));
+        assert_eq!(file.share_name(), file.file_name());
+
+        // Served files are matched by the name a request carries
+        let other = dir.join("cafe.txt");
+        fs::write(&other, b"other").unwrap();
+        let files = [Shareable::new(other).unwrap(), file];
+        let found = files.iter().find(|f| f.file_name().as_deref() == Some("caf%E9 %FF%FE.txt")).unwrap();
+        assert_eq!(found.path, path);
+        assert_eq!(found.path.file_name().unwrap().as_bytes(), b"caf\xe9 \xff\xfe.txt");
+        assert_eq!(fs::read(&found.path).unwrap(), b"contents");
+
+        // Inside a shared folder every part of the relative path is escaped
+        let mut nested = found.clone();
+        nested.relative_path = Some(Path::new(OsStr::from_bytes(b"d\xe9mo")).join(name));
+        assert_eq!(nested.file_name().as_deref(), Some("d%E9mo/caf%E9 %FF%FE.txt"));
+    }
+
+    // A literal '%' is escaped too, so a name spelling out an escape never collides
+    // with the name it would stand for
+    #[cfg(unix)]
+    #[test]
+    fn percent_signs_are_escaped() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(wire_name(OsStr::new("a%FF")), "a%25FF");
+        assert_eq!(wire_name(OsStr::from_bytes(b"a\xff")), "a%FF");
+        assert_eq!(wire_name(OsStr::new("100%.txt")), "100%25.txt");
+    }
+
+    // Sorting by size reads the size recorded on the file, which only changes when refreshed
+    #[test]
+    fn size_sort_uses_the_size_last_seen() {
+        let dir = TestDir::new("size-sort");
+        fs::write(dir.join("small.txt"), b"a").unwrap();
+        fs::write(dir.join("large.txt"), b"abcdefghij").unwrap();
+        let mut files = [
+            Shareable::new(dir.join("small.txt")).unwrap(),
+            Shareable::new(dir.join("large.txt")).unwrap(),
+        ];
+        assert_eq!(files[1].disk_size, Some(10));
+
+        fs::remove_file(dir.join("large.txt")).unwrap();
+        let mut indices = [0, 1];
+        ShareSort::Size.sort(&files, &mut indices);
+        assert_eq!(indices, [1, 0]);
+
+        // Missing files sort last once the change is seen
+        files[1].refresh_disk_state();
+        assert_eq!(files[1].disk_size, None);
+        ShareSort::Size.sort(&files, &mut indices);
+        assert_eq!(indices, [0, 1]);
     }
 }
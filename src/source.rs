@@ -0,0 +1,90 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader};
+
+// Standard library
+use std::future::Future;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+
+// Local
+use crate::shareable::Shareable;
+
+
+/// Byte stream opened on a share source.
+pub type ShareStream = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Content that serving_manager can serve.
+///
+/// Shares are only read through this trait, so content that is not a file on
+/// disk (an in-memory buffer, a pipe, generated output) can be served by
+/// implementing it. Files on disk ([`Shareable`]) are the only source today.
+pub trait ShareSource: Send + Sync {
+    /// Name the content is shared and requested under.
+    fn share_name(&self) -> Option<String>;
+
+    /// Length of the content in bytes, or None when it is not known up front (e.g. a live pipe).
+    fn content_len(&self) -> impl Future<Output = io::Result<Option<u64>>> + Send;
+
+    /// Opens the content for reading, positioned `start` bytes in.
+    fn open_at(&self, start: u64) -> impl Future<Output = io::Result<ShareStream>> + Send;
+
+    /// Opens bytes [offset, offset + len) behind a buffered reader, so the content
+    /// can be sent a chunk at a time without holding all of it in memory.
+    fn chunk_reader(&self, offset: u64, len: u64) -> impl Future<Output = io::Result<ShareStream>> + Send {
+        async move {
+            let stream = self.open_at(offset).await?;
+            Ok(Box::pin(BufReader::new(stream.take(len))) as ShareStream)
+        }
+    }
+}
+
+impl ShareSource for Shareable {
+    fn share_name(&self) -> Option<String> {
+        self.file_name()
+    }
+
+    async fn content_len(&self) -> io::Result<Option<u64>> {
+        Ok(Some(tokio::fs::metadata(&self.path).await?.len()))
+    }
+
+    async fn open_at(&self, start: u64) -> io::Result<ShareStream> {
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        Ok(Box::pin(file))
+    }
+}
+
+/// Reads bytes [start, end) of a source, or from `start` to the end of the content when `end` is None.
+/// Used for transfers sent as a single message, so more than `limit` bytes is an error rather than a truncated read.
+pub async fn read_source<S: ShareSource>(source: &S, start: u64, end: Option<u64>, limit: u64) -> io::Result<Vec<u8>> {
+    let wanted = end.map_or(u64::MAX, |end| end.saturating_sub(start)).min(limit.saturating_add(1));
+    let mut buffer = Vec::new();
+    source.open_at(start).await?.take(wanted).read_to_end(&mut buffer).await?;
+    if buffer.len() as u64 > limit {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("share is larger than {} bytes", limit)));
+    }
+    Ok(buffer)
+}
@@ -0,0 +1,101 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// External crates
+use serde::{de::DeserializeOwned, Serialize};
+use log::warn;
+
+// Standard library
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Local
+use crate::vault;
+
+
+/// Directory holding persisted state, relative to the working directory
+/// (next to `serving_datadir`).
+pub const CONFIG_DIR: &str = "config";
+
+/// Set by an emergency wipe: nothing is written to CONFIG_DIR until the user turns saving back on.
+static WIPED: AtomicBool = AtomicBool::new(false);
+
+/// Stops or resumes every write to CONFIG_DIR.
+pub fn set_wiped(wiped: bool) {
+    WIPED.store(wiped, Ordering::SeqCst);
+}
+
+/// Whether the config was wiped and saving is still off.
+pub fn is_wiped() -> bool {
+    WIPED.load(Ordering::SeqCst)
+}
+
+/// Returns the path of a persisted JSON file inside CONFIG_DIR.
+pub fn config_path(name: &str) -> PathBuf {
+    PathBuf::from(CONFIG_DIR).join(name)
+}
+
+/// Loads a JSON file from CONFIG_DIR.
+/// Returns None if the file does not exist or cannot be parsed, and always while
+/// the vault is locked.
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = config_path(name);
+    let data = vault::open(fs::read(&path).ok()?)?;
+    match serde_json::from_slice(&data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Ignoring unreadable {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Saves a value as JSON in CONFIG_DIR, sealed when encryption is on.
+/// Does nothing while the vault is locked or after a wipe, so state stays in memory only.
+pub fn save<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    if is_wiped() {
+        return Ok(());
+    }
+    let data = serde_json::to_vec_pretty(value).map_err(io::Error::other)?;
+    let Some(data) = vault::seal(data) else { return Ok(()); };
+    write_atomic(&config_path(name), &data)
+}
+
+/// Deletes a JSON file from CONFIG_DIR; a missing file is not an error.
+pub fn remove(name: &str) -> io::Result<()> {
+    match fs::remove_file(config_path(name)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a state file through a temporary file so a crash never leaves it truncated.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)
+}
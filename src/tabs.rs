@@ -31,16 +31,17 @@ use tokio::sync::Mutex;
 
 
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveTime, Timelike};
 use uuid::Uuid;
 use nymlib::nymsocket::SockAddr;
 use nymlib::nymsocket::SocketMode;
 
 
 // Standard library
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::time::Instant;
 use std::time::Duration;
 use std::sync::Arc;
@@ -51,14 +52,28 @@ use std::sync::Arc;
 
 
 // local 
-use crate::app::FileSharingApp;
-use crate::shareable::Shareable;
-use crate::request::{DownLoadRequest, ExploreRequest};
+use crate::app::{start_hash, AppUpdate, FileSharingApp, LinkPreview, VerifyStatus, post_tab_message, post_update};
+use crate::shareable::{Shareable, ShareSort};
+use crate::request::{ClearTarget, CollisionPolicy, DownLoadRequest, DuplicatePolicy, ExploreMode, ExploreRequest, Priority, RequestSort, RequestStatus};
 use crate::theme::Tab;
-use crate::helper::time_ago;
+use crate::helper::{file_type_icon, relative_download_path, time_ago, time_left, sha256_file, format_size, system_time_ago, download_matches_filter, time_in_window, absolute_dir, check_writable_dir, open_with_default_app, reveal_in_file_manager, is_service_address, is_sha256_hex};
+use crate::peers::{remember_service, sorted_peers, PeerSort, SavedService};
+use crate::throughput;
+use crate::audit::{self, AuditKind, AuditRange};
 use crate::app::VERSION;
+use crate::serving::ServeEvent;
+use crate::diagnostics::build_report;
 use crate::apply_button_style;
-use crate::network::reinitialize_download_socket;
+use crate::backoff::Backoff;
+use crate::netconfig::{self, NetworkConfig, MAX_POLL_MS, MIN_POLL_MS};
+use crate::bus::{post_command, ManagerCommand};
+use crate::collection::{self, Collection};
+use crate::filetype::FileType;
+use crate::manifest::{Manifest, MANIFEST_EXTENSION};
+use crate::wipe::{self, WIPE_CONFIRMATION};
+use crate::vault;
+use crate::archive::BundleMode;
+use crate::network::{add_serving_identity, file_status, ping, push_advertise, reinitialize_download_socket, reinitialize_serving_socket, remove_serving_identity, rotate_serving_identity, validate_identity_name, DEFAULT_IDENTITY, FILE_CHUNK_SIZE, MAX_ADVERTISE_RESPONSES, MAX_CHUNK_WINDOW, MAX_REQUESTS_PER_MINUTE, MAX_UPLOADS_LIMIT, MIN_FILE_CHUNK_SIZE};
 
 
 
@@ -74,18 +89,24 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         let mut added_count = 0;
         for file in dropped_files {
             if let Some(path) = file.path {
-                if !app.shareable_files.iter().any(|f| f.path == path) {
-                    match Shareable::new(path.clone()) {
-                        Ok(s) => {
-                            app.shareable_files.push(s);
-                            added_count += 1;
-                        }
+                // Dropped folders are shared file by file
+                if path.is_dir() {
+                    match app.add_share_dir(&path) {
+                        Ok(count) => added_count += count,
                         Err(e) => {
                             app.set_message(e);
                             return;
                         }
                     }
-                    app.download_url.clear();
+                    continue;
+                }
+                match app.add_share(path) {
+                    Ok(true) => added_count += 1,
+                    Ok(false) => {}
+                    Err(e) => {
+                        app.set_message(e.to_string());
+                        return;
+                    }
                 }
             }
         }
@@ -113,7 +134,7 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         painter.text(
             rect.center(),
             Align2::CENTER_CENTER,
-            "📂 Drop files here to add",
+            "📂 Drop files or folders here to add",
             eframe::egui::TextStyle::Heading.resolve(ui.style()),
             Color32::BLACK,
         );
@@ -127,18 +148,13 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
             let mut added_count = 0;
             if let Some(paths) = rfd::FileDialog::new().pick_files() {
                 for path in paths {
-                    if !app.shareable_files.iter().any(|f| f.path == path) {
-                        match Shareable::new(path) {
-                            Ok(s) => {
-                                app.shareable_files.push(s);
-                                added_count += 1;
-                            }
-                            Err(e) => {
-                                app.set_message(e);
-                                return;
-                            }
+                    match app.add_share(path) {
+                        Ok(true) => added_count += 1,
+                        Ok(false) => {}
+                        Err(e) => {
+                            app.set_message(e.to_string());
+                            return;
                         }
-                        app.download_url.clear();
                     }
                 }
             }
@@ -150,9 +166,19 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
             }
         }
 
+        // Add Folder button; every file below it is shared under its relative path
+        if ui.button("📁 Add Folder").on_hover_text("Share every file in a folder, keeping its structure").clicked()
+            && let Some(dir) = rfd::FileDialog::new().pick_folder() {
+            match app.add_share_dir(&dir) {
+                Ok(0) => app.set_message("No new files added"),
+                Ok(count) => app.set_message(format!("Added {} file(s) from {:?}", count, dir)),
+                Err(e) => app.set_message(e),
+            }
+        }
+
         // Search bar
         ui.label("🔍");
-        Frame::default()
+        let search = Frame::default()
             .rounding(Rounding::same(4))
             .inner_margin(4)
             .show(ui, |ui| {
@@ -161,7 +187,11 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         .hint_text("Search in selected files...")
                         .desired_width(250.0),
                 )
-            });
+            })
+            .inner;
+        if std::mem::take(&mut app.focus_search) {
+            search.request_focus();
+        }
 
         if ui.button("❌").on_hover_text("Clear search").clicked() {
             app.search_query.clear();
@@ -171,11 +201,28 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
     ui.separator();
     ui.label("📑 Selected Files:");
 
+    // Explorers get an empty list when nothing is active
+    if app.advertise_mode && !app.shareable_files.iter().any(|f| f.is_active()) && !app.collections.iter().any(|c| c.active) {
+        ui.label(RichText::new("⚠ Advertise is on but no active files — explorers will see nothing")
+            .color(Color32::from_rgb(200, 120, 0)));
+    }
+
     // Hide/Activate controls
     ui.horizontal(|ui| {
         apply_button_style!(ui, Color32::LIGHT_BLUE);
         ui.checkbox(&mut app.hide_inactive, "Hide Inactive Files")
             .on_hover_text("Hide files that are not currently active for sharing");
+        type_filter_combo(ui, "share_type_filter", &mut app.share_type_filter);
+        ui.checkbox(&mut app.share_recent_only, "Recently Added")
+            .on_hover_text("Only list files added in the last 24 hours");
+        ui.label("Sort by:");
+        egui::ComboBox::from_id_salt("share_sort")
+            .selected_text(app.share_sort.label())
+            .show_ui(ui, |ui| {
+                for option in ShareSort::ALL {
+                    ui.selectable_value(&mut app.share_sort, option, option.label());
+                }
+            });
 
         let activate_count = app.shareable_files.iter().filter(|f| !f.is_active()).count();
         let deactivate_count = app.shareable_files.iter().filter(|f| f.is_active()).count();
@@ -187,6 +234,7 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         file.activate();
                     }
                 }
+                app.save_shares();
                 app.set_message(format!("{} file(s) activated", activate_count));
             }
         });
@@ -198,10 +246,31 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         file.deactivate();
                     }
                 }
+                app.save_shares();
                 app.set_message(format!("{} file(s) deactivated", deactivate_count));
             }
         });
 
+        ui.add_enabled_ui(!app.shareable_files.is_empty(), |ui| {
+            if ui.button("📁 Remove by Folder").on_hover_text("Remove every file under a chosen folder").clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                app.pending_folder_removal = Some(dir);
+            }
+        });
+
+        if !app.removed_files_undo.is_empty()
+            && ui.button("↶ Undo Remove").on_hover_text("Restore the files removed by the last folder removal").clicked() {
+            let mut restored = 0;
+            for file in std::mem::take(&mut app.removed_files_undo) {
+                if !app.shareable_files.iter().any(|f| f.path == file.path) {
+                    app.shareable_files.push(file);
+                    restored += 1;
+                }
+            }
+            app.save_shares();
+            app.set_message(format!("Restored {} file(s)", restored));
+        }
+
         if !app.share_message.is_empty() && app.show_share_message() {
             ui.separator();
             ui.label(egui::RichText::new(&app.share_message).color(Color32::BLACK));
@@ -211,11 +280,14 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
     ui.add_space(5.0);
 
     // File list
-    let matching_indices: Vec<usize> = if app.search_query.trim().is_empty() {
+    let type_filter = app.share_type_filter;
+    let mut matching_indices: Vec<usize> = if app.search_query.trim().is_empty() {
         app.shareable_files
             .iter()
             .enumerate()
             .filter(|(_, f)| !app.hide_inactive || f.is_active())
+            .filter(|(_, f)| type_filter.is_none_or(|t| f.file_type == t))
+            .filter(|(_, f)| !app.share_recent_only || f.recently_added())
             .map(|(i, _)| i)
             .collect()
     } else {
@@ -227,15 +299,30 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                 f.file_name().unwrap_or_default().to_lowercase().contains(&q)
                     && (!app.hide_inactive || f.is_active())
             })
+            .filter(|(_, f)| type_filter.is_none_or(|t| f.file_type == t))
+            .filter(|(_, f)| !app.share_recent_only || f.recently_added())
             .map(|(i, _)| i)
             .collect()
     };
+    app.share_sort.sort(&app.shareable_files, &mut matching_indices);
+
+    render_collections(app, ui, &matching_indices);
 
-    if matching_indices.is_empty() {
+    if matching_indices.is_empty() && app.is_first_run() {
+        first_run_hint(ui, "📂 Share your first file", &[
+            "Drag files onto this window, or click \"✚ Add Files\" above.",
+            "Peers can download the files you activate using your serving address.",
+        ]);
+    } else if matching_indices.is_empty() {
         ui.label("No matching files found.");
     } else {
         let mut remove_index: Option<usize> = None;
         let mut new_message: Option<String> = None;
+        let mut shares_changed = false;
+        // Taken up front: the file rows hold a mutable borrow of the share list
+        let identities: Vec<(String, String)> = app.identity_names().into_iter()
+            .map(|name| { let addr = app.serving_addr_of(&name).to_string(); (name, addr) })
+            .collect();
 
         ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
             for &i in &matching_indices {
@@ -243,12 +330,135 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                 ui.group(|ui| {
                     ui.horizontal(|ui| {
                         ui.vertical(|ui| {
-                            ui.label(format!("Name: {}", file.file_name().unwrap_or("Unknown".into()))).on_hover_text("File name");
+                            let selected = app.selected_share.as_ref() == Some(&file.path);
+                            let name = format!("{} Name: {}", file_type_icon(&file.path), file.file_name().unwrap_or("Unknown".into()));
+                            if ui.selectable_label(selected, name).on_hover_text("Click to select; Delete removes the selected file").clicked() {
+                                app.selected_share = if selected { None } else { Some(file.path.clone()) };
+                            }
                             ui.label(format!("Path: {}", file.path.display())).on_hover_text("Full path");
+                            // Files deleted since they were added are listed, not treated as errors
+                            let size = file.disk_size.map_or_else(|| "(missing)".to_string(), format_size);
+                            ui.label(format!("Size: {}", size)).on_hover_text("Size on disk when last checked");
+                            ui.label(format!("Added: {}", system_time_ago(file.added_at)))
+                                .on_hover_text(DateTime::<Local>::from(file.added_at).format("%Y-%m-%d %H:%M:%S").to_string());
                             ui.label(format!("Total Advertise: {}", file.advertise)).on_hover_text("Advertise count");
                             ui.label(format!("Total Downloads: {}", file.downloads)).on_hover_text("Downloads count");
                             ui.label(format!("Status: {}", if file.is_active() { "✅ Active" } else { "❌ Inactive" }))
                                 .on_hover_text("Active status");
+
+                            // Content hash
+                            ui.horizontal(|ui| {
+                                match file.known_hash() {
+                                    Some(hash) => {
+                                        ui.label(format!("SHA-256: {}…", &hash[..16])).on_hover_text(hash);
+                                        if ui.small_button("📋").on_hover_text("Copy hash").clicked() {
+                                            ui.ctx().copy_text(hash.to_string());
+                                            new_message = Some("Hash copied".to_string());
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("SHA-256: not computed").on_hover_text("File is new or changed since it was hashed");
+                                        if ui.small_button("Compute").on_hover_text("Hash this file in the background").clicked() {
+                                            start_hash(file.path.clone());
+                                        }
+                                    }
+                                }
+                            });
+
+                            // Download limit
+                            ui.horizontal(|ui| {
+                                let mut limited = file.max_downloads.is_some();
+                                if ui.checkbox(&mut limited, "Limit downloads")
+                                    .on_hover_text("Stop sharing this file after a number of downloads")
+                                    .changed() {
+                                    file.max_downloads = if limited { Some(file.downloads.saturating_add(1)) } else { None };
+                                }
+                                if let Some(max) = file.max_downloads.as_mut() {
+                                    ui.add(egui::DragValue::new(max).range(1..=u32::MAX))
+                                        .on_hover_text("Maximum number of downloads");
+                                }
+                                match file.remaining_downloads() {
+                                    Some(0) => { ui.label(RichText::new("Download limit reached").color(Color32::RED)); }
+                                    Some(remaining) => { ui.label(format!("Remaining: {}", remaining)); }
+                                    None => {}
+                                }
+                            });
+
+                            // Expiry
+                            ui.horizontal(|ui| {
+                                match file.expires_at {
+                                    Some(t) => match time_left(t) {
+                                        Some(left) => { ui.label(format!("Expires in: {}", left)).on_hover_text("Time until this share expires"); }
+                                        None => { ui.label(RichText::new("Expired").color(Color32::RED)); }
+                                    },
+                                    None => { ui.label("Expires: never"); }
+                                }
+
+                                let base = file.expires_at.filter(|_| !file.is_expired()).unwrap_or_else(SystemTime::now);
+                                if ui.small_button("+1h").on_hover_text("Extend expiry by one hour").clicked() {
+                                    file.expires_at = Some(base + Duration::from_secs(3600));
+                                }
+                                if ui.small_button("+1d").on_hover_text("Extend expiry by one day").clicked() {
+                                    file.expires_at = Some(base + Duration::from_secs(86400));
+                                }
+                                if file.expires_at.is_some() && ui.small_button("Clear").on_hover_text("Remove the expiry").clicked() {
+                                    file.expires_at = None;
+                                }
+                            });
+
+                            // Allowed peers
+                            let mut restricted = file.allowed_peers.is_some();
+                            if ui.checkbox(&mut restricted, "Only allow listed peers")
+                                .on_hover_text("Refuse downloads from addresses not on the list.\n\
+                                                Requesters are only identified in Individual mode; anonymous requests are refused.")
+                                .changed() {
+                                file.allowed_peers = restricted.then(Vec::new);
+                                shares_changed = true;
+                            }
+                            if let Some(peers) = file.allowed_peers.as_mut() {
+                                let mut remove_peer = None;
+                                for (n, peer) in peers.iter().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        let addr = peer.to_string();
+                                        let short = if addr.len() > 24 { format!("{}…", &addr[..24]) } else { addr.clone() };
+                                        ui.label(format!("• {}", short)).on_hover_text(&addr);
+                                        if ui.small_button("✖").on_hover_text("Remove from the allowlist").clicked() {
+                                            remove_peer = Some(n);
+                                        }
+                                    });
+                                }
+                                if let Some(n) = remove_peer {
+                                    peers.remove(n);
+                                    shares_changed = true;
+                                }
+                                if peers.is_empty() {
+                                    ui.label(RichText::new("No peers allowed; every request is refused").color(Color32::RED));
+                                }
+                                ui.horizontal(|ui| {
+                                    let input = app.allowlist_inputs.entry(file.path.clone()).or_default();
+                                    ui.add(egui::TextEdit::singleline(input).hint_text("Peer address").desired_width(250.0));
+                                    if ui.small_button("Add").clicked() {
+                                        let peer = SockAddr::from(input.trim());
+                                        if peer.is_null() {
+                                            new_message = Some("Invalid peer address".to_string());
+                                        } else if peers.contains(&peer) {
+                                            new_message = Some("Peer is already allowed".to_string());
+                                        } else {
+                                            peers.push(peer);
+                                            input.clear();
+                                            shares_changed = true;
+                                        }
+                                    }
+                                });
+                            }
+
+                            // Serving identity, once there is more than one to choose from
+                            if identities.len() > 1 || !file.served_by(DEFAULT_IDENTITY) {
+                                ui.horizontal(|ui| {
+                                    ui.label("Served by:");
+                                    identity_combo(ui, ("share_identity", i), &identities, &mut file.identity);
+                                });
+                            }
                         });
 
                         ui.with_layout(
@@ -260,8 +470,13 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                                     new_message = Some("File removed".to_string());
                                 }
 
-                                if ui.button("📋 Copy Link").clicked() {
-                                    let link = format!("{}::{}", app.serving_addr, file.file_name().unwrap_or_default());
+                                let service = identities.iter()
+                                    .find(|(name, _)| file.served_by(name))
+                                    .map_or("", |(_, addr)| addr.as_str());
+                                if ui.add_enabled(!service.is_empty(), egui::Button::new("📋 Copy Link"))
+                                    .on_disabled_hover_text(format!("Identity '{}' is not running", file.identity_name()))
+                                    .clicked() {
+                                    let link = share_link(service, file, app.link_includes_hash);
                                     ui.ctx().output_mut(|out| out.copied_text = link.clone());
                                     new_message = Some("Link copied".to_string());
                                 }
@@ -269,10 +484,12 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                                 if file.is_active() {
                                     if ui.button("⏸ Deactivate").clicked() {
                                         file.deactivate();
+                                        shares_changed = true;
                                         new_message = Some(format!("Deactivated {}", file.file_name().unwrap_or_default()));
                                     }
                                 } else if ui.button("▶ Activate").clicked() {
                                     file.activate();
+                                    shares_changed = true;
                                     new_message = Some(format!("Activated {}", file.file_name().unwrap_or_default()));
                                 }
                             },
@@ -284,7 +501,9 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         });
 
         if let Some(i) = remove_index {
-            app.shareable_files.remove(i);
+            remove_share(app, i);
+        } else if shares_changed {
+            app.save_shares();
         }
 
         if let Some(msg) = new_message {
@@ -313,6 +532,12 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                     ui.ctx().output_mut(|out| out.copied_text = app.serving_addr.clone());
                     app.set_message("Serving address copied to clipboard");
                 }
+
+                if ui.button("📤 Export Manifest")
+                    .on_hover_text("Save the active files as a .nymshare file others can open to download them")
+                    .clicked() {
+                    export_manifest(app);
+                }
             }
 
             // Right-aligned settings button
@@ -331,6 +556,125 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         });
     });
 
+    // Confirmation for files in sensitive locations
+    if !app.pending_sensitive_files.is_empty() {
+        let mut decision: Option<bool> = None;
+        egui::Window::new("⚠ Sensitive location")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.label("These files are in locations that usually hold keys or credentials:");
+                for path in &app.pending_sensitive_files {
+                    ui.label(format!("  - {}", path.display()));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Share anyway").clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+
+        match decision {
+            Some(true) => {
+                let mut added_count = 0;
+                for path in std::mem::take(&mut app.pending_sensitive_files) {
+                    match Shareable::new_allow_sensitive(path) {
+                        Ok(s) => {
+                            start_hash(s.path.clone());
+                            app.shareable_files.push(s);
+                            added_count += 1;
+                        }
+                        Err(e) => app.set_message(e.to_string()),
+                    }
+                }
+                if added_count > 0 {
+                    app.save_shares();
+                    app.set_message(format!("Added {} file(s) from sensitive locations", added_count));
+                }
+            }
+            Some(false) => {
+                app.pending_sensitive_files.clear();
+                app.set_message("Skipped files in sensitive locations");
+            }
+            None => {}
+        }
+    }
+
+    // Confirmation for rotating the serving identity
+    if app.confirm_new_identity {
+        let mut decision: Option<bool> = None;
+        egui::Window::new("🆕 New Identity")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.label("This creates a new serving address.");
+                ui.label(RichText::new("Every link you have shared so far will stop working.").color(Color32::RED));
+                ui.horizontal(|ui| {
+                    if ui.button("Create new identity").clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+
+        match decision {
+            Some(true) => {
+                app.confirm_new_identity = false;
+                app.serving_addr.clear();
+                app.set_message("Creating a new identity...");
+                tokio::spawn(rotate_serving_identity());
+            }
+            Some(false) => app.confirm_new_identity = false,
+            None => {}
+        }
+    }
+
+    // Confirmation for removing every file under a folder
+    if let Some(dir) = app.pending_folder_removal.clone() {
+        let count = app.shareable_files.iter().filter(|f| f.path.starts_with(&dir)).count();
+        let mut decision: Option<bool> = None;
+        egui::Window::new("📁 Remove by folder")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.label(format!("Remove {} file(s) under {}?", count, dir.display()));
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(count > 0, |ui| {
+                        if ui.button("Remove").clicked() {
+                            decision = Some(true);
+                        }
+                    });
+                    if ui.button("Cancel").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+
+        match decision {
+            Some(true) => {
+                let (removed, kept): (Vec<Shareable>, Vec<Shareable>) = std::mem::take(&mut app.shareable_files)
+                    .into_iter()
+                    .partition(|f| f.path.starts_with(&dir));
+                app.shareable_files = kept;
+                app.removed_files_undo = removed;
+                remove_from_collections(app, |path| path.starts_with(&dir));
+                app.save_shares();
+                app.pending_folder_removal = None;
+                app.set_message(format!("Removed {} file(s) under {}", count, dir.display()));
+            }
+            Some(false) => app.pending_folder_removal = None,
+            None => {}
+        }
+    }
+
     // Sidebar for Settings
     if app.show_share_settings_sidebar {
         egui::SidePanel::right("share_settings_sidebar")
@@ -351,6 +695,203 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                     ));
                 }
 
+                // Advertise schedule, in local time
+                let mut scheduled = app.advertise_window.is_some();
+                if ui.add_enabled(app.advertise_mode, egui::Checkbox::new(&mut scheduled, "Only advertise at set hours"))
+                    .on_hover_text("Answer requests for our file list only during these hours (local time)")
+                    .changed() {
+                    app.advertise_window = if scheduled {
+                        NaiveTime::from_hms_opt(9, 0, 0).zip(NaiveTime::from_hms_opt(17, 0, 0))
+                    } else {
+                        None
+                    };
+                }
+                if let Some((start, end)) = &mut app.advertise_window {
+                    ui.horizontal(|ui| {
+                        ui.label("From");
+                        time_field(ui, start);
+                        ui.label("to");
+                        time_field(ui, end);
+                        let now = if time_in_window(Local::now().time(), (*start, *end)) { "advertising now" } else { "outside hours now" };
+                        ui.weak(format!("({})", now));
+                    });
+                    ui.checkbox(&mut app.advertise_window_silent, "Ignore requests outside these hours")
+                        .on_hover_text("Otherwise explorers get an empty list saying files are only advertised at certain hours");
+                }
+
+                // Push the current catalog to peers that explored us
+                let explorers = app.push_targets();
+                let explorer_count = explorers.len();
+                let notify_hover = if !app.advertise_mode {
+                    "Enable advertise mode to notify explorers".to_string()
+                } else if explorer_count == 0 && app.push_saved_peers_only && !app.recent_explorers.is_empty() {
+                    "None of the recent explorers is in the Peers list or the address book".to_string()
+                } else if explorer_count == 0 {
+                    "No Individual-mode explorers in the last day".to_string()
+                } else {
+                    format!("Send the current file list to {} recent explorer(s)", explorer_count)
+                };
+                if ui.add_enabled(app.advertise_mode && explorer_count > 0, egui::Button::new("📣 Notify explorers of catalog changes"))
+                    .on_hover_text(&notify_hover)
+                    .on_disabled_hover_text(&notify_hover)
+                    .clicked() {
+                    tokio::spawn(push_advertise(explorers, app.shareable_files.clone(), app.collections.clone()));
+                    app.set_message(format!("Notifying {} explorer(s)...", explorer_count));
+                }
+                ui.checkbox(&mut app.push_saved_peers_only, "Only notify saved peers")
+                    .on_hover_text("Send catalog updates only to explorers in the Peers list or the address book");
+
+                ui.checkbox(&mut app.link_includes_hash, "Include hash in links")
+                    .on_hover_text("Copy links as service::filename#sha256 so downloaders can verify the file");
+
+                ui.checkbox(&mut app.watch_shared_files, "Watch shared files for changes")
+                    .on_hover_text("Rehash files when they change on disk and stop sharing files that are deleted");
+                ui.add_enabled(app.watch_shared_files, egui::Checkbox::new(&mut app.readvertise_on_change, "Notify explorers when files change"))
+                    .on_hover_text("Push the updated file list to recent explorers after a shared file changes (advertise mode only)");
+
+                ui.separator();
+
+                // Advertise privacy
+                ui.label("Advertise privacy:");
+                ui.horizontal(|ui| {
+                    ui.label("Random reply delay up to (ms):");
+                    ui.add(egui::DragValue::new(&mut app.advertise_max_delay_ms).range(0..=10_000).speed(50))
+                        .on_hover_text(
+                            "Wait a random time before answering each request for our file list (0 = answer at once).\n\
+                             Instant answers let an observer time our replies and link them to this machine's \
+                             load and network; a random delay blurs that signal at the cost of slower exploring.",
+                        );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("File lists per peer per 10 min:");
+                    ui.add(egui::DragValue::new(&mut app.advertise_peer_cap).range(1..=MAX_ADVERTISE_RESPONSES * 4))
+                        .on_hover_text(
+                            "How often one peer may fetch our file list.\n\
+                             Every answer tells the asker what we share right now; repeated polling reveals \
+                             when files come and go and when we are online. A lower cap gives less away.",
+                        );
+                });
+                let rate = app.advertise_rate;
+                ui.label(format!(
+                    "Last 10 min: {} list(s) sent to {} peer(s), {} refused",
+                    rate.responses, rate.peers, rate.refused
+                ))
+                .on_hover_text("Advertise traffic in the current window, including follow-up pages and collection lists");
+
+                ui.separator();
+
+                // Serving concurrency
+                ui.checkbox(&mut app.compress_uploads, "Compress files we send")
+                    .on_hover_text("Deflate files for requesters that accept it; hashes still cover the original bytes.\n\
+                                    Images, audio, video and archives are already compressed and always go out as is.");
+                ui.horizontal(|ui| {
+                    ui.label("Files served at once:");
+                    ui.add(egui::DragValue::new(&mut app.max_concurrent_uploads).range(1..=MAX_UPLOADS_LIMIT))
+                        .on_hover_text("Transfers to peers that run side by side; further requests wait for a free slot.\nThe upload limit is shared by all of them.");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("File requests per peer per minute:");
+                    ui.add(egui::DragValue::new(&mut app.max_requests_per_minute).range(0..=MAX_REQUESTS_PER_MINUTE * 20))
+                        .on_hover_text("Requests for files, file status, collections and pings one peer may send per minute (0 = unlimited).\n\
+                                        Requests over the limit are dropped unanswered, so a peer spamming requests \
+                                        cannot keep us reading and sending files.");
+                });
+                if app.requests_dropped > 0 {
+                    ui.label(format!("Last minute: {} request(s) dropped", app.requests_dropped))
+                        .on_hover_text("File requests dropped by the per-peer limit; see the audit log for the peers");
+                }
+
+                // Upload cap, shared by every transfer; takes effect for transfers started after a change
+                ui.horizontal(|ui| {
+                    ui.label("Upload limit (KB/s):");
+                    let mut kib_per_sec = app.upload_limit.map_or(0, |rate| rate.div_ceil(1024));
+                    let slider = egui::Slider::new(&mut kib_per_sec, 0..=MAX_UPLOAD_LIMIT_KIB)
+                        .logarithmic(true)
+                        .custom_formatter(|v, _| if v == 0.0 { "unlimited".to_string() } else { format!("{:.0}", v) });
+                    if ui.add(slider)
+                        .on_hover_text("Total upload rate across all transfers to peers (0 = unlimited)")
+                        .changed() {
+                        app.upload_limit = (kib_per_sec > 0).then_some(kib_per_sec * 1024);
+                    }
+                });
+
+                // Bounds of the chunk size transfers adapt within; takes effect for transfers started after a change
+                ui.horizontal(|ui| {
+                    let (min, max) = (MIN_FILE_CHUNK_SIZE / 1024, FILE_CHUNK_SIZE / 1024);
+                    let (mut low, mut high) = (app.chunk_size_bounds.0 / 1024, app.chunk_size_bounds.1 / 1024);
+                    ui.label("Chunk size (KB):");
+                    let low_changed = ui.add(egui::DragValue::new(&mut low).range(min..=max)).changed();
+                    ui.label("to");
+                    let high_changed = ui.add(egui::DragValue::new(&mut high).range(min..=max)).changed();
+                    if low_changed || high_changed {
+                        // Keep the bounds in order, moving the one not being dragged
+                        if low > high {
+                            if low_changed { high = low } else { low = high }
+                        }
+                        app.chunk_size_bounds = (low * 1024, high * 1024);
+                    }
+                })
+                .response
+                .on_hover_text("Chunks grow while requesters confirm them and shrink when they have to be sent again.\n\
+                                Only for requesters that confirm chunks; others get the largest chunks they take.");
+
+                ui.separator();
+
+                // Serving identity
+                if ui.button("🆕 New Identity")
+                    .on_hover_text("Create a fresh serving address; existing links stop working")
+                    .clicked() {
+                    app.confirm_new_identity = true;
+                }
+                if ui.add_enabled(!app.offline, egui::Button::new("🔄 Restart Serving"))
+                    .on_hover_text("Recreate the serving socket of every identity, keeping their addresses.\n\
+                                    Use it when an address stays empty after startup or peers get no answers.")
+                    .on_disabled_hover_text(OFFLINE_HINT)
+                    .clicked() {
+                    for name in app.identity_names() {
+                        tokio::spawn(reinitialize_serving_socket(name, app.backoff.clone()));
+                    }
+                    app.set_message("Restarting serving...".to_string());
+                }
+
+                // Further serving identities, each with its own address; files are bound to one of them
+                ui.label("Serving identities:");
+                for name in app.identity_names() {
+                    ui.horizontal(|ui| {
+                        let addr = app.serving_addr_of(&name).to_string();
+                        ui.label(RichText::new(&name).strong());
+                        if addr.is_empty() {
+                            ui.label(RichText::new("starting…").weak());
+                        } else {
+                            ui.label(RichText::new(format!("{}…", &addr[..addr.len().min(16)])).monospace()).on_hover_text(&addr);
+                            if ui.small_button("📋").on_hover_text("Copy this identity's address").clicked() {
+                                ui.ctx().copy_text(addr.clone());
+                                app.set_message(format!("Address of '{}' copied to clipboard", name));
+                            }
+                        }
+                        if name != DEFAULT_IDENTITY && ui.small_button("✖").on_hover_text("Stop serving from this identity; its files are not served until moved to another").clicked() {
+                            tokio::spawn(remove_serving_identity(name.clone()));
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut app.new_identity_name).hint_text("name").desired_width(140.0));
+                    if ui.add_enabled(!app.offline && !app.new_identity_name.trim().is_empty(), egui::Button::new("➕ Add identity"))
+                        .on_hover_text("Start another serving socket with its own address")
+                        .on_disabled_hover_text(if app.offline { OFFLINE_HINT } else { "Enter a name for the identity" })
+                        .clicked() {
+                        let name = app.new_identity_name.trim().to_string();
+                        match validate_identity_name(&name) {
+                            Ok(()) => {
+                                app.new_identity_name.clear();
+                                app.set_message(format!("Starting identity '{}'...", name));
+                                tokio::spawn(add_serving_identity(name, app.backoff.clone()));
+                            }
+                            Err(e) => app.set_message(e),
+                        }
+                    }
+                });
+
                 // Sidebar footer
                 ui.allocate_space(ui.available_size_before_wrap());
                 ui.with_layout(Layout::bottom_up(Align::LEFT), |ui| {
@@ -363,8 +904,163 @@ pub fn render_share_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
 }
 
 
+/// Lists the collections and groups the files currently listed (after search and filters) into new ones.
+fn render_collections(app: &mut FileSharingApp, ui: &mut egui::Ui, listed: &[usize]) {
+    egui::CollapsingHeader::new(format!("📦 Collections ({})", app.collections.len()))
+        .id_salt("share_collections")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                apply_button_style!(ui, Color32::LIGHT_BLUE);
+                ui.add(
+                    egui::TextEdit::singleline(&mut app.new_collection_name)
+                        .hint_text("Collection name")
+                        .desired_width(200.0),
+                );
+                if ui.add_enabled(!listed.is_empty(), egui::Button::new("✚ Create from listed files"))
+                    .on_hover_text("Group the files listed below into a collection shared as one entry")
+                    .clicked() {
+                    let name = app.new_collection_name.trim().to_string();
+                    match collection::validate_name(&name, &app.collections) {
+                        Ok(()) => {
+                            let created = Collection::new(name.clone(), listed.iter().map(|&i| &app.shareable_files[i]));
+                            let count = created.members.len();
+                            app.collections.push(created);
+                            collection::save(&app.collections);
+                            app.new_collection_name.clear();
+                            app.set_message(format!("Created collection '{}' with {} file(s)", name, count));
+                        }
+                        Err(e) => app.set_popup_message(e),
+                    }
+                }
+            });
+
+            let mut remove_index: Option<usize> = None;
+            let mut dropped_member: Option<(usize, PathBuf)> = None;
+            let mut new_message: Option<String> = None;
+            let mut changed = false;
+
+            for (i, item) in app.collections.iter_mut().enumerate() {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        apply_button_style!(ui, Color32::LIGHT_BLUE);
+                        ui.label(RichText::new(&item.name).strong());
+                        ui.label(format!("Status: {}", if item.active { "✅ Active" } else { "❌ Inactive" }));
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("✖ Remove").on_hover_text("Delete the collection; its files stay shared as they are").clicked() {
+                                remove_index = Some(i);
+                            }
+                            let (label, hover) = if item.active {
+                                ("⏸ Deactivate", "Stop advertising the collection and serving files only shared through it")
+                            } else {
+                                ("▶ Activate", "Advertise the collection and serve its files")
+                            };
+                            if ui.button(label).on_hover_text(hover).clicked() {
+                                item.active = !item.active;
+                                changed = true;
+                                new_message = Some(format!("{} collection '{}'", if item.active { "Activated" } else { "Deactivated" }, item.name));
+                            }
+                        });
+                    });
+
+                    egui::CollapsingHeader::new(format!("{} file(s)", item.members.len()))
+                        .id_salt(("share_collection", i))
+                        .show(ui, |ui| {
+                            for path in &item.members {
+                                ui.horizontal(|ui| {
+                                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                                    ui.label(name).on_hover_text(path.display().to_string());
+                                    if ui.small_button("✖").on_hover_text("Take this file out of the collection").clicked() {
+                                        dropped_member = Some((i, path.clone()));
+                                    }
+                                });
+                            }
+                        });
+                });
+            }
+
+            if let Some((i, path)) = dropped_member {
+                app.collections[i].members.retain(|p| p != &path);
+                changed = true;
+            }
+            if let Some(i) = remove_index {
+                let removed = app.collections.remove(i);
+                new_message = Some(format!("Removed collection '{}'", removed.name));
+                changed = true;
+            }
+            if changed {
+                collection::save(&app.collections);
+            }
+            if let Some(msg) = new_message {
+                app.set_message(msg);
+            }
+        });
+}
+
+/// Removes a file from the share list and from every collection, and saves the list.
+/// Returns the removed file.
+pub fn remove_share(app: &mut FileSharingApp, index: usize) -> Shareable {
+    let removed = app.shareable_files.remove(index);
+    app.allowlist_inputs.remove(&removed.path);
+    if app.selected_share.as_ref() == Some(&removed.path) {
+        app.selected_share = None;
+    }
+    remove_from_collections(app, |path| *path == removed.path);
+    app.save_shares();
+    removed
+}
+
+/// Takes files removed from the share list out of every collection.
+fn remove_from_collections(app: &mut FileSharingApp, removed: impl Fn(&PathBuf) -> bool) {
+    let before: usize = app.collections.iter().map(|c| c.members.len()).sum();
+    for item in &mut app.collections {
+        item.members.retain(|path| !removed(path));
+    }
+    if app.collections.iter().map(|c| c.members.len()).sum::<usize>() != before {
+        collection::save(&app.collections);
+    }
+}
+
+/// Saves the active files served by the default identity as a .nymshare manifest chosen by the user.
+fn export_manifest(app: &mut FileSharingApp) {
+    let manifest = Manifest::for_files(&app.serving_addr, app.shareable_files.iter().filter(|f| f.is_active() && f.served_by(DEFAULT_IDENTITY)));
+    if manifest.files.is_empty() {
+        app.set_message("No active files to export");
+        return;
+    }
+
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("NymShare manifest", &[MANIFEST_EXTENSION])
+        .set_file_name(format!("files.{}", MANIFEST_EXTENSION))
+        .save_file()
+    else {
+        return;
+    };
+
+    match manifest.save(&path) {
+        Ok(()) => {
+            let unhashed = manifest.files.iter().filter(|e| e.sha256.is_none()).count();
+            if unhashed > 0 {
+                app.set_message(format!("Manifest saved; {} file(s) not hashed yet, so they cannot be verified", unhashed));
+            } else {
+                app.set_message(format!("Manifest saved to {}", path.display()));
+            }
+        }
+        Err(e) => app.set_message(e),
+    }
+}
+
+
+/// Highest upload limit offered by the Share Settings slider, in KB/s.
+const MAX_UPLOAD_LIMIT_KIB: u64 = 1024 * 1024;
+
+/// Tooltip on request buttons that offline mode disables.
+const OFFLINE_HINT: &str = "Offline mode: go online from the top bar to send requests";
+
+
 // Renders the download tab UI for the file-sharing application.
 pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
+    render_socket_mode_confirmation(app, ui.ctx());
+
     // Main panel 
     egui::CentralPanel::default().show(ui.ctx(), |ui| {
         // URL input + Download button
@@ -375,21 +1071,79 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                 .rounding(Rounding::same(4))
                 .inner_margin(4.0)
                 .show(ui, |ui| {
-                    ui.add(
+                    // The link box is the Download tab's Ctrl+F target
+                    let link = ui.add(
                         egui::TextEdit::singleline(&mut app.download_url)
-                            .desired_width(ui.available_width() - 120.0)
+                            .desired_width(ui.available_width() - 400.0)
                             .hint_text("🔗 Enter a NymShare service link"),
                     );
+                    if std::mem::take(&mut app.focus_search) {
+                        link.request_focus();
+                    }
                 });
 
             // Download button
-            if ui.button("🔽 Download").clicked() {
+            if ui.add_enabled(!app.offline, egui::Button::new("🔽 Download"))
+                .on_disabled_hover_text(OFFLINE_HINT)
+                .clicked()
+            {
                 let url = app.download_url.clone();
                 app.download_url.clear();
-                handle_download_request(app, &url);
+                handle_download_request(app, &url, None);
+            }
+
+            // Ask the service about the file before committing to the transfer
+            if ui.add_enabled(!app.offline, egui::Button::new("🔎 Preview"))
+                .on_hover_text("Check the file exists and see its size and hash before downloading")
+                .on_disabled_hover_text(OFFLINE_HINT)
+                .clicked()
+            {
+                let url = app.download_url.trim().to_string();
+                start_link_preview(app, &url);
+            }
+
+            // Download into a folder picked for this file only
+            if ui.add_enabled(!app.offline, egui::Button::new("📂 Save to…"))
+                .on_hover_text("Choose where to save this download instead of the download directory")
+                .on_disabled_hover_text(OFFLINE_HINT)
+                .clicked()
+            {
+                if app.download_url.trim().is_empty() {
+                    app.set_popup_message("Please enter a URL");
+                } else if let Some(dir) = pick_save_dir(app) {
+                    let url = app.download_url.clone();
+                    app.download_url.clear();
+                    handle_download_request(app, &url, Some(dir));
+                }
+            }
+
+            // Import a .nymshare manifest
+            if ui.button("📥 Import").on_hover_text("Queue the downloads listed in a .nymshare manifest").clicked()
+                && let Some(path) = rfd::FileDialog::new().add_filter("NymShare manifest", &[MANIFEST_EXTENSION]).pick_file() {
+                match Manifest::load(&path) {
+                    Ok(manifest) => {
+                        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                        let added = manifest.queue_downloads(app, app.bundle_mode, &name);
+                        app.set_message(format!("Queued {} download(s) from {}", added, path.display()));
+                    }
+                    Err(e) => app.set_popup_message(e),
+                }
             }
+            bundle_mode_combo(ui, "import_bundle_mode", &mut app.bundle_mode);
         });
 
+        render_link_preview(app, ui);
+
+        // Limits published with the link, before anything is requested
+        let link_query = app.download_url.split_once("::").and_then(|(_, target)| split_link_query(target.trim()).1);
+        if let Some(limits) = link_query
+            .and_then(|query| parse_link_query(query).ok())
+            .and_then(|params| describe_link_limits(params.expires_at, params.downloads_left))
+        {
+            ui.label(RichText::new(format!("ℹ This share {}", limits)).weak())
+                .on_hover_text("Published by the sharer when the link was copied; the service enforces the real limits");
+        }
+
         ui.add_space(10.0);
 
         // Download display options
@@ -457,59 +1211,111 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         let today = Local::now().date_naive();
         let app_start_time = app.start_time.unwrap_or(now);
 
-        // Read all files from the download directory
-        let mut download_files: Vec<_> = match fs::read_dir(&app.download_dir) {
-            Ok(entries) => entries
-                .filter_map(|entry| entry.ok())
-                .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-                .map(|entry| entry.path())
-                .collect(),
-            Err(e) => {
-                app.download_message = format!("Failed to read download directory: {}", e);
-                Vec::new()
-            }
-        };
+        // Downloads from the history, newest first; files deleted since are still listed
+        let mut download_records: Vec<usize> = (0..app.download_history.len()).rev().collect();
 
         if !app.hide_all_downloads {
-            // Declarative filter closure accepting &PathBuf
-            let filter_file = |path_buf: &PathBuf| -> bool {
-                let path = path_buf.as_path();
-                if app.show_all_downloads {
-                    return true;
-                }
-                let metadata = match fs::metadata(path) {
-                    Ok(m) => m,
-                    Err(_) => return false,
-                };
-                let modified = match metadata.modified() {
-                    Ok(t) => t,
-                    Err(_) => return false,
-                };
-                let file_date = DateTime::<Local>::from(modified).date_naive();
-
-                (app.show_today_downloads && file_date == today)
-                    || (app.show_runtime_downloads && modified >= app.start_time.unwrap_or(now))
-            };
-
-            download_files.retain(filter_file);
+            if !app.show_all_downloads {
+                download_records.retain(|&i| download_matches_filter(
+                    app.download_history[i].completed_at,
+                    now,
+                    app_start_time,
+                    today,
+                    app.show_today_downloads,
+                    app.show_runtime_downloads,
+                ));
+            }
 
-            if download_files.is_empty() {
+            if download_records.is_empty() && app.is_first_run() {
+                first_run_hint(ui, "⬇️ Download your first file", &[
+                    "Paste a link of the form <service address>::<file name> into the box above and press Download.",
+                    "Ask whoever shares the file for its link, or explore their service to browse what they offer.",
+                ]);
+            } else if download_records.is_empty() {
                 ui.label("No files match the selected filters.");
             } else {
                 egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
                     let mut delete_path = None;
-                    for path in &download_files {
+                    let mut verify_path = None;
+                    let mut forget_index = None;
+                    let mut open_path = None;
+                    let mut reveal_path = None;
+                    for &index in &download_records {
+                        let record = &app.download_history[index];
+                        let path = &record.path;
+                        let exists = record.on_disk;
+                        let verify_status = app.verify_results.lock().unwrap_or_else(|e| e.into_inner()).get(path).cloned();
                         ui.group(|ui| {
                             ui.horizontal(|ui| {
                                 ui.vertical(|ui| {
-                                    ui.label(path.file_name().unwrap_or_default().to_string_lossy());
+                                    ui.horizontal(|ui| {
+                                        ui.label(format!("{} {}", file_type_icon(path), path.file_name().unwrap_or_default().to_string_lossy()));
+                                        if !exists {
+                                            ui.label(RichText::new("(deleted)").color(Color32::RED))
+                                                .on_hover_text("The file is no longer where it was saved");
+                                        }
+                                    });
+                                    let short = if record.source_addr.len() > 24 {
+                                        format!("{}…", &record.source_addr[..24])
+                                    } else {
+                                        record.source_addr.clone()
+                                    };
+                                    ui.label(format!("From: {}", short)).on_hover_text(&record.source_addr);
+                                    ui.label(format!("Size: {} · {}", format_size(record.bytes), system_time_ago(record.completed_at)))
+                                        .on_hover_text(DateTime::<Local>::from(record.completed_at).format("%Y-%m-%d %H:%M:%S").to_string());
                                     ui.label(format!("Path: {}", path.display()));
+
+                                    // Integrity re-check result
+                                    match &verify_status {
+                                        Some(VerifyStatus::Running) => {
+                                            ui.label("⏳ Verifying...");
+                                        }
+                                        Some(VerifyStatus::Passed(hash)) => {
+                                            ui.label(RichText::new("✅ Integrity check passed").color(Color32::DARK_GREEN))
+                                                .on_hover_text(format!("SHA-256: {}", hash));
+                                        }
+                                        Some(VerifyStatus::Failed { expected, actual }) => {
+                                            ui.label(RichText::new("❌ Integrity check failed").color(Color32::RED))
+                                                .on_hover_text(format!("Expected: {}\nActual: {}", expected, actual));
+                                        }
+                                        Some(VerifyStatus::Computed(hash)) => {
+                                            ui.label(format!("SHA-256: {}", hash))
+                                                .on_hover_text("No stored hash for this file; compare manually");
+                                        }
+                                        Some(VerifyStatus::Error(e)) => {
+                                            ui.label(RichText::new(format!("⚠ Verify failed: {}", e)).color(Color32::RED));
+                                        }
+                                        None => {}
+                                    }
                                 });
 
                                 apply_button_style!(ui, Color32::LIGHT_BLUE);
                                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                    if ui.button("❌ Delete").clicked() {
-                                        delete_path = Some(path.clone());
+                                    if ui.button("✖ Forget").on_hover_text("Remove this entry from the history; the file is kept").clicked() {
+                                        forget_index = Some(index);
+                                    }
+
+                                    if ui.add_enabled(exists, egui::Button::new("❌ Delete")).clicked() {
+                                        delete_path = Some((path.clone(), record.request_id.clone()));
+                                    }
+
+                                    let verifying = matches!(verify_status, Some(VerifyStatus::Running));
+                                    if ui.add_enabled(exists && !verifying, egui::Button::new("🔍 Verify"))
+                                        .on_hover_text("Recompute the file's SHA-256 and compare it to the stored hash")
+                                        .clicked() {
+                                        verify_path = Some((path.clone(), record.expected_hash.clone()));
+                                    }
+
+                                    if ui.add_enabled(exists, egui::Button::new("🗁 Show in folder"))
+                                        .on_hover_text("Reveal the file in the file manager")
+                                        .clicked() {
+                                        reveal_path = Some(path.clone());
+                                    }
+
+                                    if ui.add_enabled(exists, egui::Button::new("📂 Open"))
+                                        .on_hover_text("Open the file with its default application")
+                                        .clicked() {
+                                        open_path = Some(path.clone());
                                     }
                                 });
                             });
@@ -517,18 +1323,39 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         ui.add_space(5.0);
                     }
 
-                    if let Some(path) = delete_path {
+                    if let Some((path, expected)) = verify_path {
+                        start_verify(app, path, expected);
+                        app.history_checked = None;
+                    }
+
+                    // The file may have gone since the list was drawn; say so rather than fail silently
+                    if let Some(path) = open_path
+                        && let Err(e) = open_with_default_app(&path) {
+                        app.set_message(format!("Cannot open '{}': {}", path.display(), e));
+                    }
+                    if let Some(path) = reveal_path
+                        && let Err(e) = reveal_in_file_manager(&path) {
+                        app.set_message(format!("Cannot show '{}': {}", path.display(), e));
+                    }
+
+                    if let Some((path, request_id)) = delete_path {
                         let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
                         if let Err(e) = fs::remove_file(&path) {
                             app.set_message(format!("Failed to delete file: {}", e));
                         } else {
-                            // Remove the corresponding request from requested_files
-                            app.requested_files.retain(|req| {
-                                let expected_path = app.download_dir.join(&req.filename);
-                                expected_path != path
-                            });
+                            // Remove the request that saved this file; older records carry no id
+                            if !request_id.is_empty() {
+                                post_command(ManagerCommand::RemoveDownloads(vec![request_id]));
+                            }
                             app.set_message(format!("Deleted file: {}", file_name));
                         }
+                        app.history_checked = None;
+                    }
+
+                    if let Some(index) = forget_index {
+                        let record = app.download_history.remove(index);
+                        app.save_history();
+                        app.set_message(format!("Removed '{}' from the history", record.filename));
                     }
                 });
             }
@@ -544,7 +1371,7 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                 ui.separator();
 
                 // Count total downloads
-                let total_count = download_files.len();
+                let total_count = download_records.len();
                 ui.label(format!("Total downloads: {}", total_count));
                 ui.separator();
 
@@ -668,94 +1495,169 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                     if app.hide_all_requests {
                         ui.label("Requests hidden (uncheck 'Hide All' to show).");
                     } else {
-                        // Filtered requests
-                        let filtered_requests: Vec<_> = app
+                        ui.horizontal(|ui| {
+                            clear_buttons(ui, app, ClearTarget::CompletedDownloads, ClearTarget::FailedDownloads);
+                        });
+
+                        let mut filtered_requests: Vec<_> = app
                             .requested_files
-                            .iter_mut()
+                            .iter()
                             .filter(|r| {
                                 if app.show_all_requests {
                                     true
                                 } else if app.show_accepted_requests {
                                     r.accepted
                                 } else if app.show_completed_requests {
-                                    r.completed
+                                    r.is_completed()
                                 } else {
                                     true
                                 }
                             })
                             .collect();
 
+                        // Sort control; sorts are stable so ties keep insertion order
+                        ui.horizontal(|ui| {
+                            ui.label("Sort by:");
+                            sort_combo(ui, "download_request_sort", &mut app.download_request_sort, &[
+                                RequestSort::Priority,
+                                RequestSort::Status,
+                                RequestSort::Recency,
+                            ]);
+                        });
+                        match app.download_request_sort {
+                            RequestSort::Priority => filtered_requests.sort_by_key(|r| std::cmp::Reverse(r.priority)),
+                            RequestSort::Status => filtered_requests.sort_by_key(|r| r.status()),
+                            RequestSort::Recency => filtered_requests.sort_by_key(|r| recency_key(r.sent_time)),
+                        }
+                        // Requests of a batch follow the first of them, under one header
+                        let mut batch_rank = HashMap::new();
+                        for (i, r) in filtered_requests.iter().enumerate() {
+                            if let Some(batch) = &r.batch_id {
+                                batch_rank.entry(batch.as_str()).or_insert(i);
+                            }
+                        }
+                        let ranks: Vec<usize> = filtered_requests.iter().enumerate()
+                            .map(|(i, r)| r.batch_id.as_deref().map_or(i, |b| batch_rank[b]))
+                            .collect();
+                        let mut ranked: Vec<_> = ranks.into_iter().zip(filtered_requests).collect();
+                        ranked.sort_by_key(|(rank, _)| *rank);
+                        let filtered_requests: Vec<_> = ranked.into_iter().map(|(_, r)| r).collect();
+
                         if filtered_requests.is_empty() {
                             ui.label("No requests match the selected filters.");
                         } else {
-                            // Scrollable request frames
+                            // One row per request: status, file, priority and actions
                             ScrollArea::vertical()
                                 .auto_shrink([false; 2])
                                 .show(ui, |ui| {
-                                    for req in filtered_requests {
-                                        Frame::group(ui.style())
-                                            .fill(ui.style().visuals.panel_fill)
-                                            .corner_radius(6.0)
-                                            .inner_margin(6.0)
-                                            .show(ui, |ui| {
+                                    egui::Grid::new("download_requests_table")
+                                        .striped(true)
+                                        .num_columns(4)
+                                        .show(ui, |ui| {
+                                            let mut current_batch = None;
+                                            for req in filtered_requests {
+                                                if let Some(batch) = req.batch_id.as_deref().filter(|b| current_batch != Some(*b)) {
+                                                    batch_header(ui, &app.requested_files, batch);
+                                                }
+                                                current_batch = req.batch_id.as_deref();
+                                                status_chip(ui, req.status());
+
+                                                // File name, details on hover
+                                                let mut details = vec![format!("Priority: {}", req.priority.label())];
+                                                if let Some((start, end)) = req.range {
+                                                    let end = if end == u64::MAX { String::new() } else { (end - 1).to_string() };
+                                                    details.push(format!("Range: bytes {}-{}", start, end));
+                                                }
+                                                if let Some(sent_time) = req.sent_time {
+                                                    details.push(format!("Sent: {}", time_ago(sent_time)));
+                                                }
+                                                if req.retry.attempts > 1 {
+                                                    details.push(format!("Attempts: {}", req.retry.attempts));
+                                                }
+                                                if req.bytes_received > 0 {
+                                                    details.push(format!("Received: {}", format_size(req.bytes_received)));
+                                                }
+                                                if let Some(reason) = req.failure_reason() {
+                                                    details.push(format!("Failed: {}", reason));
+                                                }
+                                                if let Some(warning) = req.warning() {
+                                                    details.push(format!("Warning: {}", warning));
+                                                }
+                                                if let Some(limits) = describe_link_limits(req.advertised_expiry, req.advertised_downloads_left) {
+                                                    details.push(format!("Share {} (as of the link)", limits));
+                                                }
+                                                if let Some(encoding) = req.encoding {
+                                                    details.push(format!("Encoding: {}", encoding.label()));
+                                                }
+                                                match (&req.archive, &req.save_dir) {
+                                                    (Some(archive), _) => details.push(format!("Saving into: {}", archive)),
+                                                    (None, Some(dir)) => details.push(format!("Saving to: {}", dir.display())),
+                                                    (None, None) => {}
+                                                }
+                                                ui.vertical(|ui| {
+                                                    ui.add(egui::Label::new(format!("{} {}", file_type_icon(Path::new(&req.filename)), req.filename)).truncate())
+                                                        .on_hover_text(details.join("\n"));
+                                                    ui.add(egui::ProgressBar::new(req.progress)
+                                                        .desired_width(200.0)
+                                                        .text(progress_text(req)));
+                                                });
+
+                                                // Reprioritize (only affects requests not yet sent)
                                                 ui.horizontal(|ui| {
-                                                    // Request info
-                                                    ui.vertical(|ui| {
-                                                        ui.label(format!("Filename: {}", req.filename))
-                                                            .on_hover_text("Name of the requested file");
-                                                        ui.label(format!(
-                                                            "Status: {}",
-                                                            if req.sent { "✅ Sent" } else { "⏳ Pending" }
-                                                        ))
-                                                            .on_hover_text("Request status");
-
-                                                        if let Some(sent_time) = req.sent_time {
-                                                            ui.label(format!("Sent: {}", time_ago(sent_time)))
-                                                                .on_hover_text("Time since the request was sent");
-                                                            ui.label(format!(
-                                                                "Accepted: {}",
-                                                                if req.accepted { "✅" } else { "⏳ Pending" }
-                                                            ))
-                                                                .on_hover_text("Whether the request has been accepted");
-                                                            ui.label(format!(
-                                                                "Completed: {}",
-                                                                if req.completed { "✅" } else { "⏳ Pending" }
-                                                            ))
-                                                                .on_hover_text("Whether the request has been completed");
-                                                        }
-                                                    });
+                                                    if ui.add_enabled(req.priority != Priority::Low, egui::Button::new("▼").small())
+                                                        .on_hover_text("Lower priority")
+                                                        .clicked() {
+                                                        post_command(ManagerCommand::SetPriority(req.request_id.clone(), req.priority.lower()));
+                                                    }
+                                                    if ui.add_enabled(req.priority != Priority::High, egui::Button::new("▲").small())
+                                                        .on_hover_text("Raise priority")
+                                                        .clicked() {
+                                                        post_command(ManagerCommand::SetPriority(req.request_id.clone(), req.priority.raise()));
+                                                    }
+                                                });
 
-                                                    // Buttons
-                                                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-                                                        apply_button_style!(ui, Color32::LIGHT_BLUE);
-
-                                                        let (resend_enabled, hover_msg) = if !req.sent {
-                                                            (false, "Cannot resend: Request not yet sent")
-                                                        } else if req.accepted {
-                                                            (false, "Cannot resend: Request already accepted")
-                                                        } else if let Some(sent_time) = req.sent_time {
-                                                            if sent_time.elapsed() < Duration::from_secs(60) {
-                                                                (false, "Cannot resend: Wait 1 minute before resending")
-                                                            } else {
-                                                                (true, "Resend the request")
-                                                            }
+                                                // Failed requests can be sent again at once; they start over
+                                                let (resend_enabled, hover_msg) = if req.is_failed() {
+                                                    (true, "Send the request again")
+                                                } else if !req.sent {
+                                                    (false, "Cannot resend: Request not yet sent")
+                                                } else if req.accepted {
+                                                    (false, "Cannot resend: Request already accepted")
+                                                } else if let Some(sent_time) = req.sent_time {
+                                                    if sent_time.elapsed() < Duration::from_secs(60) {
+                                                        (false, "Cannot resend: Wait 1 minute before resending")
+                                                    } else {
+                                                        (true, "Resend the request")
+                                                    }
+                                                } else {
+                                                    (false, "Cannot resend: Unknown state")
+                                                };
+
+                                                ui.horizontal(|ui| {
+                                                    apply_button_style!(ui, Color32::LIGHT_BLUE);
+                                                    if ui.add_enabled(resend_enabled, egui::Button::new("🔁").small())
+                                                        .on_hover_text(hover_msg)
+                                                        .on_disabled_hover_text(hover_msg)
+                                                        .clicked() {
+                                                        post_command(if req.is_failed() {
+                                                            ManagerCommand::RefreshDownload(req.request_id.clone())
                                                         } else {
-                                                            (false, "Cannot resend: Unknown state")
-                                                        };
-
-                                                        ui.add_enabled(resend_enabled, egui::Button::new("🔁 Resend"))
-                                                            .on_hover_text(hover_msg)
-                                                            .on_disabled_hover_text(hover_msg)
-                                                            .clicked()
-                                                            .then(|| {
-                                                                req.sent = false;
-                                                                req.sent_time = None;
-                                                            });
-                                                    });
+                                                            ManagerCommand::ResendDownload(req.request_id.clone())
+                                                        });
+                                                    }
+
+                                                    // Abandon the download; any partial file is deleted
+                                                    apply_button_style!(ui, Color32::from_rgb(255, 150, 150));
+                                                    if ui.add_enabled(!req.is_done(), egui::Button::new("✖").small())
+                                                        .on_hover_text("Cancel the download")
+                                                        .on_disabled_hover_text("Cannot cancel: Request already finished")
+                                                        .clicked() {
+                                                        post_command(ManagerCommand::CancelDownload(req.request_id.clone()));
+                                                    }
                                                 });
-                                            });
-                                        ui.add_space(4.0);
-                                    }
+                                                ui.end_row();
+                                            }
+                                        });
                                 });
                         }
                     }
@@ -767,11 +1669,19 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                     ui.horizontal(|ui| {
                         let total = app.requested_files.len();
                         let accepted = app.requested_files.iter().filter(|r| r.accepted).count();
-                        let completed = app.requested_files.iter().filter(|r| r.completed).count();
+                        let completed = app.requested_files.iter().filter(|r| r.is_completed()).count();
+                        let warnings = app.requested_files.iter().filter(|r| r.warning().is_some()).count();
+                        let failed = app.requested_files.iter().filter(|r| r.is_failed()).count();
                         ui.label(format!(
                             "Total Requests: {} | Accepted: {} | Completed: {}",
                             total, accepted, completed
                         ));
+                        if warnings > 0 {
+                            ui.colored_label(status_color(RequestStatus::CompletedWithWarnings), format!("⚠ {} with warnings", warnings));
+                        }
+                        if failed > 0 {
+                            ui.colored_label(status_color(RequestStatus::Failed), format!("✖ {} failed", failed));
+                        }
                     });
                 });
             });
@@ -793,13 +1703,14 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
 
                 apply_button_style!(ui, Color32::LIGHT_BLUE);
                 if ui.button("📂 Change Download Directory").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    if let Some(path) = pick_save_dir(app) {
                         app.download_dir = path;
+                        app.check_download_dir();
                         app.set_message(format!(
                             "Download directory changed to: {}",
                             app.download_dir.display()
                         ));
-                    } else {
+                    } else if !app.show_popup_message() {
                         app.set_message("No directory selected".to_string());
                     }
                 }
@@ -816,27 +1727,77 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         .radio(!is_individual, "🕶 Anonymous Mode")
                         .on_hover_text("Use anonymous connection mode for downloads");
 
-                    if individual_resp.clicked() {
-                        is_individual = true;
-                        app.download_socket_mode = SocketMode::Individual;
-                        // Reinitialize socket
-                        let app_clone = Arc::new(Mutex::new(app.clone()));
-                        tokio::spawn(async move {
-                            reinitialize_download_socket(app_clone).await;
-                        });
-                        app.set_message("Switched to Individual mode".to_string());
-                    } else if anonymous_resp.clicked() {
-                        is_individual = false;
-                        app.download_socket_mode = SocketMode::Anonymous;
-                        // Reinitialize socket
-                        let app_clone = Arc::new(Mutex::new(app.clone()));
-                        tokio::spawn(async move {
-                            reinitialize_download_socket(app_clone).await;
-                        });
-                        app.set_message("Switched to Anonymous mode".to_string());
+                    // Replies to in-flight requests go to the old socket, so ask before switching
+                    let requested = if individual_resp.clicked() && !is_individual {
+                        Some(SocketMode::Individual)
+                    } else if anonymous_resp.clicked() && is_individual {
+                        Some(SocketMode::Anonymous)
+                    } else {
+                        None
+                    };
+                    if let Some(mode) = requested {
+                        if in_flight_request_count(app) > 0 {
+                            app.pending_socket_mode = Some(mode);
+                        } else {
+                            is_individual = matches!(mode, SocketMode::Individual);
+                            switch_download_socket_mode(app, mode);
+                        }
                     }
                 });
 
+                // Concurrency cap
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.label("Max concurrent downloads:");
+                    ui.add(egui::DragValue::new(&mut app.max_concurrent_downloads).range(0..=100))
+                        .on_hover_text("Maximum number of requests in flight at once (0 = unlimited)");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Max send attempts:");
+                    ui.add(egui::DragValue::new(&mut app.max_send_attempts).range(1..=100))
+                        .on_hover_text("Failed sends in a row before a request is given up.\nRetries wait 1s, 2s, 4s, ... up to 60s.");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Chunks in flight:");
+                    ui.add(egui::DragValue::new(&mut app.chunk_window).range(0..=MAX_CHUNK_WINDOW))
+                        .on_hover_text("Chunks a service may send before we confirm the ones that arrived.\nLower it on a slow disk or link (0 = no confirmations; the service sends as fast as it can)");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Request timeout (s):");
+                    ui.add(egui::DragValue::new(&mut app.request_timeout_secs).range(0..=3600).speed(5))
+                        .on_hover_text("Fail a sent request that has had no answer for this long (0 = never)");
+                });
+
+                // Transfer encoding
+                ui.add_space(6.0);
+                ui.checkbox(&mut app.prefer_compression, "Compress transfers")
+                    .on_hover_text("Ask services to compress the files they send.\nSaves bandwidth on slow links at the cost of CPU; services that cannot compress send the file as is.");
+
+                // Duplicate requests
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.label("On duplicate request:");
+                    duplicate_policy_combo(ui, "download_duplicate_policy", &mut app.download_duplicate_policy);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("If the file exists:");
+                    egui::ComboBox::from_id_salt("collision_policy")
+                        .selected_text(app.collision_policy.label())
+                        .show_ui(ui, |ui| {
+                            for option in CollisionPolicy::ALL {
+                                ui.selectable_value(&mut app.collision_policy, option, option.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Rename saves the download as \"name (1).ext\"; Skip keeps the existing file and fails the download");
+                });
+
+                // Retry policy
+                ui.add_space(6.0);
+                ui.collapsing("Retry policy", |ui| {
+                    render_backoff_settings(&mut app.backoff, ui);
+                });
+
                 // Sidebar footer
                 ui.allocate_space(ui.available_size_before_wrap());
                 ui.with_layout(Layout::bottom_up(Align::LEFT), |ui| {
@@ -849,6 +1810,548 @@ pub fn render_download_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
 }
 
 
+/// Fill color of a status chip.
+fn status_color(status: RequestStatus) -> Color32 {
+    match status {
+        RequestStatus::Queued => Color32::from_gray(200),
+        RequestStatus::Sent => Color32::LIGHT_BLUE,
+        RequestStatus::Accepted => Color32::from_rgb(255, 220, 130),
+        RequestStatus::Downloading => Color32::from_rgb(190, 160, 255),
+        RequestStatus::Completed => Color32::LIGHT_GREEN,
+        RequestStatus::CompletedWithWarnings => Color32::from_rgb(255, 180, 90),
+        RequestStatus::Failed => Color32::from_rgb(255, 150, 150),
+    }
+}
+
+/// Label for a request's progress bar, e.g. "24% · 1.2 MB / 5.0 MB".
+fn progress_text(req: &DownLoadRequest) -> String {
+    let percent = (req.progress * 100.0).round();
+    match req.total_bytes {
+        Some(total) => format!("{}% · {} / {}", percent, format_size(req.bytes_received), format_size(total)),
+        None if req.bytes_received > 0 => format!("{}% · {}", percent, format_size(req.bytes_received)),
+        None => format!("{}%", percent),
+    }
+}
+
+/// Draws the header row of a multi-file link's requests: file count and progress over all of them.
+fn batch_header(ui: &mut egui::Ui, requests: &[DownLoadRequest], batch_id: &str) {
+    let members: Vec<_> = requests.iter().filter(|r| r.batch_id.as_deref() == Some(batch_id)).collect();
+    let done = members.iter().filter(|r| r.is_done()).count();
+    let received: u64 = members.iter().map(|r| r.bytes_received).sum();
+    let progress = members.iter().map(|r| r.progress).sum::<f32>() / members.len().max(1) as f32;
+    let service = members.first().map(|r| r.from.to_string()).unwrap_or_default();
+
+    ui.label("📦");
+    ui.vertical(|ui| {
+        ui.add(egui::Label::new(RichText::new(format!("Batch of {} files", members.len())).strong()).truncate())
+            .on_hover_text(format!("From: {}", service));
+        ui.add(egui::ProgressBar::new(progress)
+            .desired_width(200.0)
+            .text(format!("{} / {} done · {}", done, members.len(), format_size(received))));
+    });
+    ui.label("");
+    ui.label("");
+    ui.end_row();
+}
+
+/// Hour and minute inputs for a time of day.
+fn time_field(ui: &mut egui::Ui, time: &mut NaiveTime) {
+    let (mut hour, mut minute) = (time.hour(), time.minute());
+    let hour_changed = ui.add(egui::DragValue::new(&mut hour).range(0..=23).custom_formatter(|n, _| format!("{:02}", n))).changed();
+    ui.label(":");
+    let minute_changed = ui.add(egui::DragValue::new(&mut minute).range(0..=59).custom_formatter(|n, _| format!("{:02}", n))).changed();
+    if (hour_changed || minute_changed)
+        && let Some(changed) = NaiveTime::from_hms_opt(hour, minute, 0) {
+        *time = changed;
+    }
+}
+
+/// Draws a compact colored label for a request status.
+fn status_chip(ui: &mut egui::Ui, status: RequestStatus) {
+    Frame::new()
+        .fill(status_color(status))
+        .corner_radius(8.0)
+        .inner_margin(egui::Margin::symmetric(6, 1))
+        .show(ui, |ui| {
+            ui.label(RichText::new(status.label()).small().color(Color32::BLACK));
+        });
+}
+
+/// Number of download and explore requests sent and still waiting on a reply.
+fn in_flight_request_count(app: &FileSharingApp) -> usize {
+    let downloads = app.requested_files.iter().filter(|r| r.sent && !r.is_done()).count();
+    let explores = app.explore_requests.iter().filter(|r| (r.sent && !r.completed) || r.page_pending).count();
+    downloads + explores
+}
+
+/// Switches the download socket mode and recreates the socket with it.
+fn switch_download_socket_mode(app: &mut FileSharingApp, mode: SocketMode) {
+    let message = match mode {
+        SocketMode::Individual => "Switched to Individual mode",
+        SocketMode::Anonymous => "Switched to Anonymous mode",
+    };
+    app.download_socket_mode = mode;
+    // Reinitialize socket
+    let app_clone = Arc::new(Mutex::new(app.clone()));
+    tokio::spawn(async move {
+        reinitialize_download_socket(app_clone).await;
+    });
+    app.set_message(message.to_string());
+}
+
+/// Confirmation for the emergency wipe: one checkbox per part and the confirmation word typed out.
+pub fn render_wipe_confirmation(app: &mut FileSharingApp, ctx: &egui::Context) {
+    let Some(mut options) = app.wipe_options else { return; };
+    let mut decision: Option<bool> = None;
+    egui::Window::new("🚨 Emergency wipe")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(RichText::new("This cannot be undone.").strong().color(Color32::RED));
+            ui.add_space(4.0);
+            ui.checkbox(&mut options.stop_transfers, "Stop all transfers and go offline")
+                .on_hover_text("Stops the managers and closes both sockets; transfers in progress are abandoned");
+            ui.add_enabled(!options.stop_transfers, egui::Checkbox::new(&mut options.clear_requests, "Clear download and explore requests"))
+                .on_disabled_hover_text("Stopping transfers always clears the requests");
+            ui.checkbox(&mut options.clear_shares, "Clear the share list and collections")
+                .on_hover_text("Only forgets them; the files themselves are not touched");
+            ui.checkbox(&mut options.new_identity, "Delete the serving identity")
+                .on_hover_text("Deletes the serving data directory and old identities; the next address is unrelated to this one");
+            ui.checkbox(&mut options.clear_config, "Delete saved settings and history")
+                .on_hover_text("Deletes the config directory: peers, audit log, collections and view settings");
+            ui.checkbox(&mut options.clear_logs, "Empty the debug log");
+            ui.add_space(6.0);
+
+            ui.label(format!("Type {} to confirm:", WIPE_CONFIRMATION));
+            ui.text_edit_singleline(&mut app.wipe_confirmation);
+            let confirmed = app.wipe_confirmation.trim() == WIPE_CONFIRMATION;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(confirmed, egui::Button::new(RichText::new("Wipe").color(Color32::RED))).clicked() {
+                    decision = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    decision = Some(false);
+                }
+            });
+        });
+
+    match decision {
+        Some(true) => {
+            app.wipe_options = None;
+            app.wipe_confirmation.clear();
+            wipe::start(app, options);
+        }
+        Some(false) => {
+            app.wipe_options = None;
+            app.wipe_confirmation.clear();
+        }
+        None => app.wipe_options = Some(options),
+    }
+}
+
+/// Asks for the passphrase at startup when saved state is encrypted.
+/// Until it is given the app runs on in-memory state and saves nothing.
+pub fn render_unlock_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if app.unlock_dismissed || !vault::is_locked() {
+        return;
+    }
+    egui::Window::new("🔒 Unlock saved state")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("Saved settings and history are encrypted. Enter the passphrase to load them.");
+            let field = ui.add(egui::TextEdit::singleline(&mut app.unlock_passphrase).password(true).hint_text("Passphrase"));
+            let submitted = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.horizontal(|ui| {
+                if ui.button("🔓 Unlock").clicked() || submitted {
+                    match vault::unlock(&app.unlock_passphrase) {
+                        Ok(()) => {
+                            app.unlock_passphrase.clear();
+                            app.restore_persisted();
+                            app.set_message("Saved state unlocked");
+                        }
+                        Err(e) => app.set_popup_message(e),
+                    }
+                }
+                if ui.button("Continue without")
+                    .on_hover_text("Run on in-memory state; nothing is loaded or saved this session")
+                    .clicked()
+                {
+                    app.unlock_passphrase.clear();
+                    app.unlock_dismissed = true;
+                    app.set_message("Running without saved state; nothing is saved this session");
+                }
+            });
+
+            ui.separator();
+            ui.checkbox(&mut app.unlock_forget, "I forgot the passphrase");
+            if app.unlock_forget
+                && ui.button(RichText::new("Delete saved state and start fresh").color(Color32::RED))
+                    .on_hover_text("Deletes the config directory; peers, audit log, collections and view settings are lost")
+                    .clicked()
+            {
+                match vault::start_fresh() {
+                    Ok(()) => {
+                        app.unlock_forget = false;
+                        app.unlock_passphrase.clear();
+                        app.set_message("Saved state deleted; starting fresh without encryption");
+                    }
+                    Err(e) => app.set_popup_message(format!("Cannot delete saved state: {}", e)),
+                }
+            }
+        });
+}
+
+/// Turns encryption of saved settings and history on or off.
+pub fn render_vault_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    let mut open = app.show_vault_window;
+    egui::Window::new("🔒 Encryption")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if vault::is_locked() {
+                ui.label("Saved state is encrypted and locked; nothing is saved this session.");
+                if ui.button("🔓 Unlock…").clicked() {
+                    app.unlock_dismissed = false;
+                }
+                return;
+            }
+
+            if vault::is_enabled() {
+                ui.label("Saved settings and history are encrypted.");
+                ui.label(RichText::new("Window size and position are not encrypted.").italics());
+                ui.label(RichText::new("The debug log is not written while encryption is on.").italics());
+                if ui.button("Turn off encryption")
+                    .on_hover_text("Decrypts the saved files and removes the passphrase")
+                    .clicked()
+                {
+                    match vault::disable() {
+                        Ok(()) => app.set_message("Saved state is no longer encrypted"),
+                        Err(e) => app.set_popup_message(e),
+                    }
+                }
+                return;
+            }
+
+            ui.label("Encrypt peers, audit log, collections and view settings on disk.");
+            ui.label(RichText::new("The passphrase is asked at every start and cannot be recovered; if it is forgotten, saved state is deleted.").italics());
+            ui.label(RichText::new("Window size and position are still saved unencrypted.").italics());
+            ui.label(RichText::new("The debug log is emptied and not written while encryption is on.").italics());
+            ui.add(egui::TextEdit::singleline(&mut app.vault_passphrase).password(true).hint_text("Passphrase"));
+            ui.add(egui::TextEdit::singleline(&mut app.vault_passphrase_confirm).password(true).hint_text("Repeat passphrase"));
+            let long_enough = app.vault_passphrase.chars().count() >= vault::MIN_PASSPHRASE_LEN;
+            let matching = app.vault_passphrase == app.vault_passphrase_confirm;
+            if !long_enough {
+                ui.label(format!("At least {} characters.", vault::MIN_PASSPHRASE_LEN));
+            } else if !matching {
+                ui.label("The passphrases differ.");
+            }
+            if ui.add_enabled(long_enough && matching, egui::Button::new("🔒 Encrypt")).clicked() {
+                match vault::enable(&app.vault_passphrase) {
+                    Ok(()) => {
+                        app.vault_passphrase.clear();
+                        app.vault_passphrase_confirm.clear();
+                        app.set_message("Saved state encrypted");
+                    }
+                    Err(e) => app.set_popup_message(e),
+                }
+            }
+        });
+    app.show_vault_window = open;
+}
+
+/// Confirmation for switching the download socket mode while requests are in flight.
+/// Proceeding queues the affected requests again so they are resent from the new socket.
+fn render_socket_mode_confirmation(app: &mut FileSharingApp, ctx: &egui::Context) {
+    let Some(mode) = app.pending_socket_mode.clone() else { return; };
+    let count = in_flight_request_count(app);
+    let mut decision: Option<bool> = None;
+    egui::Window::new("🔀 Switch download mode")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(format!("{} request(s) are waiting on replies addressed to the current socket.", count));
+            ui.label("Switching resets them so they are sent again; transfers in progress start over.");
+            ui.horizontal(|ui| {
+                if ui.button("Switch and resend").clicked() {
+                    decision = Some(true);
+                }
+                if ui.button("Wait").on_hover_text("Keep the current mode until these requests finish").clicked() {
+                    decision = Some(false);
+                }
+            });
+        });
+
+    match decision {
+        Some(true) => {
+            for request in app.requested_files.iter().filter(|r| r.sent && !r.is_done()) {
+                post_command(ManagerCommand::RefreshDownload(request.request_id.clone()));
+            }
+            for request in &app.explore_requests {
+                if request.page_pending {
+                    post_command(ManagerCommand::RetryPage(request.request_id.clone()));
+                } else if request.sent && !request.completed {
+                    post_command(ManagerCommand::RefreshExplore(request.request_id.clone()));
+                }
+            }
+            app.pending_socket_mode = None;
+            switch_download_socket_mode(app, mode);
+        }
+        Some(false) => app.pending_socket_mode = None,
+        None => {}
+    }
+}
+
+/// Centered heading and a few lines of guidance, shown in place of an empty list on first run.
+fn first_run_hint(ui: &mut egui::Ui, heading: &str, lines: &[&str]) {
+    ui.vertical_centered(|ui| {
+        ui.add_space(30.0);
+        ui.heading(heading);
+        ui.add_space(6.0);
+        for line in lines {
+            ui.label(*line);
+        }
+        ui.add_space(6.0);
+    });
+}
+
+/// Combo box choosing how a requests view is sorted.
+fn sort_combo(ui: &mut egui::Ui, id: &str, sort: &mut RequestSort, options: &[RequestSort]) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(sort.label())
+        .show_ui(ui, |ui| {
+            for option in options {
+                ui.selectable_value(sort, *option, option.label());
+            }
+        });
+}
+
+/// Combo box choosing whether a duplicate request is rejected or refreshes the existing one.
+fn duplicate_policy_combo(ui: &mut egui::Ui, id: &str, policy: &mut DuplicatePolicy) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(policy.label())
+        .show_ui(ui, |ui| {
+            for option in [DuplicatePolicy::Reject, DuplicatePolicy::Refresh] {
+                ui.selectable_value(policy, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("Refreshing resets the existing request so it is sent again");
+}
+
+/// Combo box choosing the socket mode new explore requests are sent in.
+fn explore_mode_combo(ui: &mut egui::Ui, mode: &mut ExploreMode) {
+    egui::ComboBox::from_id_salt("explore_mode")
+        .selected_text(mode.label())
+        .show_ui(ui, |ui| {
+            for option in ExploreMode::ALL {
+                ui.selectable_value(mode, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("Anonymous hides your address from the service; Individual lets it reply directly.\nA mode other than the download socket's uses a second socket, created on first use.");
+}
+
+/// Combo box choosing whether collections and manifests download as loose files or one zip.
+fn bundle_mode_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, mode: &mut BundleMode) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(mode.label())
+        .show_ui(ui, |ui| {
+            for option in BundleMode::ALL {
+                ui.selectable_value(mode, option, option.label());
+            }
+        })
+        .response
+        .on_hover_text("Zip collects every file into one archive in the download directory as each one arrives.\nFiles that fail are skipped and listed in the archive comment.");
+}
+
+/// Combo box choosing the serving identity a file is offered from.
+/// A file bound to a removed identity shows that name until it is moved.
+fn identity_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, identities: &[(String, String)], identity: &mut Option<String>) {
+    let current = identity.as_deref().unwrap_or(DEFAULT_IDENTITY).to_string();
+    let known = identities.iter().any(|(name, _)| *name == current);
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(if known { current.clone() } else { format!("{} (removed)", current) })
+        .show_ui(ui, |ui| {
+            for (name, _) in identities {
+                if ui.selectable_label(*name == current, name).clicked() {
+                    *identity = (name != DEFAULT_IDENTITY).then(|| name.clone());
+                }
+            }
+        })
+        .response
+        .on_hover_text("Peers reach this file through the chosen identity's address only");
+}
+
+/// Combo box restricting a file list to one content type.
+fn type_filter_combo(ui: &mut egui::Ui, id: &str, filter: &mut Option<FileType>) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(filter.map_or("All types", |t| t.label()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(filter, None, "All types");
+            for file_type in FileType::ALL {
+                ui.selectable_value(filter, Some(file_type), file_type.label());
+            }
+        })
+        .response
+        .on_hover_text("Only list files of this type");
+}
+
+/// Sort key putting unsent requests first, then the most recently sent.
+fn recency_key(sent_time: Option<Instant>) -> std::cmp::Reverse<(bool, Option<Instant>)> {
+    std::cmp::Reverse((sent_time.is_none(), sent_time))
+}
+
+/// Renders the controls for the retry policy used by sockets, pings and unanswered requests.
+fn render_backoff_settings(backoff: &mut Backoff, ui: &mut egui::Ui) {
+    egui::Grid::new("backoff_settings").num_columns(2).show(ui, |ui| {
+        let mut initial = backoff.initial.as_secs_f64();
+        ui.label("First delay:");
+        if ui.add(egui::DragValue::new(&mut initial).range(0.5..=600.0).speed(0.5).suffix(" s"))
+            .on_hover_text("Wait after the first attempt before retrying")
+            .changed() {
+            backoff.initial = Duration::from_secs_f64(initial);
+        }
+        ui.end_row();
+
+        let mut max = backoff.max.as_secs_f64();
+        ui.label("Longest delay:");
+        if ui.add(egui::DragValue::new(&mut max).range(initial..=3600.0).speed(1.0).suffix(" s"))
+            .on_hover_text("Delays stop growing at this value")
+            .changed() {
+            backoff.max = Duration::from_secs_f64(max);
+        }
+        ui.end_row();
+
+        ui.label("Multiplier:");
+        ui.add(egui::DragValue::new(&mut backoff.multiplier).range(1.0..=10.0).speed(0.1))
+            .on_hover_text("Each delay is this many times the previous one");
+        ui.end_row();
+
+        let mut jitter = backoff.jitter * 100.0;
+        ui.label("Jitter:");
+        if ui.add(egui::DragValue::new(&mut jitter).range(0.0..=100.0).suffix(" %"))
+            .on_hover_text("Random spread applied to each delay")
+            .changed() {
+            backoff.jitter = jitter / 100.0;
+        }
+        ui.end_row();
+
+        ui.label("Retries:");
+        ui.add(egui::DragValue::new(&mut backoff.retries).range(0..=20))
+            .on_hover_text("Attempts after the first one before giving up");
+        ui.end_row();
+    });
+
+    if ui.button("Reset to defaults").clicked() {
+        *backoff = Backoff::default();
+    }
+}
+
+
+/// Renders the diagnostics window used to collect bug report information.
+pub fn render_diagnostics_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if !app.show_diagnostics_window {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("🐞 Diagnostics")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("Collect version, settings, request counts and recent log lines for a bug report.");
+            ui.checkbox(&mut app.diagnostics_redact_addresses, "Mask Nym addresses")
+                .on_hover_text("Replace Nym addresses with a placeholder");
+            ui.checkbox(&mut app.diagnostics_redact_paths, "Mask file paths")
+                .on_hover_text("Replace shared file and download paths with a placeholder");
+
+            apply_button_style!(ui, Color32::LIGHT_BLUE);
+            ui.horizontal(|ui| {
+                if ui.button("📋 Copy to clipboard").clicked() {
+                    let report = build_report(app, app.diagnostics_redact_addresses, app.diagnostics_redact_paths);
+                    ui.ctx().copy_text(report);
+                    app.set_message("Diagnostics copied to clipboard");
+                }
+
+                if ui.button("💾 Save to file").clicked()
+                    && let Some(path) = rfd::FileDialog::new().set_file_name("nymshare-diagnostics.txt").save_file() {
+                    let report = build_report(app, app.diagnostics_redact_addresses, app.diagnostics_redact_paths);
+                    match fs::write(&path, report) {
+                        Ok(_) => app.set_message(format!("Diagnostics saved to {}", path.display())),
+                        Err(e) => app.set_message(format!("Failed to save diagnostics: {}", e)),
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.label("Throughput (last minute)");
+            render_throughput_graph(ui);
+            // Keep the graph moving while the window is open
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        });
+
+    if !open {
+        app.show_diagnostics_window = false;
+    }
+}
+
+/// Paints the last minute of sent/received bytes per second as two lines.
+fn render_throughput_graph(ui: &mut egui::Ui) {
+    let samples = throughput::history();
+    let peak = samples.iter().map(|(s, r)| (*s).max(*r)).max().unwrap_or(0).max(1);
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(400.0, 120.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 2.0, Stroke::new(1.0, Color32::GRAY), egui::StrokeKind::Inside);
+
+    let point = |i: usize, value: u64| {
+        let x = rect.left() + rect.width() * i as f32 / (throughput::HISTORY_SECONDS - 1) as f32;
+        let y = rect.bottom() - rect.height() * value as f32 / peak as f32;
+        egui::pos2(x, y)
+    };
+    let sent: Vec<_> = samples.iter().enumerate().map(|(i, (s, _))| point(i, *s)).collect();
+    let received: Vec<_> = samples.iter().enumerate().map(|(i, (_, r))| point(i, *r)).collect();
+    painter.add(egui::Shape::line(sent, Stroke::new(1.5, Color32::LIGHT_BLUE)));
+    painter.add(egui::Shape::line(received, Stroke::new(1.5, Color32::GREEN)));
+
+    let (last_sent, last_received) = samples.last().copied().unwrap_or_default();
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(format!("▬ Sent {}/s", format_size(last_sent))).color(Color32::LIGHT_BLUE));
+        ui.label(RichText::new(format!("▬ Received {}/s", format_size(last_received))).color(Color32::GREEN));
+        ui.label(format!("Peak {}/s", format_size(peak)));
+    });
+}
+
+
+/// Recomputes a downloaded file's hash on a blocking thread.
+///
+/// Compares against the expected hash kept in the file's download history record,
+/// or just records the current hash when none is known.
+fn start_verify(app: &mut FileSharingApp, path: PathBuf, expected: Option<String>) {
+    let results = app.verify_results.clone();
+    results.lock().unwrap_or_else(|e| e.into_inner()).insert(path.clone(), VerifyStatus::Running);
+
+    tokio::task::spawn_blocking(move || {
+        let status = match sha256_file(&path) {
+            Ok(actual) => match expected {
+                Some(expected) if expected.eq_ignore_ascii_case(&actual) => VerifyStatus::Passed(actual),
+                Some(expected) => VerifyStatus::Failed { expected, actual },
+                None => VerifyStatus::Computed(actual),
+            },
+            Err(e) => VerifyStatus::Error(e.to_string()),
+        };
+        results.lock().unwrap_or_else(|e| e.into_inner()).insert(path, status);
+    });
+}
+
+
 /// Renders the explore tab UI for the file-sharing application.
 pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
     // Service address input + Explore/Clear buttons
@@ -861,19 +2364,23 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                 ui.add(
                     egui::TextEdit::singleline(&mut app.explore_address)
                         .desired_width(ui.available_width() - 120.0)
-                        .hint_text("🔗 Enter a nymshare service address or file name to search"),
+                        .hint_text("🔗 Enter a nymshare service address"),
                 );
             });
 
         
 
-        let explore_clicked = ui.button("🔎 Explore").clicked();
-        let enter_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let explore_clicked = ui.add_enabled(!app.offline, egui::Button::new("🔎 Explore"))
+            .on_disabled_hover_text(OFFLINE_HINT)
+            .clicked();
+        let enter_pressed = !app.offline && ui.input(|i| i.key_pressed(egui::Key::Enter));
         if explore_clicked || enter_pressed {
             let addr = app.explore_address.trim().to_string();
-            if addr.len() > 45 {
+            if is_service_address(&addr) {
                 handle_explore_request(app, &addr);
                 app.explore_address.clear();
+            } else if explore_clicked && !addr.is_empty() {
+                app.set_message("Not a service address; use the search box to find files");
             }
         }
 
@@ -882,6 +2389,25 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
         }
     });
 
+    // Search across the files of every explored service
+    ui.horizontal(|ui| {
+        let search = ui.add(
+            egui::TextEdit::singleline(&mut app.explore_search_query)
+                .desired_width(ui.available_width() - 240.0)
+                .hint_text("🔍 Search the files of every explored service"),
+        );
+        if std::mem::take(&mut app.focus_search) {
+            search.request_focus();
+        }
+        ui.checkbox(&mut app.explore_flat_results, "One list")
+            .on_hover_text("List the matching files of all services together");
+        if ui.button("❌").on_hover_text("Clear search").clicked() {
+            app.explore_search_query.clear();
+        }
+    });
+
+    render_address_book(app, ui);
+
     ui.add_space(10.0);
     ui.separator();
 
@@ -900,6 +2426,21 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
             app.show_all_explore_requests = false;
         }
 
+        ui.separator();
+        ui.checkbox(&mut app.hide_completed_explore_requests, "Hide Completed")
+            .on_hover_text("Hide explore requests that already returned their file list");
+        ui.label("Sort by:");
+        sort_combo(ui, "explore_request_sort", &mut app.explore_request_sort, &[RequestSort::Status, RequestSort::Recency]);
+        type_filter_combo(ui, "explore_type_filter", &mut app.explore_type_filter);
+        ui.checkbox(&mut app.ping_before_explore, "Ping First")
+            .on_hover_text("Check the service answers before queuing the request.\nSends an extra message to the service; leave off for strict anonymity.");
+        ui.label("On duplicate:");
+        duplicate_policy_combo(ui, "explore_duplicate_policy", &mut app.explore_duplicate_policy);
+        ui.label("Mode:");
+        explore_mode_combo(ui, &mut app.explore_mode);
+
+        clear_buttons(ui, app, ClearTarget::CompletedExplores, ClearTarget::FailedExplores);
+
         if !app.explore_message.is_empty() && app.show_message() {
             ui.separator();
             ui.label(egui::RichText::new(&app.explore_message).color(Color32::BLACK));
@@ -932,52 +2473,87 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
     }
 
     // Filter requests based on search query
-    let search_query = if app.explore_address.trim().len() <= 45 {
-        app.explore_address.trim().to_lowercase()
-    } else {
-        String::new()
-    };
+    let search_query = app.explore_search_query.trim().to_lowercase();
 
-    let filtered_requests: Vec<_> = app
+    // Indices of the requests to show; the requests themselves are borrowed, not cloned
+    let type_filter = app.explore_type_filter;
+    let filtering = !search_query.is_empty() || type_filter.is_some();
+    let mut filtered_requests: Vec<usize> = app
         .explore_requests
         .iter()
-        .filter(|r| {
-            if search_query.is_empty() {
-                true
-            } else {
-                r.advertise_files
-                    .iter()
-                    .any(|file| file.to_lowercase().contains(&search_query))
-            }
-        })
-        .cloned()
+        .enumerate()
+        .filter(|(_, r)| !(app.hide_completed_explore_requests && r.completed))
+        .filter(|(_, r)| !filtering || r.has_matching_file(&search_query, type_filter))
+        .map(|(i, _)| i)
         .collect();
 
+    // Sorts are stable so ties keep insertion order
+    let requests = &app.explore_requests;
+    match app.explore_request_sort {
+        RequestSort::Status => filtered_requests.sort_by_key(|&i| requests[i].status()),
+        _ => filtered_requests.sort_by_key(|&i| recency_key(requests[i].sent_time)),
+    }
+
+    if filtered_requests.is_empty() && app.is_first_run() {
+        first_run_hint(ui, "🔎 Explore a service", &[
+            "Enter a nymshare service address above and press Explore to list the files it shares.",
+            "To let others explore you, give them your own serving address:",
+        ]);
+        ui.vertical_centered(|ui| {
+            if app.serving_addr.is_empty() {
+                ui.label(RichText::new("Your address appears here once connected.").weak());
+            } else {
+                ui.label(RichText::new(&app.serving_addr).monospace());
+                if ui.button("📋 Copy my address").clicked() {
+                    ui.ctx().copy_text(app.serving_addr.clone());
+                    app.set_message("Serving address copied to clipboard");
+                }
+            }
+        });
+        return;
+    }
     if filtered_requests.is_empty() {
         ui.label("No explore requests or matching files found.");
         return;
     }
 
+    if filtering && app.explore_flat_results {
+        render_explore_matches(app, ui, &filtered_requests, &search_query, type_filter);
+        return;
+    }
+
+    // Clicks are collected while the requests are borrowed and applied afterwards
+    let mut remove_request_id: Option<String> = None;
+    let mut load_more_ids: Vec<String> = Vec::new();
+    let mut toggle_ids: Vec<String> = Vec::new();
+    let mut resend_ids: Vec<String> = Vec::new();
+    let mut download_urls: Vec<(String, bool)> = Vec::new(); // (link, ask where to save)
+    let mut collection_fetches: Vec<(String, String)> = Vec::new(); // (request id, collection name)
+    let mut collection_downloads: Vec<(String, Manifest)> = Vec::new(); // (collection name, members)
+    let mut bundle_mode = app.bundle_mode;
+
     // Scrollable request frames
     ScrollArea::vertical()
     .auto_shrink([false; 2])
     .show(ui, |ui| {
-        let mut remove_request_id: Option<String> = None;
-
-        for req in filtered_requests {
-            let frame_fill = if !search_query.is_empty()
-                && req
-                    .advertise_files
-                    .iter()
-                    .any(|file| file.to_lowercase().contains(&search_query))
-            {
-                Color32::LIGHT_YELLOW
-            } else {
-                Color32::from_gray(245)
+        for &index in &filtered_requests {
+            let req = &app.explore_requests[index];
+
+            // Files matching the search and type filter; none when neither is set
+            let matching_files = if !filtering || req.advertise_files.is_empty() {
+                Vec::new()
+            } else {
+                req.matching_files(&search_query, type_filter)
+            };
+            let frame_stroke = if matching_files.is_empty() {
+                ui.style().visuals.widgets.noninteractive.bg_stroke
+            } else {
+                Stroke::new(1.5, Color32::GOLD)
             };
 
             Frame::group(ui.style())
                 .fill(ui.style().visuals.panel_fill)
+                .stroke(frame_stroke)
                 .corner_radius(6.0)
                 .inner_margin(6.0)
                 .show(ui, |ui| {
@@ -985,85 +2561,135 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                         apply_button_style!(ui, Color32::LIGHT_BLUE);
                         // Request info
                         ui.vertical(|ui| {
-                            ui.label(format!("Service: {:?}", req.from.to_string()))
-                                .on_hover_text("Service address");
-                            ui.label(format!(
-                                "Status: {}",
-                                if req.sent { "✅ Sent" } else { "⏳ Pending" }
-                            ))
-                                .on_hover_text("Request status");
-
-                            if let Some(sent_time) = req.sent_time {
-                                ui.label(format!("Sent: {}", time_ago(sent_time)))
-                                    .on_hover_text("Time since sent");
-                                ui.label(format!(
-                                    "Accepted: {}",
-                                    if req.accepted { "✅" } else { "⏳ Pending" }
-                                ))
-                                    .on_hover_text("Accepted status");
-                                ui.label(format!(
-                                    "Completed: {}",
-                                    if req.completed { "✅" } else { "⏳ Pending" }
-                                ))
-                                    .on_hover_text("Completed status");
-                            }
+                            ui.horizontal(|ui| {
+                                status_chip(ui, req.status());
+                                ui.label(format!("Service: {}", req.from))
+                                    .on_hover_text("Service address");
+                                if req.mode != ExploreMode::Downloads {
+                                    ui.label(RichText::new(req.mode.label()).weak())
+                                        .on_hover_text("Socket mode this request is sent in");
+                                }
+                                if let Some(sent_time) = req.sent_time {
+                                    ui.label(RichText::new(format!("sent {}", time_ago(sent_time))).weak())
+                                        .on_hover_text("Time since sent");
+                                }
+                            });
 
                             // Expand/Collapse advertised files
                             if !req.advertise_files.is_empty() {
-                                let is_expanded =
-                                    app.expanded_requests.contains(&req.request_id.clone());
+                                let is_expanded = app.expanded_requests.contains(&req.request_id);
                                 let toggle_label =
                                     if is_expanded { "▼ Hide Files" } else { "▶ Show Files" };
 
                                 if ui.button(toggle_label).clicked() {
-                                    if is_expanded {
-                                        app.expanded_requests.remove(&req.request_id.clone());
-                                    } else {
-                                        app.expanded_requests.insert(req.request_id.clone());
-                                    }
+                                    toggle_ids.push(req.request_id.clone());
                                 }
 
-                                // collect matching files
-                                let matching_files: Vec<_> = if search_query.is_empty() {
-                                    Vec::new()
-                                } else {
-                                    req.advertise_files
-                                        .iter()
-                                        .filter(|file| {
-                                            file.to_lowercase().contains(&search_query)
-                                        })
-                                        .collect()
-                                };
-
                                 // decide what to show
                                 if is_expanded || !matching_files.is_empty() {
-                                    let files_to_show: Vec<_> =
-                                        if is_expanded && search_query.is_empty() {
-                                            req.advertise_files.iter().collect()
-                                        } else if is_expanded && !search_query.is_empty() {
-                                            matching_files.clone()
-                                        } else {
-                                            matching_files.clone()
-                                        };
+                                    let files_to_show: Vec<usize> = if !filtering {
+                                        (0..req.advertise_files.len()).collect()
+                                    } else {
+                                        matching_files
+                                    };
 
                                     ui.label(format!(
-                                        "Advertised Files: {}",
-                                        files_to_show.len()
+                                        "Advertised Files: {}{}",
+                                        files_to_show.len(),
+                                        if req.has_more_pages() { " (more available)" } else { "" }
                                     ));
-                                    for file in files_to_show {
-                                        ui.horizontal(|ui| {
-                                            ui.label(format!("  - {}", file));
-                                            if ui.button("⬇️ Download").clicked() {
-                                                let url =
-                                                    format!("{}::{}", req.from.to_string(), file);
-                                                handle_download_request(app, &url);
+
+                                    // Only the rows scrolled into view are laid out
+                                    let row_height = ui.spacing().interact_size.y;
+                                    let mut end_visible = false;
+                                    ScrollArea::vertical()
+                                        .id_salt(("advertised_files", &req.request_id))
+                                        .max_height(row_height * ADVERTISED_ROWS_VISIBLE as f32)
+                                        .auto_shrink([false, true])
+                                        .show_rows(ui, row_height, files_to_show.len(), |ui, rows| {
+                                            end_visible = rows.end >= files_to_show.len();
+                                            for &file_index in &files_to_show[rows] {
+                                                let file = &req.advertise_files[file_index];
+                                                ui.horizontal(|ui| {
+                                                    let label = ui.label(highlighted_name(
+                                                        ui,
+                                                        file,
+                                                        &req.advertise_search[file_index],
+                                                        &search_query,
+                                                    ));
+                                                    if let Some(hash) = req.advertise_hashes.get(file.as_str()) {
+                                                        label.on_hover_text(format!("SHA-256: {}", hash));
+                                                    }
+                                                    let url = file_link(&req.from, file);
+                                                    let button = ui.add_enabled(!app.offline, egui::Button::new("⬇️ Download"))
+                                                        .on_hover_text("Right-click to choose where to save it")
+                                                        .on_disabled_hover_text(OFFLINE_HINT);
+                                                    if button.clicked() {
+                                                        download_urls.push((url.clone(), false));
+                                                    }
+                                                    button.context_menu(|ui| {
+                                                        if ui.button("📂 Save to…").clicked() {
+                                                            ui.close();
+                                                            download_urls.push((url, true));
+                                                        }
+                                                    });
+                                                });
                                             }
                                         });
+
+                                    // Fetch the next page once the end of the list scrolls into view
+                                    if is_expanded && req.has_more_pages() {
+                                        if req.page_pending || req.page_wanted {
+                                            ui.label(RichText::new("⏳ Loading more files...").italics());
+                                        } else {
+                                            let more = ui.button("⏬ Load more")
+                                                .on_hover_text(format!("{} of {} pages loaded", req.pages_loaded, req.total_pages));
+                                            if more.clicked() || (end_visible && ui.is_rect_visible(more.rect)) {
+                                                load_more_ids.push(req.request_id.clone());
+                                            }
+                                        }
                                     }
                                 }
                             } else {
                                 ui.label("Advertised Files: 0")
                                     .on_hover_text("No files available from this service");
+                                if let Some(note) = &req.advertise_note {
+                                    ui.label(RichText::new(format!("Service says: {}", note)).italics());
+                                }
+                            }
+
+                            // Collections, each listed as one entry and downloaded together
+                            for name in &req.advertise_collections {
+                                egui::CollapsingHeader::new(format!("📦 {}", name))
+                                    .id_salt(("explore_collection", &req.request_id, name))
+                                    .show(ui, |ui| match req.collection_manifests.get(name) {
+                                        Some(manifest) => {
+                                            for entry in &manifest.files {
+                                                ui.label(format!("{} ({})", entry.name, format_size(entry.size)));
+                                            }
+                                            ui.horizontal(|ui| {
+                                                if ui.add_enabled(!app.offline, egui::Button::new(format!("⬇️ Download all ({})", manifest.files.len())))
+                                                    .on_disabled_hover_text(OFFLINE_HINT)
+                                                    .clicked()
+                                                {
+                                                    collection_downloads.push((name.clone(), manifest.clone()));
+                                                }
+                                                bundle_mode_combo(ui, ("collection_bundle_mode", &req.request_id, name), &mut bundle_mode);
+                                            });
+                                        }
+                                        None if req.collections_wanted.contains(name) => {
+                                            ui.label(RichText::new("⏳ Loading members...").italics());
+                                        }
+                                        None => {
+                                            if ui.add_enabled(!app.offline, egui::Button::new("⟳ Load members"))
+                                                .on_hover_text("Ask the service which files the collection holds")
+                                                .on_disabled_hover_text(OFFLINE_HINT)
+                                                .clicked()
+                                            {
+                                                collection_fetches.push((req.request_id.clone(), name.clone()));
+                                            }
+                                        }
+                                    });
                             }
                         });
 
@@ -1077,7 +2703,9 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                             }
 
                             // Resend button
-                            let (resend_enabled, hover_msg) = if !req.sent {
+                            let (resend_enabled, hover_msg) = if req.failed {
+                                (true, "Send the request again")
+                            } else if !req.sent {
                                 (false, "Cannot resend: Request not yet sent")
                             } else if req.accepted {
                                 (false, "Cannot resend: Request already accepted")
@@ -1097,26 +2725,396 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
                                 .on_disabled_hover_text(hover_msg)
                                 .clicked()
                             {
-                                if let Some(orig_req) = app
-                                    .explore_requests
-                                    .iter_mut()
-                                    .find(|r| r.request_id == req.request_id)
-                                {
-                                    orig_req.sent = false;
-                                    orig_req.sent_time = None;
-                                }
+                                resend_ids.push(req.request_id.clone());
                             }
                         });
                     });
                 });
             ui.add_space(4.0);
         }
+    });
+
+    for request_id in toggle_ids {
+        if !app.expanded_requests.remove(&request_id) {
+            app.expanded_requests.insert(request_id);
+        }
+    }
+
+    for request_id in load_more_ids {
+        post_command(ManagerCommand::LoadMorePages(request_id));
+    }
+    for request_id in resend_ids {
+        post_command(ManagerCommand::ResendExplore(request_id));
+    }
+    for (request_id, name) in collection_fetches {
+        post_command(ManagerCommand::FetchCollection(request_id, name));
+    }
+    app.bundle_mode = bundle_mode;
+    for (name, manifest) in collection_downloads {
+        let added = manifest.queue_downloads(app, app.bundle_mode, &name);
+        app.set_message(format!("Queued {} download(s) from the collection", added));
+    }
+
+    for (url, ask) in download_urls {
+        let save_dir = if ask {
+            match pick_save_dir(app) {
+                Some(dir) => Some(dir),
+                None => continue,
+            }
+        } else {
+            None
+        };
+        handle_download_request(app, &url, save_dir);
+    }
+
+    if let Some(request_id) = remove_request_id {
+        post_command(ManagerCommand::RemoveExplores(vec![request_id.clone()]));
+        app.set_message(format!("Explore request removed: {:?}", request_id));
+    }
+}
+
+/// Advertised file rows visible at once before the list scrolls.
+const ADVERTISED_ROWS_VISIBLE: usize = 15;
+
+/// Lists the files of the `requests` (indices into explore_requests) matching the search
+/// and type filter as one list, with a button to download them all.
+fn render_explore_matches(app: &mut FileSharingApp, ui: &mut egui::Ui, requests: &[usize], query: &str, file_type: Option<FileType>) {
+    // (request index, file index) of every match, service by service
+    let matches: Vec<(usize, usize)> = requests.iter()
+        .flat_map(|&r| app.explore_requests[r].matching_files(query, file_type).into_iter().map(move |f| (r, f)))
+        .collect();
+    let services = requests.iter().filter(|&&r| matches.iter().any(|&(m, _)| m == r)).count();
+
+    let mut download_urls: Vec<(String, bool)> = Vec::new(); // (link, ask where to save)
+    let mut download_all = false;
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{} matching file(s) from {} service(s)", matches.len(), services));
+        apply_button_style!(ui, Color32::LIGHT_BLUE);
+        if ui.add_enabled(!app.offline && !matches.is_empty(), egui::Button::new(format!("⬇️ Download all ({})", matches.len())))
+            .on_hover_text("Queue a download for every file listed")
+            .on_disabled_hover_text(if app.offline { OFFLINE_HINT } else { "No matching files" })
+            .clicked()
+        {
+            download_all = true;
+        }
+    });
+    ui.add_space(4.0);
+
+    // Only the rows scrolled into view are laid out
+    let row_height = ui.spacing().interact_size.y;
+    ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .show_rows(ui, row_height, matches.len(), |ui, rows| {
+            for &(request_index, file_index) in &matches[rows] {
+                let req = &app.explore_requests[request_index];
+                let file = &req.advertise_files[file_index];
+                let service = req.from.to_string();
+                ui.horizontal(|ui| {
+                    let label = ui.label(highlighted_name(ui, file, &req.advertise_search[file_index], query));
+                    if let Some(hash) = req.advertise_hashes.get(file.as_str()) {
+                        label.on_hover_text(format!("SHA-256: {}", hash));
+                    }
+                    let short = if service.len() > 24 { format!("{}…", &service[..24]) } else { service.clone() };
+                    ui.label(RichText::new(short).weak()).on_hover_text(&service);
+
+                    let url = file_link(&service, file);
+                    let button = ui.add_enabled(!app.offline, egui::Button::new("⬇️ Download"))
+                        .on_hover_text("Right-click to choose where to save it")
+                        .on_disabled_hover_text(OFFLINE_HINT);
+                    if button.clicked() {
+                        download_urls.push((url.clone(), false));
+                    }
+                    button.context_menu(|ui| {
+                        if ui.button("📂 Save to…").clicked() {
+                            ui.close();
+                            download_urls.push((url, true));
+                        }
+                    });
+                });
+            }
+        });
+
+    if download_all {
+        download_urls = matches.iter()
+            .map(|&(r, f)| {
+                let req = &app.explore_requests[r];
+                (file_link(&req.from, &req.advertise_files[f]), false)
+            })
+            .collect();
+    }
+    let queued = download_urls.len();
+    for (url, ask) in download_urls {
+        let save_dir = if ask {
+            match pick_save_dir(app) {
+                Some(dir) => Some(dir),
+                None => continue,
+            }
+        } else {
+            None
+        };
+        handle_download_request(app, &url, save_dir);
+    }
+    if download_all {
+        app.set_message(format!("Queued {} download(s) from the search results", queued));
+    }
+}
+
+/// Lays out an advertised file name with the part matching `query` highlighted.
+/// `lower` is the lowercased name; when lowercasing changed byte offsets the whole name is highlighted.
+fn highlighted_name(ui: &egui::Ui, name: &str, lower: &str, query: &str) -> egui::text::LayoutJob {
+    let plain = egui::TextFormat {
+        font_id: egui::TextStyle::Body.resolve(ui.style()),
+        color: ui.visuals().text_color(),
+        ..Default::default()
+    };
+    let marked = egui::TextFormat {
+        background: Color32::from_rgba_unmultiplied(255, 215, 0, 90),
+        ..plain.clone()
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    job.append("  - ", 0.0, plain.clone());
+    match lower.find(query).filter(|_| !query.is_empty()) {
+        Some(start) if lower.len() == name.len() && name.is_char_boundary(start) && name.is_char_boundary(start + query.len()) => {
+            let end = start + query.len();
+            job.append(&name[..start], 0.0, plain.clone());
+            job.append(&name[start..end], 0.0, marked);
+            job.append(&name[end..], 0.0, plain);
+        }
+        Some(_) => job.append(name, 0.0, marked),
+        None => job.append(name, 0.0, plain),
+    }
+    job
+}
+
+
+
+/// IDs of the requests a Clear button would remove.
+fn clear_target_ids(app: &FileSharingApp, target: ClearTarget) -> Vec<String> {
+    match target {
+        ClearTarget::CompletedDownloads => app.requested_files.iter()
+            .filter(|r| r.is_completed())
+            .map(|r| r.request_id.clone())
+            .collect(),
+        ClearTarget::FailedDownloads => app.requested_files.iter()
+            .filter(|r| r.is_failed())
+            .map(|r| r.request_id.clone())
+            .collect(),
+        ClearTarget::CompletedExplores => app.explore_requests.iter()
+            .filter(|r| r.completed && !r.failed)
+            .map(|r| r.request_id.clone())
+            .collect(),
+        ClearTarget::FailedExplores => app.explore_requests.iter()
+            .filter(|r| r.failed)
+            .map(|r| r.request_id.clone())
+            .collect(),
+    }
+}
+
+/// Lists the services in the address book with the files they last advertised,
+/// newest first; each can be explored again or forgotten.
+fn render_address_book(app: &mut FileSharingApp, ui: &mut egui::Ui) {
+    if app.address_book.is_empty() {
+        return;
+    }
+
+    let mut services: Vec<(&String, &SavedService)> = app.address_book.iter().collect();
+    services.sort_by_key(|(_, s)| std::cmp::Reverse(s.explored_at));
+
+    let mut explore_addr: Option<String> = None;
+    let mut forget_addr: Option<String> = None;
+    egui::CollapsingHeader::new(format!("📒 Address Book ({})", services.len()))
+        .id_salt("explore_address_book")
+        .show(ui, |ui| {
+            for (addr, service) in services {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        apply_button_style!(ui, Color32::LIGHT_BLUE);
+                        let short = if addr.len() > 24 { format!("{}…", &addr[..24]) } else { addr.clone() };
+                        ui.label(RichText::new(short).strong()).on_hover_text(addr);
+                        ui.label(format!("{} file(s) · {}", service.files.len(), system_time_ago(service.explored_at)));
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("✖ Forget").on_hover_text("Remove this service from the address book").clicked() {
+                                forget_addr = Some(addr.clone());
+                            }
+                            if ui.add_enabled(!app.offline, egui::Button::new("🔎 Explore"))
+                                .on_hover_text("Ask the service for its current list")
+                                .on_disabled_hover_text(OFFLINE_HINT)
+                                .clicked() {
+                                explore_addr = Some(addr.clone());
+                            }
+                        });
+                    });
+                    if let Some(note) = &service.note {
+                        ui.label(RichText::new(note).italics());
+                    }
+                    egui::CollapsingHeader::new("Files")
+                        .id_salt(("address_book_files", addr))
+                        .show(ui, |ui| {
+                            for name in &service.files {
+                                let label = ui.label(name);
+                                if let Some(hash) = service.hashes.get(name) {
+                                    label.on_hover_text(format!("SHA-256: {}", hash));
+                                }
+                            }
+                            for name in &service.collections {
+                                ui.label(format!("📦 {}", name));
+                            }
+                        });
+                });
+            }
+        });
+
+    if let Some(addr) = explore_addr {
+        handle_explore_request(app, &addr);
+    }
+    if let Some(addr) = forget_addr {
+        app.address_book.remove(&addr);
+        app.save_address_book();
+        app.set_message("Removed the service from the address book");
+    }
+}
+
+/// "Clear Completed" and "Clear Failed" buttons; each asks for confirmation first.
+fn clear_buttons(ui: &mut egui::Ui, app: &mut FileSharingApp, completed: ClearTarget, failed: ClearTarget) {
+    for (target, label) in [(completed, "🧹 Clear Completed"), (failed, "🧹 Clear Failed")] {
+        let count = clear_target_ids(app, target).len();
+        if ui.add_enabled(count > 0, egui::Button::new(label))
+            .on_hover_text(format!("Remove the {} {}", count, target.label()))
+            .on_disabled_hover_text(format!("No {}", target.label()))
+            .clicked() {
+            app.pending_clear = Some(target);
+        }
+    }
+}
+
+/// Asks before clearing finished requests; files already saved are not touched.
+pub fn render_clear_confirmation(app: &mut FileSharingApp, ctx: &egui::Context) {
+    let Some(target) = app.pending_clear else { return; };
+    let request_ids = clear_target_ids(app, target);
+    let mut decision: Option<bool> = None;
+    egui::Window::new("🧹 Clear requests")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(format!("Remove {} {}?", request_ids.len(), target.label()));
+            ui.label(RichText::new("Downloaded files are kept.").weak());
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    decision = Some(true);
+                }
+                if ui.button("Cancel").clicked() {
+                    decision = Some(false);
+                }
+            });
+        });
 
-        if let Some(request_id) = remove_request_id {
-            app.explore_requests.retain(|req| req.request_id != request_id);
-            app.expanded_requests.remove(&request_id);
-            app.set_message(format!("Explore request removed: {:?}", request_id));
+    match decision {
+        Some(true) => {
+            app.pending_clear = None;
+            let count = request_ids.len();
+            match target {
+                ClearTarget::CompletedDownloads | ClearTarget::FailedDownloads => {
+                    post_command(ManagerCommand::RemoveDownloads(request_ids));
+                }
+                ClearTarget::CompletedExplores => {
+                    // Keep what the services listed in the address book before the requests go
+                    for request in app.explore_requests.iter().filter(|r| request_ids.contains(&r.request_id)) {
+                        remember_service(&mut app.address_book, request.from.to_string(), SavedService::of(request));
+                    }
+                    app.save_address_book();
+                    post_command(ManagerCommand::RemoveExplores(request_ids));
+                }
+                ClearTarget::FailedExplores => {
+                    post_command(ManagerCommand::RemoveExplores(request_ids));
+                }
+            }
+            app.set_message(format!("Cleared {} {}", count, target.label()));
         }
+        Some(false) => app.pending_clear = None,
+        None => {}
+    }
+}
+
+
+
+/// Renders the Serving tab: files sent to peers since startup, newest first.
+pub fn render_serving_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.label("Show:");
+        ui.radio_value(&mut app.serving_today_only, false, "All")
+            .on_hover_text("Every file served since startup");
+        ui.radio_value(&mut app.serving_today_only, true, "Today")
+            .on_hover_text("Only files served today");
+        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+            apply_button_style!(ui, Color32::LIGHT_BLUE);
+            if ui.add_enabled(!app.serve_events.is_empty(), egui::Button::new("🗑 Clear"))
+                .on_hover_text("Forget the listed events; the total served is kept")
+                .clicked() {
+                app.serve_events.clear();
+                app.set_message("Serving log cleared");
+            }
+        });
+    });
+    ui.separator();
+
+    let now = SystemTime::now();
+    let today = Local::now().date_naive();
+    let events: Vec<&ServeEvent> = app.serve_events.iter()
+        .rev()
+        .filter(|e| !app.serving_today_only || download_matches_filter(e.served_at, now, now, today, true, false))
+        .collect();
+    let listed_bytes: u64 = events.iter().map(|e| e.bytes).sum();
+
+    if events.is_empty() {
+        ui.label(if app.serve_events.is_empty() {
+            "No files served yet. Peers' downloads of your shared files show up here."
+        } else {
+            "No files served today."
+        });
+    } else {
+        // One row per transfer: when, file, peer and size
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                egui::Grid::new("serve_events_table")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        ui.strong("Served");
+                        ui.strong("File");
+                        ui.strong("Peer");
+                        ui.strong("Size");
+                        ui.end_row();
+                        for event in &events {
+                            ui.label(system_time_ago(event.served_at))
+                                .on_hover_text(DateTime::<Local>::from(event.served_at).format("%Y-%m-%d %H:%M:%S").to_string());
+                            ui.add(egui::Label::new(format!("{} {}", file_type_icon(Path::new(&event.filename)), event.filename)).truncate());
+                            let short = if event.peer.len() > 24 { format!("{}…", &event.peer[..24]) } else { event.peer.clone() };
+                            ui.label(short).on_hover_text(&event.peer);
+                            ui.label(format_size(event.bytes));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    // Footer
+    eframe::egui::TopBottomPanel::bottom("serving_bottom_panel").show(ui.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label(format!("NymShare v{}", VERSION));
+            ui.separator();
+            ui.label(format!("Listed: {} ({})", events.len(), format_size(listed_bytes)));
+            ui.separator();
+            ui.label(format!("Total served: {}", format_size(app.bytes_served)))
+                .on_hover_text("Bytes sent to peers since startup");
+
+            if !app.serving_message.is_empty() && app.show_message() {
+                ui.label(RichText::new(&app.serving_message).color(Color32::BLACK));
+            }
+        });
     });
 }
 
@@ -1124,38 +3122,49 @@ pub fn render_explore_tab(app: &mut FileSharingApp, ui: &mut egui::Ui) {
 
 /// Handles adding a new download request.
 ///
-/// Splits the provided URL into service address and filename, validates it,
-/// prevents duplicates, and pushes a new Requests into the app state.
+/// Splits the provided URL into service address and filenames, validates them,
+/// prevents duplicates, and pushes one request per file into the app state.
+/// Files of a multi-file link share a batch id.
+///
+/// File names that could leave the download directory are refused, each with its own
+/// popup message: empty names and folders, '\', '..', '.', absolute paths and control
+/// characters. '/' is the one separator let through on purpose: files shared from a folder
+/// are named by their path inside it ("photos/2024/a.jpg") and saved in the same folders
+/// under the download directory.
 ///
 /// Arguments:
 /// - app: mutable reference to FileSharingApp
-/// - url: the download URL, in the format service::filename
-pub fn handle_download_request(app: &mut FileSharingApp, url: &str) {
+/// - url: the download URL, in the format service::filename or service::file1,file2,...;
+///   each file optionally suffixed with #<sha256>, with any ',' in a name written as %2C
+/// - save_dir: folder to save these downloads to, or None for the download directory
+pub fn handle_download_request(app: &mut FileSharingApp, url: &str, save_dir: Option<PathBuf>) {
     // Ignore empty input
-    if url.trim().is_empty() {
+    let url = url.trim();
+    if url.is_empty() {
         app.set_popup_message("Please enter a URL");
         return;
     }
 
-    // Split URL into service address and filename
-    let parts: Vec<&str> = url.split("::").collect();
-
-    // Ensure valid format
-    if parts.len() != 2 {
+    // Split URL into service address and file list, each trimmed
+    let Some((service, files)) = url.split_once("::").map(|(s, f)| (s.trim(), f.trim())) else {
         app.set_popup_message("Invalid URL format. Use service::filename");
         return;
+    };
+    if service.is_empty() {
+        app.set_popup_message("Missing service address. Use service::filename");
+        return;
+    }
+    if files.is_empty() {
+        app.set_popup_message("Missing file name. Use service::filename");
+        return;
+    }
+    if files.contains("::") {
+        app.set_popup_message("File names cannot contain '::'");
+        return;
     }
-
-    // Service address
-    let service_addr = parts[0].to_string();
-    // Requested filename
-    let filename = parts[1].to_string();
-
-    // Generate unique request ID
-    let request_id = Uuid::new_v4().to_string();
 
     // Convert service address to SockAddr
-    let sock_addr = SockAddr::from(service_addr.as_str());
+    let sock_addr = SockAddr::from(service);
 
     // Check if sock_addr is valid
     if sock_addr.is_null() {
@@ -1163,21 +3172,383 @@ pub fn handle_download_request(app: &mut FileSharingApp, url: &str) {
         return;
     }
 
+    // Comma separated files, with commas in names escaped; a file listed twice is requested once
+    let mut seen = HashSet::new();
+    let targets: Vec<String> = files
+        .split(',')
+        .map(|t| t.trim().replace(LINK_COMMA, ","))
+        .filter(|t| !t.is_empty() && seen.insert(t.clone()))
+        .collect();
+    if targets.is_empty() {
+        app.set_popup_message("Invalid URL format. Use service::filename");
+        return;
+    }
 
-    // Check for duplicate requests
-    let already_requested = app.requested_files.iter().any(|r| {
-        r.filename == filename && r.from == sock_addr
-    });
+    // Validate every file before adding any, so a bad entry does not leave half a batch queued
+    let mut files = Vec::with_capacity(targets.len());
+    for target in &targets {
+        match parse_link_file(target) {
+            Ok(file) => files.push(file),
+            Err(msg) if targets.len() == 1 => {
+                app.set_popup_message(msg);
+                return;
+            }
+            Err(msg) => {
+                app.set_popup_message(format!("'{}': {}", target, msg));
+                return;
+            }
+        }
+    }
 
-    if already_requested {
-        app.set_message(format!("Download request for '{}' from this service already exists", filename));
+    if files.len() == 1 {
+        let file = files.remove(0);
+        let (_, message) = add_link_file(app, &sock_addr, file, save_dir, None);
+        app.set_message(message);
         return;
     }
 
+    let batch_id = Uuid::new_v4().to_string();
+    let total = files.len();
+    let mut added = 0;
+    for file in files {
+        if add_link_file(app, &sock_addr, file, save_dir.clone(), Some(batch_id.clone())).0 {
+            added += 1;
+        }
+    }
+    if added == total {
+        app.set_message(format!("Download batch added: {} files", total));
+    } else {
+        app.set_message(format!("Download batch added: {} of {} files; the rest were already requested", added, total));
+    }
+}
+
+/// One file of a download link, validated.
+struct LinkFile {
+    filename: String,                 // Requested name, or the hash as a placeholder for #<sha256> links
+    content_hash: Option<String>,     // Set for service::#<sha256> links
+    expected_hash: Option<String>,    // Hash the file must have once received
+    params: LinkParams,               // Range and advisory limits from the query string
+}
+
+/// Parses one file entry of a download link: filename or #<sha256>,
+/// optionally followed by ?range=START-END (inclusive, END may be omitted) and advisory exp=/max= limits.
+fn parse_link_file(target: &str) -> Result<LinkFile, &'static str> {
+    let (mut filename, params) = match split_link_query(target) {
+        (name, Some(query)) => (name.to_string(), parse_link_query(query)?),
+        (name, None) => (name.to_string(), LinkParams::default()),
+    };
+    let range = params.range;
+
+    let content_hash = filename.strip_prefix('#').map(|h| h.to_lowercase());
+    if let Some(hash) = &content_hash {
+        if !is_sha256_hex(hash) {
+            return Err("Invalid content hash. Use service::#<sha256>");
+        }
+        if range.is_some() {
+            return Err("Ranges cannot be combined with content hash links");
+        }
+        // Placeholder name until the service tells us the real one
+        filename = hash.clone();
+    }
+
+    // A filename#<sha256> link publishes the hash the file is expected to have;
+    // any other '#' is taken as part of the file name
+    let mut expected_hash = content_hash.clone();
+    if content_hash.is_none()
+        && let Some((name, hash)) = filename.rsplit_once('#').filter(|(_, h)| is_sha256_hex(h)) {
+        if range.is_some() {
+            return Err("Ranges cannot be combined with a file hash");
+        }
+        expected_hash = Some(hash.to_lowercase());
+        filename = name.to_string();
+    }
+
+    check_link_file_name(&filename, params.folder)?;
+
+    Ok(LinkFile { filename, content_hash, expected_hash, params })
+}
+
+/// Checks a requested file name before it is joined onto the download directory.
+/// Files from shared folders are named by their relative path ("photos/2024/a.jpg"), so '/'
+/// separates folders in folder links and is refused in any other; anything that could leave
+/// the download directory is refused.
+fn check_link_file_name(name: &str, folder: bool) -> Result<(), &'static str> {
+    if name.trim().is_empty() {
+        return Err("Missing file name. Use service::filename");
+    }
+    if name.contains('\\') {
+        return Err("File names cannot contain '\\'");
+    }
+    if name.starts_with('/') {
+        return Err("File names cannot be absolute paths");
+    }
+    if name.split('/').any(|part| part == "..") {
+        return Err("File names cannot contain '..'");
+    }
+    if name.split('/').any(|part| part.is_empty() || part == ".") {
+        return Err("File names cannot contain empty or '.' folders");
+    }
+    if name.chars().any(char::is_control) {
+        return Err("File names cannot contain control characters");
+    }
+    if !folder && name.contains('/') {
+        return Err("File names cannot contain '/'; links to files in shared folders end in ?folder=1");
+    }
+    // Whatever else this platform would not take as a plain relative path (drive prefixes, ...)
+    if relative_download_path(name).is_none() {
+        return Err("Invalid file name in link");
+    }
+    Ok(())
+}
+
+/// Queues a download of one link file, unless it is already requested from the service,
+/// in which case the duplicate policy applies. Returns whether a new request was queued,
+/// and the message to show.
+fn add_link_file(
+    app: &mut FileSharingApp,
+    sock_addr: &SockAddr,
+    file: LinkFile,
+    save_dir: Option<PathBuf>,
+    batch_id: Option<String>,
+) -> (bool, String) {
+    let LinkFile { filename, content_hash, expected_hash, params } = file;
+    let range = params.range;
+
+    // Check for duplicate requests
+    if let Some(existing) = app.requested_files.iter().find(|r| {
+        r.filename == filename && r.from == *sock_addr && r.range == range
+    }) {
+        let message = match app.download_duplicate_policy {
+            DuplicatePolicy::Reject => {
+                format!("Download request for '{}' from this service already exists", filename)
+            }
+            DuplicatePolicy::Refresh => {
+                post_command(ManagerCommand::RefreshDownload(existing.request_id.clone()));
+                format!("Download request refreshed: {}", filename)
+            }
+        };
+        return (false, message);
+    }
+
     // Create and push new request
-    let mut request = DownLoadRequest::new(sock_addr, filename.clone(), request_id);
-    app.requested_files.push(request);
-    app.set_message(format!("Download request added: {}", filename));
+    let mut request = DownLoadRequest::new(sock_addr.clone(), filename.clone(), Uuid::new_v4().to_string());
+    request.expected_hash = expected_hash;
+    request.content_hash = content_hash;
+    request.range = range;
+    request.advertised_expiry = params.expires_at;
+    request.advertised_downloads_left = params.downloads_left;
+    request.save_dir = save_dir;
+    request.batch_id = batch_id;
+    post_command(ManagerCommand::AddDownload(request));
+    (true, format!("Download request added: {}", filename))
+}
+
+/// Asks for a folder to save downloads to, accepting it only if files can be written there.
+fn pick_save_dir(app: &mut FileSharingApp) -> Option<PathBuf> {
+    let dir = rfd::FileDialog::new().set_directory(&app.download_dir).pick_folder()?;
+    match check_writable_dir(&dir) {
+        Ok(()) => Some(absolute_dir(&dir)),
+        Err(e) => {
+            app.set_popup_message(format!("Cannot save to {}: {}", dir.display(), e));
+            None
+        }
+    }
+}
+
+/// Query parameters of a download link.
+#[derive(Default)]
+struct LinkParams {
+    range: Option<(u64, u64)>,        // Bytes to fetch, from range=START-END
+    expires_at: Option<SystemTime>,   // Advisory share expiry, from exp=<unix seconds>
+    downloads_left: Option<u32>,      // Advisory downloads left when the link was copied, from max=<n>
+    folder: bool,                     // Name is a path inside a shared folder, from folder=1
+}
+
+/// Query parameter marking a link to a file inside a shared folder, whose name may contain '/'.
+const FOLDER_LINK_PARAM: &str = "folder=1";
+
+/// How a ',' in a file name is written in a link, where ',' separates the files.
+/// Wire names escape '%' as %25 (see shareable::wire_name), so none contains this itself.
+const LINK_COMMA: &str = "%2C";
+
+/// Escapes a listed file name for a link, so a ',' in it is not read as a separator.
+fn escape_link_name(name: &str) -> String {
+    name.replace(',', LINK_COMMA)
+}
+
+/// Builds the download link for a file a service listed. Names from shared folders contain
+/// '/', which plain links refuse, so their links are marked as folder links.
+fn file_link(service: impl std::fmt::Display, name: &str) -> String {
+    if name.contains('/') {
+        format!("{}::{}?{}", service, escape_link_name(name), FOLDER_LINK_PARAM)
+    } else {
+        format!("{}::{}", service, escape_link_name(name))
+    }
+}
+
+/// Builds the link Copy Link puts on the clipboard for a file we share: its name, the hash
+/// when `include_hash` is set and the hash is known, and the advisory expiry and download
+/// limit. Files from shared folders are marked as folder links.
+pub fn share_link(service: &str, file: &Shareable, include_hash: bool) -> String {
+    let mut link = format!("{}::{}", service, escape_link_name(&file.file_name().unwrap_or_default()));
+    // Publish the hash with the link so downloaders can verify what they get
+    if include_hash
+        && let Some(hash) = file.known_hash() {
+        link.push('#');
+        link.push_str(hash);
+    }
+    // Advisory limits, shown to the downloader before they request
+    let mut params = Vec::new();
+    if let Some(secs) = file.expires_at.and_then(|t| t.duration_since(UNIX_EPOCH).ok()) {
+        params.push(format!("exp={}", secs.as_secs()));
+    }
+    if let Some(left) = file.remaining_downloads() {
+        params.push(format!("max={}", left));
+    }
+    if file.relative_path.is_some() {
+        params.push(FOLDER_LINK_PARAM.to_string());
+    }
+    if !params.is_empty() {
+        link.push('?');
+        link.push_str(&params.join("&"));
+    }
+    link
+}
+
+/// Asks the service in a download link whether it serves the file, for the preview
+/// shown under the URL input. The answer arrives as an AppUpdate.
+fn start_link_preview(app: &mut FileSharingApp, link: &str) {
+    let Some((service, target)) = link.split_once("::").map(|(s, t)| (s.trim(), t.trim())) else {
+        app.set_popup_message("Invalid URL format. Use service::filename");
+        return;
+    };
+    let service_addr = SockAddr::from(service);
+    if service_addr.is_null() {
+        app.set_popup_message("Invalid service address");
+        return;
+    }
+    let name = split_link_query(target).0.replace(LINK_COMMA, ",");
+    if name.is_empty() {
+        app.set_popup_message("Missing file name. Use service::filename");
+        return;
+    }
+
+    app.link_preview = Some(LinkPreview { link: link.to_string(), result: None });
+    let backoff = app.backoff.clone();
+    let link = link.to_string();
+    tokio::spawn(async move {
+        let result = file_status(service_addr, name, &backoff).await;
+        post_update(AppUpdate::LinkPreviewed(link, result));
+    });
+}
+
+/// Shows the last link preview: what the service said about the file, with a button
+/// to download it.
+fn render_link_preview(app: &mut FileSharingApp, ui: &mut egui::Ui) {
+    let Some(preview) = app.link_preview.clone() else { return; };
+    let mut download = false;
+    let mut dismiss = false;
+
+    ui.horizontal(|ui| {
+        apply_button_style!(ui, Color32::LIGHT_BLUE);
+        match &preview.result {
+            None => {
+                ui.spinner();
+                ui.label("Asking the service about this file…");
+            }
+            Some(Ok(status)) => {
+                ui.label(RichText::new(format!("✔ {}", status.name)).color(Color32::from_rgb(0, 150, 0)))
+                    .on_hover_text(&preview.link);
+                ui.label(status.size.map_or_else(|| "size unknown".to_string(), format_size));
+                match &status.hash {
+                    Some(hash) => {
+                        ui.label(format!("SHA-256: {}…", &hash[..12])).on_hover_text(hash);
+                        if ui.small_button("📋").on_hover_text("Copy the hash").clicked() {
+                            ui.ctx().copy_text(hash.clone());
+                        }
+                    }
+                    None => {
+                        ui.label(RichText::new("hash unknown").weak());
+                    }
+                }
+                download = ui.add_enabled(!app.offline, egui::Button::new("🔽 Download"))
+                    .on_disabled_hover_text(OFFLINE_HINT)
+                    .clicked();
+            }
+            Some(Err(reason)) => {
+                ui.label(RichText::new(format!("✖ Cannot download: {}", reason)).color(Color32::from_rgb(200, 60, 60)))
+                    .on_hover_text(&preview.link);
+            }
+        }
+        dismiss = ui.small_button("❌").on_hover_text("Dismiss the preview").clicked();
+    });
+
+    if download {
+        if app.download_url.trim() == preview.link {
+            app.download_url.clear();
+        }
+        handle_download_request(app, &preview.link, None);
+    }
+    if download || dismiss {
+        app.link_preview = None;
+    }
+}
+
+/// Splits "name?key=value&..." at the last '?' followed only by key=value pairs,
+/// so a '?' that is part of a file name is left alone.
+fn split_link_query(target: &str) -> (&str, Option<&str>) {
+    match target.rsplit_once('?') {
+        Some((name, query)) if query.split('&').all(|pair| pair.contains('=')) => (name, Some(query)),
+        _ => (target, None),
+    }
+}
+
+/// Parses a link query string. Unknown parameters and malformed advisory values are ignored;
+/// a malformed range is an error since it changes what is fetched.
+fn parse_link_query(query: &str) -> Result<LinkParams, &'static str> {
+    let mut params = LinkParams::default();
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match key {
+            "range" => {
+                params.range = Some(parse_range(value).ok_or("Invalid range. Use ?range=START-END or ?range=START-")?);
+            }
+            "exp" => {
+                params.expires_at = value.parse().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            }
+            "max" => params.downloads_left = value.parse().ok(),
+            "folder" => params.folder = value == "1",
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+/// Describes the advisory limits carried by a link, e.g. "expires in 2h 5m · 3 download(s) left".
+/// None when the link carries none.
+fn describe_link_limits(expires_at: Option<SystemTime>, downloads_left: Option<u32>) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(deadline) = expires_at {
+        parts.push(match time_left(deadline) {
+            Some(left) => format!("expires in {}", left),
+            None => "has expired".to_string(),
+        });
+    }
+    if let Some(left) = downloads_left {
+        parts.push(format!("{} download(s) left", left));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Parses an HTTP-Range style "START-END" (inclusive) or "START-" spec into
+/// (start, end exclusive), using u64::MAX for an open end.
+fn parse_range(spec: &str) -> Option<(u64, u64)> {
+    let (start, end) = spec.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => u64::MAX,
+        end => end.parse::<u64>().ok()?.checked_add(1)?,
+    };
+    (start < end).then_some((start, end))
 }
 
 
@@ -1211,16 +3582,367 @@ pub fn handle_explore_request(app: &mut FileSharingApp, url: &str) {
     let request_id = Uuid::new_v4().to_string();
 
     // Check for duplicate requests
-    let already_requested = app.explore_requests.iter().any(|r| r.from == sock_addr);
-
-    if already_requested {
-        app.set_message("Explore request for this address already exists".to_string());
+    let policy = app.explore_duplicate_policy;
+    if let Some(existing) = app.explore_requests.iter().find(|r| r.from == sock_addr) {
+        match policy {
+            DuplicatePolicy::Reject => {
+                app.set_message("Explore request for this address already exists".to_string());
+            }
+            DuplicatePolicy::Refresh => {
+                post_command(ManagerCommand::RefreshExplore(existing.request_id.clone()));
+                app.set_message(format!("Explore request refreshed: {:?}", sock_addr));
+            }
+        }
         return;
     }
 
-    // Create and push new request
-    let request = ExploreRequest::new(sock_addr.clone(), request_id);
-    app.explore_requests.push(request);
+    // Create the request; with ping_before_explore it is only queued once the service answers
+    let request = ExploreRequest::new(sock_addr.clone(), request_id, app.explore_mode);
+    if app.ping_before_explore {
+        app.set_message(format!("Pinging {:?}...", sock_addr));
+        let backoff = app.backoff.clone();
+        tokio::spawn(async move {
+            if ping(sock_addr.clone(), &backoff).await {
+                post_command(ManagerCommand::AddExplore(request));
+                post_tab_message(Tab::Explore, format!("Explore request added: {:?}", sock_addr));
+            } else {
+                post_tab_message(Tab::Explore, format!(
+                    "Service unreachable: no reply from {:?} after {} attempt(s)",
+                    sock_addr,
+                    backoff.attempts(),
+                ));
+            }
+        });
+        return;
+    }
+    post_command(ManagerCommand::AddExplore(request));
 
     app.set_message(format!("Explore request added: {:?}", sock_addr));
 }
+
+
+/// Renders the peers window: a sortable per-requester transfer summary.
+pub fn render_peers_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if !app.show_peers_window {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("👥 Peers")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            if app.peer_stats.is_empty() {
+                ui.label("No downloads recorded yet.");
+                ui.label("Only Individual requesters are listed; Anonymous requests carry no address.");
+                return;
+            }
+
+            ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                egui::Grid::new("peers_table").striped(true).num_columns(4).show(ui, |ui| {
+                    for (column, title) in [
+                        (PeerSort::Address, "Address"),
+                        (PeerSort::Downloads, "Files"),
+                        (PeerSort::Bytes, "Bytes"),
+                        (PeerSort::LastSeen, "Last seen"),
+                    ] {
+                        let arrow = match (app.peer_sort == column, app.peer_sort_descending) {
+                            (true, true) => " ⏷",
+                            (true, false) => " ⏶",
+                            (false, _) => "",
+                        };
+                        if ui.selectable_label(app.peer_sort == column, format!("{}{}", title, arrow))
+                            .on_hover_text("Sort by this column")
+                            .clicked() {
+                            if app.peer_sort == column {
+                                app.peer_sort_descending = !app.peer_sort_descending;
+                            } else {
+                                app.peer_sort = column;
+                                app.peer_sort_descending = true;
+                            }
+                        }
+                    }
+                    ui.end_row();
+
+                    for (addr, stats) in sorted_peers(&app.peer_stats, app.peer_sort, app.peer_sort_descending) {
+                        let short = if addr.len() > 24 { format!("{}…", &addr[..24]) } else { addr.clone() };
+                        ui.label(short).on_hover_text(addr);
+                        ui.label(stats.downloads.to_string());
+                        ui.label(format_size(stats.bytes));
+                        ui.label(system_time_ago(stats.last_seen));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    if !open {
+        app.show_peers_window = false;
+    }
+}
+
+
+/// Renders the network settings window; the managers pick up changes as they are made.
+pub fn render_network_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if !app.show_network_window {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("📡 Network")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("Longer intervals suit slow mixnet routes; shorter ones answer faster on a LAN.");
+            ui.add_space(6.0);
+
+            let config = &mut app.network_config;
+            egui::Grid::new("network_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Serving poll:");
+                ui.add(egui::DragValue::new(&mut config.serve_poll_ms).range(MIN_POLL_MS..=MAX_POLL_MS).speed(10).suffix(" ms"))
+                    .on_hover_text("How often incoming requests are read");
+                ui.end_row();
+
+                ui.label("Send poll:");
+                ui.add(egui::DragValue::new(&mut config.send_poll_ms).range(MIN_POLL_MS..=MAX_POLL_MS).speed(10).suffix(" ms"))
+                    .on_hover_text("How often queued download and explore requests are sent");
+                ui.end_row();
+
+                ui.label("Process poll:");
+                ui.add(egui::DragValue::new(&mut config.process_poll_ms).range(MIN_POLL_MS..=MAX_POLL_MS).speed(10).suffix(" ms"))
+                    .on_hover_text("How often replies from services are read");
+                ui.end_row();
+            });
+
+            if ui.button("Reset to defaults").clicked() {
+                *config = NetworkConfig::default();
+            }
+        });
+
+    netconfig::publish(&app.network_config);
+
+    if !open {
+        app.show_network_window = false;
+    }
+}
+
+
+/// Renders the keyboard shortcuts help, toggled with F1.
+pub fn render_shortcuts_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if !app.show_shortcuts_window {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("⌨ Keyboard Shortcuts")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            let modifier = if cfg!(target_os = "macos") { "Cmd" } else { "Ctrl" };
+            egui::Grid::new("shortcuts").num_columns(2).striped(true).show(ui, |ui| {
+                let shortcuts = [
+                    (format!("{}+1 / 2 / 3 / 4", modifier), "Share / Download / Explore / Serving tab"),
+                    (format!("{}+F", modifier), "Focus the search box (link box in Download)"),
+                    ("Delete".to_string(), "Remove the selected shared file"),
+                    ("Enter".to_string(), "Explore the address typed in Explore"),
+                    ("F1".to_string(), "Show or hide this help"),
+                ];
+                for (keys, action) in shortcuts {
+                    ui.strong(keys);
+                    ui.label(action);
+                    ui.end_row();
+                }
+            });
+        });
+
+    if !open {
+        app.show_shortcuts_window = false;
+    }
+}
+
+
+/// Audit log entries shown per page.
+const AUDIT_PAGE_SIZE: usize = 50;
+
+/// Renders the audit log window: filterable, paginated protocol activity, newest first.
+pub fn render_audit_window(app: &mut FileSharingApp, ctx: &egui::Context) {
+    if !app.show_audit_window {
+        return;
+    }
+
+    let mut open = true;
+    egui::Window::new("📜 Audit Log")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_width(750.0)
+        .show(ctx, |ui| {
+            // Filters; any change goes back to the first page
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("audit_kind")
+                    .selected_text(app.audit_filter.kind.map(|k| k.label()).unwrap_or("All kinds"))
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut app.audit_filter.kind, None, "All kinds").changed();
+                        for kind in AuditKind::ALL {
+                            changed |= ui.selectable_value(&mut app.audit_filter.kind, Some(kind), kind.label()).changed();
+                        }
+                    });
+
+                egui::ComboBox::from_id_salt("audit_range")
+                    .selected_text(app.audit_filter.range.label())
+                    .show_ui(ui, |ui| {
+                        for range in AuditRange::ALL {
+                            changed |= ui.selectable_value(&mut app.audit_filter.range, range, range.label()).changed();
+                        }
+                    });
+
+                changed |= ui.add(egui::TextEdit::singleline(&mut app.audit_filter.peer)
+                    .hint_text("Peer address")
+                    .desired_width(150.0)).changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut app.audit_filter.search)
+                    .hint_text("🔍 Search command or detail")
+                    .desired_width(200.0)).changed();
+            });
+            if changed {
+                app.audit_page = 0;
+            }
+
+            let events = audit::query(&app.audit_filter);
+            let pages = events.len().div_ceil(AUDIT_PAGE_SIZE).max(1);
+            app.audit_page = app.audit_page.min(pages - 1);
+
+            ui.horizontal(|ui| {
+                apply_button_style!(ui, Color32::LIGHT_BLUE);
+                if ui.add_enabled(app.audit_page > 0, egui::Button::new("◀")).clicked() {
+                    app.audit_page -= 1;
+                }
+                ui.label(format!("Page {} of {} ({} events)", app.audit_page + 1, pages, events.len()));
+                if ui.add_enabled(app.audit_page + 1 < pages, egui::Button::new("▶")).clicked() {
+                    app.audit_page += 1;
+                }
+            });
+            ui.separator();
+
+            ScrollArea::vertical().auto_shrink([false; 2]).max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("audit_table").striped(true).num_columns(5).show(ui, |ui| {
+                    ui.strong("Time");
+                    ui.strong("Kind");
+                    ui.strong("Command");
+                    ui.strong("Peer");
+                    ui.strong("Detail");
+                    ui.end_row();
+
+                    for event in events.iter().skip(app.audit_page * AUDIT_PAGE_SIZE).take(AUDIT_PAGE_SIZE) {
+                        let time = DateTime::<Local>::from(event.time).format("%Y-%m-%d %H:%M:%S");
+                        ui.label(time.to_string());
+                        let kind = RichText::new(event.kind.label());
+                        ui.label(match event.kind {
+                            AuditKind::Error | AuditKind::Nack => kind.color(Color32::RED),
+                            _ => kind,
+                        });
+                        ui.label(&event.command);
+                        let short = if event.peer.len() > 16 { format!("{}…", &event.peer[..16]) } else { event.peer.clone() };
+                        ui.label(short).on_hover_text(&event.peer);
+                        ui.label(&event.detail);
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    if !open {
+        app.show_audit_window = false;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::helper::{TestDir, TEST_ADDRESS};
+
+    /// Runs a link through handle_download_request and returns the popup it showed, if any.
+    fn link_popup(link: &str) -> Option<String> {
+        let mut app = FileSharingApp { active_tab: Tab::Download, ..Default::default() };
+        handle_download_request(&mut app, link, None);
+        app.show_download_popup_message().then(|| app.download_popup_message.clone())
+    }
+
+    #[test]
+    fn links_that_leave_the_download_dir_are_refused() {
+        let refused = [
+            ("../../etc/passwd", "File names cannot contain '..'"),
+            ("photos/../../etc/passwd", "File names cannot contain '..'"),
+            ("..\\..\\windows\\system32", "File names cannot contain '\\'"),
+            ("photos\\a.jpg", "File names cannot contain '\\'"),
+            ("/etc/passwd", "File names cannot be absolute paths"),
+            ("photos//a.jpg", "File names cannot contain empty or '.' folders"),
+            ("photos/", "File names cannot contain empty or '.' folders"),
+            ("./a.jpg", "File names cannot contain empty or '.' folders"),
+            ("a\u{7}.jpg", "File names cannot contain control characters"),
+        ];
+        for (name, popup) in refused {
+            let link = format!("{}::{}", TEST_ADDRESS, name);
+            assert_eq!(link_popup(&link).as_deref(), Some(popup), "{}", link);
+        }
+    }
+
+    #[test]
+    fn plain_links_with_slashes_are_refused() {
+        assert_eq!(
+            link_popup(&format!("{}::a/b", TEST_ADDRESS)).as_deref(),
+            Some("File names cannot contain '/'; links to files in shared folders end in ?folder=1")
+        );
+        assert!(check_link_file_name("a/b", false).is_err());
+        assert_eq!(file_link(TEST_ADDRESS, "a.jpg"), format!("{}::a.jpg", TEST_ADDRESS));
+    }
+
+    #[test]
+    fn links_with_missing_parts_are_refused() {
+        assert_eq!(link_popup("   ").as_deref(), Some("Please enter a URL"));
+        assert_eq!(link_popup("no-separator").as_deref(), Some("Invalid URL format. Use service::filename"));
+        assert_eq!(link_popup("::a.jpg").as_deref(), Some("Missing service address. Use service::filename"));
+        assert_eq!(link_popup(&format!("{}::   ", TEST_ADDRESS)).as_deref(), Some("Missing file name. Use service::filename"));
+        assert_eq!(link_popup(&format!("{}::a::b", TEST_ADDRESS)).as_deref(), Some("File names cannot contain '::'"));
+        assert_eq!(link_popup("service::../../etc/passwd").as_deref(), Some("Invalid service address"));
+    }
+
+    #[test]
+    fn folder_paths_with_slashes_are_accepted() {
+        // '/' separates the folders of a shared folder, in links marked as folder links
+        assert_eq!(link_popup(&format!("  {}  ::  photos/2024/a.jpg?folder=1  ", TEST_ADDRESS)), None);
+        assert_eq!(file_link(TEST_ADDRESS, "photos/2024/a.jpg"), format!("{}::photos/2024/a.jpg?folder=1", TEST_ADDRESS));
+        assert!(check_link_file_name("photos/2024/a.jpg", true).is_ok());
+        assert_eq!(relative_download_path("photos/2024/a.jpg"), Some(PathBuf::from("photos").join("2024").join("a.jpg")));
+    }
+
+    #[test]
+    fn commas_in_names_survive_a_copied_link() {
+        let link = file_link(TEST_ADDRESS, "a,b.pdf");
+        assert_eq!(link, format!("{}::a%2Cb.pdf", TEST_ADDRESS));
+
+        let mut app = FileSharingApp { active_tab: Tab::Download, ..Default::default() };
+        handle_download_request(&mut app, &link, None);
+        assert_eq!(app.download_message, "Download request added: a,b.pdf");
+
+        // A name listed with an escaped '%' is requested as it is listed
+        handle_download_request(&mut app, &file_link(TEST_ADDRESS, "100%252C.txt"), None);
+        assert_eq!(app.download_message, "Download request added: 100%252C.txt");
+    }
+
+    // Headless mode prints the links Copy Link would copy
+    #[test]
+    fn share_links_escape_commas() {
+        let dir = TestDir::new("share-link");
+        fs::write(dir.join("a,b.txt"), b"a").unwrap();
+        let file = Shareable::new(dir.join("a,b.txt")).unwrap();
+        assert_eq!(share_link(TEST_ADDRESS, &file, true), file_link(TEST_ADDRESS, "a,b.txt"));
+    }
+}
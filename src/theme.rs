@@ -19,17 +19,52 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use eframe::egui::{self, Context, Visuals};
+use serde::{Deserialize, Serialize};
+
 // UI theme settings for the application
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Theme {
-    Light, // Light mode visuals
-    Dark,  // Dark mode visuals
+    Light,  // Light mode visuals
+    Dark,   // Dark mode visuals
+    System, // Follow the OS preference
+}
+
+impl Theme {
+    // Theme the top panel button switches to: Light -> Dark -> System -> Light
+    pub fn next(self) -> Theme {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::System,
+            Theme::System => Theme::Light,
+        }
+    }
+
+    // Button label offering this theme
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Light => "☀️ Light Mode",
+            Theme::Dark => "🌙 Dark Mode",
+            Theme::System => "💻 System Theme",
+        }
+    }
+
+    // Visuals to draw with; System uses the preference eframe reads from the OS, dark if unknown
+    pub fn visuals(self, ctx: &Context) -> Visuals {
+        let dark = match self {
+            Theme::Light => false,
+            Theme::Dark => true,
+            Theme::System => ctx.system_theme() != Some(egui::Theme::Light),
+        };
+        if dark { Visuals::dark() } else { Visuals::light() }
+    }
 }
 
 // Tabs used for navigation in the main app
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Tab {
     Share,    // Sharing tab
     Download, // Download tab
     Explore, // Explore files tab 
+    Serving,  // Files served to peers
 }
\ No newline at end of file
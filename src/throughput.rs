@@ -0,0 +1,98 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// Standard library
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+
+/// Number of one-second samples kept (the last minute).
+pub const HISTORY_SECONDS: usize = 60;
+
+/// Bytes sent and received per second over the last minute, across both managers.
+pub struct Throughput {
+    /// Completed one-second samples as (sent, received), oldest first.
+    samples: VecDeque<(u64, u64)>,
+
+    /// Bytes sent during the current second.
+    sent: u64,
+
+    /// Bytes received during the current second.
+    received: u64,
+
+    /// Start of the current second.
+    second_start: Instant,
+}
+
+impl Throughput {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::from(vec![(0, 0); HISTORY_SECONDS]),
+            sent: 0,
+            received: 0,
+            second_start: Instant::now(),
+        }
+    }
+
+    /// Closes every second that has elapsed, pushing zero samples for idle seconds.
+    fn roll(&mut self) {
+        let elapsed = self.second_start.elapsed().as_secs();
+        if elapsed == 0 {
+            return;
+        }
+        for i in 0..elapsed.min(HISTORY_SECONDS as u64) {
+            let sample = if i == 0 { (self.sent, self.received) } else { (0, 0) };
+            self.samples.push_back(sample);
+        }
+        while self.samples.len() > HISTORY_SECONDS {
+            self.samples.pop_front();
+        }
+        self.sent = 0;
+        self.received = 0;
+        self.second_start += Duration::from_secs(elapsed);
+    }
+}
+
+/// Global throughput counters, updated by both managers and read by the UI.
+static THROUGHPUT: LazyLock<Mutex<Throughput>> = LazyLock::new(|| Mutex::new(Throughput::new()));
+
+/// Records bytes sent over either socket.
+pub fn record_sent(bytes: u64) {
+    let mut t = THROUGHPUT.lock().unwrap_or_else(|e| e.into_inner());
+    t.roll();
+    t.sent = t.sent.saturating_add(bytes);
+}
+
+/// Records bytes received on either socket.
+pub fn record_received(bytes: u64) {
+    let mut t = THROUGHPUT.lock().unwrap_or_else(|e| e.into_inner());
+    t.roll();
+    t.received = t.received.saturating_add(bytes);
+}
+
+/// Returns the last minute of (sent, received) bytes per second, oldest first.
+pub fn history() -> Vec<(u64, u64)> {
+    let mut t = THROUGHPUT.lock().unwrap_or_else(|e| e.into_inner());
+    t.roll();
+    t.samples.iter().copied().collect()
+}
@@ -0,0 +1,456 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+// External crates
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+// Standard library
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+// Local
+use crate::helper;
+use crate::storage::{self, CONFIG_DIR};
+
+
+/// File in CONFIG_DIR holding the salt and passphrase check; its presence turns encryption on.
+pub const VAULT_FILE: &str = "vault.json";
+
+/// Vault format version written by this build.
+const VAULT_VERSION: u32 = 1;
+
+/// Marks a whole file sealed by the vault.
+const SEALED_MAGIC: &[u8] = b"NYMSHARE-SEALED1";
+
+/// Marks a sealed line in an append-only file such as the audit log.
+const SEALED_LINE_PREFIX: &str = "sealed:";
+
+/// Sealed in the vault file so a wrong passphrase is told apart from damaged state.
+const CHECK_TEXT: &[u8] = b"nymshare vault check";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Shortest passphrase accepted when turning encryption on.
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Salt and passphrase check, stored in plain text next to the sealed files.
+#[derive(Serialize, Deserialize)]
+struct VaultHeader {
+    version: u32,
+    salt: String,   // Hex
+    check: String,  // Hex of CHECK_TEXT sealed with the derived key
+}
+
+/// How persisted state is read and written.
+enum State {
+    /// Encryption off; files are plain JSON.
+    Plain,
+    /// Encryption on but no passphrase given; nothing is read or written.
+    Locked,
+    /// Encryption on and unlocked; files are sealed with this cipher.
+    Unlocked(XChaCha20Poly1305),
+}
+
+static STATE: LazyLock<RwLock<State>> = LazyLock::new(|| {
+    RwLock::new(if vault_path().exists() { State::Locked } else { State::Plain })
+});
+
+fn vault_path() -> PathBuf {
+    storage::config_path(VAULT_FILE)
+}
+
+/// Whether saved state is encrypted, locked or not.
+pub fn is_enabled() -> bool {
+    !matches!(*STATE.read().unwrap_or_else(|e| e.into_inner()), State::Plain)
+}
+
+/// Whether saved state is encrypted and the passphrase has not been given;
+/// the app then keeps everything in memory only.
+pub fn is_locked() -> bool {
+    matches!(*STATE.read().unwrap_or_else(|e| e.into_inner()), State::Locked)
+}
+
+/// Unlocks saved state with `passphrase`. A wrong passphrase leaves the vault locked
+/// and touches nothing on disk.
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let cipher = check_passphrase(&vault_path(), passphrase)?;
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = State::Unlocked(cipher);
+    info!("Saved state unlocked");
+    Ok(())
+}
+
+/// Turns encryption on: writes the vault file, then seals every file already in CONFIG_DIR.
+pub fn enable(passphrase: &str) -> Result<(), String> {
+    if is_enabled() {
+        return Err("Saved state is already encrypted".to_string());
+    }
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("Use a passphrase of at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+
+    let cipher = seal_dir(Path::new(CONFIG_DIR), passphrase)?;
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = State::Unlocked(cipher);
+    info!("Saved state encrypted");
+    // The log cannot be sealed: empty it and stop writing it while encryption is on
+    if let Err(e) = helper::empty_log() {
+        warn!("Failed to empty {}: {}", helper::LOG_FILE, e);
+    }
+    helper::sync_log_level();
+    Ok(())
+}
+
+/// Turns encryption off: opens every sealed file in CONFIG_DIR, then removes the vault file.
+pub fn disable() -> Result<(), String> {
+    let cipher = match &*STATE.read().unwrap_or_else(|e| e.into_inner()) {
+        State::Unlocked(cipher) => cipher.clone(),
+        State::Locked => return Err("Unlock saved state first".to_string()),
+        State::Plain => return Err("Saved state is not encrypted".to_string()),
+    };
+    open_dir(Path::new(CONFIG_DIR), &cipher)?;
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = State::Plain;
+    helper::sync_log_level();
+    info!("Saved state decrypted");
+    Ok(())
+}
+
+/// For a forgotten passphrase: deletes the sealed state and starts over unencrypted.
+pub fn start_fresh() -> io::Result<()> {
+    match fs::remove_dir_all(CONFIG_DIR) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    reset();
+    warn!("Encrypted saved state deleted; starting fresh");
+    Ok(())
+}
+
+/// Forgets the vault after CONFIG_DIR was deleted from under it.
+pub fn reset() {
+    *STATE.write().unwrap_or_else(|e| e.into_inner()) = State::Plain;
+    helper::sync_log_level();
+}
+
+/// Seals a whole file before it is written. Returns None while locked: nothing may be written then.
+pub fn seal(data: Vec<u8>) -> Option<Vec<u8>> {
+    match &*STATE.read().unwrap_or_else(|e| e.into_inner()) {
+        State::Plain => Some(data),
+        State::Locked => None,
+        State::Unlocked(cipher) => seal_with(cipher, &data).ok(),
+    }
+}
+
+/// Opens a whole file after it is read. Plain files are passed through once unlocked,
+/// as left by an interrupted [`enable`]. Returns None while locked or if the file cannot be opened.
+pub fn open(data: Vec<u8>) -> Option<Vec<u8>> {
+    let sealed = data.starts_with(SEALED_MAGIC);
+    match &*STATE.read().unwrap_or_else(|e| e.into_inner()) {
+        State::Plain if sealed => {
+            warn!("Ignoring a sealed file while encryption is off");
+            None
+        }
+        State::Plain => Some(data),
+        State::Locked => None,
+        State::Unlocked(cipher) if sealed => open_with(cipher, &data),
+        State::Unlocked(_) => Some(data),
+    }
+}
+
+/// Line counterpart of [`seal`] for append-only files.
+pub fn seal_line(line: String) -> Option<String> {
+    match &*STATE.read().unwrap_or_else(|e| e.into_inner()) {
+        State::Plain => Some(line),
+        State::Locked => None,
+        State::Unlocked(cipher) => seal_line_with(cipher, line.as_bytes()),
+    }
+}
+
+/// Line counterpart of [`open`] for append-only files.
+pub fn open_line(line: &str) -> Option<String> {
+    let sealed = line.strip_prefix(SEALED_LINE_PREFIX);
+    match (&*STATE.read().unwrap_or_else(|e| e.into_inner()), sealed) {
+        (State::Plain, Some(_)) | (State::Locked, _) => None,
+        (State::Plain, None) | (State::Unlocked(_), None) => Some(line.to_string()),
+        (State::Unlocked(cipher), Some(hex)) => open_line_with(cipher, hex),
+    }
+}
+
+/// Cipher for `passphrase` if it matches the vault file at `path`.
+fn check_passphrase(path: &Path, passphrase: &str) -> Result<XChaCha20Poly1305, String> {
+    let data = fs::read(path).map_err(|e| format!("Cannot read {}: {}", VAULT_FILE, e))?;
+    let header: VaultHeader = serde_json::from_slice(&data).map_err(|e| format!("{} is damaged: {}", VAULT_FILE, e))?;
+    if header.version != VAULT_VERSION {
+        return Err(format!("Unsupported vault version {}", header.version));
+    }
+    let salt = from_hex(&header.salt).ok_or_else(|| format!("{} is damaged", VAULT_FILE))?;
+    let check = from_hex(&header.check).ok_or_else(|| format!("{} is damaged", VAULT_FILE))?;
+
+    let cipher = derive_cipher(passphrase, &salt)?;
+    if open_with(&cipher, &check).as_deref() != Some(CHECK_TEXT) {
+        return Err("Wrong passphrase".to_string());
+    }
+    Ok(cipher)
+}
+
+/// Writes a vault file for `passphrase` into `dir`, then seals every file already there.
+fn seal_dir(dir: &Path, passphrase: &str) -> Result<XChaCha20Poly1305, String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = derive_cipher(passphrase, &salt)?;
+    let header = VaultHeader {
+        version: VAULT_VERSION,
+        salt: to_hex(&salt),
+        check: to_hex(&seal_with(&cipher, CHECK_TEXT)?),
+    };
+    let data = serde_json::to_vec_pretty(&header).map_err(|e| e.to_string())?;
+
+    // The vault file goes first: if sealing stops halfway, unlocking still reads the plain leftovers
+    storage::write_atomic(&dir.join(VAULT_FILE), &data).map_err(|e| format!("Cannot write {}: {}", VAULT_FILE, e))?;
+    convert_files(dir, |data| Some(data.to_vec()), |data| seal_with(&cipher, data).ok())?;
+    Ok(cipher)
+}
+
+/// Opens every sealed file in `dir`, then removes its vault file.
+fn open_dir(dir: &Path, cipher: &XChaCha20Poly1305) -> Result<(), String> {
+    convert_files(
+        dir,
+        |data| if data.starts_with(SEALED_MAGIC) { open_with(cipher, data) } else { Some(data.to_vec()) },
+        |data| Some(data.to_vec()),
+    )?;
+    fs::remove_file(dir.join(VAULT_FILE)).map_err(|e| format!("Cannot remove {}: {}", VAULT_FILE, e))
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, String> {
+    // Wiped on drop so the raw key does not linger on the stack
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("Cannot derive key: {}", e))?;
+    Ok(XChaCha20Poly1305::new(key.as_ref().into()))
+}
+
+/// SEALED_MAGIC, a random nonce, then the ciphertext.
+fn seal_with(cipher: &XChaCha20Poly1305, data: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, data).map_err(|_| "Encryption failed".to_string())?;
+    let mut sealed = Vec::with_capacity(SEALED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open_with(cipher: &XChaCha20Poly1305, data: &[u8]) -> Option<Vec<u8>> {
+    let body = data.strip_prefix(SEALED_MAGIC)?;
+    if body.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    cipher.decrypt(XNonce::from_slice(nonce), ciphertext).ok()
+}
+
+fn seal_line_with(cipher: &XChaCha20Poly1305, line: &[u8]) -> Option<String> {
+    let sealed = seal_with(cipher, line).ok()?;
+    Some(format!("{}{}", SEALED_LINE_PREFIX, to_hex(&sealed[SEALED_MAGIC.len()..])))
+}
+
+fn open_line_with(cipher: &XChaCha20Poly1305, hex: &str) -> Option<String> {
+    let mut sealed = SEALED_MAGIC.to_vec();
+    sealed.extend(from_hex(hex)?);
+    String::from_utf8(open_with(cipher, &sealed)?).ok()
+}
+
+/// Rewrites every state file in `dir`: `read` turns its current contents into plain
+/// bytes and `write` into what goes to disk. `.jsonl` files are rewritten line by line.
+/// Files or lines that cannot be read are dropped with a warning rather than failing halfway.
+fn convert_files(
+    dir: &Path,
+    read: impl Fn(&[u8]) -> Option<Vec<u8>>,
+    write: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Result<(), String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Cannot read {}: {}", dir.display(), e)),
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if !path.is_file() || name == VAULT_FILE || name.ends_with(".tmp") {
+            continue;
+        }
+        let data = fs::read(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let converted = if is_line_file(&path) {
+            Some(convert_lines(&data, &read, &write))
+        } else {
+            read(&data).and_then(|plain| write(&plain))
+        };
+        match converted {
+            Some(converted) => storage::write_atomic(&path, &converted)
+                .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?,
+            None => {
+                warn!("Dropping unreadable {}", path.display());
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_line_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "jsonl")
+}
+
+/// Converts each line of an append-only file; lines are sealed one by one.
+/// Lines that cannot be converted are dropped; the rest of the file is kept.
+fn convert_lines(
+    data: &[u8],
+    read: &impl Fn(&[u8]) -> Option<Vec<u8>>,
+    write: &impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Vec<u8> {
+    let text = String::from_utf8_lossy(data);
+    let mut out = Vec::with_capacity(data.len());
+    for line in text.lines().filter(|l| !l.is_empty()) {
+        // Lines go through the whole-file helpers with their prefix swapped for SEALED_MAGIC
+        let current = match line.strip_prefix(SEALED_LINE_PREFIX) {
+            Some(hex) => match from_hex(hex) {
+                Some(body) => [SEALED_MAGIC, &body[..]].concat(),
+                None => {
+                    warn!("Dropping a damaged line");
+                    continue;
+                }
+            },
+            None => line.as_bytes().to_vec(),
+        };
+        let Some(plain) = read(&current) else {
+            warn!("Dropping an unreadable line");
+            continue;
+        };
+        let Some(converted) = write(&plain) else {
+            warn!("Dropping a line that could not be converted");
+            continue;
+        };
+        match converted.strip_prefix(SEALED_MAGIC) {
+            Some(body) => out.extend(format!("{}{}", SEALED_LINE_PREFIX, to_hex(body)).into_bytes()),
+            None => out.extend(converted),
+        }
+        out.push(b'\n');
+    }
+    out
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helper::TestDir;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    fn test_cipher() -> XChaCha20Poly1305 {
+        derive_cipher(PASSPHRASE, &[7u8; SALT_LEN]).unwrap()
+    }
+
+    #[test]
+    fn sealed_files_and_lines_open_again() {
+        let cipher = test_cipher();
+        let sealed = seal_with(&cipher, b"{\"shares\":[]}").unwrap();
+        assert!(sealed.starts_with(SEALED_MAGIC));
+        assert_eq!(open_with(&cipher, &sealed).as_deref(), Some(&b"{\"shares\":[]}"[..]));
+
+        let line = seal_line_with(&cipher, b"{\"kind\":\"Ack\"}").unwrap();
+        let hex = line.strip_prefix(SEALED_LINE_PREFIX).unwrap();
+        assert_eq!(open_line_with(&cipher, hex).as_deref(), Some("{\"kind\":\"Ack\"}"));
+
+        // Another key opens neither
+        let other = derive_cipher("another passphrase", &[7u8; SALT_LEN]).unwrap();
+        assert_eq!(open_with(&other, &sealed), None);
+        assert_eq!(open_line_with(&other, hex), None);
+    }
+
+    #[test]
+    fn wrong_passphrase_does_not_unlock() {
+        let dir = TestDir::new("vault-passphrase");
+        fs::write(dir.join("shares.json"), b"[]").unwrap();
+        seal_dir(&dir, PASSPHRASE).unwrap();
+
+        assert_eq!(check_passphrase(&dir.join(VAULT_FILE), "not the passphrase").err().as_deref(), Some("Wrong passphrase"));
+        assert!(fs::read(dir.join("shares.json")).unwrap().starts_with(SEALED_MAGIC));
+        assert!(check_passphrase(&dir.join(VAULT_FILE), PASSPHRASE).is_ok());
+    }
+
+    #[test]
+    fn enabling_then_disabling_restores_plain_files() {
+        let dir = TestDir::new("vault-round-trip");
+        fs::write(dir.join("shares.json"), b"[\"a.txt\"]").unwrap();
+        fs::write(dir.join("audit.jsonl"), b"{\"n\":1}\n{\"n\":2}\n").unwrap();
+
+        let cipher = seal_dir(&dir, PASSPHRASE).unwrap();
+        assert!(fs::read(dir.join("shares.json")).unwrap().starts_with(SEALED_MAGIC));
+        let audit = fs::read_to_string(dir.join("audit.jsonl")).unwrap();
+        assert!(audit.lines().all(|l| l.starts_with(SEALED_LINE_PREFIX)), "{}", audit);
+
+        open_dir(&dir, &cipher).unwrap();
+        assert_eq!(fs::read(dir.join("shares.json")).unwrap(), b"[\"a.txt\"]");
+        assert_eq!(fs::read_to_string(dir.join("audit.jsonl")).unwrap(), "{\"n\":1}\n{\"n\":2}\n");
+        assert!(!dir.join(VAULT_FILE).exists());
+    }
+
+    #[test]
+    fn a_damaged_line_drops_only_that_line() {
+        let dir = TestDir::new("vault-damaged-line");
+        fs::write(dir.join("audit.jsonl"), b"{\"n\":1}\n{\"n\":2}\n").unwrap();
+        let cipher = seal_dir(&dir, PASSPHRASE).unwrap();
+
+        // One line with a bad hex body and one that does not open
+        let mut audit = fs::read_to_string(dir.join("audit.jsonl")).unwrap();
+        audit.push_str(&format!("{}zz\n", SEALED_LINE_PREFIX));
+        audit.push_str(&format!("{}{}\n", SEALED_LINE_PREFIX, "00".repeat(NONCE_LEN + 20)));
+        fs::write(dir.join("audit.jsonl"), audit).unwrap();
+
+        open_dir(&dir, &cipher).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("audit.jsonl")).unwrap(), "{\"n\":1}\n{\"n\":2}\n");
+    }
+}
@@ -0,0 +1,138 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+
+
+// External crates
+use log::{info, warn};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+
+// Standard library
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+// Local
+use crate::app::{post_update, AppUpdate};
+use crate::shareable::{HashCache, Shareable};
+
+
+/// How long a file must stay untouched before its changes are acted on,
+/// so a save written in several steps is handled once.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// The running watcher and the directories it watches.
+/// Parent directories are watched rather than the files, so files replaced by
+/// an editor's save-and-rename are still seen.
+struct Watch {
+    debouncer: Debouncer<RecommendedWatcher>,
+    dirs: HashSet<PathBuf>,
+}
+
+static WATCH: LazyLock<Mutex<Option<Watch>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Shared files whose changes are reported; kept apart from WATCH so the
+/// event handler never waits on a (un)watch in progress.
+static WATCHED_FILES: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Watches the directories of the shared files, starting the watcher on first use.
+/// Cheap when the share list has not changed, so it is called every frame.
+pub fn sync(files: &[Shareable]) {
+    {
+        let watched = WATCHED_FILES.lock().unwrap_or_else(|e| e.into_inner());
+        if watched.len() == files.len() && files.iter().all(|f| watched.contains(&f.path)) {
+            return;
+        }
+    }
+
+    let mut guard = WATCH.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        match new_debouncer(DEBOUNCE, handle_events) {
+            Ok(debouncer) => *guard = Some(Watch { debouncer, dirs: HashSet::new() }),
+            Err(e) => {
+                warn!("Failed to start watching shared files: {}", e);
+                return;
+            }
+        }
+    }
+    let Some(watch) = guard.as_mut() else { return; };
+
+    let dirs: HashSet<PathBuf> = files.iter()
+        .filter_map(|f| f.path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    for dir in watch.dirs.difference(&dirs) {
+        let _ = watch.debouncer.watcher().unwatch(dir);
+    }
+    for dir in dirs.difference(&watch.dirs) {
+        if let Err(e) = watch.debouncer.watcher().watch(dir, RecursiveMode::NonRecursive) {
+            warn!("Failed to watch {}: {}", dir.display(), e);
+        }
+    }
+    watch.dirs = dirs;
+
+    *WATCHED_FILES.lock().unwrap_or_else(|e| e.into_inner()) = files.iter().map(|f| f.path.clone()).collect();
+}
+
+/// Stops watching; the next sync starts over.
+pub fn stop() {
+    if WATCH.lock().unwrap_or_else(|e| e.into_inner()).take().is_some() {
+        info!("Stopped watching shared files");
+    }
+    WATCHED_FILES.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Rehashes shared files that changed and reports those that are gone, in one update per batch.
+/// Runs on the debouncer's thread, so hashing here does not block the UI or the runtime.
+fn handle_events(result: DebounceEventResult) {
+    let events = match result {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("File watcher error: {}", e);
+            return;
+        }
+    };
+
+    let paths: HashSet<PathBuf> = {
+        let watched = WATCHED_FILES.lock().unwrap_or_else(|e| e.into_inner());
+        events.into_iter().map(|e| e.path).filter(|p| watched.contains(p)).collect()
+    };
+
+    let (mut changed, mut removed) = (Vec::new(), Vec::new());
+    for path in paths {
+        if !path.is_file() {
+            removed.push(path);
+            continue;
+        }
+        match HashCache::compute(&path) {
+            Ok(cache) => changed.push((path, cache)),
+            Err(e) => warn!("Failed to rehash changed file {}: {}", path.display(), e),
+        }
+    }
+
+    if !changed.is_empty() || !removed.is_empty() {
+        post_update(AppUpdate::SharedFilesChanged(changed, removed));
+    }
+}
@@ -0,0 +1,186 @@
+// MIT License
+// Copyright (c) Valan Sai 2025
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions.
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+
+// External crates
+use log::{info, warn};
+
+// Standard library
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Local
+use crate::app::{post_message, post_update, AppUpdate, FileSharingApp};
+use crate::audit;
+use crate::bus::{post_command, ManagerCommand};
+use crate::helper::{self, LOG_FILE};
+use crate::network::{self, DEFAULT_IDENTITY, SERVING_DATADIR};
+use crate::request::DOWNLOADS_FILE;
+use crate::storage::{self, CONFIG_DIR};
+use crate::vault;
+
+/// Text the user types to confirm an emergency wipe.
+pub const WIPE_CONFIRMATION: &str = "WIPE";
+
+/// Parts of an emergency wipe; each one can be turned off before confirming.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WipeOptions {
+    pub stop_transfers: bool,   // Fire STOP_SIGNAL, drop the sockets and go offline
+    pub clear_requests: bool,   // Forget every download and explore request
+    pub clear_shares: bool,     // Empty the share list and collections
+    pub new_identity: bool,     // Delete the serving identity; the next one gets a new address
+    pub clear_config: bool,     // Delete the config directory and the history kept in memory
+    pub clear_logs: bool,       // Empty the debug log
+}
+
+impl Default for WipeOptions {
+    fn default() -> Self {
+        Self {
+            stop_transfers: true,
+            clear_requests: true,
+            clear_shares: true,
+            new_identity: true,
+            clear_config: true,
+            clear_logs: true,
+        }
+    }
+}
+
+/// Runs an emergency wipe.
+///
+/// In-memory state is cleared here, on the UI thread; stopping the managers and deleting
+/// files happens on a background task. With `stop_transfers` the managers are started again
+/// once the wipe is done, offline and with empty queues, so the app stays usable.
+pub fn start(app: &mut FileSharingApp, options: WipeOptions) {
+    info!("[*] Emergency wipe: {:?}", options);
+
+    // Before STOP fires, so download_manager does not save the requests on the way out
+    if options.clear_requests {
+        network::set_discarding_downloads(true);
+    }
+
+    // Stopping the managers drops their queues, so the request lists go either way
+    if options.clear_requests || options.stop_transfers {
+        let downloads: Vec<String> = app.requested_files.iter().map(|r| r.request_id.clone()).collect();
+        let explores: Vec<String> = app.explore_requests.iter().map(|r| r.request_id.clone()).collect();
+        if !options.stop_transfers {
+            post_command(ManagerCommand::RemoveDownloads(downloads.clone()));
+            post_command(ManagerCommand::RemoveExplores(explores.clone()));
+        }
+        post_update(AppUpdate::DownloadsRemoved(downloads));
+        post_update(AppUpdate::ExploresRemoved(explores));
+        app.link_preview = None;
+    }
+    if options.stop_transfers {
+        app.offline = true;
+        app.serving_addr.clear();
+        app.identity_addrs.values_mut().for_each(String::clear);
+    }
+    if options.clear_shares {
+        app.shareable_files.clear();
+        app.collections.clear();
+        app.removed_files_undo.clear();
+        app.pending_sensitive_files.clear();
+        app.save_shares();
+    }
+    if options.clear_config {
+        app.peer_stats.clear();
+        app.download_history.clear();
+        app.recent_explorers.clear();
+        app.address_book.clear();
+        storage::set_wiped(true);
+        audit::clear();
+    }
+
+    tokio::spawn(run(options));
+}
+
+/// Background part of the wipe: sockets, identity and files on disk.
+async fn run(options: WipeOptions) {
+    if options.stop_transfers {
+        network::stop().await;
+    }
+
+    if options.new_identity {
+        // A running serving socket holds the identity; swap it for a fresh one first
+        if !options.stop_transfers {
+            network::rotate_serving_identity().await;
+        } else if let Err(e) = remove_dir(Path::new(SERVING_DATADIR)) {
+            warn!("Failed to delete {}: {}", SERVING_DATADIR, e);
+        }
+        // Further identities are dropped rather than replaced
+        for identity in network::serving_identities().into_iter().filter(|i| i.name != DEFAULT_IDENTITY) {
+            network::remove_serving_identity(identity.name.clone()).await;
+        }
+        remove_identity_backups();
+    }
+
+    // Unfinished downloads would otherwise be resumed when the managers restart
+    if options.clear_requests {
+        if let Err(e) = storage::remove(DOWNLOADS_FILE) {
+            warn!("Failed to delete {}: {}", DOWNLOADS_FILE, e);
+        }
+        network::set_discarding_downloads(false);
+    }
+
+    if options.clear_config {
+        if let Err(e) = remove_dir(Path::new(CONFIG_DIR)) {
+            warn!("Failed to delete {}: {}", CONFIG_DIR, e);
+        }
+        vault::reset();
+    }
+
+    if options.stop_transfers {
+        network::init_stop_signal().await;
+        network::restart_managers();
+    }
+
+    // Last, so nothing above is logged after the log is emptied
+    if options.clear_logs
+        && let Err(e) = helper::empty_log() {
+        warn!("Failed to empty {}: {}", LOG_FILE, e);
+    }
+
+    post_message("Emergency wipe complete");
+}
+
+/// Deletes a directory and everything in it; a missing directory is not an error.
+fn remove_dir(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Deletes the `serving_datadir.old-<secs>` directories kept by identity rotation
+/// and the `serving_datadir-<name>` directories of further identities.
+fn remove_identity_backups() {
+    let prefixes = [format!("{}.old-", SERVING_DATADIR), format!("{}-", SERVING_DATADIR)];
+    let Ok(entries) = fs::read_dir(".") else { return; };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if prefixes.iter().any(|p| name.starts_with(p))
+            && let Err(e) = remove_dir(&entry.path()) {
+            warn!("Failed to delete {}: {}", entry.path().display(), e);
+        }
+    }
+}